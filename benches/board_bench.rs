@@ -1,5 +1,6 @@
-use connect4::Board;
+use connect4::{Board, Piece};
 use criterion::{Criterion, criterion_group, criterion_main};
+use std::hint::black_box;
 
 const TEST_BOARDS: [&str; 7] = [
     "!////RR B/BB R",
@@ -24,6 +25,14 @@ fn bench_basic_operations(c: &mut Criterion) {
         })
     });
 
+    c.bench_function("count winning opportunities", |b| {
+        b.iter(|| {
+            for board in boards.iter().filter(|board| board.has_winner().is_none()) {
+                black_box(board.count_winning_opportunities(Piece::Red));
+            }
+        })
+    });
+
     c.bench_function("has winner", |b| {
         b.iter(|| {
             for board in &boards {
@@ -32,6 +41,42 @@ fn bench_basic_operations(c: &mut Criterion) {
         })
     });
 
+    // The vector path only runs when built with `--features simd`
+    c.bench_function("fours", |b| {
+        b.iter(|| {
+            for board in &boards {
+                black_box(black_box(board).fours());
+            }
+        })
+    });
+
+    c.bench_function("fours scalar", |b| {
+        b.iter(|| {
+            for board in &boards {
+                black_box(black_box(board).fours_scalar());
+            }
+        })
+    });
+
+    // Batches are meant for thousands of positions: every board four plies deep
+    let mut batch = vec![Board::new()];
+    for _ in 0..4 {
+        let next: Vec<Board> = batch.iter().flat_map(Board::next_states).collect();
+        batch.extend(next);
+    }
+
+    c.bench_function("has winner batch", |b| {
+        b.iter(|| Board::has_winner_batch(black_box(&batch)))
+    });
+
+    c.bench_function("has winner batch scalar", |b| {
+        b.iter(|| {
+            (black_box(&batch).iter())
+                .map(Board::fours_scalar)
+                .collect::<Vec<_>>()
+        })
+    });
+
     c.bench_function("next states", |b| {
         b.iter(|| {
             for board in &boards {
@@ -43,7 +88,7 @@ fn bench_basic_operations(c: &mut Criterion) {
     c.bench_function("get next player", |b| {
         b.iter(|| {
             for board in &boards {
-                board.next_player();
+                black_box(black_box(board).next_player());
             }
         })
     });
@@ -80,6 +125,11 @@ fn board_graph_opertaions(c: &mut Criterion) {
             }
         })
     });
+
+    // Move generation end to end: every line five plies deep from the empty board
+    c.bench_function("perft 5", |b| {
+        b.iter(|| black_box(Board::new()).perft(5, Piece::Red))
+    });
 }
 
 criterion_group!(benches, bench_basic_operations, board_graph_opertaions);
@@ -0,0 +1,89 @@
+use connect4::search_for_win::SearchForWinCache;
+use connect4::strategy::{Connect4AI, StrategyDecider};
+use connect4::strategy_cache::{StrategyCache, StrategyCacheStats};
+use connect4::strategy_kind::{build_stack, parse_stack};
+use connect4::{Board, GameState, Piece};
+use criterion::{Criterion, criterion_group, criterion_main};
+use std::hint::black_box;
+
+/// Games whose every position is played through the caches, in order. Half are the
+/// mirror images of the other half, so symmetry has something to find.
+const GAMES: [&str; 6] = [
+    "3241526", "3425140", "33224411", "33442255", "2354106", "4312560",
+];
+
+const STACK: &str = "TriesToWin => AvoidTraps => BlockSetups";
+
+/// Every position of the scripted games and the side to move in it.
+fn script() -> Vec<(Board, Piece)> {
+    GAMES
+        .iter()
+        .flat_map(|game| {
+            (0..game.len()).map(move |plies| {
+                let state = GameState::from_moves(&game[..plies]).unwrap();
+                (*state.board(), state.to_move())
+            })
+        })
+        .collect()
+}
+
+fn strategy_cache(symmetry: bool) -> StrategyCache {
+    let cache = StrategyCache::new(build_stack(&parse_stack(STACK).unwrap()));
+    if symmetry {
+        cache.with_symmetry()
+    } else {
+        cache
+    }
+}
+
+fn search_cache(symmetry: bool) -> SearchForWinCache {
+    let cache = SearchForWinCache::new(4);
+    if symmetry {
+        cache.with_symmetry()
+    } else {
+        cache
+    }
+}
+
+fn report(name: &str, stats: StrategyCacheStats) {
+    let lookups = stats.hits + stats.misses;
+    println!(
+        "{name}: {:.1}% hits over {lookups} lookups, {} entries",
+        stats.hits as f64 / lookups.max(1) as f64 * 100.0,
+        stats.entries
+    );
+}
+
+fn bench_caches(c: &mut Criterion) {
+    let script = script();
+    for symmetry in [false, true] {
+        let label = if symmetry { "with" } else { "without" };
+
+        let name = format!("StrategyCache {label} symmetry");
+        let play = |cache: &StrategyCache| {
+            for (board, piece) in &script {
+                black_box(cache.play(board, *piece));
+            }
+        };
+        let cache = strategy_cache(symmetry);
+        play(&cache);
+        report(&name, cache.cache_stats());
+        // A fresh cache each time, so every run starts cold and sees the same script
+        c.bench_function(&name, |b| b.iter(|| play(&strategy_cache(symmetry))));
+
+        let name = format!("SearchForWinCache(4) {label} symmetry");
+        let choose = |cache: &SearchForWinCache| {
+            for (board, piece) in &script {
+                black_box(cache.choose(board, *piece, &board.valid_moves()));
+            }
+        };
+        let cache = search_cache(symmetry);
+        choose(&cache);
+        report(&name, cache.get_stats());
+        c.bench_function(&name, |b| b.iter(|| choose(&search_cache(symmetry))));
+    }
+}
+
+criterion_group!(benches, bench_caches);
+
+criterion_main!(benches);
@@ -0,0 +1,63 @@
+use connect4::GameState;
+use connect4::search_for_win::SearchForWinCache;
+use connect4::strategy::{SearchForWin, StrategyDecider};
+use criterion::{Criterion, criterion_group, criterion_main};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Counts allocations so the benchmarks can report how many a decision makes.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// An early position with no forced win, so the searches run to their full depth.
+const OPENING: &str = "3241";
+
+/// Runs `decide` once and prints how many allocations it made.
+fn report_allocations(name: &str, decide: impl Fn()) {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    decide();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    println!("{name}: {allocations} allocations per decision");
+}
+
+fn bench_searches(c: &mut Criterion) {
+    let state = GameState::from_moves(OPENING).unwrap();
+    let (board, piece) = (*state.board(), state.to_move());
+    let options = board.valid_moves();
+
+    let search = SearchForWin::new(3);
+    report_allocations("SearchForWin(3)", || {
+        black_box(search.choose(&board, piece, &options));
+    });
+    c.bench_function("SearchForWin(3)", |b| {
+        b.iter(|| black_box(search.choose(&board, piece, &options)))
+    });
+
+    // A fresh cache each time, or every decision after the first is a lookup
+    report_allocations("SearchForWinCache(4)", || {
+        black_box(SearchForWinCache::new(4).choose(&board, piece, &options));
+    });
+    c.bench_function("SearchForWinCache(4)", |b| {
+        b.iter(|| black_box(SearchForWinCache::new(4).choose(&board, piece, &options)))
+    });
+}
+
+criterion_group!(benches, bench_searches);
+
+criterion_main!(benches);
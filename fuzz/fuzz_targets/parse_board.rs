@@ -0,0 +1,29 @@
+#![no_main]
+
+use connect4::board::{Board, COLUMNS, Piece, ROWS};
+use libfuzzer_sys::fuzz_target;
+
+/// Whatever a parser accepts must be a board the game could have reached.
+fn check_invariants(board: Board) {
+    assert!(board.num_pieces_played() <= ROWS * COLUMNS);
+    // Panics if the piece counts are unbalanced
+    let next = board.next_player();
+    assert_ne!(next, Piece::Empty);
+    assert_eq!(board.short_string().parse::<Board>().unwrap(), board);
+    assert_eq!(Board::from_u64_checked(board.as_u64()).unwrap(), board);
+    assert!(board.valid_moves().iter().all(|&col| col < COLUMNS));
+    board.has_winner();
+}
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(text) = std::str::from_utf8(data)
+        && let Ok(board) = text.parse::<Board>()
+    {
+        check_invariants(board);
+    }
+    if let Some(bytes) = data.first_chunk::<8>()
+        && let Ok(board) = Board::from_u64_checked(u64::from_le_bytes(*bytes))
+    {
+        check_invariants(board);
+    }
+});
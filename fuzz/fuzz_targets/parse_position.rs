@@ -0,0 +1,18 @@
+#![no_main]
+
+use connect4::game::{GameState, PASS};
+use connect4::strategy_kind::parse_stack;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|text: &str| {
+    if let Ok(state) = GameState::from_moves(text) {
+        let rebuilt = GameState::from_history(state.history()).unwrap();
+        assert_eq!(rebuilt.board(), state.board());
+        let moves = state.history().iter().filter(|&&col| col != PASS).count();
+        assert_eq!(state.board().num_pieces_played(), moves);
+    }
+    if let Ok(kinds) = parse_stack(text) {
+        let printed: Vec<String> = kinds.iter().map(ToString::to_string).collect();
+        assert_eq!(parse_stack(&printed.join(" => ")).unwrap(), kinds);
+    }
+});
@@ -0,0 +1,122 @@
+#ifndef CONNECT4_H
+#define CONNECT4_H
+
+/* Generated by cbindgen from src/ffi.rs. Regenerate with `make include/connect4.h`. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * The state of a game as seen from C.
+ */
+typedef enum {
+  C4_STATUS_IN_PROGRESS = 0,
+  C4_STATUS_RED_WINS = 1,
+  C4_STATUS_YELLOW_WINS = 2,
+  C4_STATUS_TIE = 3,
+  /**
+   * A null handle, or something went wrong inside the library.
+   */
+  C4_STATUS_ERROR = -1,
+} C4Status;
+
+/**
+ * An opaque reinforcement learning environment. Create it with `c4_env_new` and release
+ * it with `c4_env_free`.
+ */
+typedef struct C4Env C4Env;
+
+/**
+ * An opaque game handle. Create it with `c4_game_new` and release it with `c4_game_free`.
+ */
+typedef struct C4Game C4Game;
+
+/**
+ * Starts a new game with Red to move. Free it with `c4_game_free`.
+ */
+C4Game *c4_game_new(void);
+
+/**
+ * Frees a game. Passing null is allowed and does nothing.
+ *
+ * # Safety
+ * `game` must be null or a handle from `c4_game_new` that hasn't been freed yet.
+ */
+void c4_game_free(C4Game *game);
+
+/**
+ * Plays `column` (numbered from 0) for the side to move. Returns 0 on success and -1
+ * if the move is illegal or the handle is null.
+ *
+ * # Safety
+ * `game` must be null or a live handle from `c4_game_new`.
+ */
+int32_t c4_game_apply_move(C4Game *game, uint32_t column);
+
+/**
+ * Whether the game is still going, and if not, how it ended.
+ *
+ * # Safety
+ * `game` must be null or a live handle from `c4_game_new`.
+ */
+C4Status c4_game_status(C4Game *game);
+
+/**
+ * The side to move: 1 for Red, 2 for Yellow, or -1 for a null handle.
+ *
+ * # Safety
+ * `game` must be null or a live handle from `c4_game_new`.
+ */
+int32_t c4_game_to_move(C4Game *game);
+
+/**
+ * The column the AI would play for the side to move, at a difficulty from 0 (random)
+ * to 4. Does not play the move. Returns -1 if the game is over or the handle is null.
+ *
+ * # Safety
+ * `game` must be null or a live handle from `c4_game_new`.
+ */
+int32_t c4_game_ai_move(C4Game *game, uint8_t difficulty);
+
+/**
+ * Creates an environment where the caller's agent plays `agent` (1 for Red, 2 for
+ * Yellow) against the AI at a difficulty from 0 (random) to 4. Returns null for any
+ * other `agent`. Call `c4_env_reset` to start a game, and free it with `c4_env_free`.
+ */
+C4Env *c4_env_new(int32_t agent, uint8_t difficulty);
+
+/**
+ * Frees an environment. Passing null is allowed and does nothing.
+ *
+ * # Safety
+ * `env` must be null or a handle from `c4_env_new` that hasn't been freed yet.
+ */
+void c4_env_free(C4Env *env);
+
+/**
+ * Starts a new game and writes the agent's first observation to `observation`: 84
+ * floats in two planes of 6 rows by 7 columns, the agent's pieces first, each plane
+ * with the bottom row first. Returns 0 on success and -1 if a pointer is null.
+ *
+ * # Safety
+ * `env` must be null or a live handle from `c4_env_new`, and `observation` must be
+ * null or point to room for 84 floats.
+ */
+int32_t c4_env_reset(C4Env *env, float *observation);
+
+/**
+ * Plays `action`, a column, for the agent and then the opponent's reply. Writes the
+ * next observation and the agent's reward: 1 for a win, -1 for a loss, 0 otherwise.
+ * Returns 1 if the game is over, 0 if it goes on, and -1 if the action is illegal,
+ * the game is already over or a pointer is null.
+ *
+ * # Safety
+ * `env` must be null or a live handle from `c4_env_new`, `observation` must be null or
+ * point to room for 84 floats, and `reward` must be null or point
+ * to a float.
+ */
+int32_t c4_env_step(C4Env *env, uint32_t action, float *observation, float *reward);
+
+#endif  /* CONNECT4_H */
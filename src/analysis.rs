@@ -0,0 +1,273 @@
+use crate::board::{Board, Piece};
+use crate::game::{PASS, ROTATE, SWAP};
+
+/// What the side to move can force from a position.
+///
+/// Ordered from worst to best for the side to move, so positions can be compared with `max`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Evaluation {
+    /// The opponent can force a win.
+    Loss,
+    /// The board fills up no matter what.
+    Draw,
+    /// Nothing could be proven within the search depth.
+    Unknown,
+    /// The side to move can force a win.
+    Win,
+}
+
+impl Evaluation {
+    /// The same position seen from the other player's side.
+    pub fn flip(self) -> Self {
+        match self {
+            Evaluation::Loss => Evaluation::Win,
+            Evaluation::Win => Evaluation::Loss,
+            other => other,
+        }
+    }
+}
+
+/// Anything that can judge a position. The post-game analysis only needs this,
+/// so a stronger engine can be swapped in without touching the annotation rules.
+pub trait AnalysisEngine {
+    /// Evaluates `board` for `to_move`, the side about to play.
+    fn evaluate(&self, board: &Board, to_move: Piece) -> Evaluation;
+
+    /// Evaluates each board for the piece paired with it, as `evaluate` does. The
+    /// analysis judges every reply to a move in one call. Engines that can share work
+    /// across positions, like a neural network, should override this.
+    fn evaluate_batch(&self, positions: &[(Board, Piece)]) -> Vec<Evaluation> {
+        positions
+            .iter()
+            .map(|(board, to_move)| self.evaluate(board, *to_move))
+            .collect()
+    }
+}
+
+/// Plain depth-limited search over every move. Slow, but exact within its horizon.
+pub struct DepthSearch {
+    /// How many plies to look ahead.
+    pub depth: usize,
+}
+
+impl DepthSearch {
+    pub fn new(depth: usize) -> Self {
+        DepthSearch { depth }
+    }
+
+    fn search(&self, board: &Board, to_move: Piece, depth: usize) -> Evaluation {
+        // Whoever just moved is the only one who could have won
+        if board.has_winner().is_some() {
+            return Evaluation::Loss;
+        }
+        let moves = board.valid_moves();
+        if moves.is_empty() {
+            return Evaluation::Draw;
+        }
+        if depth == 0 {
+            return Evaluation::Unknown;
+        }
+
+        let mut best = Evaluation::Loss;
+        for col in moves {
+            let next = board.place(col, to_move);
+            let value = self.search(&next, to_move.opponent(), depth - 1).flip();
+            if value == Evaluation::Win {
+                return value;
+            }
+            best = best.max(value);
+        }
+        best
+    }
+}
+
+impl AnalysisEngine for DepthSearch {
+    fn evaluate(&self, board: &Board, to_move: Piece) -> Evaluation {
+        self.search(board, to_move, self.depth)
+    }
+
+    /// Finished games are picked out in one batched pass before searching the rest.
+    fn evaluate_batch(&self, positions: &[(Board, Piece)]) -> Vec<Evaluation> {
+        let boards: Vec<Board> = positions.iter().map(|&(board, _)| board).collect();
+        (positions.iter().zip(Board::has_winner_batch(&boards)))
+            .map(|((board, to_move), winner)| match winner {
+                Some(_) => Evaluation::Loss,
+                None => self.search(board, *to_move, self.depth),
+            })
+            .collect()
+    }
+}
+
+/// How a move compared to the alternatives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MoveQuality {
+    /// As good as any other move.
+    Best,
+    /// Worse than the best move, but didn't change the outcome.
+    Ok,
+    /// Walked into a forced loss, or let a forced win slip.
+    Blunder,
+}
+
+impl MoveQuality {
+    pub fn name(&self) -> &'static str {
+        match self {
+            MoveQuality::Best => "best",
+            MoveQuality::Ok => "ok",
+            MoveQuality::Blunder => "blunder",
+        }
+    }
+}
+
+/// The verdict on a single move of a game.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MoveAnnotation {
+    pub player: Piece,
+    pub column: usize,
+    pub quality: MoveQuality,
+    /// The position after the move, from the mover's side.
+    pub evaluation: Evaluation,
+    /// The best evaluation any move could have reached.
+    pub best_evaluation: Evaluation,
+    /// Every column that reached `best_evaluation`.
+    pub best_columns: Vec<usize>,
+}
+
+/// Replays `history` from the empty board and annotates every move. Passes, swaps and
+/// rotations are skipped.
+pub fn analyze_game(history: &[usize], engine: &dyn AnalysisEngine) -> Vec<MoveAnnotation> {
+    let mut board = Board::new();
+    let mut player = Piece::Red;
+    let mut annotations = Vec::with_capacity(history.len());
+
+    for &column in history {
+        if column == PASS {
+            player = player.opponent();
+            continue;
+        }
+        if column == SWAP {
+            // Red's first move becomes Yellow's, and Red moves again
+            board = Board::new().place(history[0], Piece::Yellow);
+            player = Piece::Red;
+            continue;
+        }
+        if column == ROTATE {
+            board = board
+                .rotate_and_settle()
+                .expect("only legal rotations are recorded");
+            player = player.opponent();
+            continue;
+        }
+        let columns = board.valid_moves();
+        let replies: Vec<(Board, Piece)> = (columns.iter())
+            .map(|&col| (board.place(col, player), player.opponent()))
+            .collect();
+        let options: Vec<(usize, Evaluation)> = (columns.into_iter())
+            .zip(engine.evaluate_batch(&replies))
+            .map(|(col, value)| (col, value.flip()))
+            .collect();
+        let best_evaluation = options
+            .iter()
+            .map(|&(_, value)| value)
+            .max()
+            .unwrap_or(Evaluation::Unknown);
+        let evaluation = options
+            .iter()
+            .find(|&&(col, _)| col == column)
+            .map(|&(_, value)| value)
+            .unwrap_or(Evaluation::Unknown);
+
+        let quality = if evaluation == best_evaluation {
+            MoveQuality::Best
+        } else if evaluation == Evaluation::Loss || best_evaluation == Evaluation::Win {
+            MoveQuality::Blunder
+        } else {
+            MoveQuality::Ok
+        };
+
+        annotations.push(MoveAnnotation {
+            player,
+            column,
+            quality,
+            evaluation,
+            best_evaluation,
+            best_columns: options
+                .iter()
+                .filter(|&&(_, value)| value == best_evaluation)
+                .map(|&(col, _)| col)
+                .collect(),
+        });
+
+        board = board.place(column, player);
+        player = player.opponent();
+    }
+
+    annotations
+}
+
+/// The index of the move where `player` went from a position they could hold into a
+/// forced loss, if there was one.
+pub fn losing_move(annotations: &[MoveAnnotation], player: Piece) -> Option<usize> {
+    annotations.iter().position(|annotation| {
+        annotation.player == player
+            && annotation.evaluation == Evaluation::Loss
+            && annotation.best_evaluation != Evaluation::Loss
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_an_immediate_win() {
+        // Red has three stacked in column 0 and is to move
+        let board = Board::from("!///R/RB/RB");
+        let board = board.place(1, Piece::Yellow);
+        assert_eq!(
+            DepthSearch::new(1).evaluate(&board, Piece::Red),
+            Evaluation::Win
+        );
+        assert_eq!(
+            DepthSearch::new(0).evaluate(&board, Piece::Red),
+            Evaluation::Unknown
+        );
+
+        let won = board.place(0, Piece::Red);
+        let positions = [
+            (board, Piece::Red),
+            (won, Piece::Yellow),
+            (Board::new(), Piece::Red),
+        ];
+        assert_eq!(
+            DepthSearch::new(1).evaluate_batch(&positions),
+            [Evaluation::Win, Evaluation::Loss, Evaluation::Unknown]
+        );
+
+        // After a pass the piece counts no longer say whose turn it is
+        let passed = (0..3).fold(Board::new(), |board, _| board.place(0, Piece::Red));
+        assert_eq!(
+            DepthSearch::new(1).evaluate_batch(&[(passed, Piece::Red)]),
+            [Evaluation::Win]
+        );
+    }
+
+    #[test]
+    fn flags_the_move_that_ignored_a_threat() {
+        // Red stacks column 0 while Yellow ignores it by playing column 1 and then 6.
+        let history = [0, 1, 0, 1, 0, 6, 0];
+        let annotations = analyze_game(&history, &DepthSearch::new(2));
+        assert_eq!(annotations.len(), history.len());
+
+        // Yellow's 6 let Red complete the column
+        assert_eq!(annotations[5].quality, MoveQuality::Blunder);
+        assert_eq!(annotations[5].evaluation, Evaluation::Loss);
+        assert_eq!(annotations[5].best_columns, vec![0]);
+        // Red's winning move was the best one
+        assert_eq!(annotations[6].quality, MoveQuality::Best);
+        assert_eq!(annotations[6].evaluation, Evaluation::Win);
+
+        assert_eq!(losing_move(&annotations, Piece::Yellow), Some(5));
+        assert_eq!(losing_move(&annotations, Piece::Red), None);
+    }
+}
@@ -0,0 +1,315 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+
+use anyhow::{Context, Result, bail, ensure};
+use serde::{Deserialize, Serialize};
+
+use crate::board::Piece;
+use crate::explain::DecisionTrace;
+use crate::game::GameState;
+use crate::mcts::{Mcts, MoveStats};
+use crate::strategy::Connect4AI;
+use crate::strategy_kind::{
+    StrategyKind, StrategyRegistry, build_stack, difficulty_stack, parse_stack,
+};
+
+/// Bodies larger than this are refused, so a bad client can't make us buffer forever.
+const MAX_BODY: usize = 64 * 1024;
+
+/// The most playouts `POST /analyze` will run; larger requests are refused.
+pub const MAX_ITERATIONS: usize = 200_000;
+
+/// `POST /move`: the column the AI plays in `position`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MoveRequest {
+    /// The columns played so far, numbered from 0, e.g. `"3324"`, or a board's short
+    /// string if legal play can reach it.
+    #[serde(default)]
+    pub position: String,
+    /// From 0 (random) to 4. Ignored when `strategy` is given.
+    #[serde(default)]
+    pub difficulty: Option<u8>,
+    /// A strategy stack, e.g. `"SearchForWin(3) => TriesToWin"`.
+    #[serde(default)]
+    pub strategy: Option<String>,
+    /// Also return how the stack picked the move.
+    #[serde(default)]
+    pub trace: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MoveResponse {
+    pub column: usize,
+    pub piece: Piece,
+    /// Each strategy's input and output options, when the request asked for them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace: Option<DecisionTrace>,
+}
+
+/// `POST /analyze`: an MCTS search of `position`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnalyzeRequest {
+    #[serde(default)]
+    pub position: String,
+    /// How many playouts to run, 10,000 by default.
+    #[serde(default)]
+    pub iterations: Option<usize>,
+    /// The strategy stack whose move probabilities are the priors. Uniform if unset.
+    #[serde(default)]
+    pub priors: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnalyzeResponse {
+    pub to_move: Piece,
+    pub playouts: u32,
+    /// Most visited first.
+    pub moves: Vec<MoveStats>,
+    pub principal_variation: Vec<usize>,
+}
+
+/// One entry of `GET /strategies`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StrategySummary {
+    pub name: String,
+    /// The strategy with its default parameters, as written in a stack.
+    pub default: String,
+    pub role: String,
+    pub cost: String,
+    pub parameters: String,
+    pub description: String,
+}
+
+/// The body of every error response.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorResponse {
+    pub error: String,
+}
+
+/// Answers one request, returning the HTTP status and the JSON body.
+pub fn handle(method: &str, path: &str, body: &str) -> (u16, String) {
+    let result = match (method, path) {
+        ("POST", "/move") => parse(body).and_then(|request| to_json(&best_move(&request)?)),
+        ("POST", "/analyze") => parse(body).and_then(|request| to_json(&analyze(&request)?)),
+        ("GET", "/strategies") => to_json(&strategies()),
+        (_, "/move" | "/analyze" | "/strategies") => {
+            return error(405, format!("{method} isn't allowed on {path}"));
+        }
+        _ => return error(404, format!("No endpoint at {path}")),
+    };
+    match result {
+        Ok(json) => (200, json),
+        Err(e) => error(400, format!("{e:#}")),
+    }
+}
+
+fn parse<T: for<'de> Deserialize<'de>>(body: &str) -> Result<T> {
+    serde_json::from_str(body).context("Invalid request body")
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<String> {
+    Ok(serde_json::to_string(value)?)
+}
+
+fn error(status: u16, error: String) -> (u16, String) {
+    let json = to_json(&ErrorResponse { error }).expect("errors serialize");
+    (status, json)
+}
+
+fn playable(position: &str) -> Result<GameState> {
+    let state = GameState::from_moves(position)?;
+    ensure!(!state.is_over(), "The game is already over");
+    Ok(state)
+}
+
+pub fn best_move(request: &MoveRequest) -> Result<MoveResponse> {
+    let state = playable(&request.position)?;
+    let kinds: Vec<StrategyKind> = match (&request.strategy, request.difficulty) {
+        (Some(strategy), _) => parse_stack(strategy)?,
+        (None, difficulty) => difficulty_stack(difficulty.unwrap_or(3)),
+    };
+    let piece = state.to_move();
+    let stack = build_stack(&kinds);
+    let column = stack
+        .play(state.board(), piece)
+        .context("The strategy failed to choose a move")?;
+    let trace = (request.trace).then(|| stack.explain(state.board(), piece));
+    Ok(MoveResponse {
+        column,
+        piece,
+        trace,
+    })
+}
+
+pub fn analyze(request: &AnalyzeRequest) -> Result<AnalyzeResponse> {
+    let state = playable(&request.position)?;
+    let iterations = request.iterations.unwrap_or(10_000);
+    ensure!(
+        (1..=MAX_ITERATIONS).contains(&iterations),
+        "iterations must be from 1 to {MAX_ITERATIONS}"
+    );
+    let mut mcts = Mcts::new(iterations);
+    if let Some(priors) = &request.priors {
+        mcts = mcts.with_priors(&parse_stack(priors)?);
+    }
+    let tree = mcts.search(state.board(), state.to_move());
+    Ok(AnalyzeResponse {
+        to_move: state.to_move(),
+        playouts: tree.visits(),
+        moves: tree.root_stats(),
+        principal_variation: tree.principal_variation(),
+    })
+}
+
+pub fn strategies() -> Vec<StrategySummary> {
+    (StrategyRegistry::strategies().into_iter())
+        .map(|info| StrategySummary {
+            name: info.default.name().to_string(),
+            default: info.default.to_string(),
+            role: info.role.to_string(),
+            cost: info.cost.to_string(),
+            parameters: info.parameters.to_string(),
+            description: info.description.to_string(),
+        })
+        .collect()
+}
+
+/// Serves the API over HTTP/1.1, one thread per connection, until the listener fails.
+/// Every response closes its connection.
+pub fn serve(listener: &TcpListener) -> Result<()> {
+    for stream in listener.incoming() {
+        let stream = stream?;
+        std::thread::spawn(move || {
+            if let Err(e) = respond(stream) {
+                tracing::warn!(error = %e, "request failed");
+            }
+        });
+    }
+    Ok(())
+}
+
+fn respond(mut stream: TcpStream) -> Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let (status, body) = match read_request(&mut reader) {
+        Ok((method, path, body)) => {
+            tracing::debug!(method, path, "api request");
+            handle(&method, &path, &body)
+        }
+        Err(e) => error(400, format!("{e:#}")),
+    };
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "Error",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {status} {reason}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()?;
+    Ok(())
+}
+
+/// The method, path (without any query) and body of one request.
+fn read_request(reader: &mut impl BufRead) -> Result<(String, String, String)> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        bail!("Malformed request line {line:?}");
+    };
+    let path = target.split('?').next().unwrap_or(target).to_string();
+    let method = method.to_string();
+
+    let mut length = 0;
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header)?;
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.trim().eq_ignore_ascii_case("content-length")
+        {
+            length = value.trim().parse().context("Invalid Content-Length")?;
+        }
+    }
+    ensure!(length <= MAX_BODY, "The body is over {MAX_BODY} bytes");
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    Ok((method, path, String::from_utf8(body)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::*;
+
+    #[test]
+    fn move_takes_a_position_and_difficulty() {
+        // Red wins at once in column 0
+        let (status, body) = handle("POST", "/move", r#"{"position":"010101","difficulty":2}"#);
+        assert_eq!(status, 200, "{body}");
+        let response: MoveResponse = serde_json::from_str(&body).unwrap();
+        assert_eq!(
+            response,
+            MoveResponse {
+                column: 0,
+                piece: Piece::Red,
+                trace: None
+            }
+        );
+
+        let body = r#"{"position":"010101","strategy":"TriesToWin => AvoidTraps","trace":true}"#;
+        let (status, body) = handle("POST", "/move", body);
+        assert_eq!(status, 200, "{body}");
+        let response: MoveResponse = serde_json::from_str(&body).unwrap();
+        let trace = response.trace.unwrap();
+        assert_eq!(trace.decider(), Some("TriesToWin"));
+        assert_eq!(trace.options, [response.column]);
+
+        let (status, body) = handle("POST", "/move", r#"{"position":"0101010"}"#);
+        assert_eq!(status, 400);
+        assert!(body.contains("over"), "{body}");
+        assert_eq!(handle("GET", "/move", "").0, 405);
+        assert_eq!(handle("GET", "/nowhere", "").0, 404);
+    }
+
+    #[test]
+    fn analyze_and_strategies_over_http() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        std::thread::spawn(move || serve(&listener));
+
+        let request = |head: &str, body: &str| {
+            let mut stream = TcpStream::connect(address).unwrap();
+            write!(
+                stream,
+                "{head} HTTP/1.1\r\nContent-Length: {}\r\n\r\n{body}",
+                body.len()
+            )
+            .unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        };
+
+        let response = request("POST /analyze", r#"{"position":"33","iterations":200}"#);
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "{response}");
+        let (_, body) = response.split_once("\r\n\r\n").unwrap();
+        let analysis: AnalyzeResponse = serde_json::from_str(body).unwrap();
+        assert_eq!(analysis.to_move, Piece::Red);
+        assert_eq!(analysis.playouts, 200);
+        assert_eq!(analysis.moves.len(), 7);
+
+        let response = request("GET /strategies", "");
+        let (_, body) = response.split_once("\r\n\r\n").unwrap();
+        let strategies: Vec<StrategySummary> = serde_json::from_str(body).unwrap();
+        assert!(strategies.iter().any(|s| s.default == "SearchForWin(3)"));
+    }
+}
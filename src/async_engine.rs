@@ -0,0 +1,229 @@
+use std::future::Future;
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::pin::Pin;
+use std::sync::mpsc::{Sender, channel};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use anyhow::{Result, anyhow};
+
+use crate::board::{Board, Piece};
+use crate::strategy::{Connect4AI, StrategyStack};
+use crate::strategy_kind::{StrategyKind, build_stack};
+
+/// How to pick one move.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MoveConfig {
+    pub strategy: Vec<StrategyKind>,
+    /// Time left on the mover's clock, so expensive deciders can be skipped when short.
+    pub remaining: Option<Duration>,
+}
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Runs strategies on a pool of worker threads so async code, like a web server, can
+/// await moves without blocking its runtime. It needs no particular runtime: the
+/// futures it hands out are woken from the workers.
+///
+/// Stacks are built once per strategy and shared between workers, so caching searches
+/// keep their caches across requests.
+pub struct AsyncEngine {
+    jobs: Option<Sender<Job>>,
+    workers: Vec<JoinHandle<()>>,
+    stacks: Mutex<Vec<(Vec<StrategyKind>, Arc<StrategyStack>)>>,
+}
+
+impl AsyncEngine {
+    /// Starts `workers` threads; that many moves are searched at once.
+    pub fn new(workers: usize) -> Self {
+        assert!(workers > 0, "The engine needs at least one worker");
+        let (jobs, queue) = channel::<Job>();
+        let queue = Arc::new(Mutex::new(queue));
+        let workers = (0..workers)
+            .map(|_| {
+                let queue = Arc::clone(&queue);
+                std::thread::spawn(move || {
+                    loop {
+                        // Hold the lock only while waiting, not while running the job
+                        let job = queue.lock().unwrap().recv();
+                        match job {
+                            Ok(job) => job(),
+                            Err(_) => break,
+                        }
+                    }
+                })
+            })
+            .collect();
+        AsyncEngine {
+            jobs: Some(jobs),
+            workers,
+            stacks: Mutex::new(vec![]),
+        }
+    }
+
+    /// The move `config`'s strategy plays for `piece` on `board`. Dropping the future
+    /// cancels the request if no worker has started on it; a search already running
+    /// finishes and its move is thrown away.
+    pub fn best_move(&self, board: Board, piece: Piece, config: MoveConfig) -> MoveFuture {
+        let slot = Arc::new(Mutex::new(Slot::default()));
+        let stack = self.stack(&config.strategy);
+        let job_slot = Arc::clone(&slot);
+        let job = Box::new(move || {
+            if job_slot.lock().unwrap().cancelled {
+                tracing::debug!("move cancelled before it started");
+                return;
+            }
+            let played = catch_unwind(AssertUnwindSafe(|| match config.remaining {
+                Some(remaining) => stack.play_timed(&board, piece, remaining),
+                None => stack.play(&board, piece),
+            }));
+            let result = match played {
+                Ok(Some(column)) => Ok(column),
+                Ok(None) => Err(anyhow!("The strategy failed to choose a move")),
+                Err(_) => Err(anyhow!("The strategy panicked")),
+            };
+            job_slot.lock().unwrap().finish(result);
+        });
+        let sent = (self.jobs.as_ref()).is_some_and(|jobs| jobs.send(job).is_ok());
+        if !sent {
+            slot.lock()
+                .unwrap()
+                .finish(Err(anyhow!("The engine has shut down")));
+        }
+        MoveFuture { slot }
+    }
+
+    fn stack(&self, strategy: &[StrategyKind]) -> Arc<StrategyStack> {
+        let mut stacks = self.stacks.lock().unwrap();
+        if let Some((_, stack)) = stacks.iter().find(|(kinds, _)| kinds == strategy) {
+            return Arc::clone(stack);
+        }
+        let stack = Arc::new(build_stack(strategy));
+        stacks.push((strategy.to_vec(), Arc::clone(&stack)));
+        stack
+    }
+}
+
+impl Drop for AsyncEngine {
+    /// Lets queued moves finish, then stops the workers.
+    fn drop(&mut self) {
+        self.jobs = None;
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[derive(Default)]
+struct Slot {
+    result: Option<Result<usize>>,
+    waker: Option<Waker>,
+    cancelled: bool,
+}
+
+impl Slot {
+    fn finish(&mut self, result: Result<usize>) {
+        self.result = Some(result);
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// A move being searched by an `AsyncEngine`. Resolves to the column played.
+pub struct MoveFuture {
+    slot: Arc<Mutex<Slot>>,
+}
+
+impl Future for MoveFuture {
+    type Output = Result<usize>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut slot = self.slot.lock().unwrap();
+        match slot.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                slot.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl Drop for MoveFuture {
+    fn drop(&mut self) {
+        self.slot.lock().unwrap().cancelled = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::Receiver;
+    use std::task::Wake;
+
+    use super::*;
+    use crate::game::GameState;
+    use crate::strategy_kind::parse_stack;
+
+    /// Wakes the test thread blocked in `block_on`.
+    struct Unpark(std::thread::Thread);
+
+    impl Wake for Unpark {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = std::pin::pin!(future);
+        let waker = Waker::from(Arc::new(Unpark(std::thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+            std::thread::park();
+        }
+    }
+
+    fn config(spec: &str) -> MoveConfig {
+        MoveConfig {
+            strategy: parse_stack(spec).unwrap(),
+            remaining: None,
+        }
+    }
+
+    #[test]
+    fn awaits_moves_from_the_workers() {
+        let engine = AsyncEngine::new(2);
+        // Red wins at once in column 0
+        let state = GameState::from_moves("010101").unwrap();
+        let moves: Vec<_> = (0..4)
+            .map(|_| engine.best_move(*state.board(), state.to_move(), config("TriesToWin")))
+            .collect();
+        for future in moves {
+            assert_eq!(block_on(future).unwrap(), 0);
+        }
+        // Each strategy is built once, however many moves ask for it
+        engine.best_move(Board::new(), Piece::Red, config("AvoidTraps"));
+        assert_eq!(engine.stacks.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn dropped_futures_are_never_searched() {
+        let engine = AsyncEngine::new(1);
+        // Keep the only worker busy until the queued move has been dropped
+        let (release, wait): (_, Receiver<()>) = channel();
+        let busy: Job = Box::new(move || wait.recv().unwrap());
+        engine.jobs.as_ref().unwrap().send(busy).unwrap();
+
+        let queued = engine.best_move(Board::new(), Piece::Red, config("AvoidTraps"));
+        let slot = Arc::clone(&queued.slot);
+        drop(queued);
+        release.send(()).unwrap();
+        drop(engine);
+        assert!(slot.lock().unwrap().result.is_none());
+    }
+}
@@ -0,0 +1,273 @@
+use std::fmt;
+
+use anyhow::{Result, ensure};
+use rand::Rng;
+use rand::seq::IndexedRandom;
+
+use crate::board::{COLUMNS, Piece, ROWS};
+use crate::game::GameResult;
+
+/// A board that may hold neutral blockers as well as Red and Yellow pieces. The packed
+/// `Board` only has one bit per piece, so this variant keeps a plain grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BlockerBoard {
+    /// Indexed by column, then row from the bottom.
+    cells: [[Piece; ROWS]; COLUMNS],
+}
+
+impl BlockerBoard {
+    pub fn new() -> Self {
+        BlockerBoard {
+            cells: [[Piece::Empty; ROWS]; COLUMNS],
+        }
+    }
+
+    /// A board with `count` blockers dropped into random columns.
+    pub fn random_setup(count: usize, rng: &mut impl Rng) -> Result<Self> {
+        ensure!(
+            count < ROWS * COLUMNS,
+            "At most {} blockers fit on the board",
+            ROWS * COLUMNS - 1
+        );
+        let mut board = Self::new();
+        for _ in 0..count {
+            let column = *board.valid_moves().choose(rng).expect("checked above");
+            board = board.place(column, Piece::Blocker);
+        }
+        Ok(board)
+    }
+
+    pub fn get(&self, column: usize, row: usize) -> Piece {
+        self.cells[column][row]
+    }
+
+    pub fn height(&self, column: usize) -> usize {
+        self.cells[column]
+            .iter()
+            .take_while(|&&p| p != Piece::Empty)
+            .count()
+    }
+
+    /// Columns with room left. Unlike `Board`, columns fill all the way to the top.
+    pub fn valid_moves(&self) -> Vec<usize> {
+        (0..COLUMNS)
+            .filter(|&column| self.height(column) < ROWS)
+            .collect()
+    }
+
+    /// Drops `piece`, which may be a blocker, into `column`.
+    pub fn place(&self, column: usize, piece: Piece) -> Self {
+        let row = self.height(column);
+        assert!(row < ROWS, "column {column} is full");
+        let mut next = *self;
+        next.cells[column][row] = piece;
+        next
+    }
+
+    /// The player with four in a row. Blockers break lines and never win.
+    pub fn winner(&self) -> Option<Piece> {
+        let at = |column: isize, row: isize| {
+            if (0..COLUMNS as isize).contains(&column) && (0..ROWS as isize).contains(&row) {
+                self.cells[column as usize][row as usize]
+            } else {
+                Piece::Empty
+            }
+        };
+        for column in 0..COLUMNS as isize {
+            for row in 0..ROWS as isize {
+                let piece = at(column, row);
+                if !matches!(piece, Piece::Red | Piece::Yellow) {
+                    continue;
+                }
+                for (dc, dr) in [(1, 0), (0, 1), (1, 1), (1, -1)] {
+                    if (1..4).all(|i| at(column + dc * i, row + dr * i) == piece) {
+                        return Some(piece);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// Columns where `piece` would complete four-in-a-row.
+    pub fn winning_moves(&self, piece: Piece) -> Vec<usize> {
+        (self.valid_moves().into_iter())
+            .filter(|&column| self.place(column, piece).winner() == Some(piece))
+            .collect()
+    }
+}
+
+impl Default for BlockerBoard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for BlockerBoard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in (0..ROWS).rev() {
+            for column in 0..COLUMNS {
+                write!(f, "{} ", self.cells[column][row])?;
+            }
+            if row != 0 {
+                writeln!(f)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A game between Red and Yellow on a board that starts with blockers in place.
+#[derive(Debug, Clone, Default)]
+pub struct BlockerGame {
+    board: BlockerBoard,
+    history: Vec<usize>,
+}
+
+impl BlockerGame {
+    /// Starts from `setup`, which holds only blockers. Red moves first.
+    pub fn new(setup: BlockerBoard) -> Result<Self> {
+        ensure!(
+            setup
+                .cells
+                .iter()
+                .flatten()
+                .all(|&p| p != Piece::Red && p != Piece::Yellow),
+            "The setup may only hold blockers"
+        );
+        Ok(BlockerGame {
+            board: setup,
+            history: vec![],
+        })
+    }
+
+    pub fn board(&self) -> &BlockerBoard {
+        &self.board
+    }
+
+    pub fn to_move(&self) -> Piece {
+        if self.history.len().is_multiple_of(2) {
+            Piece::Red
+        } else {
+            Piece::Yellow
+        }
+    }
+
+    /// The columns played so far, not counting the setup.
+    pub fn history(&self) -> &[usize] {
+        &self.history
+    }
+
+    pub fn result(&self) -> Option<GameResult> {
+        if let Some(winner) = self.board.winner() {
+            Some(GameResult::Winner(winner))
+        } else if self.board.valid_moves().is_empty() {
+            Some(GameResult::Tie)
+        } else {
+            None
+        }
+    }
+
+    /// Plays `column` for the side to move and hands the turn to the opponent.
+    pub fn apply_move(&mut self, column: usize) -> Result<Option<GameResult>> {
+        ensure!(self.result().is_none(), "The game is already over");
+        ensure!(
+            self.board.valid_moves().contains(&column),
+            "Column {} is not a legal move",
+            column
+        );
+        self.board = self.board.place(column, self.to_move());
+        self.history.push(column);
+        Ok(self.result())
+    }
+}
+
+/// A simple AI for the blockers variant: wins if it can, blocks, avoids setting up a
+/// win for the opponent, and otherwise plays near the center.
+pub fn choose_move(board: &BlockerBoard, piece: Piece, rng: &mut impl Rng) -> Option<usize> {
+    if let Some(&column) = board.winning_moves(piece).first() {
+        return Some(column);
+    }
+    if let Some(&column) = board.winning_moves(piece.opponent()).first() {
+        return Some(column);
+    }
+
+    let moves = board.valid_moves();
+    let safe: Vec<usize> = (moves.iter().copied())
+        .filter(|&column| {
+            board
+                .place(column, piece)
+                .winning_moves(piece.opponent())
+                .is_empty()
+        })
+        .collect();
+    let options = if safe.is_empty() { moves } else { safe };
+    let distance = |column: usize| column.abs_diff(COLUMNS / 2);
+    let closest = options.iter().map(|&c| distance(c)).min()?;
+    let central: Vec<usize> = (options.into_iter())
+        .filter(|&c| distance(c) <= closest + 1)
+        .collect();
+    central.choose(rng).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blockers_count_for_neither_player() {
+        let board = [0, 1, 2, 3]
+            .iter()
+            .fold(BlockerBoard::new(), |board, &column| {
+                board.place(column, Piece::Blocker)
+            });
+        assert_eq!(board.winner(), None);
+        assert_eq!(board.height(2), 1);
+
+        // A blocker in the middle of a line breaks it
+        let broken = [0, 1, 3]
+            .iter()
+            .fold(BlockerBoard::new(), |board, &column| {
+                board.place(column, Piece::Red)
+            })
+            .place(2, Piece::Blocker);
+        assert_eq!(broken.winner(), None);
+        assert!(broken.winning_moves(Piece::Red).is_empty());
+
+        let mut game = BlockerGame::new(BlockerBoard::new().place(3, Piece::Blocker)).unwrap();
+        for column in [3, 0, 3, 0, 3, 0, 3] {
+            game.apply_move(column).unwrap();
+        }
+        // Red's four sit on top of the blocker
+        assert_eq!(game.result(), Some(GameResult::Winner(Piece::Red)));
+        assert_eq!(game.board().height(3), 5);
+    }
+
+    #[test]
+    fn random_setups_hold_only_blockers() {
+        let mut rng = rand::rng();
+        let setup = BlockerBoard::random_setup(6, &mut rng).unwrap();
+        let blockers: usize = (0..COLUMNS).map(|column| setup.height(column)).sum();
+        assert_eq!(blockers, 6);
+        let game = BlockerGame::new(setup).unwrap();
+        assert_eq!(game.to_move(), Piece::Red);
+        assert!(BlockerBoard::random_setup(ROWS * COLUMNS, &mut rng).is_err());
+        assert!(BlockerGame::new(setup.place(0, Piece::Red)).is_err());
+    }
+
+    #[test]
+    fn ai_wins_then_blocks() {
+        let mut rng = rand::rng();
+        let three = (0..3).fold(BlockerBoard::new(), |board, column| {
+            board.place(column + 1, Piece::Red)
+        });
+        assert_eq!(
+            choose_move(&three.place(0, Piece::Blocker), Piece::Yellow, &mut rng),
+            Some(4)
+        );
+        assert_eq!(
+            choose_move(&three.place(4, Piece::Blocker), Piece::Red, &mut rng),
+            Some(0)
+        );
+    }
+}
@@ -0,0 +1,201 @@
+use super::{Board, BoardArray, COLUMNS, Piece, ROWS};
+
+/// A slow but obviously correct board: a plain grid and brute-force rules, for
+/// differential tests against the packed `Board`.
+#[derive(Debug, Clone)]
+pub struct SimpleBoard {
+    /// Indexed by column, then row from the bottom.
+    cells: [[Piece; ROWS]; COLUMNS],
+}
+
+impl SimpleBoard {
+    pub fn new() -> Self {
+        SimpleBoard {
+            cells: [[Piece::Empty; ROWS]; COLUMNS],
+        }
+    }
+
+    pub fn height(&self, column: usize) -> usize {
+        self.cells[column]
+            .iter()
+            .take_while(|&&p| p != Piece::Empty)
+            .count()
+    }
+
+    /// Drops a piece, which may fill a column all the way to the top.
+    pub fn place(&mut self, column: usize, piece: Piece) {
+        let row = self.height(column);
+        assert!(row < ROWS, "column {column} is full");
+        self.cells[column][row] = piece;
+    }
+
+    pub fn count(&self, piece: Piece) -> usize {
+        self.cells.iter().flatten().filter(|&&p| p == piece).count()
+    }
+
+    pub fn num_pieces_played(&self) -> usize {
+        self.count(Piece::Red) + self.count(Piece::Yellow)
+    }
+
+    pub fn next_player(&self) -> Piece {
+        if self.count(Piece::Red) == self.count(Piece::Yellow) {
+            Piece::Red
+        } else {
+            Piece::Yellow
+        }
+    }
+
+    /// Columns with room for another piece, by the real rules: up to the top row.
+    /// `Board` stops one piece short; the tests allow for that with `below_top`.
+    pub fn valid_moves(&self) -> Vec<usize> {
+        (0..COLUMNS)
+            .filter(|&column| self.height(column) < ROWS)
+            .collect()
+    }
+
+    /// `columns` without those whose next piece would go in the top row, which
+    /// `Board::valid_moves` leaves unplayed: a known difference from the real rules.
+    pub fn below_top(&self, columns: Vec<usize>) -> Vec<usize> {
+        (columns.into_iter())
+            .filter(|&column| self.height(column) < ROWS - 1)
+            .collect()
+    }
+
+    /// Every line of four cells on the board.
+    fn lines() -> impl Iterator<Item = [(usize, usize); 4]> {
+        let on_board = |column: isize, row: isize| {
+            (0..COLUMNS as isize).contains(&column) && (0..ROWS as isize).contains(&row)
+        };
+        (0..COLUMNS as isize).flat_map(move |column| {
+            (0..ROWS as isize).flat_map(move |row| {
+                [(1, 0), (0, 1), (1, 1), (1, -1)]
+                    .into_iter()
+                    .filter(move |(dc, dr)| on_board(column + dc * 3, row + dr * 3))
+                    .map(move |(dc, dr)| {
+                        [0, 1, 2, 3].map(|i| ((column + dc * i) as usize, (row + dr * i) as usize))
+                    })
+            })
+        })
+    }
+
+    pub fn winner(&self) -> Option<Piece> {
+        Self::lines()
+            .map(|line| line.map(|(column, row)| self.cells[column][row]))
+            .find(|cells| cells[0] != Piece::Empty && cells.iter().all(|&p| p == cells[0]))
+            .map(|cells| cells[0])
+    }
+
+    /// Lines holding three of `piece` and one empty cell.
+    pub fn count_winning_opportunities(&self, piece: Piece) -> usize {
+        Self::lines()
+            .map(|line| line.map(|(column, row)| self.cells[column][row]))
+            .filter(|cells| {
+                cells.iter().filter(|&&p| p == piece).count() == 3 && cells.contains(&Piece::Empty)
+            })
+            .count()
+    }
+
+    /// Empty cells that win for `piece` if it could be set there directly.
+    pub fn threat_squares(&self, piece: Piece) -> Vec<(usize, usize)> {
+        let mut squares = vec![];
+        for column in 0..COLUMNS {
+            for row in 0..ROWS {
+                if self.cells[column][row] != Piece::Empty {
+                    continue;
+                }
+                let mut next = self.clone();
+                next.cells[column][row] = piece;
+                if next.winner() == Some(piece) {
+                    squares.push((row, column));
+                }
+            }
+        }
+        squares
+    }
+
+    pub fn winning_moves(&self, piece: Piece) -> Vec<usize> {
+        (self.valid_moves().into_iter())
+            .filter(|&column| {
+                let mut next = self.clone();
+                next.place(column, piece);
+                next.winner() == Some(piece)
+            })
+            .collect()
+    }
+
+    /// The grid in `Board`'s array layout, top row first.
+    pub fn to_array(&self) -> BoardArray {
+        let mut array = [[Piece::Empty; COLUMNS]; ROWS];
+        for (column, cells) in self.cells.iter().enumerate() {
+            for (row, &piece) in cells.iter().enumerate() {
+                array[ROWS - 1 - row][column] = piece;
+            }
+        }
+        array
+    }
+}
+
+/// Plays `columns` in turn on both boards, skipping columns that are full (all the
+/// way to the top, to exercise every row) and stopping at the first win.
+pub fn play_both(columns: &[usize]) -> (Board, SimpleBoard) {
+    let mut board = Board::new();
+    let mut simple = SimpleBoard::new();
+    for &column in columns {
+        if simple.height(column) == ROWS {
+            continue;
+        }
+        let piece = simple.next_player();
+        board.with_placed(column, piece);
+        simple.place(column, piece);
+        if simple.winner().is_some() {
+            break;
+        }
+    }
+    (board, simple)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn games_play_out_the_same(columns in proptest::collection::vec(0..COLUMNS, 0..60)) {
+            let mut board = Board::new();
+            let mut simple = SimpleBoard::new();
+            for column in columns {
+                let moves = simple.valid_moves();
+                prop_assert_eq!(board.valid_moves(), simple.below_top(moves.clone()));
+                prop_assert_eq!(board.next_player(), simple.next_player());
+                prop_assert_eq!(board.num_pieces_played(), simple.num_pieces_played());
+                for piece in [Piece::Red, Piece::Yellow] {
+                    prop_assert_eq!(
+                        board.winning_moves(piece),
+                        simple.below_top(simple.winning_moves(piece))
+                    );
+                    prop_assert_eq!(
+                        board.count_winning_opportunities(piece),
+                        simple.count_winning_opportunities(piece)
+                    );
+                }
+                // Top-row drops are played too, so the boards are compared full
+                if !moves.contains(&column) {
+                    continue;
+                }
+                let piece = simple.next_player();
+                board = board.place(column, piece);
+                simple.place(column, piece);
+                prop_assert_eq!(board.has_winner(), simple.winner());
+                if simple.winner().is_some() {
+                    // Nothing is left to win once the game is over
+                    for piece in [Piece::Red, Piece::Yellow] {
+                        prop_assert!(board.winning_moves(piece).is_empty());
+                        prop_assert_eq!(board.count_winning_opportunities(piece), 0);
+                    }
+                    break;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,387 @@
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+
+use anyhow::{Context, Result, bail, ensure};
+
+use crate::board::{Board, COLUMNS, Piece, TextStyle};
+use crate::game::{GameResult, GameState};
+use crate::strategy::{Connect4AI, StrategyStack};
+use crate::strategy_kind::{MAX_DIFFICULTY, build_stack, difficulty_stack};
+
+/// The board as chat text, top row first. ASCII boards go in a code block so they line
+/// up, and every style numbers the columns from 1, as commands do.
+pub fn render(board: &Board, style: TextStyle) -> String {
+    let text = board.to_text(style);
+    match style {
+        TextStyle::Ascii => format!("```\n{text}\n1 2 3 4 5 6 7\n```"),
+        TextStyle::Emoji => format!("{text}\n1️⃣2️⃣3️⃣4️⃣5️⃣6️⃣7️⃣"),
+        TextStyle::Markdown => text,
+    }
+}
+
+/// Who plays one color in a chat game.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Player {
+    User(String),
+    Ai { difficulty: u8 },
+}
+
+impl Player {
+    fn mention(&self) -> String {
+        match self {
+            Player::User(name) => name.clone(),
+            Player::Ai { difficulty } => format!("the AI (difficulty {difficulty})"),
+        }
+    }
+}
+
+/// One game in one channel.
+pub struct Session {
+    pub state: GameState,
+    /// Red's and Yellow's players.
+    pub players: [Player; 2],
+    ai: Option<StrategyStack>,
+}
+
+impl Session {
+    fn new(red: Player, yellow: Player) -> Self {
+        let ai = [&red, &yellow].into_iter().find_map(|player| match player {
+            Player::Ai { difficulty } => Some(build_stack(&difficulty_stack(*difficulty))),
+            Player::User(_) => None,
+        });
+        Session {
+            state: GameState::new(),
+            players: [red, yellow],
+            ai,
+        }
+    }
+
+    pub fn player(&self, piece: Piece) -> &Player {
+        &self.players[usize::from(piece == Piece::Yellow)]
+    }
+
+    /// Lets the AI move while it's its turn.
+    fn play_ai(&mut self) -> Result<()> {
+        while !self.state.is_over()
+            && let Player::Ai { .. } = self.player(self.state.to_move())
+        {
+            let stack = self.ai.as_ref().expect("AI players have a stack");
+            let column = stack
+                .play(self.state.board(), self.state.to_move())
+                .context("The AI failed to choose a move")?;
+            self.state.apply_move(column)?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs games in many channels at once from chat commands. Commands start with the
+/// prefix, `!c4` by default, and columns are numbered from 1 as people count them:
+///
+/// ```text
+/// !c4 play [red|yellow] [difficulty]   a game against the AI
+/// !c4 challenge <user>                 a game against another user, who plays Yellow
+/// !c4 <column>                         drop a piece
+/// !c4 board | resign | help
+/// ```
+pub struct SessionManager {
+    prefix: String,
+    style: TextStyle,
+    sessions: HashMap<String, Session>,
+}
+
+impl SessionManager {
+    pub fn new(style: TextStyle) -> Self {
+        SessionManager {
+            prefix: "!c4".to_string(),
+            style,
+            sessions: HashMap::new(),
+        }
+    }
+
+    pub fn with_prefix(mut self, prefix: &str) -> Self {
+        self.prefix = prefix.to_string();
+        self
+    }
+
+    pub fn session(&self, channel: &str) -> Option<&Session> {
+        self.sessions.get(channel)
+    }
+
+    /// The reply to a message from `user` in `channel`, or None when the message isn't
+    /// a command. Mistakes are answered, not returned as errors.
+    pub fn handle(&mut self, channel: &str, user: &str, text: &str) -> Option<String> {
+        let rest = text.trim().strip_prefix(&self.prefix)?;
+        if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+            return None;
+        }
+        let words: Vec<&str> = rest.split_whitespace().collect();
+        Some(
+            self.command(channel, user, &words)
+                .unwrap_or_else(|e| format!("{e:#}")),
+        )
+    }
+
+    fn command(&mut self, channel: &str, user: &str, words: &[&str]) -> Result<String> {
+        match words {
+            [] | ["help"] => Ok(self.help()),
+            ["play", options @ ..] => {
+                let mut piece = Piece::Red;
+                let mut difficulty = 3;
+                for option in options {
+                    match option.to_ascii_lowercase().as_str() {
+                        "red" => piece = Piece::Red,
+                        "yellow" => piece = Piece::Yellow,
+                        number => {
+                            difficulty = number
+                                .parse()
+                                .ok()
+                                .filter(|&d| d <= MAX_DIFFICULTY)
+                                .with_context(|| {
+                                    format!("Expected red, yellow or a difficulty from 0 to {MAX_DIFFICULTY}, not {option:?}")
+                                })?;
+                        }
+                    }
+                }
+                let human = Player::User(user.to_string());
+                let ai = Player::Ai { difficulty };
+                let (red, yellow) = match piece {
+                    Piece::Red => (human, ai),
+                    _ => (ai, human),
+                };
+                self.start(channel, red, yellow)
+            }
+            ["challenge", opponent] => {
+                let opponent = opponent.trim_start_matches('@');
+                ensure!(opponent != user, "You can't challenge yourself");
+                let red = Player::User(user.to_string());
+                self.start(channel, red, Player::User(opponent.to_string()))
+            }
+            ["board"] => {
+                let session = self.sessions.get(channel).context("No game here")?;
+                Ok(self.status(session))
+            }
+            ["resign"] => {
+                let session = self.sessions.get(channel).context("No game here")?;
+                let piece = self.seat(session, user)?;
+                let winner = session.player(piece.opponent()).mention();
+                self.sessions.remove(channel);
+                Ok(format!("{user} resigned. {winner} wins!"))
+            }
+            [column] if column.parse::<usize>().is_ok() => self.play(channel, user, column),
+            _ => bail!("Unknown command. Try `{} help`", self.prefix),
+        }
+    }
+
+    fn start(&mut self, channel: &str, red: Player, yellow: Player) -> Result<String> {
+        ensure!(
+            !self.sessions.contains_key(channel),
+            "A game is already running here"
+        );
+        let mut session = Session::new(red, yellow);
+        session.play_ai()?;
+        let reply = format!(
+            "{} ({}) vs {} ({})\n{}",
+            session.player(Piece::Red).mention(),
+            Piece::Red.name(),
+            session.player(Piece::Yellow).mention(),
+            Piece::Yellow.name(),
+            self.status(&session)
+        );
+        self.sessions.insert(channel.to_string(), session);
+        Ok(reply)
+    }
+
+    fn play(&mut self, channel: &str, user: &str, column: &str) -> Result<String> {
+        let session = self.sessions.get(channel).context("No game here")?;
+        let piece = self.seat(session, user)?;
+        ensure!(piece == session.state.to_move(), "It's not your turn");
+        let column: usize = column.parse()?;
+        ensure!(
+            (1..=COLUMNS).contains(&column),
+            "Columns are numbered from 1 to {COLUMNS}"
+        );
+        let session = self.sessions.get_mut(channel).expect("checked above");
+        session
+            .state
+            .apply_move(column - 1)
+            .map_err(|_| anyhow::anyhow!("Column {column} is full"))?;
+        session.play_ai()?;
+        let session = &self.sessions[channel];
+        let reply = self.status(session);
+        if session.state.is_over() {
+            self.sessions.remove(channel);
+        }
+        Ok(reply)
+    }
+
+    /// The piece `user` plays in `session`.
+    fn seat(&self, session: &Session, user: &str) -> Result<Piece> {
+        [Piece::Red, Piece::Yellow]
+            .into_iter()
+            .find(|&piece| *session.player(piece) == Player::User(user.to_string()))
+            .context("You aren't playing in this game")
+    }
+
+    /// The board and whose turn it is, or how the game ended.
+    fn status(&self, session: &Session) -> String {
+        let board = render(session.state.board(), self.style);
+        let line = match session.state.result() {
+            Some(GameResult::Winner(winner)) => {
+                format!("{} wins!", session.player(winner).mention())
+            }
+            Some(GameResult::Tie) => "It's a tie.".to_string(),
+            None => {
+                let piece = session.state.to_move();
+                format!(
+                    "{} ({}) to move.",
+                    session.player(piece).mention(),
+                    piece.name()
+                )
+            }
+        };
+        format!("{board}\n{line}")
+    }
+
+    fn help(&self) -> String {
+        let prefix = &self.prefix;
+        format!(
+            "{prefix} play [red|yellow] [0-{MAX_DIFFICULTY}]: play the AI\n\
+             {prefix} challenge <user>: play another user\n\
+             {prefix} <1-{COLUMNS}>: drop a piece\n\
+             {prefix} board | resign"
+        )
+    }
+}
+
+/// A chat message, as a transport hands it over.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChatMessage {
+    pub channel: String,
+    pub user: String,
+    pub text: String,
+}
+
+/// The chat side of a bot: Discord, IRC or anything else that can deliver messages
+/// and post replies.
+pub trait ChatTransport {
+    /// The next message, or None once the chat has gone away.
+    fn receive(&mut self) -> Result<Option<ChatMessage>>;
+    fn send(&mut self, channel: &str, text: &str) -> Result<()>;
+}
+
+/// Answers commands from `transport` until it runs out of messages.
+pub fn run(transport: &mut impl ChatTransport, sessions: &mut SessionManager) -> Result<()> {
+    while let Some(message) = transport.receive()? {
+        if let Some(reply) = sessions.handle(&message.channel, &message.user, &message.text) {
+            tracing::debug!(
+                channel = message.channel,
+                user = message.user,
+                "bot replied"
+            );
+            transport.send(&message.channel, &reply)?;
+        }
+    }
+    Ok(())
+}
+
+/// A transport over lines of `channel user text`, for piping a chat bridge through
+/// stdin and stdout. Replies are written as `channel text`, one line each.
+pub struct LineTransport<R, W> {
+    input: R,
+    output: W,
+}
+
+impl<R: BufRead, W: Write> LineTransport<R, W> {
+    pub fn new(input: R, output: W) -> Self {
+        LineTransport { input, output }
+    }
+}
+
+impl<R: BufRead, W: Write> ChatTransport for LineTransport<R, W> {
+    fn receive(&mut self) -> Result<Option<ChatMessage>> {
+        loop {
+            let mut line = String::new();
+            if self.input.read_line(&mut line)? == 0 {
+                return Ok(None);
+            }
+            let mut parts = line.trim_end().splitn(3, ' ');
+            if let (Some(channel), Some(user), Some(text)) =
+                (parts.next(), parts.next(), parts.next())
+            {
+                return Ok(Some(ChatMessage {
+                    channel: channel.to_string(),
+                    user: user.to_string(),
+                    text: text.to_string(),
+                }));
+            }
+            tracing::warn!(line = line.trim_end(), "ignoring malformed line");
+        }
+    }
+
+    fn send(&mut self, channel: &str, text: &str) -> Result<()> {
+        for line in text.lines() {
+            writeln!(self.output, "{channel} {line}")?;
+        }
+        self.output.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::ROWS;
+
+    #[test]
+    fn renders_for_chat() {
+        let board = *GameState::from_moves("33").unwrap().board();
+        let emoji = render(&board, TextStyle::Emoji);
+        assert_eq!(emoji.lines().count(), ROWS + 1);
+        assert!(emoji.contains("⚪⚪⚪🟡⚪⚪⚪\n⚪⚪⚪🔴⚪⚪⚪\n1️⃣"));
+        let plain = render(&board, TextStyle::Ascii);
+        assert!(plain.contains(". . . Y . . .\n. . . R . . .\n1 2 3 4 5 6 7\n```"));
+    }
+
+    #[test]
+    fn users_play_each_other_per_channel() {
+        let mut bot = SessionManager::new(TextStyle::Ascii);
+        assert_eq!(bot.handle("#a", "ann", "hello"), None);
+        assert_eq!(bot.handle("#a", "ann", "!c4x"), None);
+        bot.handle("#a", "ann", "!c4 challenge @bob").unwrap();
+        bot.handle("#b", "cat", "!c4 challenge dan").unwrap();
+
+        let reply = bot.handle("#a", "bob", "!c4 1").unwrap();
+        assert_eq!(reply, "It's not your turn");
+        let reply = bot.handle("#a", "eve", "!c4 1").unwrap();
+        assert_eq!(reply, "You aren't playing in this game");
+        for (user, column) in [("ann", 1), ("bob", 2), ("ann", 1), ("bob", 2), ("ann", 1)] {
+            bot.handle("#a", user, &format!("!c4 {column}")).unwrap();
+        }
+        bot.handle("#a", "bob", "!c4 2").unwrap();
+        let reply = bot.handle("#a", "ann", "!c4 1").unwrap();
+        assert!(reply.ends_with("ann wins!"), "{reply}");
+        assert!(bot.session("#a").is_none());
+        assert_eq!(bot.session("#b").unwrap().state.history(), &[] as &[usize]);
+
+        let reply = bot.handle("#b", "dan", "!c4 resign").unwrap();
+        assert_eq!(reply, "dan resigned. cat wins!");
+    }
+
+    #[test]
+    fn the_ai_answers_over_a_transport() {
+        let input = "#c ann !c4 play yellow 1\n#c ann !c4 4\n";
+        let mut output = vec![];
+        let mut transport = LineTransport::new(input.as_bytes(), &mut output);
+        let mut bot = SessionManager::new(TextStyle::Emoji);
+        run(&mut transport, &mut bot).unwrap();
+
+        // The AI opened as Red, then answered ann's move
+        let history = bot.session("#c").unwrap().state.history();
+        assert_eq!(history.len(), 3);
+        assert_eq!(history[1], 3);
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("#c the AI (difficulty 1) (Red) vs ann (Yellow)\n"));
+        assert!(output.lines().all(|line| line.starts_with("#c ")));
+    }
+}
@@ -0,0 +1,304 @@
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::board::{Board, COLUMNS, Piece, ROWS};
+use crate::strategy::StrategyDecider;
+
+/// Limits for a `BoundedSearch`. Memory is the transposition table, allocated once up
+/// front; time is the node cap, shared by every iteration of one decision.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchBudget {
+    /// Slots in the transposition table, each `BoundedSearch::ENTRY_BYTES` bytes.
+    pub table_entries: usize,
+    /// Positions searched per move before giving up on deeper iterations.
+    pub max_nodes: u64,
+    /// The deepest iteration, in plies.
+    pub max_depth: usize,
+}
+
+impl SearchBudget {
+    /// The table's size in bytes.
+    pub fn table_bytes(&self) -> usize {
+        self.table_entries * BoundedSearch::ENTRY_BYTES
+    }
+}
+
+/// 64 KiB of table and 20,000 nodes a move, small enough for a microcontroller.
+impl Default for SearchBudget {
+    fn default() -> Self {
+        SearchBudget {
+            table_entries: 4096,
+            max_nodes: 20_000,
+            max_depth: 12,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Bound {
+    Exact,
+    Lower,
+    Upper,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    key: u64,
+    score: i32,
+    depth: u8,
+    bound: Bound,
+    best: u8,
+}
+
+impl Entry {
+    /// An unused slot. Its key is the empty board's, but at depth 0 it only ever
+    /// suggests which move to try first.
+    const EMPTY: Entry = Entry {
+        key: 0,
+        score: 0,
+        depth: 0,
+        bound: Bound::Exact,
+        best: 0,
+    };
+}
+
+/// Alpha-beta search with iterative deepening that never allocates while searching.
+/// It keeps a fixed-size transposition table and stops deepening once the node cap is
+/// reached, playing the best move of the last finished iteration. Positions are scored
+/// by open lines of three, and wins by how few pieces they take. Decisions on other
+/// threads wait for the table.
+pub struct BoundedSearch {
+    budget: SearchBudget,
+    table: Mutex<Vec<Entry>>,
+    nodes: AtomicU64,
+}
+
+impl BoundedSearch {
+    /// Bytes per transposition table slot.
+    pub const ENTRY_BYTES: usize = std::mem::size_of::<Entry>();
+
+    /// Center columns first, which makes alpha-beta cut off much sooner.
+    const MOVE_ORDER: [usize; COLUMNS] = [3, 2, 4, 1, 5, 0, 6];
+    const WIN: i32 = 1_000;
+
+    pub fn new(budget: SearchBudget) -> Self {
+        assert!(
+            budget.table_entries > 0,
+            "The table needs at least one slot"
+        );
+        BoundedSearch {
+            budget,
+            table: Mutex::new(vec![Entry::EMPTY; budget.table_entries]),
+            nodes: AtomicU64::new(0),
+        }
+    }
+
+    /// How many positions the last decision searched.
+    pub fn nodes_searched(&self) -> u64 {
+        self.nodes.load(Ordering::Relaxed)
+    }
+
+    /// The score of `board` for `to_move`, or None once `nodes` reaches the node cap.
+    #[allow(clippy::too_many_arguments)]
+    fn negamax(
+        &self,
+        table: &mut [Entry],
+        nodes: &mut u64,
+        board: &Board,
+        to_move: Piece,
+        depth: usize,
+        mut alpha: i32,
+        beta: i32,
+    ) -> Option<i32> {
+        if *nodes >= self.budget.max_nodes {
+            return None;
+        }
+        *nodes += 1;
+
+        // Only the last move can have won, so the winner is always the opponent
+        if board.has_winner().is_some() {
+            return Some(board.num_pieces_played() as i32 - Self::WIN);
+        }
+        if !Self::MOVE_ORDER
+            .iter()
+            .any(|&col| Self::is_open(board, col))
+        {
+            return Some(0);
+        }
+        if depth == 0 {
+            let ours = board.count_winning_opportunities(to_move) as i32;
+            let theirs = board.count_winning_opportunities(to_move.opponent()) as i32;
+            return Some(ours - theirs);
+        }
+
+        let slot = self.slot(board);
+        let entry = table[slot];
+        let hint = (entry.key == board.as_u64()).then_some(entry.best as usize);
+        if let Some(hint) = hint
+            && entry.depth as usize >= depth
+        {
+            match entry.bound {
+                Bound::Exact => return Some(entry.score),
+                Bound::Lower if entry.score >= beta => return Some(entry.score),
+                Bound::Upper if entry.score <= alpha => return Some(entry.score),
+                _ => {}
+            }
+            debug_assert!(hint < COLUMNS);
+        }
+
+        let original_alpha = alpha;
+        let mut best = (i32::MIN, 0);
+        let ordered = hint.into_iter().chain(Self::MOVE_ORDER);
+        for (i, col) in ordered.enumerate() {
+            // The hint is tried first, so skip it when the usual order reaches it
+            if (i > 0 && Some(col) == hint) || !Self::is_open(board, col) {
+                continue;
+            }
+            let next = board.place(col, to_move);
+            let score = -self.negamax(
+                table,
+                nodes,
+                &next,
+                to_move.opponent(),
+                depth - 1,
+                -beta,
+                -alpha,
+            )?;
+            if score > best.0 {
+                best = (score, col);
+            }
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        let bound = if best.0 <= original_alpha {
+            Bound::Upper
+        } else if best.0 >= beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        table[slot] = Entry {
+            key: board.as_u64(),
+            score: best.0,
+            depth: depth as u8,
+            bound,
+            best: best.1 as u8,
+        };
+        Some(best.0)
+    }
+
+    /// Matches `Board::valid_moves`, which leaves the top row unplayed.
+    fn is_open(board: &Board, col: usize) -> bool {
+        board.get_checked(col, ROWS - 2) == Piece::Empty
+    }
+
+    fn slot(&self, board: &Board) -> usize {
+        // Fibonacci hashing spreads the packed columns over the whole table
+        let hash = board.as_u64().wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        (hash >> 32) as usize % self.budget.table_entries
+    }
+}
+
+impl StrategyDecider for BoundedSearch {
+    /// Always picks, from the deepest iteration that finished within the node cap.
+    fn choose(&self, board: &Board, piece: Piece, options: &[usize]) -> Option<usize> {
+        let mut table = self.table.lock().unwrap();
+        let mut nodes = 0;
+        let mut choice = *options.first()?;
+        for depth in 1..=self.budget.max_depth {
+            let mut best = (-i32::MAX, choice);
+            let mut finished = true;
+            for &col in options {
+                let next = board.place(col, piece);
+                // Only a strictly better score matters, so the window starts at the best
+                let score = self.negamax(
+                    &mut table,
+                    &mut nodes,
+                    &next,
+                    piece.opponent(),
+                    depth - 1,
+                    -i32::MAX,
+                    -best.0,
+                );
+                match score {
+                    Some(score) if -score > best.0 => best = (-score, col),
+                    Some(_) => {}
+                    None => {
+                        finished = false;
+                        break;
+                    }
+                }
+            }
+            if !finished {
+                tracing::debug!(depth, nodes, "node cap reached");
+                break;
+            }
+            choice = best.1;
+            // A forced result won't change with more depth
+            if best.0.abs() > Self::WIN / 2 {
+                break;
+            }
+        }
+        self.nodes.store(nodes, Ordering::Relaxed);
+        Some(choice)
+    }
+
+    fn name(&self) -> &'static str {
+        "BoundedSearch"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategy::{Connect4AI, StrategyStack};
+
+    #[test]
+    fn finds_wins_and_blocks_within_the_cap() {
+        let budget = SearchBudget {
+            table_entries: 64,
+            max_nodes: 2_000,
+            max_depth: 6,
+        };
+        let search = BoundedSearch::new(budget);
+        // Both sides have three in the bottom row, and Red moves first
+        let board = Board::from("!/////RRR BBB");
+        assert_eq!(
+            search.choose(&board, Piece::Red, &board.valid_moves()),
+            Some(3)
+        );
+        assert!(search.nodes_searched() <= budget.max_nodes);
+
+        // Yellow threatens column 0; Red has nothing better than blocking
+        let board = Board::from("!///B/BR/BR    R");
+        assert_eq!(
+            search.choose(&board, Piece::Red, &board.valid_moves()),
+            Some(0)
+        );
+        assert!(search.nodes_searched() <= budget.max_nodes);
+        assert_eq!(search.table.lock().unwrap().len(), 64);
+    }
+
+    #[test]
+    fn bounded_stacks_play_whole_games() {
+        assert_eq!(
+            SearchBudget::default().table_bytes(),
+            4096 * BoundedSearch::ENTRY_BYTES
+        );
+        let budget = SearchBudget {
+            max_nodes: 1_000,
+            ..SearchBudget::default()
+        };
+        // One search, and one table, plays both sides
+        let stack = StrategyStack::bounded(budget);
+        let mut board = Board::new();
+        let mut to_move = Piece::Red;
+        while board.has_winner().is_none() && !board.valid_moves().is_empty() {
+            board = board.place(stack.play(&board, to_move).unwrap(), to_move);
+            to_move = to_move.opponent();
+        }
+    }
+}
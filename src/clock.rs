@@ -0,0 +1,128 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result, ensure};
+
+use crate::board::Piece;
+
+/// Chess-style time control: every player starts with `base` and gains `increment`
+/// after each of their moves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeControl {
+    pub base: Duration,
+    pub increment: Duration,
+}
+
+impl std::str::FromStr for TimeControl {
+    type Err = anyhow::Error;
+
+    /// Parses "BASE+INCREMENT" in seconds, e.g. "60+2" or "0.5+0.01". The increment
+    /// may be left off.
+    fn from_str(s: &str) -> Result<Self> {
+        let (base, increment) = s.split_once('+').unwrap_or((s, "0"));
+        let seconds = |part: &str| -> Result<Duration> {
+            let seconds: f64 = part
+                .trim()
+                .parse()
+                .with_context(|| format!("Invalid number of seconds {:?}", part))?;
+            Duration::try_from_secs_f64(seconds)
+                .with_context(|| format!("Invalid number of seconds {:?}", part))
+        };
+        let control = TimeControl {
+            base: seconds(base)?,
+            increment: seconds(increment)?,
+        };
+        ensure!(!control.base.is_zero(), "The base time must be more than 0");
+        Ok(control)
+    }
+}
+
+impl std::fmt::Display for TimeControl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}s+{}s",
+            self.base.as_secs_f64(),
+            self.increment.as_secs_f64()
+        )
+    }
+}
+
+/// Both players' remaining time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Clock {
+    control: TimeControl,
+    red: Duration,
+    yellow: Duration,
+}
+
+impl Clock {
+    pub fn new(control: TimeControl) -> Self {
+        Clock {
+            control,
+            red: control.base,
+            yellow: control.base,
+        }
+    }
+
+    pub fn remaining(&self, player: Piece) -> Duration {
+        match player {
+            Piece::Red => self.red,
+            Piece::Yellow => self.yellow,
+            Piece::Empty | Piece::Blocker => panic!("{player:?} has no clock"),
+        }
+    }
+
+    /// Takes `elapsed` off `player`'s clock and adds the increment. Returns false if
+    /// their flag fell, in which case the clock stays at zero.
+    pub fn charge(&mut self, player: Piece, elapsed: Duration) -> bool {
+        let remaining = match player {
+            Piece::Red => &mut self.red,
+            Piece::Yellow => &mut self.yellow,
+            Piece::Empty | Piece::Blocker => panic!("{player:?} has no clock"),
+        };
+        match remaining.checked_sub(elapsed) {
+            Some(left) if !left.is_zero() => {
+                *remaining = left + self.control.increment;
+                true
+            }
+            _ => {
+                *remaining = Duration::ZERO;
+                false
+            }
+        }
+    }
+}
+
+/// Formats remaining time as m:ss.t, e.g. "1:05.3".
+pub fn format_clock(time: Duration) -> String {
+    let tenths = time.as_millis() / 100;
+    format!("{}:{:02}.{}", tenths / 600, tenths / 10 % 60, tenths % 10)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_time_controls() {
+        let control: TimeControl = "60+2".parse().unwrap();
+        assert_eq!(control.base, Duration::from_secs(60));
+        assert_eq!(control.increment, Duration::from_secs(2));
+        let control: TimeControl = "0.5".parse().unwrap();
+        assert_eq!(control.base, Duration::from_millis(500));
+        assert!(control.increment.is_zero());
+        assert!("0+1".parse::<TimeControl>().is_err());
+        assert!("fast".parse::<TimeControl>().is_err());
+    }
+
+    #[test]
+    fn charges_moves_and_flags() {
+        let mut clock = Clock::new("10+1".parse().unwrap());
+        assert!(clock.charge(Piece::Red, Duration::from_secs(4)));
+        assert_eq!(clock.remaining(Piece::Red), Duration::from_secs(7));
+        assert_eq!(clock.remaining(Piece::Yellow), Duration::from_secs(10));
+        assert!(!clock.charge(Piece::Yellow, Duration::from_secs(10)));
+        assert_eq!(clock.remaining(Piece::Yellow), Duration::ZERO);
+        assert_eq!(format_clock(Duration::from_millis(65_300)), "1:05.3");
+    }
+}
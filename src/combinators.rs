@@ -0,0 +1,300 @@
+use std::time::Duration;
+
+use crate::board::{Board, Piece};
+use crate::strategy::{Connect4AI, Strategy, StrategyDecider, StrategyStack};
+
+/// Asks each decider in turn and plays the first choice made.
+pub struct FirstOf(pub Vec<Box<dyn StrategyDecider>>);
+
+impl StrategyDecider for FirstOf {
+    fn choose(&self, board: &Board, piece: Piece, options: &[usize]) -> Option<usize> {
+        (self.0.iter()).find_map(|decider| decider.choose(board, piece, options))
+    }
+
+    fn name(&self) -> &'static str {
+        "FirstOf"
+    }
+
+    fn principal_variation(
+        &self,
+        board: &Board,
+        piece: Piece,
+        options: &[usize],
+    ) -> Option<Vec<usize>> {
+        (self.0.iter()).find_map(|decider| decider.principal_variation(board, piece, options))
+    }
+
+    fn is_expensive(&self) -> bool {
+        self.0.iter().any(|decider| decider.is_expensive())
+    }
+}
+
+/// Asks `backup` only when `primary` doesn't choose.
+pub struct Fallback<P, B>(pub P, pub B);
+
+impl<P: StrategyDecider, B: StrategyDecider> StrategyDecider for Fallback<P, B> {
+    fn choose(&self, board: &Board, piece: Piece, options: &[usize]) -> Option<usize> {
+        (self.0.choose(board, piece, options)).or_else(|| self.1.choose(board, piece, options))
+    }
+
+    fn name(&self) -> &'static str {
+        "Fallback"
+    }
+
+    fn principal_variation(
+        &self,
+        board: &Board,
+        piece: Piece,
+        options: &[usize],
+    ) -> Option<Vec<usize>> {
+        (self.0.principal_variation(board, piece, options))
+            .or_else(|| self.1.principal_variation(board, piece, options))
+    }
+
+    fn is_expensive(&self) -> bool {
+        self.0.is_expensive() || self.1.is_expensive()
+    }
+}
+
+/// Lets each stack play the position and picks the move more than half of them
+/// played. Without a majority it doesn't choose, leaving the move to later layers.
+pub struct MajorityVote(pub Vec<StrategyStack>);
+
+impl StrategyDecider for MajorityVote {
+    fn choose(&self, board: &Board, piece: Piece, options: &[usize]) -> Option<usize> {
+        let mut votes = vec![0; options.len()];
+        for stack in &self.0 {
+            let vote = stack.play(board, piece);
+            if let Some(i) = options.iter().position(|&col| Some(col) == vote) {
+                votes[i] += 1;
+            }
+        }
+        tracing::trace!(?options, ?votes, "stacks voted");
+        (options.iter().zip(votes))
+            .find(|&(_, votes)| 2 * votes > self.0.len())
+            .map(|(&col, _)| col)
+    }
+
+    fn name(&self) -> &'static str {
+        "MajorityVote"
+    }
+
+    fn is_expensive(&self) -> bool {
+        self.0.iter().any(StrategyStack::is_expensive)
+    }
+}
+
+/// Plays a different stack in each phase of the game, e.g. an opening book early, cheap
+/// heuristics in the middle and an exact search once few moves are left. Phases start
+/// at a number of pieces played and last until the next one starts.
+pub struct PhasedStrategy {
+    /// Sorted by the number of pieces each phase starts at; the first starts at 0.
+    phases: Vec<(usize, StrategyStack)>,
+}
+
+impl PhasedStrategy {
+    /// `opening` plays until a later phase starts.
+    pub fn new(opening: StrategyStack) -> Self {
+        PhasedStrategy {
+            phases: vec![(0, opening)],
+        }
+    }
+
+    /// Switches to `stack` once `pieces` pieces are on the board, replacing any phase
+    /// that starts there.
+    pub fn then_at(mut self, pieces: usize, stack: StrategyStack) -> Self {
+        let i = (self.phases).partition_point(|&(start, _)| start < pieces);
+        if self
+            .phases
+            .get(i)
+            .is_some_and(|&(start, _)| start == pieces)
+        {
+            self.phases[i].1 = stack;
+        } else {
+            self.phases.insert(i, (pieces, stack));
+        }
+        self
+    }
+
+    /// Only asks `decider` once `pieces` pieces are on the board, for deciders too
+    /// expensive or too weak early on.
+    pub fn only_after(pieces: usize, decider: impl StrategyDecider + 'static) -> Self {
+        let stack = StrategyStack::new(vec![Strategy::Decision(Box::new(decider))]);
+        PhasedStrategy::new(StrategyStack::new(vec![])).then_at(pieces, stack)
+    }
+
+    /// The stack playing `board`'s phase.
+    pub fn stack(&self, board: &Board) -> &StrategyStack {
+        let played = board.num_pieces_played();
+        let i = (self.phases).partition_point(|&(start, _)| start <= played);
+        &self.phases[i - 1].1
+    }
+}
+
+impl StrategyDecider for PhasedStrategy {
+    /// Chooses only when a decider in the current phase's stack does.
+    fn choose(&self, board: &Board, piece: Piece, options: &[usize]) -> Option<usize> {
+        self.stack(board).decide(board, piece, options)
+    }
+
+    /// Named after the first strategy of the last phase, usually the one the phases
+    /// build up to.
+    fn name(&self) -> &'static str {
+        let (_, last) = self.phases.last().expect("there is always an opening");
+        last.strategy_names().next().unwrap_or("PhasedStrategy")
+    }
+
+    fn principal_variation(
+        &self,
+        board: &Board,
+        piece: Piece,
+        options: &[usize],
+    ) -> Option<Vec<usize>> {
+        (self.stack(board))
+            .principal_variation(board, piece)
+            .filter(|line| options.contains(&line[0]))
+    }
+
+    fn is_expensive(&self) -> bool {
+        self.phases.iter().any(|(_, stack)| stack.is_expensive())
+    }
+}
+
+impl Connect4AI for PhasedStrategy {
+    fn play(&self, board: &Board, piece: Piece) -> Option<usize> {
+        self.stack(board).play(board, piece)
+    }
+
+    fn play_timed(&self, board: &Board, piece: Piece, remaining: Duration) -> Option<usize> {
+        self.stack(board).play_timed(board, piece, remaining)
+    }
+
+    fn swaps(&self, board: &Board) -> bool {
+        self.stack(board).swaps(board)
+    }
+
+    fn rotates(&self, board: &Board, piece: Piece) -> bool {
+        self.stack(board).rotates(board, piece)
+    }
+}
+
+impl std::fmt::Display for PhasedStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "PhasedStrategy(")?;
+        for (i, (start, stack)) in self.phases.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}: {}", start, stack)?;
+        }
+        write!(f, ")")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::GameState;
+    use crate::strategy::SearchForWin;
+
+    /// Always picks the same column when it is an option.
+    struct Always(usize);
+
+    impl StrategyDecider for Always {
+        fn choose(&self, _board: &Board, _piece: Piece, options: &[usize]) -> Option<usize> {
+            options.contains(&self.0).then_some(self.0)
+        }
+
+        fn name(&self) -> &'static str {
+            "Always"
+        }
+    }
+
+    fn always(column: usize) -> StrategyStack {
+        StrategyStack::new(vec![Strategy::Decision(Box::new(Always(column)))])
+    }
+
+    #[test]
+    fn first_of_and_fallback_take_the_first_choice() {
+        let board = Board::new();
+        let options = [1, 2, 3];
+        let first_of = FirstOf(vec![
+            Box::new(Always(0)),
+            Box::new(Always(2)),
+            Box::new(Always(3)),
+        ]);
+        assert_eq!(first_of.choose(&board, Piece::Red, &options), Some(2));
+        assert_eq!(
+            Fallback(Always(3), Always(1)).choose(&board, Piece::Red, &options),
+            Some(3)
+        );
+        assert_eq!(
+            Fallback(Always(5), Always(1)).choose(&board, Piece::Red, &options),
+            Some(1)
+        );
+        assert_eq!(
+            Fallback(Always(5), Always(6)).choose(&board, Piece::Red, &options),
+            None
+        );
+
+        // Inside a stack a combinator is just another decider
+        let first_of = FirstOf(vec![Box::new(Always(9)), Box::new(Always(2))]);
+        let stack = StrategyStack::new(vec![Strategy::Decision(Box::new(first_of))]);
+        assert_eq!(stack.evaluate_options(&board, Piece::Red), vec![2]);
+        assert_eq!(stack.principal_variation(&board, Piece::Red), Some(vec![2]));
+    }
+
+    #[test]
+    fn majority_vote_needs_more_than_half() {
+        let board = Board::new();
+        let options = board.valid_moves();
+        let vote = MajorityVote(vec![always(4), always(1), always(4)]);
+        assert_eq!(vote.choose(&board, Piece::Red, &options), Some(4));
+        assert_eq!(vote.choose(&board, Piece::Red, &[1, 2]), None);
+
+        let split = MajorityVote(vec![always(4), always(1), always(4), always(1)]);
+        assert_eq!(split.choose(&board, Piece::Red, &options), None);
+        assert!(!split.is_expensive());
+    }
+
+    #[test]
+    fn phases_switch_by_pieces_played() {
+        let phased = PhasedStrategy::new(always(3))
+            .then_at(20, always(0))
+            .then_at(8, always(1))
+            .then_at(20, always(6));
+        assert_eq!(
+            phased.to_string(),
+            "PhasedStrategy(0: StrategyStack(Always), 8: StrategyStack(Always), 20: StrategyStack(Always))"
+        );
+
+        let play = |moves: &str| {
+            let state = GameState::from_moves(moves).unwrap();
+            phased.play(state.board(), state.to_move())
+        };
+        assert_eq!(play(""), Some(3));
+        assert_eq!(play("0000222"), Some(3));
+        assert_eq!(play("00002222"), Some(1));
+        assert_eq!(play("00000222223333344444"), Some(6));
+    }
+
+    #[test]
+    fn only_after_gates_a_search() {
+        // Red's forced win takes a search to find
+        let board = Board::from("!   RB/   BR/ BRBB/ RBBB/ RRRB/BRRBR R");
+        let options = board.valid_moves();
+        let played = board.num_pieces_played();
+        let search = || SearchForWin::new(1);
+        let early = PhasedStrategy::only_after(played, search());
+        let late = PhasedStrategy::only_after(played + 1, search());
+        assert_eq!(
+            early.choose(&board, Piece::Red, &options),
+            search().choose(&board, Piece::Red, &options)
+        );
+        assert!(early.choose(&board, Piece::Red, &options).is_some());
+        assert_eq!(late.choose(&board, Piece::Red, &options), None);
+        assert_eq!(late.principal_variation(&board, Piece::Red, &options), None);
+        assert_eq!(late.name(), "SearchForWin");
+        assert!(late.is_expensive());
+    }
+}
@@ -0,0 +1,136 @@
+use rand::seq::IndexedRandom;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::board::Piece;
+use crate::game::{GameResult, GameState};
+use crate::strategy_kind::{MAX_DIFFICULTY, StrategyKind, difficulty_stack};
+
+/// The challenge everyone plays on a given day: the same opponent, color and opening.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DailyChallenge {
+    /// The UTC date, as YYYY-MM-DD.
+    pub date: String,
+    pub difficulty: u8,
+    pub opponent: Vec<StrategyKind>,
+    pub human: Piece,
+    /// Moves played for both sides before the human takes over.
+    pub opening: Vec<usize>,
+}
+
+impl DailyChallenge {
+    /// The challenge for today's UTC date.
+    pub fn today() -> Self {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or(0);
+        Self::for_day(seconds / 86_400)
+    }
+
+    /// The challenge for the given number of days since 1970-01-01.
+    pub fn for_day(day: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(day);
+        let difficulty = rng.random_range(2..=MAX_DIFFICULTY);
+        let human = if rng.random_bool(0.5) {
+            Piece::Red
+        } else {
+            Piece::Yellow
+        };
+
+        let mut state = GameState::new();
+        for _ in 0..rng.random_range(2..=4) {
+            let column = *state.legal_moves().choose(&mut rng).unwrap();
+            state.apply_move(column).unwrap();
+        }
+        // Finish the opening on the human's turn
+        if state.to_move() != human {
+            let column = *state.legal_moves().choose(&mut rng).unwrap();
+            state.apply_move(column).unwrap();
+        }
+
+        DailyChallenge {
+            date: civil_date(day),
+            difficulty,
+            opponent: difficulty_stack(difficulty),
+            human,
+            opening: state.history().to_vec(),
+        }
+    }
+
+    /// The position after the opening, with the human to move.
+    pub fn state(&self) -> GameState {
+        GameState::from_history(&self.opening).expect("openings are generated legally")
+    }
+
+    /// A one-line summary to paste to friends. `moves` is how many moves the human
+    /// made after the opening.
+    pub fn share(&self, result: GameResult, moves: usize) -> String {
+        let outcome = match result {
+            GameResult::Winner(winner) if winner == self.human => "Won 🏆",
+            GameResult::Winner(_) => "Lost 💀",
+            GameResult::Tie => "Tied 🤝",
+        };
+        format!(
+            "Connect 4 Daily {} | Level {} as {} | {} in {} moves",
+            self.date,
+            self.difficulty,
+            self.human.name(),
+            outcome,
+            moves
+        )
+    }
+}
+
+/// Formats days since 1970-01-01 as YYYY-MM-DD in the proleptic Gregorian calendar.
+fn civil_date(day: u64) -> String {
+    // Howard Hinnant's days-to-civil algorithm, with eras of 400 years starting in March
+    let z = day as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day_of_month = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day_of_month)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dates_match_the_calendar() {
+        assert_eq!(civil_date(0), "1970-01-01");
+        assert_eq!(civil_date(11_016), "2000-02-29");
+        assert_eq!(civil_date(20_743), "2026-10-17");
+    }
+
+    #[test]
+    fn every_player_gets_the_same_challenge() {
+        let today = DailyChallenge::for_day(20_743);
+        assert_eq!(today, DailyChallenge::for_day(20_743));
+        assert_eq!(today.state().to_move(), today.human);
+        assert!(!today.state().is_over());
+        assert!((2..=5).contains(&today.opening.len()));
+
+        let days: Vec<_> = (20_743..20_753).map(DailyChallenge::for_day).collect();
+        assert!(days.iter().any(|day| day.opening != today.opening));
+    }
+
+    #[test]
+    fn share_string_has_the_outcome_and_move_count() {
+        let challenge = DailyChallenge::for_day(20_743);
+        let share = challenge.share(GameResult::Winner(challenge.human), 12);
+        assert!(share.starts_with("Connect 4 Daily 2026-10-17"));
+        assert!(share.ends_with("Won 🏆 in 12 moves"));
+        assert!(challenge.share(GameResult::Tie, 18).contains("Tied"));
+    }
+}
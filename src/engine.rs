@@ -0,0 +1,181 @@
+use std::io::{BufRead, Write};
+
+use anyhow::{Context, Result};
+
+use crate::game::GameState;
+use crate::strategy::{Connect4AI, StrategyStack};
+use crate::strategy_kind::{StrategyKind, build_stack, parse_stack};
+
+/// The stack used until a GUI picks another one with `setoption`.
+pub const DEFAULT_STRATEGY: &str = "SearchForWin(3) => TriesToWin => AvoidTraps";
+
+/// A line-based protocol modelled on chess's UCI, so GUIs and tournament managers can
+/// drive the AI over stdin/stdout:
+///
+/// ```text
+/// c4i                                 -> id name ..., c4iok
+/// isready                             -> readyok
+/// setoption name Strategy value <spec>
+/// newgame
+/// position startpos [moves 3 3 2 ...]
+/// go                                  -> bestmove <column>
+/// stop                                   (searches are synchronous, so nothing to stop)
+/// quit
+/// ```
+///
+/// Columns are numbered from 0.
+pub struct Engine {
+    state: GameState,
+    strategy: Vec<StrategyKind>,
+    stack: StrategyStack,
+}
+
+impl Engine {
+    pub fn new() -> Self {
+        let strategy = parse_stack(DEFAULT_STRATEGY).expect("The default strategy parses");
+        Engine {
+            state: GameState::new(),
+            stack: build_stack(&strategy),
+            strategy,
+        }
+    }
+
+    /// Handles one command, writing any reply to `out`. Returns false once the
+    /// controller asks us to quit. Bad commands are reported with `info string` so a
+    /// typo doesn't kill the engine mid-tournament.
+    pub fn handle(&mut self, line: &str, out: &mut impl Write) -> Result<bool> {
+        let mut words = line.split_whitespace();
+        let Some(command) = words.next() else {
+            return Ok(true);
+        };
+        let args: Vec<&str> = words.collect();
+        tracing::debug!(command, ?args, "engine command");
+
+        let outcome = match command {
+            "c4i" => {
+                writeln!(out, "id name connect-4 {}", env!("CARGO_PKG_VERSION"))?;
+                writeln!(
+                    out,
+                    "option name Strategy type string default {DEFAULT_STRATEGY}"
+                )?;
+                writeln!(out, "c4iok")?;
+                Ok(())
+            }
+            "isready" => {
+                writeln!(out, "readyok")?;
+                Ok(())
+            }
+            "setoption" => self.set_option(&args),
+            "newgame" => {
+                self.state = GameState::new();
+                Ok(())
+            }
+            "position" => self.set_position(&args),
+            "go" => self.go(out),
+            // Searches finish before we read the next line, so there's never anything to stop
+            "stop" | "ponderhit" => Ok(()),
+            "quit" => return Ok(false),
+            _ => Err(anyhow::anyhow!("Unknown command {command:?}")),
+        };
+
+        if let Err(e) = outcome {
+            writeln!(out, "info string {e:#}")?;
+        }
+        out.flush()?;
+        Ok(true)
+    }
+
+    fn set_option(&mut self, args: &[&str]) -> Result<()> {
+        let line = args.join(" ");
+        let (name, value) = line
+            .strip_prefix("name ")
+            .and_then(|rest| rest.split_once(" value "))
+            .context("Expected `setoption name <name> value <value>`")?;
+        anyhow::ensure!(
+            name.trim().eq_ignore_ascii_case("strategy"),
+            "Unknown option {name:?}"
+        );
+        self.strategy = parse_stack(value)?;
+        self.stack = build_stack(&self.strategy);
+        Ok(())
+    }
+
+    fn set_position(&mut self, args: &[&str]) -> Result<()> {
+        let moves = match args {
+            ["startpos"] => &[][..],
+            ["startpos", "moves", moves @ ..] => moves,
+            _ => anyhow::bail!("Expected `position startpos [moves ...]`"),
+        };
+        let history = moves
+            .iter()
+            .map(|m| m.parse().with_context(|| format!("Invalid column {m:?}")))
+            .collect::<Result<Vec<usize>>>()?;
+        self.state = GameState::from_history(&history)?;
+        Ok(())
+    }
+
+    fn go(&self, out: &mut impl Write) -> Result<()> {
+        anyhow::ensure!(!self.state.is_over(), "The game is already over");
+        let column = (self.stack)
+            .play(self.state.board(), self.state.to_move())
+            .context("The strategy failed to choose a move")?;
+        writeln!(out, "bestmove {column}")?;
+        Ok(())
+    }
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Runs the protocol until `quit` or the end of the input.
+pub fn run(input: impl BufRead, mut output: impl Write) -> Result<()> {
+    let mut engine = Engine::new();
+    for line in input.lines() {
+        if !engine.handle(&line?, &mut output)? {
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(input: &str) -> String {
+        let mut output = Vec::new();
+        run(input.as_bytes(), &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn handshake() {
+        let output = session("c4i\nisready\nquit\ngo\n");
+        let lines: Vec<_> = output.lines().collect();
+        assert!(lines[0].starts_with("id name connect-4"));
+        assert_eq!(lines.last(), Some(&"readyok"));
+        assert!(lines.contains(&"c4iok"));
+    }
+
+    #[test]
+    fn blocks_a_win_from_a_position() {
+        let output = session(
+            "setoption name Strategy value TriesToWin\nposition startpos moves 0 1 0 1 0\ngo\n",
+        );
+        assert_eq!(output.trim(), "bestmove 0");
+    }
+
+    #[test]
+    fn reports_bad_commands_and_keeps_going() {
+        let output = session(
+            "fly\nposition startpos moves 9\nsetoption name Strategy value Nope\nisready\n",
+        );
+        let lines: Vec<_> = output.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[..3].iter().all(|l| l.starts_with("info string")));
+        assert_eq!(lines[3], "readyok");
+    }
+}
@@ -0,0 +1,170 @@
+use anyhow::{Context, Result, ensure};
+
+use crate::board::Piece;
+use crate::game::{GameResult, GameState};
+use crate::neural::{ENCODED_LEN, encode};
+use crate::strategy::Connect4AI;
+
+/// What the agent sees: the board encoded for its side, as `neural::encode` writes it.
+pub type Observation = [f32; ENCODED_LEN];
+
+/// A gym-style environment for reinforcement learning: an outside agent plays one color
+/// against an AI, one move per `step`. Rewards are 1 for a win, -1 for a loss and 0
+/// for anything else, ties included.
+pub struct Connect4Env {
+    opponent: Box<dyn Connect4AI>,
+    agent: Piece,
+    state: GameState,
+}
+
+impl Connect4Env {
+    /// The agent plays `agent` and `opponent` the other color. Call `reset` to start.
+    pub fn new(opponent: Box<dyn Connect4AI>, agent: Piece) -> Self {
+        Connect4Env {
+            opponent,
+            agent,
+            state: GameState::new(),
+        }
+    }
+
+    pub fn agent(&self) -> Piece {
+        self.agent
+    }
+
+    pub fn state(&self) -> &GameState {
+        &self.state
+    }
+
+    /// Starts a new game. When the agent plays Yellow, the opponent's first move is
+    /// already made.
+    pub fn reset(&mut self) -> Result<Observation> {
+        self.state = GameState::new();
+        if self.agent == Piece::Yellow {
+            self.opponent_move()?;
+        }
+        Ok(self.observation())
+    }
+
+    /// Plays `action`, a column, for the agent, then the opponent's reply unless the
+    /// game just ended. Returns what the agent sees next, its reward, and whether the
+    /// game is over. If the opponent can't reply, the agent's move is taken back before
+    /// the error is returned, so the env is left as it was.
+    pub fn step(&mut self, action: usize) -> Result<(Observation, f32, bool)> {
+        ensure!(!self.state.is_over(), "The game is over; call reset");
+        ensure!(
+            self.state.to_move() == self.agent,
+            "It isn't the agent's turn; call reset"
+        );
+        let before = self.state.clone();
+        self.state.apply_move(action)?;
+        if !self.state.is_over()
+            && let Err(err) = self.opponent_move()
+        {
+            self.state = before;
+            return Err(err);
+        }
+        let reward = match self.state.result() {
+            Some(GameResult::Winner(winner)) if winner == self.agent => 1.0,
+            Some(GameResult::Winner(_)) => -1.0,
+            Some(GameResult::Tie) | None => 0.0,
+        };
+        Ok((self.observation(), reward, self.state.is_over()))
+    }
+
+    /// The columns the agent may play now.
+    pub fn legal_actions(&self) -> Vec<usize> {
+        self.state.legal_moves()
+    }
+
+    fn opponent_move(&mut self) -> Result<()> {
+        // Handicapped opponents may pass, which hands the move back to the agent
+        let opponent = self.agent.opponent();
+        if self.opponent.passes(self.state.board(), opponent) {
+            return self.state.pass().map(|_| ());
+        }
+        let column = (self.opponent.play(self.state.board(), opponent))
+            .context("The opponent has no move")?;
+        self.state.apply_move(column)?;
+        Ok(())
+    }
+
+    fn observation(&self) -> Observation {
+        encode(self.state.board(), self.agent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Board;
+
+    /// Always plays the rightmost open column.
+    struct Rightmost;
+
+    impl Connect4AI for Rightmost {
+        fn play(&self, board: &Board, _piece: Piece) -> Option<usize> {
+            board.valid_moves().last().copied()
+        }
+    }
+
+    impl std::fmt::Display for Rightmost {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "Rightmost")
+        }
+    }
+
+    #[test]
+    fn agent_steps_to_a_win() {
+        let mut env = Connect4Env::new(Box::new(Rightmost), Piece::Red);
+        env.reset().unwrap();
+        for _ in 0..3 {
+            let (_, reward, done) = env.step(0).unwrap();
+            assert_eq!((reward, done), (0.0, false));
+        }
+        let (observation, reward, done) = env.step(0).unwrap();
+        assert_eq!((reward, done), (1.0, true));
+        // The agent's own pieces are in the first plane
+        assert_eq!(observation.iter().take(ENCODED_LEN / 2).sum::<f32>(), 4.0);
+        assert!(env.step(1).is_err());
+    }
+
+    /// Never finds a move.
+    struct Stuck;
+
+    impl Connect4AI for Stuck {
+        fn play(&self, _board: &Board, _piece: Piece) -> Option<usize> {
+            None
+        }
+    }
+
+    impl std::fmt::Display for Stuck {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "Stuck")
+        }
+    }
+
+    #[test]
+    fn failed_opponent_replies_leave_the_env_unchanged() {
+        let mut env = Connect4Env::new(Box::new(Stuck), Piece::Red);
+        env.reset().unwrap();
+        let err = env.step(3).unwrap_err();
+        assert_eq!(err.to_string(), "The opponent has no move");
+        // The agent's move was taken back, so it's still the agent's turn
+        assert!(env.state().history().is_empty());
+        assert_eq!(env.state().to_move(), Piece::Red);
+        assert_eq!(
+            env.step(3).unwrap_err().to_string(),
+            "The opponent has no move"
+        );
+    }
+
+    #[test]
+    fn opponent_moves_first_for_a_yellow_agent() {
+        let mut env = Connect4Env::new(Box::new(Rightmost), Piece::Yellow);
+        let observation = env.reset().unwrap();
+        assert_eq!(env.state().history(), &[6]);
+        assert_eq!(observation[ENCODED_LEN / 2 + 6], 1.0);
+        assert!(env.step(9).is_err());
+        assert_eq!(env.legal_actions().len(), 7);
+    }
+}
@@ -0,0 +1,248 @@
+use rand::Rng;
+use rand::seq::IndexedRandom;
+
+use crate::board::Piece;
+use crate::game::{GameState, play_game};
+use crate::strategy_kind::{StrategyKind, StrategyRegistry, build_stack};
+
+/// A strategy stack as an ordered list of layers and deciders.
+pub type Genome = Vec<StrategyKind>;
+
+/// Settings for a run of [`evolve`].
+#[derive(Debug, Clone, Copy)]
+pub struct EvolveConfig {
+    pub population: usize,
+    pub generations: usize,
+    /// Games each pair of genomes plays against each other per generation.
+    pub games_per_pair: usize,
+    /// The longest stack a genome may grow into.
+    pub max_genes: usize,
+    /// The deepest `SearchForWin`/`SearchForWinCache`/`Expectiminimax` a mutation may
+    /// produce.
+    pub max_depth: usize,
+}
+
+impl Default for EvolveConfig {
+    fn default() -> Self {
+        EvolveConfig {
+            population: 12,
+            generations: 10,
+            games_per_pair: 4,
+            max_genes: 6,
+            max_depth: 4,
+        }
+    }
+}
+
+/// A genome and the fraction of points it scored in its generation's round robin.
+#[derive(Debug, Clone)]
+pub struct Scored {
+    pub genome: Genome,
+    pub score: f64,
+}
+
+/// How many of the best genomes survive into the next generation unchanged.
+const ELITES: usize = 2;
+
+/// Plays every genome against every other genome `games_per_pair` times, alternating
+/// who moves first. Wins count fully and ties count half. Returns the genomes sorted
+/// best first.
+pub fn round_robin(population: &[Genome], games_per_pair: usize) -> Vec<Scored> {
+    let stacks: Vec<_> = population.iter().map(|g| build_stack(g)).collect();
+
+    let mut points = vec![0.0; population.len()];
+    let mut games = vec![0usize; population.len()];
+    for a in 0..population.len() {
+        for b in (a + 1)..population.len() {
+            for i in 0..games_per_pair {
+                let (red, yellow) = if i % 2 == 0 { (a, b) } else { (b, a) };
+                let game = play_game(&stacks[red], &stacks[yellow], &GameState::new(), &mut ());
+                match game.and_then(|game| game.winner()) {
+                    Some(Piece::Red) => points[red] += 1.0,
+                    Some(_) => points[yellow] += 1.0,
+                    None => {
+                        points[a] += 0.5;
+                        points[b] += 0.5;
+                    }
+                }
+                games[a] += 1;
+                games[b] += 1;
+            }
+        }
+    }
+
+    let mut scored: Vec<Scored> = population
+        .iter()
+        .zip(points.iter().zip(&games))
+        .map(|(genome, (&points, &games))| Scored {
+            genome: genome.clone(),
+            score: if games == 0 {
+                0.0
+            } else {
+                points / games as f64
+            },
+        })
+        .collect();
+    scored.sort_by(|a, b| b.score.total_cmp(&a.score));
+    scored
+}
+
+/// A random strategy with its default parameters, searching no deeper than `max_depth`.
+fn random_gene(rng: &mut impl Rng, max_depth: usize) -> StrategyKind {
+    let gene = StrategyRegistry::strategies().choose(rng).unwrap().default;
+    match gene.depth() {
+        Some(depth) => gene
+            .with_depth(depth.min(max_depth.max(1)))
+            .expect("the gene has a depth"),
+        None => gene,
+    }
+}
+
+/// A random genome of one to `config.max_genes` strategies.
+pub fn random_genome(rng: &mut impl Rng, config: &EvolveConfig) -> Genome {
+    let len = rng.random_range(1..=config.max_genes.max(1));
+    (0..len)
+        .map(|_| random_gene(rng, config.max_depth))
+        .collect()
+}
+
+/// One-point crossover: the head of `a` followed by the tail of `b`.
+pub fn crossover(rng: &mut impl Rng, a: &Genome, b: &Genome, config: &EvolveConfig) -> Genome {
+    let cut_a = rng.random_range(0..=a.len());
+    let cut_b = rng.random_range(0..=b.len());
+    let mut child: Genome = a[..cut_a].iter().chain(&b[cut_b..]).copied().collect();
+    child.truncate(config.max_genes.max(1));
+    if child.is_empty() {
+        child.push(random_gene(rng, config.max_depth));
+    }
+    child
+}
+
+/// Applies one random change: insert, remove, swap two neighbours, or nudge a search depth.
+pub fn mutate(rng: &mut impl Rng, genome: &mut Genome, config: &EvolveConfig) {
+    match rng.random_range(0..4) {
+        0 if genome.len() < config.max_genes => {
+            let at = rng.random_range(0..=genome.len());
+            genome.insert(at, random_gene(rng, config.max_depth));
+        }
+        1 if genome.len() > 1 => {
+            let at = rng.random_range(0..genome.len());
+            genome.remove(at);
+        }
+        2 if genome.len() > 1 => {
+            let at = rng.random_range(0..genome.len() - 1);
+            genome.swap(at, at + 1);
+        }
+        _ => {
+            let at = rng.random_range(0..genome.len());
+            let up = rng.random_bool(0.5);
+            if let StrategyKind::SearchForWin { depth }
+            | StrategyKind::SearchForWinCache { depth }
+            | StrategyKind::Expectiminimax { depth } = &mut genome[at]
+            {
+                *depth = if up {
+                    *depth + 1
+                } else {
+                    depth.saturating_sub(1)
+                }
+                .clamp(1, config.max_depth.max(1));
+            }
+        }
+    }
+}
+
+/// Evolves a random population for `config.generations` generations. The best genomes
+/// are kept as-is; the rest of each generation is bred from the top half by crossover
+/// and mutation. `on_generation` receives the generation number and its ranked scores.
+/// Returns the final generation's ranking.
+pub fn evolve(
+    config: &EvolveConfig,
+    mut on_generation: impl FnMut(usize, &[Scored]),
+) -> Vec<Scored> {
+    let mut rng = rand::rng();
+    let size = config.population.max(ELITES + 1);
+    let mut population: Vec<Genome> = (0..size).map(|_| random_genome(&mut rng, config)).collect();
+
+    let mut ranked = round_robin(&population, config.games_per_pair);
+    on_generation(0, &ranked);
+
+    for generation in 1..=config.generations {
+        let parents = &ranked[..ranked.len().div_ceil(2)];
+        population = ranked
+            .iter()
+            .take(ELITES)
+            .map(|s| s.genome.clone())
+            .collect();
+        while population.len() < size {
+            let a = &parents.choose(&mut rng).unwrap().genome;
+            let b = &parents.choose(&mut rng).unwrap().genome;
+            let mut child = crossover(&mut rng, a, b, config);
+            mutate(&mut rng, &mut child, config);
+            population.push(child);
+        }
+
+        ranked = round_robin(&population, config.games_per_pair);
+        on_generation(generation, &ranked);
+    }
+
+    ranked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crossover_and_mutation_keep_genomes_valid() {
+        let mut rng = rand::rng();
+        let config = EvolveConfig {
+            max_genes: 3,
+            max_depth: 2,
+            ..EvolveConfig::default()
+        };
+        for _ in 0..200 {
+            let a = random_genome(&mut rng, &config);
+            let b = random_genome(&mut rng, &config);
+            let mut child = crossover(&mut rng, &a, &b, &config);
+            mutate(&mut rng, &mut child, &config);
+            assert!(!child.is_empty() && child.len() <= config.max_genes);
+            for gene in child {
+                if let StrategyKind::SearchForWin { depth }
+                | StrategyKind::SearchForWinCache { depth }
+                | StrategyKind::Expectiminimax { depth } = gene
+                {
+                    assert!((1..=config.max_depth).contains(&depth));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn round_robin_prefers_the_stronger_stack() {
+        let population = vec![
+            vec![],
+            vec![StrategyKind::TriesToWin, StrategyKind::AvoidTraps],
+        ];
+        let ranked = round_robin(&population, 20);
+        assert_eq!(ranked.len(), 2);
+        assert_eq!(ranked[0].genome, population[1]);
+        assert!((ranked[0].score + ranked[1].score - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn evolve_reports_every_generation() {
+        let config = EvolveConfig {
+            population: 4,
+            generations: 2,
+            games_per_pair: 2,
+            max_genes: 2,
+            max_depth: 1,
+        };
+        let mut seen = vec![];
+        let ranked = evolve(&config, |generation, ranked| {
+            seen.push((generation, ranked.len()))
+        });
+        assert_eq!(seen, vec![(0, 4), (1, 4), (2, 4)]);
+        assert_eq!(ranked.len(), 4);
+    }
+}
@@ -0,0 +1,129 @@
+use crate::board::{Board, Piece};
+use crate::strategy::{StrategyDecider, StrategyStack};
+
+/// Plays for the best expected result against an opponent known to play like a given
+/// stack, instead of assuming perfect defense. Our moves take the best value and the
+/// opponent's are averaged over the stack's move probabilities. A win is worth 1 and
+/// a loss -1; anything unresolved within `depth` of our moves is worth 0.
+pub struct Expectiminimax {
+    depth: usize,
+    model: StrategyStack,
+}
+
+impl Expectiminimax {
+    /// Expected values closer than this are the same up to rounding.
+    const TOLERANCE: f64 = 1e-9;
+
+    /// Models the opponent as playing uniformly random legal moves.
+    pub fn new(depth: usize) -> Self {
+        Expectiminimax {
+            depth,
+            model: StrategyStack::new(vec![]),
+        }
+    }
+
+    /// Models the opponent as `model`.
+    pub fn with_model(mut self, model: StrategyStack) -> Self {
+        self.model = model;
+        self
+    }
+
+    /// The value of `board` to `piece` after its move, with the opponent to move.
+    fn expected_value(&self, board: &Board, piece: Piece, depth: usize) -> f64 {
+        if let Some(value) = Self::final_value(board, piece) {
+            return value;
+        }
+        let opponent = piece.opponent();
+        (self.model.move_probabilities(board, opponent).into_iter())
+            .map(|(col, chance)| {
+                chance * self.best_value(&board.place(col, opponent), piece, depth)
+            })
+            .sum()
+    }
+
+    /// The value of `board` to `piece` with it to move.
+    fn best_value(&self, board: &Board, piece: Piece, depth: usize) -> f64 {
+        if let Some(value) = Self::final_value(board, piece) {
+            return value;
+        }
+        if depth == 0 {
+            return 0.0;
+        }
+        (board.valid_moves().into_iter())
+            .map(|col| self.expected_value(&board.place(col, piece), piece, depth - 1))
+            .fold(f64::MIN, f64::max)
+    }
+
+    fn final_value(board: &Board, piece: Piece) -> Option<f64> {
+        match board.has_winner() {
+            Some(winner) if winner == piece => Some(1.0),
+            Some(_) => Some(-1.0),
+            None if board.valid_moves().is_empty() => Some(0.0),
+            None => None,
+        }
+    }
+}
+
+impl StrategyDecider for Expectiminimax {
+    /// Picks the option with the best expected value, or none when they are all worth
+    /// the same.
+    fn choose(&self, board: &Board, piece: Piece, options: &[usize]) -> Option<usize> {
+        let depth = self.depth.saturating_sub(1);
+        let values: Vec<f64> = (options.iter())
+            .map(|&col| self.expected_value(&board.place(col, piece), piece, depth))
+            .collect();
+        let best = values.iter().copied().fold(f64::MIN, f64::max);
+        if values.iter().all(|&value| best - value < Self::TOLERANCE) {
+            return None;
+        }
+        let (&col, _) =
+            (options.iter().zip(&values)).find(|&(_, &value)| best - value < Self::TOLERANCE)?;
+        tracing::debug!(column = col, value = best, "best expected move");
+        Some(col)
+    }
+
+    fn name(&self) -> &'static str {
+        "Expectiminimax"
+    }
+
+    fn is_expensive(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategy_kind::{StrategyKind, build_stack};
+
+    #[test]
+    fn takes_wins() {
+        let board = (0..3).fold(Board::new(), |board, _| {
+            board.place(0, Piece::Red).place(1, Piece::Yellow)
+        });
+        let decider = Expectiminimax::new(1);
+        assert_eq!(
+            decider.choose(&board, Piece::Red, &board.valid_moves()),
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn gambles_only_against_opponents_that_miss_threats() {
+        // Red to move with two on the bottom row, Yellow next to them: a third piece
+        // makes a single threat, which a random opponent will usually miss
+        let board = Board::new()
+            .place(1, Piece::Red)
+            .place(0, Piece::Yellow)
+            .place(2, Piece::Red)
+            .place(6, Piece::Yellow);
+        let options = board.valid_moves();
+        let random = Expectiminimax::new(2);
+        let choice = random.choose(&board, Piece::Red, &options).unwrap();
+        assert!([3, 4].contains(&choice), "{choice}");
+
+        let blocker = build_stack(&[StrategyKind::TriesToWin]);
+        let careful = Expectiminimax::new(2).with_model(blocker);
+        assert_eq!(careful.choose(&board, Piece::Red, &options), None);
+    }
+}
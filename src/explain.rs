@@ -0,0 +1,178 @@
+use std::fmt;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Whether a step of a stack came from a layer or a decider.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum StepKind {
+    Layer,
+    Decider,
+}
+
+/// What one of a stack's strategies did while it picked a move.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TraceStep {
+    pub strategy: String,
+    pub kind: StepKind,
+    /// The moves the strategy was given.
+    pub input: Vec<usize>,
+    /// What it made of them: a layer's survivors, or a decider's pick. Empty when a
+    /// layer would have pruned every move, so was ignored, or when a decider passed.
+    pub output: Vec<usize>,
+    /// Each input move's score, from layers in the scoring selections.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub scores: Option<Vec<f64>>,
+    /// Whether an expensive decider was skipped because the clock was low.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub skipped: bool,
+}
+
+/// How a stack picked its move: each strategy's input and output options in turn, and
+/// the moves it was left with. Built by `StrategyStack::explain`, and printed one line
+/// per strategy.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DecisionTrace {
+    pub steps: Vec<TraceStep>,
+    /// The moves the stack rates best, as from `evaluate_options`.
+    pub options: Vec<usize>,
+}
+
+impl TraceStep {
+    fn new(strategy: &str, kind: StepKind, input: &[usize], output: Vec<usize>) -> Self {
+        TraceStep {
+            strategy: strategy.to_string(),
+            kind,
+            input: input.to_vec(),
+            output,
+            scores: None,
+            skipped: false,
+        }
+    }
+
+    pub(crate) fn pruned(layer: &str, input: &[usize], output: &[usize]) -> Self {
+        Self::new(layer, StepKind::Layer, input, output.to_vec())
+    }
+
+    pub(crate) fn scored(layer: &str, input: &[usize], scores: &[f64]) -> Self {
+        TraceStep {
+            scores: Some(scores.to_vec()),
+            ..Self::new(layer, StepKind::Layer, input, input.to_vec())
+        }
+    }
+
+    pub(crate) fn decided(decider: &str, input: &[usize], choice: Option<usize>) -> Self {
+        Self::new(
+            decider,
+            StepKind::Decider,
+            input,
+            choice.into_iter().collect(),
+        )
+    }
+
+    pub(crate) fn skipped(decider: &str, input: &[usize]) -> Self {
+        TraceStep {
+            skipped: true,
+            ..Self::new(decider, StepKind::Decider, input, vec![])
+        }
+    }
+}
+
+impl DecisionTrace {
+    /// The decider that picked the move, if one did.
+    pub fn decider(&self) -> Option<&str> {
+        (self.steps.iter())
+            .find(|step| step.kind == StepKind::Decider && !step.output.is_empty())
+            .map(|step| step.strategy.as_str())
+    }
+}
+
+fn columns(columns: &[usize]) -> String {
+    let columns: Vec<String> = columns.iter().map(usize::to_string).collect();
+    columns.join(" ")
+}
+
+impl fmt::Display for TraceStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = &self.strategy;
+        if let Some(scores) = &self.scores {
+            let scores: Vec<String> = (self.input.iter().zip(scores))
+                .map(|(col, score)| format!("{col}:{score:.2}"))
+                .collect();
+            return write!(f, "{name:<16} scores {}", scores.join(" "));
+        }
+        match (self.kind, self.output.as_slice()) {
+            (StepKind::Decider, _) if self.skipped => write!(f, "{name:<16} skipped, low on time"),
+            (StepKind::Decider, []) => write!(f, "{name:<16} has no pick"),
+            (StepKind::Decider, output) => write!(f, "{name:<16} picks {}", columns(output)),
+            (StepKind::Layer, []) => write!(f, "{name:<16} would prune every move, so is ignored"),
+            (StepKind::Layer, output) if output == self.input => {
+                write!(f, "{name:<16} keeps all of {}", columns(output))
+            }
+            (StepKind::Layer, output) => {
+                let pruned: Vec<usize> = (self.input.iter())
+                    .filter(|col| !output.contains(col))
+                    .copied()
+                    .collect();
+                let (pruned, left) = (columns(&pruned), columns(output));
+                write!(f, "{name:<16} prunes {pruned}, leaving {left}")
+            }
+        }
+    }
+}
+
+impl fmt::Display for DecisionTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for step in &self.steps {
+            writeln!(f, "{step}")?;
+        }
+        match self.options.as_slice() {
+            [column] => write!(f, "Plays {column}"),
+            options => write!(f, "Left to chance: {}", columns(options)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_the_deciding_strategy() {
+        let trace = DecisionTrace {
+            steps: vec![
+                TraceStep::pruned("AvoidTraps", &[0, 1, 2, 3], &[1, 3]),
+                TraceStep::decided("SearchForWin", &[1, 3], None),
+                TraceStep::decided("TriesToWin", &[1, 3], Some(3)),
+            ],
+            options: vec![3],
+        };
+        assert_eq!(trace.decider(), Some("TriesToWin"));
+        let text = trace.to_string();
+        assert!(
+            text.contains("AvoidTraps       prunes 0 2, leaving 1 3"),
+            "{text}"
+        );
+        assert!(text.ends_with("Plays 3"), "{text}");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn round_trips_through_json() {
+        let trace = DecisionTrace {
+            steps: vec![TraceStep::decided("TriesToWin", &[2, 4], Some(4))],
+            options: vec![4],
+        };
+        let json = serde_json::to_string(&trace).unwrap();
+        assert!(json.contains(r#""kind":"decider""#), "{json}");
+        assert!(!json.contains("scores"), "{json}");
+        assert_eq!(serde_json::from_str::<DecisionTrace>(&json).unwrap(), trace);
+    }
+}
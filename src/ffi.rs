@@ -0,0 +1,275 @@
+use std::panic::{AssertUnwindSafe, catch_unwind};
+
+use crate::board::Piece;
+use crate::env::Connect4Env;
+use crate::game::{GameResult, GameState};
+use crate::neural::ENCODED_LEN;
+use crate::strategy::Connect4AI;
+use crate::strategy_kind::{build_stack, difficulty_stack};
+
+/// An opaque game handle. Create it with `c4_game_new` and release it with `c4_game_free`.
+pub struct C4Game {
+    state: GameState,
+}
+
+/// The state of a game as seen from C.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum C4Status {
+    InProgress = 0,
+    RedWins = 1,
+    YellowWins = 2,
+    Tie = 3,
+    /// A null handle, or something went wrong inside the library.
+    Error = -1,
+}
+
+/// Runs `f`, turning a null handle or a panic into `fallback` so nothing unwinds into C.
+fn with_handle<H, T>(handle: *mut H, fallback: T, f: impl FnOnce(&mut H) -> T) -> T {
+    // SAFETY: the caller promises `handle` came from its `_new` function and hasn't
+    // been freed
+    let Some(handle) = (unsafe { handle.as_mut() }) else {
+        return fallback;
+    };
+    catch_unwind(AssertUnwindSafe(|| f(handle))).unwrap_or(fallback)
+}
+
+/// Starts a new game with Red to move. Free it with `c4_game_free`.
+#[unsafe(no_mangle)]
+pub extern "C" fn c4_game_new() -> *mut C4Game {
+    Box::into_raw(Box::new(C4Game {
+        state: GameState::new(),
+    }))
+}
+
+/// Frees a game. Passing null is allowed and does nothing.
+///
+/// # Safety
+/// `game` must be null or a handle from `c4_game_new` that hasn't been freed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn c4_game_free(game: *mut C4Game) {
+    if !game.is_null() {
+        // SAFETY: guaranteed by the caller
+        drop(unsafe { Box::from_raw(game) });
+    }
+}
+
+/// Plays `column` (numbered from 0) for the side to move. Returns 0 on success and -1
+/// if the move is illegal or the handle is null.
+///
+/// # Safety
+/// `game` must be null or a live handle from `c4_game_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn c4_game_apply_move(game: *mut C4Game, column: u32) -> i32 {
+    with_handle(game, -1, |game| {
+        match game.state.apply_move(column as usize) {
+            Ok(_) => 0,
+            Err(_) => -1,
+        }
+    })
+}
+
+/// Whether the game is still going, and if not, how it ended.
+///
+/// # Safety
+/// `game` must be null or a live handle from `c4_game_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn c4_game_status(game: *mut C4Game) -> C4Status {
+    with_handle(game, C4Status::Error, |game| match game.state.result() {
+        None => C4Status::InProgress,
+        Some(GameResult::Winner(Piece::Red)) => C4Status::RedWins,
+        Some(GameResult::Winner(_)) => C4Status::YellowWins,
+        Some(GameResult::Tie) => C4Status::Tie,
+    })
+}
+
+/// The side to move: 1 for Red, 2 for Yellow, or -1 for a null handle.
+///
+/// # Safety
+/// `game` must be null or a live handle from `c4_game_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn c4_game_to_move(game: *mut C4Game) -> i32 {
+    with_handle(game, -1, |game| match game.state.to_move() {
+        Piece::Red => 1,
+        _ => 2,
+    })
+}
+
+/// The column the AI would play for the side to move, at a difficulty from 0 (random)
+/// to 4. Does not play the move. Returns -1 if the game is over or the handle is null.
+///
+/// # Safety
+/// `game` must be null or a live handle from `c4_game_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn c4_game_ai_move(game: *mut C4Game, difficulty: u8) -> i32 {
+    with_handle(game, -1, |game| {
+        if game.state.is_over() {
+            return -1;
+        }
+        let ai = build_stack(&difficulty_stack(difficulty));
+        ai.play(game.state.board(), game.state.to_move())
+            .map_or(-1, |column| column as i32)
+    })
+}
+
+// The C docs promise 84-float observations: two planes of 6 rows by 7 columns
+const _: () = assert!(ENCODED_LEN == 84);
+
+/// An opaque reinforcement learning environment. Create it with `c4_env_new` and release
+/// it with `c4_env_free`.
+pub struct C4Env {
+    env: Connect4Env,
+}
+
+/// Copies `observation` out to C, unless the pointer is null.
+///
+/// # Safety
+/// `out` must be null or point to room for 84 floats.
+unsafe fn write_observation(observation: &[f32; ENCODED_LEN], out: *mut f32) -> bool {
+    if out.is_null() {
+        return false;
+    }
+    // SAFETY: guaranteed by the caller
+    unsafe { std::ptr::copy_nonoverlapping(observation.as_ptr(), out, ENCODED_LEN) };
+    true
+}
+
+/// Creates an environment where the caller's agent plays `agent` (1 for Red, 2 for
+/// Yellow) against the AI at a difficulty from 0 (random) to 4. Returns null for any
+/// other `agent`. Call `c4_env_reset` to start a game, and free it with `c4_env_free`.
+#[unsafe(no_mangle)]
+pub extern "C" fn c4_env_new(agent: i32, difficulty: u8) -> *mut C4Env {
+    let agent = match agent {
+        1 => Piece::Red,
+        2 => Piece::Yellow,
+        _ => return std::ptr::null_mut(),
+    };
+    let opponent = build_stack(&difficulty_stack(difficulty));
+    Box::into_raw(Box::new(C4Env {
+        env: Connect4Env::new(Box::new(opponent), agent),
+    }))
+}
+
+/// Frees an environment. Passing null is allowed and does nothing.
+///
+/// # Safety
+/// `env` must be null or a handle from `c4_env_new` that hasn't been freed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn c4_env_free(env: *mut C4Env) {
+    if !env.is_null() {
+        // SAFETY: guaranteed by the caller
+        drop(unsafe { Box::from_raw(env) });
+    }
+}
+
+/// Starts a new game and writes the agent's first observation to `observation`: 84
+/// floats in two planes of 6 rows by 7 columns, the agent's pieces first, each plane
+/// with the bottom row first. Returns 0 on success and -1 if a pointer is null.
+///
+/// # Safety
+/// `env` must be null or a live handle from `c4_env_new`, and `observation` must be
+/// null or point to room for 84 floats.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn c4_env_reset(env: *mut C4Env, observation: *mut f32) -> i32 {
+    with_handle(env, -1, |env| match env.env.reset() {
+        // SAFETY: guaranteed by the caller
+        Ok(next) if unsafe { write_observation(&next, observation) } => 0,
+        _ => -1,
+    })
+}
+
+/// Plays `action`, a column, for the agent and then the opponent's reply. Writes the
+/// next observation and the agent's reward: 1 for a win, -1 for a loss, 0 otherwise.
+/// Returns 1 if the game is over, 0 if it goes on, and -1 if the action is illegal,
+/// the game is already over or a pointer is null.
+///
+/// # Safety
+/// `env` must be null or a live handle from `c4_env_new`, `observation` must be null or
+/// point to room for 84 floats, and `reward` must be null or point
+/// to a float.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn c4_env_step(
+    env: *mut C4Env,
+    action: u32,
+    observation: *mut f32,
+    reward: *mut f32,
+) -> i32 {
+    if observation.is_null() || reward.is_null() {
+        return -1;
+    }
+    with_handle(env, -1, |env| match env.env.step(action as usize) {
+        Ok((next, value, done)) => {
+            // SAFETY: guaranteed by the caller, and neither pointer is null
+            unsafe {
+                write_observation(&next, observation);
+                *reward = value;
+            }
+            i32::from(done)
+        }
+        Err(_) => -1,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plays_a_game_through_the_c_api() {
+        let game = c4_game_new();
+        unsafe {
+            assert_eq!(c4_game_status(game), C4Status::InProgress);
+            for _ in 0..3 {
+                assert_eq!(c4_game_apply_move(game, 0), 0);
+                assert_eq!(c4_game_apply_move(game, 1), 0);
+            }
+            // TriesToWin has to take the open column
+            assert_eq!(c4_game_ai_move(game, 1), 0);
+            assert_eq!(c4_game_apply_move(game, 9), -1);
+            assert_eq!(c4_game_apply_move(game, 0), 0);
+            assert_eq!(c4_game_status(game), C4Status::RedWins);
+            assert_eq!(c4_game_ai_move(game, 4), -1);
+            c4_game_free(game);
+        }
+    }
+
+    #[test]
+    fn steps_an_environment_through_the_c_api() {
+        let env = c4_env_new(1, 0);
+        let mut observation = [0.0f32; ENCODED_LEN];
+        let mut reward = 0.0f32;
+        unsafe {
+            assert_eq!(c4_env_reset(env, observation.as_mut_ptr()), 0);
+            assert!(observation.iter().all(|&x| x == 0.0));
+            // One move can't end a game, and the opponent's reply is in the observation
+            assert_eq!(
+                c4_env_step(env, 3, observation.as_mut_ptr(), &mut reward),
+                0
+            );
+            assert_eq!(reward, 0.0);
+            assert_eq!(observation[3], 1.0);
+            assert_eq!(observation.iter().sum::<f32>(), 2.0);
+            assert_eq!(
+                c4_env_step(env, 9, observation.as_mut_ptr(), &mut reward),
+                -1
+            );
+            assert_eq!(c4_env_step(env, 0, std::ptr::null_mut(), &mut reward), -1);
+            c4_env_free(env);
+        }
+        assert!(c4_env_new(3, 0).is_null());
+    }
+
+    #[test]
+    fn null_handles_are_rejected() {
+        let null = std::ptr::null_mut();
+        unsafe {
+            assert_eq!(c4_game_apply_move(null, 0), -1);
+            assert_eq!(c4_game_status(null), C4Status::Error);
+            assert_eq!(c4_game_to_move(null), -1);
+            assert_eq!(c4_game_ai_move(null, 0), -1);
+            c4_game_free(null);
+            assert_eq!(c4_env_reset(null.cast(), std::ptr::null_mut()), -1);
+            c4_env_free(null.cast());
+        }
+    }
+}
@@ -0,0 +1,327 @@
+use std::fmt;
+
+use anyhow::{Result, ensure};
+use rand::Rng;
+use rand::seq::IndexedRandom;
+
+use crate::board::{COLUMNS, Piece, ROWS};
+use crate::game::GameResult;
+
+/// A move in the gravity-off variant: the exact cell to fill, with rows counted from
+/// the bottom as in `Board::threat_squares`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Move {
+    pub row: usize,
+    pub column: usize,
+}
+
+impl Move {
+    pub fn new(row: usize, column: usize) -> Self {
+        assert!(
+            row < ROWS && column < COLUMNS,
+            "({row}, {column}) is off the board"
+        );
+        Move { row, column }
+    }
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "row {}, column {}", self.row, self.column)
+    }
+}
+
+/// Every line of four cells on the board.
+fn lines() -> impl Iterator<Item = [Move; 4]> {
+    let on_board = |column: isize, row: isize| {
+        (0..COLUMNS as isize).contains(&column) && (0..ROWS as isize).contains(&row)
+    };
+    (0..COLUMNS as isize).flat_map(move |column| {
+        (0..ROWS as isize).flat_map(move |row| {
+            [(1, 0), (0, 1), (1, 1), (1, -1)]
+                .into_iter()
+                .filter(move |(dc, dr)| on_board(column + dc * 3, row + dr * 3))
+                .map(move |(dc, dr)| {
+                    [0, 1, 2, 3]
+                        .map(|i| Move::new((row + dr * i) as usize, (column + dc * i) as usize))
+                })
+        })
+    })
+}
+
+/// A board where pieces may go in any empty cell, like tic-tac-toe. The packed `Board`
+/// stores pieces as column stacks, so this variant keeps a plain grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FreeBoard {
+    /// Indexed by row from the bottom, then column.
+    cells: [[Piece; COLUMNS]; ROWS],
+}
+
+impl FreeBoard {
+    pub fn new() -> Self {
+        FreeBoard {
+            cells: [[Piece::Empty; COLUMNS]; ROWS],
+        }
+    }
+
+    pub fn get(&self, at: Move) -> Piece {
+        self.cells[at.row][at.column]
+    }
+
+    pub fn place(&self, at: Move, piece: Piece) -> Self {
+        assert_eq!(self.get(at), Piece::Empty, "{at} is taken");
+        let mut next = *self;
+        next.cells[at.row][at.column] = piece;
+        next
+    }
+
+    /// Every empty cell.
+    pub fn valid_moves(&self) -> Vec<Move> {
+        (0..ROWS)
+            .flat_map(|row| (0..COLUMNS).map(move |column| Move::new(row, column)))
+            .filter(|&at| self.get(at) == Piece::Empty)
+            .collect()
+    }
+
+    pub fn num_pieces_played(&self) -> usize {
+        self.cells
+            .iter()
+            .flatten()
+            .filter(|&&p| p != Piece::Empty)
+            .count()
+    }
+
+    pub fn next_player(&self) -> Piece {
+        if self.num_pieces_played().is_multiple_of(2) {
+            Piece::Red
+        } else {
+            Piece::Yellow
+        }
+    }
+
+    pub fn has_winner(&self) -> Option<Piece> {
+        lines()
+            .map(|line| line.map(|at| self.get(at)))
+            .find(|cells| cells[0] != Piece::Empty && cells.iter().all(|&p| p == cells[0]))
+            .map(|cells| cells[0])
+    }
+
+    /// Empty cells that complete four-in-a-row for `piece`. Without gravity every one
+    /// of them can be played at once.
+    pub fn winning_moves(&self, piece: Piece) -> Vec<Move> {
+        let mut moves: Vec<Move> = lines()
+            .filter_map(|line| {
+                let mut empty = line.iter().filter(|&&at| self.get(at) == Piece::Empty);
+                let gap = *empty.next()?;
+                let ours = line.iter().filter(|&&at| self.get(at) == piece).count();
+                (empty.next().is_none() && ours == 3).then_some(gap)
+            })
+            .collect();
+        moves.sort_by_key(|at| (at.row, at.column));
+        moves.dedup();
+        moves
+    }
+
+    /// How promising `at` is for `piece`: each line through it that the opponent hasn't
+    /// blocked counts, more so the more of our pieces it already holds.
+    fn potential(&self, at: Move, piece: Piece) -> usize {
+        lines()
+            .filter(|line| line.contains(&at))
+            .map(|line| line.map(|cell| self.get(cell)))
+            .filter(|cells| !cells.contains(&piece.opponent()))
+            .map(|cells| 1 << (2 * cells.iter().filter(|&&p| p == piece).count()))
+            .sum()
+    }
+}
+
+impl Default for FreeBoard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for FreeBoard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, row) in self.cells.iter().rev().enumerate() {
+            for piece in row {
+                write!(f, "{} ", piece)?;
+            }
+            if i != ROWS - 1 {
+                writeln!(f)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A gravity-off game between Red and Yellow.
+#[derive(Debug, Clone)]
+pub struct FreeGame {
+    board: FreeBoard,
+    /// Tracked rather than read off the piece counts, as in `GameState`.
+    to_move: Piece,
+    history: Vec<Move>,
+}
+
+impl FreeGame {
+    pub fn new() -> Self {
+        FreeGame {
+            board: FreeBoard::new(),
+            to_move: Piece::Red,
+            history: vec![],
+        }
+    }
+
+    pub fn board(&self) -> &FreeBoard {
+        &self.board
+    }
+
+    pub fn to_move(&self) -> Piece {
+        self.to_move
+    }
+
+    pub fn history(&self) -> &[Move] {
+        &self.history
+    }
+
+    pub fn result(&self) -> Option<GameResult> {
+        if let Some(winner) = self.board.has_winner() {
+            Some(GameResult::Winner(winner))
+        } else if self.board.valid_moves().is_empty() {
+            Some(GameResult::Tie)
+        } else {
+            None
+        }
+    }
+
+    /// Fills `at` for the side to move and hands the turn to the opponent.
+    pub fn apply_move(&mut self, at: Move) -> Result<Option<GameResult>> {
+        ensure!(self.result().is_none(), "The game is already over");
+        ensure!(self.board.get(at) == Piece::Empty, "{at} is taken");
+        self.board = self.board.place(at, self.to_move);
+        self.history.push(at);
+        self.to_move = self.to_move.opponent();
+        Ok(self.result())
+    }
+}
+
+impl Default for FreeGame {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Whether `piece`, to move, can force a win within `depth` of its own moves by making
+/// a threat every move. With no gravity, two threats at once always win.
+pub fn wins_within(board: &FreeBoard, piece: Piece, depth: usize) -> bool {
+    forced_win(board, piece, depth).is_some()
+}
+
+/// The first move of a forced win for `piece` within `depth` of its own moves, if
+/// `wins_within` finds one.
+pub fn forced_win(board: &FreeBoard, piece: Piece, depth: usize) -> Option<Move> {
+    if depth == 0 {
+        return None;
+    }
+    if let Some(&at) = board.winning_moves(piece).first() {
+        return Some(at);
+    }
+    let blocks = board.winning_moves(piece.opponent());
+    if blocks.len() > 1 || depth == 1 {
+        return None;
+    }
+    // A forced block is the only move worth trying
+    let candidates = if blocks.is_empty() {
+        board.valid_moves()
+    } else {
+        blocks
+    };
+    candidates.into_iter().find(|&at| {
+        let next = board.place(at, piece);
+        let threats = next.winning_moves(piece);
+        if threats.is_empty() || !next.winning_moves(piece.opponent()).is_empty() {
+            return false;
+        }
+        // One threat forces the block; two can't both be blocked
+        threats.len() > 1
+            || wins_within(&next.place(threats[0], piece.opponent()), piece, depth - 1)
+    })
+}
+
+/// The gravity-off AI: wins or blocks at once, plays a forced win within `depth` moves
+/// if it finds one, and otherwise picks the cell with the most potential.
+pub fn choose_move(
+    board: &FreeBoard,
+    piece: Piece,
+    depth: usize,
+    rng: &mut impl Rng,
+) -> Option<Move> {
+    if let Some(&at) = board.winning_moves(piece).first() {
+        return Some(at);
+    }
+    if let Some(&at) = board.winning_moves(piece.opponent()).first() {
+        return Some(at);
+    }
+    if let Some(at) = forced_win(board, piece, depth) {
+        return Some(at);
+    }
+    // Both our potential and the opponent's: a good cell for them is worth taking
+    let score = |at: Move| board.potential(at, piece) + board.potential(at, piece.opponent());
+    let moves = board.valid_moves();
+    let best = moves.iter().map(|&at| score(at)).max()?;
+    let best_moves: Vec<Move> = (moves.into_iter())
+        .filter(|&at| score(at) == best)
+        .collect();
+    best_moves.choose(rng).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play(moves: &[(usize, usize)]) -> FreeGame {
+        let mut game = FreeGame::new();
+        for &(row, column) in moves {
+            game.apply_move(Move::new(row, column)).unwrap();
+        }
+        game
+    }
+
+    #[test]
+    fn pieces_can_float() {
+        let game = play(&[(5, 6), (3, 0)]);
+        assert_eq!(game.board().get(Move::new(5, 6)), Piece::Red);
+        assert_eq!(game.board().get(Move::new(3, 0)), Piece::Yellow);
+        assert_eq!(game.to_move(), Piece::Red);
+        assert_eq!(game.board().valid_moves().len(), ROWS * COLUMNS - 2);
+        assert!(play(&[(5, 6)]).apply_move(Move::new(5, 6)).is_err());
+    }
+
+    #[test]
+    fn floating_lines_win() {
+        // Red on a diagonal in the air, Yellow scattered
+        let game = play(&[(2, 1), (0, 0), (3, 2), (0, 6), (4, 3), (5, 0), (5, 4)]);
+        assert_eq!(game.result(), Some(GameResult::Winner(Piece::Red)));
+    }
+
+    #[test]
+    fn ai_wins_blocks_and_finds_open_threes() {
+        let mut rng = rand::rng();
+        let two = FreeBoard::new()
+            .place(Move::new(3, 2), Piece::Red)
+            .place(Move::new(3, 3), Piece::Red);
+        // Red extends to an open three with room on both sides, which can't be stopped
+        let at = forced_win(&two, Piece::Red, 2).unwrap();
+        assert_eq!(at.row, 3);
+        assert!([1, 4].contains(&at.column), "{at}");
+
+        let three = two.place(Move::new(3, 4), Piece::Red);
+        assert_eq!(three.winning_moves(Piece::Red).len(), 2);
+        assert_eq!(
+            choose_move(&three, Piece::Yellow, 2, &mut rng).map(|at| at.row),
+            Some(3)
+        );
+        let win = choose_move(&three, Piece::Red, 2, &mut rng).unwrap();
+        assert!(three.place(win, Piece::Red).has_winner() == Some(Piece::Red));
+    }
+}
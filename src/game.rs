@@ -0,0 +1,756 @@
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result, ensure};
+use rand::Rng;
+use rand::seq::IndexedRandom;
+
+use crate::board::{Board, COLUMNS, Piece};
+use crate::clock::{Clock, TimeControl};
+use crate::observer::GameObserver;
+use crate::strategy::Connect4AI;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// How a finished game ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum GameResult {
+    Winner(Piece),
+    Tie,
+}
+
+/// The state of a single game: the board, whose turn it is, and every column played so far.
+///
+/// Both the simulator and the interactive UI drive games through this type so the
+/// "check winner / check full / place" rules only live in one spot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(into = "GameRecord", try_from = "GameRecord")
+)]
+pub struct GameState {
+    board: Board,
+    to_move: Piece,
+    history: Vec<usize>,
+    /// The player whose clock ran out, if one did.
+    out_of_time: Option<Piece>,
+    /// Whether Yellow may take Red's first move instead of answering it.
+    swap_rule: bool,
+    /// Whether each player may rotate the board once instead of moving.
+    rotate_rule: bool,
+    /// Which players have used their rotation, Red first.
+    rotated: [bool; 2],
+}
+
+impl GameState {
+    pub fn new() -> Self {
+        GameState {
+            board: Board::new(),
+            to_move: Piece::Red,
+            history: Vec::new(),
+            out_of_time: None,
+            swap_rule: false,
+            rotate_rule: false,
+            rotated: [false; 2],
+        }
+    }
+
+    /// Plays with the swap rule: after Red's first move, Yellow may take that piece as
+    /// its own instead of answering it, and Red moves again.
+    pub fn with_swap_rule(mut self) -> Self {
+        self.swap_rule = true;
+        self
+    }
+
+    /// Plays with rotations: once per game, each player may turn the board 90° and let
+    /// the pieces settle instead of dropping a piece.
+    pub fn with_rotations(mut self) -> Self {
+        self.rotate_rule = true;
+        self
+    }
+
+    /// Rebuilds a game by replaying `history` from the empty board.
+    pub fn from_history(history: &[usize]) -> Result<Self> {
+        let mut state = GameState::new();
+        for &entry in history {
+            state.apply_entry(entry)?;
+        }
+        Ok(state)
+    }
+
+    /// Plays one entry of a history: a column, or a `PASS`, `SWAP` or `ROTATE` marker,
+    /// which turns on the rule it needs.
+    pub fn apply_entry(&mut self, entry: usize) -> Result<()> {
+        if entry == PASS {
+            self.pass()?;
+        } else if entry == SWAP {
+            self.swap_rule = true;
+            self.swap()?;
+        } else if entry == ROTATE {
+            self.rotate_rule = true;
+            self.rotate()?;
+        } else {
+            self.apply_move(entry)?;
+        }
+        Ok(())
+    }
+
+    /// Parses a position written as the columns played so far, e.g. `"3324"`.
+    /// Columns are numbered from 0, as everywhere else in the game. A board's short
+    /// string, e.g. `"!/////  RB"`, works too if legal play can reach it; the history is
+    /// then one way of getting there. Only columns are read, never the `PASS`, `SWAP`
+    /// or `ROTATE` markers of a stored history, so `"7"` is an error.
+    pub fn from_moves(moves: &str) -> Result<Self> {
+        if moves.trim().starts_with('!') {
+            let board: Board = moves.trim().parse()?;
+            let line = board.line_to().context("Unreachable position")?;
+            return Self::from_columns(&line);
+        }
+        let columns = moves
+            .trim()
+            .chars()
+            .map(|c| {
+                c.to_digit(10)
+                    .map(|d| d as usize)
+                    .with_context(|| format!("Invalid column {c:?} in position"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Self::from_columns(&columns)
+    }
+
+    fn from_columns(columns: &[usize]) -> Result<Self> {
+        let mut state = GameState::new();
+        for &column in columns {
+            state.apply_move(column)?;
+        }
+        Ok(state)
+    }
+
+    #[inline]
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    /// The piece that plays next.
+    #[inline]
+    pub fn to_move(&self) -> Piece {
+        self.to_move
+    }
+
+    /// Every column played so far, in order, with a `PASS`, `SWAP` or `ROTATE` marker
+    /// for each turn that didn't drop a piece.
+    #[inline]
+    pub fn history(&self) -> &[usize] {
+        &self.history
+    }
+
+    /// The result of the game, or None if it is still being played.
+    pub fn result(&self) -> Option<GameResult> {
+        if let Some(loser) = self.out_of_time {
+            Some(GameResult::Winner(loser.opponent()))
+        } else if self.rotate_rule
+            && self.board.has_four(Piece::Red)
+            && self.board.has_four(Piece::Yellow)
+        {
+            // Only a rotation can complete lines for both players at once
+            Some(GameResult::Tie)
+        } else if let Some(winner) = self.board.has_winner() {
+            Some(GameResult::Winner(winner))
+        } else if self.board.valid_moves().is_empty() {
+            Some(GameResult::Tie)
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    pub fn is_over(&self) -> bool {
+        self.result().is_some()
+    }
+
+    /// The columns the side to move may play. Empty once the game is over.
+    pub fn legal_moves(&self) -> Vec<usize> {
+        if self.is_over() {
+            return vec![];
+        }
+        self.board.valid_moves()
+    }
+
+    /// Plays `column` for the side to move and hands the turn to the opponent.
+    pub fn apply_move(&mut self, column: usize) -> Result<Option<GameResult>> {
+        ensure!(!self.is_over(), "The game is already over");
+        self.board = self.board.try_place(column, self.to_move)?;
+        self.history.push(column);
+        self.to_move = self.to_move.opponent();
+        Ok(self.result())
+    }
+
+    /// Ends the game with `player` losing on time.
+    pub fn lose_on_time(&mut self, player: Piece) -> Result<()> {
+        ensure!(!self.is_over(), "The game is already over");
+        self.out_of_time = Some(player);
+        Ok(())
+    }
+
+    /// The player who lost on time, if the game ended that way.
+    pub fn out_of_time(&self) -> Option<Piece> {
+        self.out_of_time
+    }
+
+    /// Whether Yellow may swap now: the swap rule is on and Red's first move is the
+    /// only one played.
+    pub fn can_swap(&self) -> bool {
+        self.swap_rule && matches!(self.history[..], [column] if column < COLUMNS)
+    }
+
+    /// Yellow takes Red's first move under the swap rule: the piece turns yellow and
+    /// Red moves again, leaving a board like the one after a pass.
+    pub fn swap(&mut self) -> Result<()> {
+        ensure!(
+            self.can_swap(),
+            "Swapping is only allowed after Red's first move"
+        );
+        self.board = Board::new().place(self.history[0], Piece::Yellow);
+        self.history.push(SWAP);
+        self.to_move = Piece::Red;
+        Ok(())
+    }
+
+    /// Whether Yellow took Red's first move.
+    pub fn swapped(&self) -> bool {
+        self.history.contains(&SWAP)
+    }
+
+    /// Whether the side to move may still rotate the board. Rotating an empty board
+    /// would only waste the turn.
+    pub fn can_rotate(&self) -> bool {
+        self.rotate_rule
+            && !self.rotated[Self::player_index(self.to_move)]
+            && !self.is_over()
+            && self.board.num_pieces_played() > 0
+            && self.board.rotate_and_settle().is_some()
+    }
+
+    /// Uses the side to move's rotation: the board turns and settles, and the turn
+    /// passes to the opponent. Either player, or both, may be left with four in a row.
+    pub fn rotate(&mut self) -> Result<Option<GameResult>> {
+        ensure!(
+            self.can_rotate(),
+            "{} can't rotate now",
+            self.to_move.name()
+        );
+        self.board = self
+            .board
+            .rotate_and_settle()
+            .expect("checked by can_rotate");
+        self.rotated[Self::player_index(self.to_move)] = true;
+        self.history.push(ROTATE);
+        self.to_move = self.to_move.opponent();
+        Ok(self.result())
+    }
+
+    fn player_index(piece: Piece) -> usize {
+        match piece {
+            Piece::Red => 0,
+            Piece::Yellow => 1,
+            Piece::Empty | Piece::Blocker => panic!("{piece:?} never moves"),
+        }
+    }
+
+    /// Gives the turn to the other player without placing a piece. Only handicaps
+    /// pass, so the board can end up with more pieces of one color than usual.
+    pub fn pass(&mut self) -> Result<()> {
+        ensure!(!self.is_over(), "The game is already over");
+        self.history.push(PASS);
+        self.to_move = self.to_move.opponent();
+        Ok(())
+    }
+}
+
+/// Stands in for a column in a game's history when a player passed.
+pub const PASS: usize = COLUMNS;
+
+/// Stands in for a column in a game's history when Yellow took Red's first move under
+/// the swap rule.
+pub const SWAP: usize = COLUMNS + 1;
+
+/// Stands in for a column in a game's history when a player rotated the board.
+pub const ROTATE: usize = COLUMNS + 2;
+
+/// A game in a form that can be stored: the moves, plus the board and result they lead
+/// to so stored games are readable without replaying them. Turning a record back into a
+/// `GameState` replays the moves and checks they match.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GameRecord {
+    pub history: Vec<usize>,
+    pub board: Board,
+    pub result: Option<GameResult>,
+    /// Who lost on time, for timed games.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub out_of_time: Option<Piece>,
+    /// Whether the game is played with the swap rule.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "std::ops::Not::not")
+    )]
+    pub swap_rule: bool,
+    /// Whether the game is played with rotations.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "std::ops::Not::not")
+    )]
+    pub rotate_rule: bool,
+}
+
+impl From<&GameState> for GameRecord {
+    fn from(state: &GameState) -> Self {
+        GameRecord {
+            history: state.history.clone(),
+            board: state.board,
+            result: state.result(),
+            out_of_time: state.out_of_time,
+            swap_rule: state.swap_rule,
+            rotate_rule: state.rotate_rule,
+        }
+    }
+}
+
+impl From<GameState> for GameRecord {
+    fn from(state: GameState) -> Self {
+        GameRecord::from(&state)
+    }
+}
+
+impl TryFrom<GameRecord> for GameState {
+    type Error = anyhow::Error;
+
+    fn try_from(record: GameRecord) -> Result<Self> {
+        let mut state = GameState::from_history(&record.history)?;
+        state.swap_rule |= record.swap_rule;
+        state.rotate_rule |= record.rotate_rule;
+        if let Some(player) = record.out_of_time {
+            state.lose_on_time(player)?;
+        }
+        ensure!(
+            state.board == record.board,
+            "The board does not match the moves"
+        );
+        ensure!(
+            state.result() == record.result,
+            "The result does not match the moves"
+        );
+        Ok(state)
+    }
+}
+
+impl Default for GameState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A game two AIs played to the end, with how long each move took.
+#[derive(Debug, Clone)]
+pub struct PlayedGame {
+    state: GameState,
+    move_times: Vec<Duration>,
+}
+
+impl PlayedGame {
+    /// The finished game, including the opening it started from.
+    pub fn state(&self) -> &GameState {
+        &self.state
+    }
+
+    pub fn board(&self) -> &Board {
+        self.state.board()
+    }
+
+    pub fn result(&self) -> GameResult {
+        self.state.result().expect("played games are finished")
+    }
+
+    pub fn winner(&self) -> Option<Piece> {
+        match self.result() {
+            GameResult::Winner(winner) => Some(winner),
+            GameResult::Tie => None,
+        }
+    }
+
+    /// Every column played, opening included, with the markers described at
+    /// `GameState::history`.
+    pub fn moves(&self) -> &[usize] {
+        self.state.history()
+    }
+
+    /// How long each move after the opening took, lining up with the end of `moves`.
+    /// Passes, swaps and rotations count as no time.
+    pub fn move_times(&self) -> &[Duration] {
+        &self.move_times
+    }
+}
+
+/// Plays a game between two AIs from `opening` to the end, notifying `observer` along
+/// the way. Returns None if an AI gave up or tried an illegal move.
+pub fn play_game(
+    red: &dyn Connect4AI,
+    yellow: &dyn Connect4AI,
+    opening: &GameState,
+    observer: &mut dyn GameObserver,
+) -> Option<PlayedGame> {
+    play_timed_game(red, yellow, opening, None, observer)
+}
+
+/// Like `play_game`, but with an optional clock: each AI is told its remaining time
+/// and loses if its flag falls.
+pub fn play_timed_game(
+    red: &dyn Connect4AI,
+    yellow: &dyn Connect4AI,
+    opening: &GameState,
+    time_control: Option<TimeControl>,
+    observer: &mut dyn GameObserver,
+) -> Option<PlayedGame> {
+    let span = tracing::debug_span!("game", %red, %yellow);
+    let _guard = span.enter();
+
+    let mut clock = time_control.map(Clock::new);
+    let mut state = opening.clone();
+    let mut move_times = vec![];
+    while !state.is_over() {
+        let piece = state.to_move();
+        let player = match piece {
+            Piece::Red => red,
+            _ => yellow,
+        };
+        if state.can_swap() && player.swaps(state.board()) {
+            tracing::debug!(player = piece.name(), "swapped");
+            state.swap().ok()?;
+            move_times.push(Duration::ZERO);
+            observer.on_special_move(&state, piece, SWAP);
+            continue;
+        }
+        if state.can_rotate() && player.rotates(state.board(), piece) {
+            tracing::debug!(player = piece.name(), "rotated");
+            state.rotate().ok()?;
+            move_times.push(Duration::ZERO);
+            observer.on_special_move(&state, piece, ROTATE);
+            continue;
+        }
+        if player.passes(state.board(), piece) {
+            tracing::debug!(player = piece.name(), "passed");
+            state.pass().ok()?;
+            move_times.push(Duration::ZERO);
+            observer.on_special_move(&state, piece, PASS);
+            continue;
+        }
+        let start = Instant::now();
+        let choice = match &clock {
+            Some(clock) => player.play_timed(state.board(), piece, clock.remaining(piece)),
+            None => player.play(state.board(), piece),
+        };
+        let Some(col) = choice else {
+            tracing::warn!(player = piece.name(), "AI gave up");
+            return None;
+        };
+        let elapsed = start.elapsed();
+        if let Some(clock) = &mut clock
+            && !clock.charge(piece, elapsed)
+        {
+            tracing::debug!(player = piece.name(), "flag fell");
+            state.lose_on_time(piece).ok()?;
+            break;
+        }
+        if let Err(e) = state.apply_move(col) {
+            tracing::warn!(player = piece.name(), column = col, "illegal move: {e}");
+            return None;
+        }
+        tracing::debug!(
+            player = piece.name(),
+            column = col,
+            elapsed_us = elapsed.as_micros() as u64,
+            "move chosen"
+        );
+        move_times.push(elapsed);
+        observer.on_move(&state, piece, col, elapsed);
+    }
+    let result = state.result()?;
+    tracing::debug!(?result, moves = state.history().len(), "game over");
+    observer.on_game_end(&state, result);
+    Some(PlayedGame { state, move_times })
+}
+
+/// `start` followed by up to `moves` random legal moves. Moves that would end the game
+/// are never picked, so the opening may stop short.
+pub fn random_opening(rng: &mut impl Rng, start: &GameState, moves: usize) -> GameState {
+    let mut state = start.clone();
+    for _ in 0..moves {
+        let safe: Vec<usize> = (state.legal_moves().into_iter())
+            .filter(|&col| {
+                let mut next = state.clone();
+                next.apply_move(col).is_ok_and(|result| result.is_none())
+            })
+            .collect();
+        let Some(&column) = safe.choose(rng) else {
+            break;
+        };
+        state.apply_move(column).expect("safe moves are legal");
+    }
+    state
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alternates_players_and_records_history() {
+        let mut game = GameState::new();
+        assert_eq!(game.to_move(), Piece::Red);
+
+        game.apply_move(3).unwrap();
+        assert_eq!(game.to_move(), Piece::Yellow);
+        game.apply_move(4).unwrap();
+        assert_eq!(game.to_move(), Piece::Red);
+
+        assert_eq!(game.history(), &[3, 4]);
+        assert_eq!(game.board().next_player(), game.to_move());
+    }
+
+    #[test]
+    fn vertical_win_ends_the_game() {
+        let mut game = GameState::new();
+        for _ in 0..3 {
+            assert_eq!(game.apply_move(0).unwrap(), None);
+            assert_eq!(game.apply_move(1).unwrap(), None);
+        }
+        assert_eq!(
+            game.apply_move(0).unwrap(),
+            Some(GameResult::Winner(Piece::Red))
+        );
+        assert!(game.legal_moves().is_empty());
+        assert!(game.apply_move(2).is_err());
+    }
+
+    #[test]
+    fn replays_history() {
+        let mut game = GameState::new();
+        for column in [3, 3, 2, 4] {
+            game.apply_move(column).unwrap();
+        }
+        assert_eq!(GameState::from_history(game.history()).unwrap(), game);
+        assert!(GameState::from_history(&[0, 0, 0, 0, 0, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn parses_move_strings() {
+        let game = GameState::from_moves("3324").unwrap();
+        assert_eq!(game.history(), &[3, 3, 2, 4]);
+        assert!(GameState::from_moves("").unwrap().history().is_empty());
+        assert!(GameState::from_moves("3x").is_err());
+        assert!(GameState::from_moves("9").is_err());
+        // Markers belong to stored histories, not to positions typed in
+        for marker in [PASS, SWAP, ROTATE] {
+            let error = GameState::from_moves(&format!("3{marker}")).unwrap_err();
+            assert_eq!(
+                error.to_string(),
+                format!("Column {marker} is off the board")
+            );
+        }
+        assert!(GameState::from_moves("7").is_err());
+
+        let board = *game.board();
+        let from_board = GameState::from_moves(&board.short_string()).unwrap();
+        assert_eq!(from_board.board(), &board);
+        let error = GameState::from_moves("!////R/B").unwrap_err();
+        assert!(format!("{error:#}").contains("No order"), "{error:#}");
+    }
+
+    #[test]
+    fn records_round_trip() {
+        let game = GameState::from_moves("3324").unwrap();
+        let record = GameRecord::from(&game);
+        assert_eq!(GameState::try_from(record.clone()).unwrap(), game);
+
+        let tampered = GameRecord {
+            result: Some(GameResult::Tie),
+            ..record
+        };
+        assert!(GameState::try_from(tampered).is_err());
+    }
+
+    #[test]
+    fn games_continue_from_random_openings() {
+        let start = GameState::from_moves("33").unwrap();
+        let opening = random_opening(&mut rand::rng(), &start, 6);
+        assert_eq!(opening.history().len(), 8);
+        assert!(opening.history().starts_with(&[3, 3]));
+        let stack = crate::strategy::StrategyStack::new(vec![]);
+        let game = play_game(&stack, &stack, &opening, &mut ()).unwrap();
+        assert!(game.moves().starts_with(opening.history()));
+        assert_eq!(
+            game.move_times().len(),
+            game.moves().len() - opening.history().len()
+        );
+        assert_eq!(game.winner(), game.board().has_winner());
+    }
+
+    #[test]
+    fn swap_rule_gives_yellow_the_first_move() {
+        let mut game = GameState::from_moves("3").unwrap();
+        assert!(!game.can_swap());
+        game = game.with_swap_rule();
+        game.swap().unwrap();
+        assert!(game.swapped());
+        assert_eq!(*game.board(), Board::new().place(3, Piece::Yellow));
+        assert_eq!(game.to_move(), Piece::Red);
+        assert!(game.swap().is_err());
+
+        let record = GameRecord::from(&game);
+        assert_eq!(GameState::try_from(record).unwrap(), game);
+        assert_eq!(GameState::from_history(&[3, SWAP]).unwrap(), game);
+        assert!(GameState::from_history(&[3, 3, SWAP]).is_err());
+
+        // The default stack takes a center opening
+        let stack = crate::strategy::StrategyStack::new(vec![]);
+        let opening = GameState::from_moves("3").unwrap().with_swap_rule();
+        let played = play_game(&stack, &stack, &opening, &mut ()).unwrap();
+        assert_eq!(played.moves()[..2], [3, SWAP]);
+    }
+
+    #[test]
+    fn each_player_may_rotate_once() {
+        // Red's rightmost piece on each of the bottom four rows: rotated, they land
+        // side by side
+        let mut game = GameState::from_moves("65656550").unwrap();
+        assert!(!game.can_rotate());
+        game = game.with_rotations();
+        let stack = crate::strategy::StrategyStack::new(vec![]);
+        assert!(stack.rotates(game.board(), game.to_move()));
+        assert_eq!(game.rotate().unwrap(), Some(GameResult::Winner(Piece::Red)));
+        assert_eq!(game.history().last(), Some(&ROTATE));
+
+        let mut game = GameState::from_moves("33").unwrap().with_rotations();
+        game.rotate().unwrap();
+        assert_eq!(game.to_move(), Piece::Yellow);
+        game.apply_move(0).unwrap();
+        assert!(!game.can_rotate());
+        assert!(game.rotate().is_err());
+        game.apply_move(0).unwrap();
+        assert!(game.can_rotate());
+        game.rotate().unwrap();
+
+        let record = GameRecord::from(&game);
+        assert_eq!(GameState::try_from(record).unwrap(), game);
+        assert_eq!(GameState::from_history(game.history()).unwrap(), game);
+    }
+
+    #[test]
+    fn observers_see_every_turn() {
+        /// Every history entry it is told about, with the history length at the time.
+        #[derive(Default)]
+        struct Recorder(Vec<(usize, usize)>);
+        impl GameObserver for Recorder {
+            fn on_move(&mut self, state: &GameState, _: Piece, column: usize, _: Duration) {
+                self.0.push((column, state.history().len()));
+            }
+            fn on_special_move(&mut self, state: &GameState, _: Piece, entry: usize) {
+                self.0.push((entry, state.history().len()));
+            }
+        }
+        /// Passes until three pieces are down, then plays like `inner`.
+        struct PassesEarly<A>(A);
+        impl<A: Connect4AI> Connect4AI for PassesEarly<A> {
+            fn play(&self, board: &Board, piece: Piece) -> Option<usize> {
+                self.0.play(board, piece)
+            }
+            fn passes(&self, board: &Board, _piece: Piece) -> bool {
+                board.num_pieces_played() < 3
+            }
+        }
+        impl<A> std::fmt::Display for PassesEarly<A> {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "PassesEarly")
+            }
+        }
+
+        let stack = crate::strategy::StrategyStack::new(vec![]);
+        let swapped = GameState::from_moves("3").unwrap().with_swap_rule();
+        let rotated = GameState::from_moves("65656550").unwrap().with_rotations();
+        for (red, yellow, opening) in [
+            (
+                &stack as &dyn Connect4AI,
+                &stack as &dyn Connect4AI,
+                swapped,
+            ),
+            (&stack, &stack, rotated),
+            (&stack, &PassesEarly(&stack), GameState::new()),
+        ] {
+            let mut recorder = Recorder::default();
+            let played = play_game(red, yellow, &opening, &mut recorder).unwrap();
+            let moves = &played.moves()[opening.history().len()..];
+            let seen: Vec<usize> = recorder.0.iter().map(|&(entry, _)| entry).collect();
+            assert_eq!(seen, moves);
+            // Each was reported once the state included it
+            for (i, &(_, length)) in recorder.0.iter().enumerate() {
+                assert_eq!(length, opening.history().len() + i + 1);
+            }
+            assert!(moves.iter().any(|&entry| entry >= COLUMNS), "{moves:?}");
+        }
+    }
+
+    #[test]
+    fn slow_players_lose_on_time() {
+        struct Slow;
+        impl Connect4AI for Slow {
+            fn play(&self, board: &Board, _piece: Piece) -> Option<usize> {
+                std::thread::sleep(std::time::Duration::from_millis(20));
+                board.valid_moves().first().copied()
+            }
+        }
+        impl std::fmt::Display for Slow {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "Slow")
+            }
+        }
+
+        let fast = crate::strategy::StrategyStack::new(vec![]);
+        let control = "0.01".parse().ok();
+        let game = play_timed_game(&Slow, &fast, &GameState::new(), control, &mut ())
+            .unwrap()
+            .state()
+            .clone();
+        assert_eq!(game.result(), Some(GameResult::Winner(Piece::Yellow)));
+        assert_eq!(game.out_of_time(), Some(Piece::Red));
+
+        let record = GameRecord::from(&game);
+        assert_eq!(GameState::try_from(record).unwrap(), game);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serializes_as_a_record() {
+        let game = GameState::from_moves("33").unwrap();
+        let json = serde_json::to_string(&game).unwrap();
+        assert_eq!(
+            json,
+            r#"{"history":[3,3],"board":"!////   B/   R","result":null}"#
+        );
+        assert_eq!(serde_json::from_str::<GameState>(&json).unwrap(), game);
+        assert!(
+            serde_json::from_str::<GameState>(r#"{"history":[3],"board":"!/////","result":null}"#)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn rejects_illegal_columns() {
+        let mut game = GameState::new();
+        assert!(game.apply_move(7).is_err());
+        assert!(game.history().is_empty());
+    }
+}
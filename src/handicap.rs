@@ -0,0 +1,256 @@
+use std::cell::Cell;
+use std::time::Duration;
+
+use anyhow::{Result, ensure};
+use rand::seq::IndexedRandom;
+
+use crate::board::{Board, COLUMNS, Piece};
+use crate::game::GameState;
+use crate::strategy::Connect4AI;
+
+/// Ways to weaken the AI so a weaker player gets a fair game. All are off by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Handicap {
+    /// The AI passes every Nth turn.
+    pub skip_every: Option<usize>,
+    /// The human starts with an extra piece in this column.
+    pub extra_piece: Option<usize>,
+    /// The AI may not play the center column for its first K moves.
+    pub no_center_moves: usize,
+}
+
+impl Handicap {
+    pub fn is_none(&self) -> bool {
+        *self == Handicap::default()
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        ensure!(
+            self.skip_every.is_none_or(|every| every >= 1),
+            "The AI can skip at most every turn"
+        );
+        ensure!(
+            self.extra_piece.is_none_or(|column| column < COLUMNS),
+            "The extra piece must go in a column from 0 to {}",
+            COLUMNS - 1
+        );
+        Ok(())
+    }
+
+    /// The starting position. The extra piece is played as the human's move with the
+    /// AI passing its answer (or passing first, when the AI moves first).
+    pub fn starting_state(&self, human: Piece) -> Result<GameState> {
+        let mut state = GameState::new();
+        if let Some(column) = self.extra_piece {
+            if human == Piece::Red {
+                state.apply_move(column)?;
+                state.pass()?;
+            } else {
+                state.pass()?;
+                state.apply_move(column)?;
+            }
+        }
+        Ok(state)
+    }
+
+    /// Wraps the AI's strategy in the handicaps that restrict it.
+    pub fn wrap(&self, ai: Box<dyn Connect4AI>) -> Box<dyn Connect4AI> {
+        let mut ai = ai;
+        if self.no_center_moves > 0 {
+            ai = Box::new(AvoidsCenter::new(ai, self.no_center_moves));
+        }
+        if let Some(every) = self.skip_every {
+            ai = Box::new(SkipsTurns::new(ai, every));
+        }
+        ai
+    }
+}
+
+impl std::fmt::Display for Handicap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut parts = vec![];
+        if let Some(every) = self.skip_every {
+            parts.push(format!("AI skips every turn {}", every));
+        }
+        if let Some(column) = self.extra_piece {
+            parts.push(format!("extra piece in column {}", column));
+        }
+        if self.no_center_moves > 0 {
+            parts.push(format!(
+                "no center for the AI's first {} moves",
+                self.no_center_moves
+            ));
+        }
+        if parts.is_empty() {
+            return write!(f, "no handicap");
+        }
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+/// Passes every `every`th turn and otherwise plays like `inner`.
+pub struct SkipsTurns {
+    inner: Box<dyn Connect4AI>,
+    every: usize,
+    turns: Cell<usize>,
+}
+
+impl SkipsTurns {
+    pub fn new(inner: Box<dyn Connect4AI>, every: usize) -> Self {
+        SkipsTurns {
+            inner,
+            every,
+            turns: Cell::new(0),
+        }
+    }
+}
+
+impl Connect4AI for SkipsTurns {
+    fn play(&self, board: &Board, piece: Piece) -> Option<usize> {
+        self.inner.play(board, piece)
+    }
+
+    fn play_timed(&self, board: &Board, piece: Piece, remaining: Duration) -> Option<usize> {
+        self.inner.play_timed(board, piece, remaining)
+    }
+
+    fn passes(&self, board: &Board, piece: Piece) -> bool {
+        let turn = self.turns.get() + 1;
+        self.turns.set(turn);
+        turn.is_multiple_of(self.every) || self.inner.passes(board, piece)
+    }
+
+    fn swaps(&self, board: &Board) -> bool {
+        self.inner.swaps(board)
+    }
+
+    fn rotates(&self, board: &Board, piece: Piece) -> bool {
+        self.inner.rotates(board, piece)
+    }
+}
+
+impl std::fmt::Display for SkipsTurns {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} (skips every turn {})", self.inner, self.every)
+    }
+}
+
+/// Plays like `inner`, except that it stays out of the center column until it has
+/// made `moves` moves.
+pub struct AvoidsCenter {
+    inner: Box<dyn Connect4AI>,
+    moves: usize,
+}
+
+impl AvoidsCenter {
+    pub fn new(inner: Box<dyn Connect4AI>, moves: usize) -> Self {
+        AvoidsCenter { inner, moves }
+    }
+}
+
+impl Connect4AI for AvoidsCenter {
+    fn play(&self, board: &Board, piece: Piece) -> Option<usize> {
+        self.avoid_center(board, self.inner.play(board, piece)?)
+    }
+
+    fn play_timed(&self, board: &Board, piece: Piece, remaining: Duration) -> Option<usize> {
+        self.avoid_center(board, self.inner.play_timed(board, piece, remaining)?)
+    }
+
+    fn passes(&self, board: &Board, piece: Piece) -> bool {
+        self.inner.passes(board, piece)
+    }
+
+    fn swaps(&self, board: &Board) -> bool {
+        self.inner.swaps(board)
+    }
+
+    fn rotates(&self, board: &Board, piece: Piece) -> bool {
+        self.inner.rotates(board, piece)
+    }
+}
+
+impl AvoidsCenter {
+    fn avoid_center(&self, board: &Board, choice: usize) -> Option<usize> {
+        // Roughly half the pieces on the board are ours
+        if choice != COLUMNS / 2 || board.num_pieces_played() / 2 >= self.moves {
+            return Some(choice);
+        }
+        let others: Vec<usize> = (board.valid_moves().into_iter())
+            .filter(|&col| col != COLUMNS / 2)
+            .collect();
+        // With only the center left, it has to be played
+        Some(*others.choose(&mut crate::rng::rng()).unwrap_or(&choice))
+    }
+}
+
+impl std::fmt::Display for AvoidsCenter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} (no center for {} moves)", self.inner, self.moves)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{GameState, PASS, play_game};
+    use crate::strategy::StrategyStack;
+
+    /// Always plays the center when it can.
+    struct Center;
+
+    impl Connect4AI for Center {
+        fn play(&self, board: &Board, _piece: Piece) -> Option<usize> {
+            let moves = board.valid_moves();
+            moves.contains(&3).then_some(3).or(moves.first().copied())
+        }
+    }
+
+    impl std::fmt::Display for Center {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "Center")
+        }
+    }
+
+    #[test]
+    fn extra_piece_is_a_move_answered_by_a_pass() {
+        let handicap = Handicap {
+            extra_piece: Some(2),
+            ..Handicap::default()
+        };
+        let red = handicap.starting_state(Piece::Red).unwrap();
+        assert_eq!(red.history(), &[2, PASS]);
+        assert_eq!(red.to_move(), Piece::Red);
+
+        let yellow = handicap.starting_state(Piece::Yellow).unwrap();
+        assert_eq!(yellow.history(), &[PASS, 2]);
+        assert_eq!(yellow.to_move(), Piece::Red);
+        assert_eq!(GameState::from_history(yellow.history()).unwrap(), yellow);
+    }
+
+    #[test]
+    fn wrapped_ai_skips_turns_and_avoids_the_center() {
+        let handicap = Handicap {
+            skip_every: Some(2),
+            no_center_moves: 2,
+            ..Handicap::default()
+        };
+        let ai = handicap.wrap(Box::new(Center));
+        let board = Board::new();
+        assert!(!ai.passes(&board, Piece::Yellow));
+        assert!(ai.passes(&board, Piece::Yellow));
+        assert_ne!(ai.play(&board, Piece::Yellow), Some(3));
+        assert_eq!(ai.play(&Board::from("!/////RRYYR"), Piece::Yellow), Some(3));
+    }
+
+    #[test]
+    fn handicapped_games_still_finish() {
+        let handicap = Handicap {
+            skip_every: Some(3),
+            ..Handicap::default()
+        };
+        let red = handicap.wrap(Box::new(StrategyStack::new(vec![])));
+        let game = play_game(red.as_ref(), &Center, &GameState::new(), &mut ());
+        assert!(game.is_some());
+    }
+}
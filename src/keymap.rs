@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+
+use anyhow::{Context, Result, bail, ensure};
+use serde::{Deserialize, Serialize};
+
+use crate::preset::config_dir;
+
+/// What a key does while picking a column in interactive play.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyAction {
+    Left,
+    Right,
+    Drop,
+    Quit,
+    /// Print the position's short string.
+    Position,
+    Save,
+    Rotate,
+    /// Explain the AI's last move.
+    Explain,
+}
+
+/// Keys with names rather than a character of their own.
+const NAMED_KEYS: [&str; 9] = [
+    "Left",
+    "Right",
+    "Up",
+    "Down",
+    "Enter",
+    "Space",
+    "Tab",
+    "Escape",
+    "Backspace",
+];
+
+/// Key bindings for interactive play, read from a TOML file. Each action lists its
+/// keys: single characters, which are case sensitive, or one of `Left`, `Right`, `Up`,
+/// `Down`, `Enter`, `Space`, `Tab`, `Escape` and `Backspace`. Actions left out of the
+/// file keep their default keys.
+///
+/// ```toml
+/// left = ["Left", "h"]
+/// right = ["Right", "l"]
+/// drop = ["Enter", "Space"]
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Keymap {
+    pub left: Vec<String>,
+    pub right: Vec<String>,
+    pub drop: Vec<String>,
+    pub quit: Vec<String>,
+    pub position: Vec<String>,
+    pub save: Vec<String>,
+    pub rotate: Vec<String>,
+    pub explain: Vec<String>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let keys = |names: &[&str]| names.iter().map(|name| name.to_string()).collect();
+        Keymap {
+            left: keys(&["Left", "a"]),
+            right: keys(&["Right", "d"]),
+            drop: keys(&["Enter"]),
+            quit: keys(&["q"]),
+            position: keys(&["p"]),
+            save: keys(&["s"]),
+            rotate: keys(&["r"]),
+            explain: keys(&["e"]),
+        }
+    }
+}
+
+/// The canonical spelling of a key name: characters as they are, named keys in any case.
+fn normalize(name: &str) -> Result<String> {
+    if name.chars().count() == 1 {
+        return Ok(name.to_string());
+    }
+    match NAMED_KEYS.iter().find(|key| key.eq_ignore_ascii_case(name)) {
+        Some(key) => Ok(key.to_string()),
+        None => bail!(
+            "Unknown key {name:?}: use a single character or one of {}",
+            NAMED_KEYS.join(", ")
+        ),
+    }
+}
+
+impl Keymap {
+    fn bindings(&self) -> [(KeyAction, &[String]); 8] {
+        [
+            (KeyAction::Left, &self.left),
+            (KeyAction::Right, &self.right),
+            (KeyAction::Drop, &self.drop),
+            (KeyAction::Quit, &self.quit),
+            (KeyAction::Position, &self.position),
+            (KeyAction::Save, &self.save),
+            (KeyAction::Rotate, &self.rotate),
+            (KeyAction::Explain, &self.explain),
+        ]
+    }
+
+    fn bindings_mut(&mut self) -> [&mut Vec<String>; 8] {
+        [
+            &mut self.left,
+            &mut self.right,
+            &mut self.drop,
+            &mut self.quit,
+            &mut self.position,
+            &mut self.save,
+            &mut self.rotate,
+            &mut self.explain,
+        ]
+    }
+
+    /// Parses a keymap file, checking that every action has a key and no key does two
+    /// things.
+    pub fn from_toml(text: &str) -> Result<Self> {
+        let mut keymap: Keymap = toml::from_str(text)?;
+        for keys in keymap.bindings_mut() {
+            for key in keys.iter_mut() {
+                *key = normalize(key)?;
+            }
+        }
+        let mut bound = HashMap::new();
+        for (action, keys) in keymap.bindings() {
+            ensure!(!keys.is_empty(), "{action:?} has no key");
+            for key in keys {
+                if let Some(other) = bound.insert(key.as_str(), action) {
+                    bail!("{key:?} is bound to both {other:?} and {action:?}");
+                }
+            }
+        }
+        Ok(keymap)
+    }
+
+    /// Reads the keymap at `path`, or None if there is no file there.
+    pub fn load(path: &str) -> Result<Option<Self>> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => Self::from_toml(&text)
+                .with_context(|| format!("Invalid keymap {path}"))
+                .map(Some),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e).with_context(|| format!("Reading {path}")),
+        }
+    }
+
+    /// What the key named `key` does, if anything.
+    pub fn action(&self, key: &str) -> Option<KeyAction> {
+        (self.bindings().into_iter())
+            .find(|(_, keys)| keys.iter().any(|bound| bound == key))
+            .map(|(action, _)| action)
+    }
+
+    /// The first key for `action`, to show in prompts.
+    pub fn key_for(&self, action: KeyAction) -> &str {
+        let (_, keys) = (self.bindings().into_iter())
+            .find(|&(bound, _)| bound == action)
+            .expect("every action has bindings");
+        keys.first().map_or("", String::as_str)
+    }
+}
+
+/// Where the keymap is read from unless `--keys` says otherwise:
+/// `$XDG_CONFIG_HOME/connect-4/keys.toml`, falling back to `~/.config`.
+pub fn keymap_path() -> String {
+    format!("{}/keys.toml", config_dir())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rebinds_keys_and_keeps_the_rest() {
+        let keymap = Keymap::from_toml("left = [\"h\", \"left\"]\ndrop = [\"space\"]").unwrap();
+        assert_eq!(keymap.action("h"), Some(KeyAction::Left));
+        assert_eq!(keymap.action("Left"), Some(KeyAction::Left));
+        assert_eq!(keymap.action("a"), None);
+        assert_eq!(keymap.action("Space"), Some(KeyAction::Drop));
+        assert_eq!(keymap.action("Enter"), None);
+        assert_eq!(keymap.action("q"), Some(KeyAction::Quit));
+        assert_eq!(keymap.key_for(KeyAction::Rotate), "r");
+    }
+
+    #[test]
+    fn rejects_clashes_and_unknown_keys() {
+        let clash = Keymap::from_toml("quit = [\"a\"]").unwrap_err();
+        assert!(clash.to_string().contains("both Left and Quit"), "{clash}");
+        assert!(Keymap::from_toml("left = [\"PageUp\"]").is_err());
+        assert!(Keymap::from_toml("rotate = []").is_err());
+        assert!(Keymap::from_toml("jump = [\"j\"]").is_err());
+    }
+}
@@ -1,3 +1,54 @@
+pub mod analysis;
+#[cfg(feature = "serde")]
+pub mod api;
+pub mod async_engine;
+pub mod blockers;
 pub mod board;
+#[cfg(feature = "bot")]
+pub mod bot;
+pub mod bounded;
+pub mod clock;
+pub mod combinators;
+pub mod daily;
+pub mod engine;
+pub mod env;
+pub mod evolve;
+pub mod expectiminimax;
+pub mod explain;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod free_placement;
+pub mod game;
+pub mod handicap;
+#[cfg(feature = "presets")]
+pub mod keymap;
+pub mod mcts;
+pub mod narration;
+#[cfg(feature = "serde")]
+pub mod net;
+pub mod neural;
+pub mod observer;
+#[cfg(feature = "presets")]
+pub mod preset;
+#[cfg(feature = "serde")]
+pub mod profile;
+pub mod puzzle;
+pub mod render;
+pub mod rng;
+pub mod scratch;
+pub mod search_for_win;
+pub mod search_trace;
+pub mod selfplay;
+pub mod sound;
+pub mod stats;
+pub mod strategy;
+pub mod strategy_cache;
+pub mod strategy_kind;
+pub mod test_positions;
+pub mod three_player;
+pub mod tune;
+#[cfg(target_arch = "wasm32")]
+pub mod wasm;
 // Re-export so it can be used as `use connect4::Board` instead of `use connect4::board::Board`
-pub use board::{Board, Piece};
+pub use board::{Board, Piece, PlayerColor, TextStyle};
+pub use game::{GameRecord, GameResult, GameState, PlayedGame, play_game};
@@ -1,34 +1,65 @@
-mod board;
-mod search_for_win;
-mod strategy;
-mod strategy_cache;
-
-use anyhow::{Context, Result};
-use board::{Board, COLUMNS, Piece};
-use clap::Parser;
-use console::{Key, Term};
-use dialoguer::Select;
+use anyhow::{Context, Result, ensure};
+use clap::{Parser, Subcommand};
+use connect4::analysis::{DepthSearch, MoveQuality, analyze_game, losing_move};
+use connect4::api;
+use connect4::blockers::{self, BlockerBoard, BlockerGame};
+use connect4::board::{Board, COLUMNS, LineDirection, Piece, ROWS};
+use connect4::clock::{Clock, TimeControl, format_clock};
+use connect4::combinators::PhasedStrategy;
+use connect4::daily::DailyChallenge;
+use connect4::engine;
+use connect4::evolve::{self, EvolveConfig};
+use connect4::explain::DecisionTrace;
+use connect4::free_placement::{self, FreeBoard, FreeGame, Move};
+use connect4::game::{
+    GameRecord, GameResult, GameState, PASS, ROTATE, SWAP, play_game, play_timed_game,
+    random_opening,
+};
+use connect4::handicap::Handicap;
+use connect4::keymap::{KeyAction, Keymap, keymap_path};
+use connect4::mcts::Mcts;
+use connect4::narration::{describe_board, describe_move};
+use connect4::net::{ClientMessage, Connection, Seat, ServerMessage, host_game, join_game};
+use connect4::observer::{GameDiversity, GameObserver, MoveTimer, OutcomeStats};
+use connect4::preset::{StrategyPreset, load_presets, presets_dir};
+use connect4::profile::{LADDER_TOP, LadderProgress, Profile, ladder_rating, ladder_stack};
+use connect4::puzzle::{Puzzle, PuzzlePack, best_defense, mine_puzzles, winning_moves_within};
+use connect4::rng;
+use connect4::search_for_win::{self, SearchForWinCache};
+use connect4::selfplay;
+use connect4::sound::{SoundConfig, SoundEffects, TerminalBell};
+use connect4::stats::{mean_duration, percentile, wilson_interval, win_difference_p_value};
+use connect4::strategy::{
+    AvoidTraps, Connect4AI, Exploring, SearchForWin, Strategy, StrategyDecider, StrategyStack,
+    TriesToWin,
+};
+use connect4::strategy_cache::{StrategyCache, StrategyCacheStats};
+use connect4::strategy_kind::{
+    MAX_DIFFICULTY, StrategyKind, StrategyRegistry, build_stack, difficulty_stack, parse_stack,
+};
+use connect4::three_player::{self, Color as ThreeColor, ThreePlayerGame, ThreePlayerResult};
+use connect4::tune;
+use console::{Key, Term, style};
+use dialoguer::{Confirm, Input, Select};
 use indicatif::{ProgressBar, ProgressStyle};
+use serde::{Deserialize, Serialize};
 use std::io::Write;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::{
     thread,
     time::{Duration, Instant},
 };
-use strategy::{Setup, StrategyLayer, TriesToWin};
-
-use crate::board::ROWS;
-use crate::search_for_win::SearchForWinCache;
-use crate::strategy::{
-    AvoidInescapableTraps, AvoidTraps, Connect4AI, SearchForWin, Strategy, StrategyDecider,
-    StrategyStack, ThreeInARow,
-};
-use crate::strategy_cache::StrategyCache;
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
 
 #[derive(Parser)]
 #[command(name = "connect-4")]
 #[command(about = "A Connect 4 game with AI strategies")]
 #[command(version)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Run AI simulation mode instead of interactive game
     #[arg(short, long)]
     sim: bool,
@@ -41,329 +72,3740 @@ struct Cli {
     /// Should we cache strategy decisions
     #[arg(short = 'c', long = "cache")]
     use_cache: bool,
-}
 
-fn game(red: &dyn Connect4AI, yellow: &dyn Connect4AI) -> Option<Board> {
-    let mut board = Board::new();
-    loop {
-        // Red plays, then yellow.
-        // If there's a winner or no moves left, leave
-        if board.has_winner().is_some() || board.valid_moves().is_empty() {
-            break;
-        }
-        let col = red.play(&board)?;
-        board = board.place(col, Piece::Red);
+    /// Evict cached decisions once each strategy's cache passes this many megabytes
+    #[arg(long, value_name = "MB", requires = "use_cache")]
+    max_cache_mb: Option<usize>,
 
-        if board.has_winner().is_some() || board.valid_moves().is_empty() {
-            break;
+    /// Confidence level used for the reported intervals and significance
+    #[arg(long, default_value_t = 0.95, value_parser = parse_confidence)]
+    confidence: f64,
+
+    /// Keep simulating batches of `iterations` games until the win-rate
+    /// difference is significant at the configured confidence level
+    #[arg(long)]
+    until_significant: bool,
+
+    /// Alternate which strategy moves first each game and report results per strategy
+    #[arg(long)]
+    swap_sides: bool,
+
+    /// Write the simulation results to a file, e.g. `--output json results.json`
+    #[arg(long, num_args = 2, value_names = ["json|csv", "PATH"])]
+    output: Vec<String>,
+
+    /// Periodically save simulation progress to this file
+    #[arg(long, value_name = "FILE")]
+    checkpoint: Option<String>,
+
+    /// Continue the simulation saved in the `--checkpoint` file
+    #[arg(long, requires = "checkpoint")]
+    resume: bool,
+
+    /// Start every simulated game from this position, given as the columns played so
+    /// far (e.g. "3324")
+    #[arg(long, value_name = "MOVES", value_parser = parse_opening)]
+    opening: Option<GameState>,
+
+    /// Start every simulated game from N random legal moves instead of the empty board,
+    /// played after `--opening` if both are given
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    random_opening: usize,
+
+    /// Explore in simulations: each of the first K moves of a game is random with
+    /// probability `--epsilon` instead of the strategy's choice
+    #[arg(long, value_name = "K", default_value_t = 0)]
+    explore: usize,
+
+    /// How often an exploring move is random, from 0 to 1
+    #[arg(long, default_value_t = 0.1, value_parser = parse_probability)]
+    epsilon: f64,
+
+    /// Seed the simulation's randomness so any game can be played again exactly. Each
+    /// game gets its own seed from this one and its index
+    #[arg(long, value_name = "SEED")]
+    seed: Option<u64>,
+
+    /// Play only game N (counting from 0) of the seeded simulation, printing every move
+    /// and the options the strategies saw
+    #[arg(long, value_name = "N", requires = "seed")]
+    replay_game: Option<usize>,
+
+    /// Append every simulated game to this file, one JSON record per line
+    #[arg(long, value_name = "FILE")]
+    save_games: Option<String>,
+
+    /// Resume an interactive game saved with `s`
+    #[arg(long, value_name = "FILE", conflicts_with = "sim")]
+    load: Option<String>,
+
+    /// Play a best-of-N series against the AI, alternating who moves first
+    #[arg(long, value_name = "N", default_value_t = 1, conflicts_with = "sim")]
+    best_of: usize,
+
+    /// What to log, e.g. "debug" or "connect4::strategy=trace". Logs go to stderr
+    /// unless `--log-file` is given
+    #[arg(long, default_value = "warn", global = true)]
+    log_level: String,
+
+    /// Write logs as JSON lines
+    #[arg(long, global = true)]
+    log_json: bool,
+
+    /// Append logs to this file instead of stderr
+    #[arg(long, value_name = "FILE", global = true)]
+    log_file: Option<String>,
+
+    /// The profile file that tracks your results. Default: ~/.connect4-profile.json
+    #[arg(long, value_name = "FILE", global = true)]
+    profile: Option<String>,
+
+    /// Climb the ladder of AI levels, advancing after enough wins at each
+    #[arg(long, conflicts_with_all = ["sim", "load", "best_of", "daily"])]
+    ladder: bool,
+
+    /// Play on a chess-style clock, in seconds: base time plus an increment per move,
+    /// e.g. "60+2". Whoever runs out of time loses
+    #[arg(long, value_name = "BASE+INC")]
+    clock: Option<TimeControl>,
+
+    /// Play with the swap rule: after Red's first move, Yellow may take that piece as
+    /// its own instead of answering, and Red moves again
+    #[arg(long)]
+    swap_rule: bool,
+
+    /// Play with rotations: once per game, each player may turn the board 90° and let
+    /// the pieces fall instead of moving (press 'r')
+    #[arg(long)]
+    rotations: bool,
+
+    /// Handicap: the AI passes every Nth turn
+    #[arg(long, value_name = "N", conflicts_with = "sim")]
+    ai_skips: Option<usize>,
+
+    /// Handicap: you start with an extra piece in this column
+    #[arg(long, value_name = "COLUMN", conflicts_with = "sim")]
+    extra_piece: Option<usize>,
+
+    /// Handicap: the AI can't play the center column for its first K moves
+    #[arg(long, value_name = "K", default_value_t = 0, conflicts_with = "sim")]
+    no_center: usize,
+
+    /// Play today's challenge: the same opponent and opening for everyone
+    #[arg(long, conflicts_with_all = ["sim", "load", "best_of"])]
+    daily: bool,
+
+    /// Warn before a move that hands the AI an immediate win
+    #[arg(long, conflicts_with = "sim")]
+    coach: bool,
+
+    /// Ring the terminal bell for these events, comma-separated: drop, end (wins and
+    /// ties), invalid, or all. Off by default
+    #[arg(long, value_name = "EVENTS", value_parser = parse_sound)]
+    sound: Option<SoundConfig>,
+
+    /// Read key bindings from this TOML file. Default: ~/.config/connect-4/keys.toml
+    #[arg(long, value_name = "FILE")]
+    keys: Option<String>,
+
+    /// Start every SearchForWinCache with the positions `precompute` saved in this file
+    #[arg(long, value_name = "FILE")]
+    warm_cache: Option<String>,
+
+    /// Play with a screen reader: type column numbers instead of moving a cursor, and
+    /// hear every move and the board in words
+    #[arg(long, conflicts_with = "sim")]
+    accessible: bool,
+}
+
+impl Cli {
+    /// Turns on the rule variants chosen on the command line.
+    fn variant(&self, state: GameState) -> GameState {
+        let state = if self.swap_rule {
+            state.with_swap_rule()
+        } else {
+            state
+        };
+        if self.rotations {
+            state.with_rotations()
+        } else {
+            state
         }
-        let col = yellow.play(&board)?;
-        board = board.place(col, Piece::Yellow);
     }
-    Some(board)
 }
 
-fn simulate_games(
-    red: &dyn Connect4AI,
-    yellow: &dyn Connect4AI,
-    games: usize,
-) -> Result<(usize, usize, usize)> {
-    let mut red_wins = 0;
-    let mut yellow_wins = 0;
-    let mut ties = 0;
+#[derive(Subcommand)]
+enum Command {
+    /// Search for the best strategy parameters by playing candidates against a baseline
+    Tune {
+        /// Games each candidate plays against the baseline
+        #[arg(short, long, default_value_t = 200)]
+        games: usize,
 
-    println!("Running with strategies:\nRed:    {red}\nYellow: {yellow}",);
+        /// Deepest forced-win search to try
+        #[arg(long, default_value_t = 3)]
+        max_depth: usize,
+    },
+    /// Evolve strategy stacks with a genetic algorithm, printing the best stack each generation
+    Evolve {
+        /// Stacks in each generation
+        #[arg(short, long, default_value_t = 12)]
+        population: usize,
 
-    let pb = ProgressBar::new(games as u64);
-    pb.set_style(
-        ProgressStyle::with_template(
-            "[{eta_precise} => {elapsed_precise}] {bar:40.cyan/blue} {pos:>7}/{len:7} {msg}",
-        )
-        .unwrap(),
-    );
-    pb.set_message("Simulating games...");
+        /// Generations to breed after the first random one
+        #[arg(long, default_value_t = 10)]
+        generations: usize,
 
-    for _ in 0..games {
-        let result = game(red, yellow).unwrap();
+        /// Games each pair of stacks plays per generation
+        #[arg(short, long, default_value_t = 4)]
+        games: usize,
 
-        match result.has_winner() {
-            Some(Piece::Red) => red_wins += 1,
-            Some(Piece::Yellow) => yellow_wins += 1,
-            Some(_) => panic!("Unexpected winner"),
-            None => ties += 1,
-        }
+        /// Longest stack a genome may grow into
+        #[arg(long, default_value_t = 6)]
+        max_genes: usize,
 
-        pb.inc(1);
-    }
-    pb.finish_and_clear();
+        /// Deepest forced-win search a genome may use
+        #[arg(long, default_value_t = 4)]
+        max_depth: usize,
+    },
+    /// Print the column a strategy stack would play in a position, then exit
+    Move {
+        /// The columns played so far, numbered from 0, e.g. "3324". Empty for the start
+        #[arg(short, long, default_value = "")]
+        position: String,
 
-    Ok((red_wins, yellow_wins, ties))
-}
+        /// The strategy stack, e.g. "SearchForWin(3) => TriesToWin => AvoidTraps"
+        #[arg(short, long)]
+        strategy: String,
 
-fn play_interactive() -> Result<()> {
-    // Welcome:
-    //
-    // [ ] [ ] [ ] [ ] [ ] [ ] [ ]
-    // [ ] [ ] [ ] [ ] [ ] [ ] [ ]
-    // [ ] [ ] [ ] [ ] [ ] [ ] [ ]
-    // [ ] [ ] [B] [ ] [ ] [ ] [ ]
-    // [ ] [ ] [R] [ ] [ ] [ ] [ ]
-    // [R] [ ] [B] [ ] [ ] [ ] [ ]
-    //      ^
-    // Pick your move
-    //
-    let mut term = console::Term::stdout();
-    let mut board = Board::new();
-    let mut selection = COLUMNS / 2;
-    let ai = build_strategy_stack(Piece::Yellow, &term)?;
+        /// Write the tree the stack's first forced-win search explores here, as
+        /// Graphviz DOT. It searches every legal move, however few pieces are down
+        #[arg(long)]
+        search_tree: Option<String>,
+    },
+    /// Search a position with Monte Carlo tree search and print each move's visits,
+    /// mean value and prior
+    Analyze {
+        /// The columns played so far, numbered from 0, e.g. "3324". Empty for the start
+        #[arg(short, long, default_value = "")]
+        position: String,
 
-    // Get a move
-    // Get the AI response
-    // Redraw the board
-    // Is there a winner?
-    // Repeat
+        /// How many playouts to run
+        #[arg(short, long, default_value_t = 10_000)]
+        iterations: usize,
 
-    term.hide_cursor()?;
-    writeln!(term, "You are Red. You are playing against {}", ai)?;
-    term.write_line("")?;
+        /// The strategy stack whose move probabilities are the priors. Uniform if unset
+        #[arg(long)]
+        priors: Option<String>,
 
-    writeln!(term, "{}", board)?;
+        /// Write the search tree here, as JSON if the name ends in `.json` and as
+        /// Graphviz DOT otherwise
+        #[arg(long)]
+        tree: Option<String>,
 
-    loop {
-        'selection: loop {
-            // Draw the selection
-            writeln!(term, " {}", "    ".repeat(selection) + "^")?;
-            write!(term, "Make your move")?;
-            'key: loop {
-                let key = term.read_key()?;
-                match key {
-                    Key::Unknown => anyhow::bail!("Problem"),
-                    Key::Char('q') => anyhow::bail!("Quit!"),
-                    Key::Char('p') => {
-                        term.clear_line()?;
-                        term.clear_last_lines(ROWS + 2)?;
-                        writeln!(term, "{}", &board.short_string())?;
-                        write!(term, "\n{}\n", board)?;
-                        continue 'selection;
-                    }
-                    Key::ArrowLeft | Key::Char('a') => {
-                        selection = selection.saturating_sub(1);
-                        break 'key;
-                    }
-                    Key::ArrowRight | Key::Char('d') => {
-                        if selection < COLUMNS - 1 {
-                            selection += 1;
-                        }
-                        break 'key;
-                    }
-                    Key::Enter => {
-                        break 'selection;
-                    }
-                    _ => {}
-                }
-            }
-            term.clear_last_lines(1)?;
-        }
+        /// Leave nodes visited fewer times than this out of the tree
+        #[arg(long, default_value_t = 10)]
+        min_visits: u32,
 
-        // Make the move
-        board = board.place(selection, Piece::Red);
+        /// Also show how this strategy stack picks its move here: which columns each
+        /// layer prunes and which decider picks
+        #[arg(long, value_name = "STACK")]
+        explain: Option<String>,
+    },
+    /// Draw a position as an image
+    Render {
+        /// The columns played so far, numbered from 0, e.g. "3324". Empty for the start
+        #[arg(default_value = "")]
+        position: String,
 
-        // Update the board display
-        term.clear_line()?;
-        term.clear_last_lines(ROWS + 2)?;
-        write!(term, "\n{}\n\n", board)?;
+        /// Where to write the image: SVG, or PNG when built with the png feature,
+        /// chosen by the file's extension
+        #[arg(short, long)]
+        output: String,
+    },
+    /// Search every opening position with SearchForWinCache and save what it learns,
+    /// for `--warm-cache`
+    Precompute {
+        /// How many plies of openings to search, from the empty board
+        #[arg(long)]
+        depth: usize,
 
-        // Is the game over?
-        if let Some(winner) = board.has_winner() {
-            match winner {
-                Piece::Red => {
-                    writeln!(term, "Red wins after {} moves.", board.num_pieces_played())?
-                }
-                Piece::Yellow => writeln!(
-                    term,
-                    "Yellow wins after {} moves.",
-                    board.num_pieces_played()
-                )?,
-                Piece::Empty => unreachable!(),
-            }
-            term.show_cursor()?;
-            return Ok(());
-        }
+        /// How many of our moves SearchForWinCache looks ahead from each position
+        #[arg(long, default_value_t = 6)]
+        search: usize,
 
-        if board.valid_moves().is_empty() {
-            writeln!(term, "Tie.")?;
-            return Ok(());
-        }
+        /// Where to write the cache
+        #[arg(long)]
+        out: String,
+    },
+    /// Count the lines of legal moves from a position, to check and time move generation
+    Perft {
+        /// How many plies deep to count
+        depth: usize,
 
-        write!(term, "AI is thinking...")?;
+        /// The columns played so far, numbered from 0, e.g. "3324". Empty for the start
+        #[arg(short, long, default_value = "")]
+        position: String,
 
-        thread::sleep(Duration::from_millis(500));
-        // Make the AI move
-        let ai_move = ai.play(&board).context("Failed to get AI move");
-        board = board.place(ai_move?, Piece::Yellow);
+        /// Also print the count under each first move
+        #[arg(long)]
+        divide: bool,
+    },
+    /// Animate a saved game as a GIF, one frame per move. Needs the gif feature
+    ExportGif {
+        /// A game saved with `s` in interactive mode, or a log of games written by
+        /// `--save-games`, one per line
+        file: String,
 
-        // Update the board display
-        term.clear_line()?;
-        term.clear_last_lines(ROWS + 2)?;
-        writeln!(term, "\n{}", board)?;
+        /// Where to write the GIF
+        #[arg(short, long)]
+        output: String,
 
-        // Is the game over?
-        if let Some(winner) = board.has_winner() {
-            match winner {
-                Piece::Red => {
-                    writeln!(term, "Red wins after {} moves.", board.num_pieces_played())?
-                }
-                Piece::Yellow => writeln!(
-                    term,
-                    "Yellow wins after {} moves.",
-                    board.num_pieces_played()
-                )?,
-                Piece::Empty => unreachable!(),
-            }
-            term.show_cursor()?;
-            return Ok(());
-        }
+        /// Which game of a log to export, counting from 1
+        #[arg(long, default_value_t = 1)]
+        game: usize,
 
-        if board.valid_moves().is_empty() {
-            writeln!(term, "Tie.")?;
-            term.show_cursor()?;
-            return Ok(());
-        }
+        /// Milliseconds between frames
+        #[arg(long, default_value_t = 800)]
+        delay: u64,
+    },
+    /// List every strategy a stack can use, with its parameters, what it does and its cost
+    Strategies,
+    /// Speak a UCI-like text protocol over stdin/stdout so external GUIs can drive the AI
+    Engine,
+    /// Serve moves, analysis and the strategy list as a JSON HTTP API, for web and
+    /// mobile frontends
+    Api {
+        /// Port to listen on
+        #[arg(short, long, default_value_t = 8080)]
+        port: u16,
+    },
+    /// Host games over TCP for remote players, one game after another
+    Serve {
+        /// Port to listen on
+        #[arg(short, long, default_value_t = 4004)]
+        port: u16,
+
+        /// Who plays Red: "remote" for a network player, or a strategy stack for the server's AI
+        #[arg(long, default_value = "remote")]
+        red: String,
+
+        /// Who plays Yellow: "remote" for a network player, or a strategy stack for the server's AI
+        #[arg(long, default_value = "remote")]
+        yellow: String,
+    },
+    /// Join a game hosted with `serve` and play it in the terminal
+    Connect {
+        /// The server to join, e.g. "localhost:4004"
+        address: String,
+    },
+    /// Watch two strategy stacks play each other, one move at a time
+    Watch {
+        /// Milliseconds to pause after each move
+        #[arg(short, long, default_value_t = 500)]
+        delay: u64,
+    },
+    /// Find the forced win in tactical positions
+    Puzzle {
+        /// A JSON puzzle pack to play instead of the bundled puzzles
+        #[arg(short, long)]
+        pack: Option<String>,
+    },
+    /// Show the results recorded in your profile
+    Stats,
+    /// Experimental: play Red against two AIs, Yellow and Green, on a wider board
+    ThreePlayer,
+    /// Play without gravity: pieces go in any empty cell, like tic-tac-toe
+    NoGravity {
+        /// How many of its own moves ahead the AI looks for a forced win
+        #[arg(short, long, default_value_t = 3)]
+        depth: usize,
+    },
+    /// Play around neutral blockers dropped into random columns before the game
+    Blockers {
+        /// How many blockers to drop
+        #[arg(short, long, default_value_t = 4)]
+        count: usize,
+    },
+    /// Play AI games against each other and save every move as training data, in a NumPy
+    /// `.npy` file
+    Selfplay {
+        /// How many games to play
+        #[arg(short, long, default_value_t = 1000)]
+        games: usize,
+
+        /// Where to write the samples
+        #[arg(short, long)]
+        out: String,
+
+        /// Red's strategy stack
+        #[arg(long, default_value = "TriesToWin => AvoidTraps")]
+        red: String,
+
+        /// Yellow's strategy stack
+        #[arg(long, default_value = "TriesToWin => AvoidTraps")]
+        yellow: String,
+    },
+    /// Mine games saved with `--save-games` for forced-win puzzles and write them as a pack
+    GeneratePuzzles {
+        /// Games saved with `--save-games`
+        games: String,
+
+        /// How many moves the forced win may take
+        #[arg(short, long, default_value_t = 2)]
+        win_in: usize,
+
+        /// Where to write the puzzle pack
+        #[arg(short, long, default_value = "puzzles.json")]
+        output: String,
+
+        /// The pack's name
+        #[arg(short, long, default_value = "Generated")]
+        name: String,
+    },
+}
+
+fn parse_confidence(s: &str) -> Result<f64, String> {
+    let confidence: f64 = s.parse().map_err(|e| format!("{e}"))?;
+    if confidence > 0.0 && confidence < 1.0 {
+        Ok(confidence)
+    } else {
+        Err("Confidence must be between 0 and 1 (exclusive)".to_string())
     }
 }
 
-fn main() -> Result<()> {
-    let cli = Cli::parse();
+fn parse_opening(s: &str) -> Result<GameState, String> {
+    let state = GameState::from_moves(s).map_err(|e| format!("{e:#}"))?;
+    if state.is_over() {
+        Err("The game is already over in that position".to_string())
+    } else {
+        Ok(state)
+    }
+}
 
-    if cli.sim {
-        // Run AI vs AI simulation
-        const GAMES: usize = if cfg!(debug_assertions) { 100 } else { 100_000 };
-        let games = cli.iterations.unwrap_or(GAMES);
-        return run_simulation(games, cli.use_cache);
+fn parse_probability(s: &str) -> Result<f64, String> {
+    let probability: f64 = s.parse().map_err(|e| format!("{e}"))?;
+    if (0.0..=1.0).contains(&probability) {
+        Ok(probability)
+    } else {
+        Err("Probability must be between 0 and 1".to_string())
     }
+}
 
-    // Default behavior: interactive mode
-    play_interactive()
+fn parse_sound(s: &str) -> Result<SoundConfig, String> {
+    s.parse().map_err(|e| format!("{e:#}"))
 }
 
-fn build_strategy_stack(piece: Piece, term: &Term) -> Result<StrategyStack> {
-    let mut stack = vec![];
+/// The sound effects chosen with `--sound`, if any.
+fn sounds(cli: &Cli) -> Option<SoundEffects<TerminalBell<std::io::Stdout>>> {
+    cli.sound
+        .map(|config| SoundEffects::new(TerminalBell::stdout(), config))
+}
 
-    term.write_line(&format!("Build a strategy stack for {}. Every layer in the stack filters the possible moves. The AI will pick randomly from possible moves at the end.", piece.name()))?;
+/// Set by the Ctrl-C handler so long simulations can stop and still report what they have.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
 
-    enum Option {
-        Done,
-        Layer(Box<dyn StrategyLayer>),
-        Decider(Box<dyn StrategyDecider>),
+fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::Relaxed)
+}
+
+/// What to call the two competitors. Without `--swap-sides` they always play the
+/// same color, so we name them by it.
+struct SimulationLabels {
+    a: &'static str,
+    b: &'static str,
+    swap_sides: bool,
+}
+
+impl SimulationLabels {
+    fn new(swap_sides: bool) -> Self {
+        if swap_sides {
+            SimulationLabels {
+                a: "Strategy A",
+                b: "Strategy B",
+                swap_sides,
+            }
+        } else {
+            SimulationLabels {
+                a: Piece::Red.name(),
+                b: Piece::Yellow.name(),
+                swap_sides,
+            }
+        }
     }
+}
 
-    impl std::fmt::Display for Option {
-        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-            match self {
-                Option::Done => write!(f, "Done"),
-                Option::Layer(x) => write!(f, "Filter Layer: {}", x.name()),
-                Option::Decider(x) => write!(f, "Decider: {}", x.name()),
+#[derive(Default)]
+struct SimulationResults {
+    a_wins: usize,
+    b_wins: usize,
+    ties: usize,
+    /// Per-color breakdown, to measure the first-move advantage.
+    red_wins: usize,
+    yellow_wins: usize,
+    a_move_times: Vec<Duration>,
+    b_move_times: Vec<Duration>,
+    outcomes: OutcomeStats,
+    /// Games lost on time. Not kept in checkpoints.
+    time_losses: usize,
+    /// Distinct move sequences. Not kept in checkpoints.
+    diversity: GameDiversity,
+}
+
+impl SimulationResults {
+    fn games(&self) -> usize {
+        self.a_wins + self.b_wins + self.ties
+    }
+
+    fn average_game_length(&self) -> f64 {
+        let total_moves: usize = (self.outcomes.lengths.iter().enumerate())
+            .map(|(length, count)| length * count)
+            .sum();
+        total_moves as f64 / self.games().max(1) as f64
+    }
+
+    fn is_significant(&self, confidence: f64) -> bool {
+        win_difference_p_value(self.a_wins, self.b_wins) < 1.0 - confidence
+    }
+}
+
+/// How each simulated game is set up.
+#[derive(Clone)]
+struct GameSettings {
+    time_control: Option<TimeControl>,
+    /// Where every game starts, before the random opening moves.
+    start: GameState,
+    /// Random moves played before the strategies take over.
+    opening_moves: usize,
+    /// Each of the first `explore` moves is random with probability `epsilon`.
+    explore: usize,
+    epsilon: f64,
+    /// Seeds each game's RNG from its index.
+    seed: Option<u64>,
+}
+
+impl GameSettings {
+    fn from_cli(cli: &Cli) -> Self {
+        GameSettings {
+            time_control: cli.clock,
+            start: cli.variant(cli.opening.clone().unwrap_or_default()),
+            opening_moves: cli.random_opening,
+            explore: cli.explore,
+            epsilon: cli.epsilon,
+            seed: cli.seed,
+        }
+    }
+
+    /// Seeds the RNG for game `index` and deals its opening. With swapped sides both
+    /// games of a pair share an opening, so neither color gets the luckier one: the
+    /// second game reuses `previous`, or deals it again from the first game's seed.
+    fn start_game(
+        &self,
+        index: usize,
+        swap_sides: bool,
+        previous: Option<&GameState>,
+    ) -> GameState {
+        let reseed = |index| {
+            if let Some(seed) = self.seed {
+                rng::seed(Some(rng::game_seed(seed, index)));
             }
+        };
+        let deal = || random_opening(&mut rng::rng(), &self.start, self.opening_moves);
+        if swap_sides && index % 2 == 1 {
+            let opening = previous.cloned().unwrap_or_else(|| {
+                reseed(index - 1);
+                deal()
+            });
+            reseed(index);
+            opening
+        } else {
+            reseed(index);
+            deal()
         }
     }
+}
 
-    loop {
-        let strategies: Vec<Option> = vec![
-            Option::Done,
-            Option::Decider(Box::new(SearchForWin::new(piece, 3))),
-            Option::Decider(Box::new(SearchForWinCache::new(piece, 6))),
-            Option::Layer(Box::new(AvoidInescapableTraps::new(piece))),
-            Option::Layer(Box::new(AvoidTraps::new(piece))),
-            Option::Layer(Box::new(ThreeInARow::new(piece))),
-            Option::Decider(Box::new(Setup::new(piece))),
-            Option::Decider(Box::new(TriesToWin::new(piece))),
-        ];
+/// Plays `games` more games, adding them to `results`.
+#[allow(clippy::too_many_arguments)]
+fn simulate_games(
+    a: &SimulationAI,
+    b: &SimulationAI,
+    games: usize,
+    swap_sides: bool,
+    results: &mut SimulationResults,
+    checkpoint: Option<&Checkpointer>,
+    recorder: &mut dyn GameObserver,
+    settings: &GameSettings,
+) -> Result<()> {
+    // How often progress is saved to the checkpoint file
+    const CHECKPOINT_EVERY: usize = 1000;
+    let target_games = results.games() + games;
 
-        let choice = Select::new()
-            .default(0)
-            .with_prompt("Select a strategy")
-            .items(&strategies)
-            .interact_on(term)
-            .unwrap();
+    if swap_sides {
+        println!("Running with strategies (alternating who moves first):\nA: {a}\nB: {b}",);
+    } else {
+        println!(
+            "Running with strategies:\n{:<7} {a}\n{:<7} {b}",
+            format!("{}:", Piece::Red.name()),
+            format!("{}:", Piece::Yellow.name())
+        );
+    }
+
+    let pb = ProgressBar::new(games as u64);
+    pb.set_style(
+        ProgressStyle::with_template(
+            "[{eta_precise} => {elapsed_precise}] {bar:40.cyan/blue} {pos:>7}/{len:7} {msg}",
+        )
+        .unwrap(),
+    );
+    pb.set_message("Simulating games...");
+
+    // Refreshing the message every game would slow down fast simulations.
+    const MESSAGE_EVERY: usize = 100;
+    let labels = SimulationLabels::new(swap_sides);
+    let start = Instant::now();
+    let mut opening = None;
+
+    for i in 0..games {
+        if interrupted() {
+            break;
+        }
+
+        // Strategy A moves first on even games. When swapping, B moves first on odd games.
+        // Count every game played so far so resumed runs keep alternating.
+        let a_color = if swap_sides && results.games() % 2 == 1 {
+            Piece::Yellow
+        } else {
+            Piece::Red
+        };
+        let b_color = a_color.opponent();
+
+        let (red, yellow) = if a_color == Piece::Red {
+            (a, b)
+        } else {
+            (b, a)
+        };
+        let explores =
+            |inner| Exploring::new(inner, settings.epsilon).for_first_moves(settings.explore);
+        let (red, yellow) = (explores(red), explores(yellow));
+
+        let mut move_times = MoveTimer::new();
+        let opening =
+            opening.insert(settings.start_game(results.games(), swap_sides, opening.as_ref()));
+        let game = play_timed_game(
+            &red,
+            &yellow,
+            opening,
+            settings.time_control,
+            &mut (
+                &mut move_times,
+                (
+                    &mut results.outcomes,
+                    (&mut results.diversity, &mut *recorder),
+                ),
+            ),
+        )
+        .unwrap();
+
+        let winner = game.winner();
+        match winner {
+            Some(Piece::Red) => results.red_wins += 1,
+            Some(Piece::Yellow) => results.yellow_wins += 1,
+            Some(_) => panic!("Unexpected winner"),
+            None => {}
+        }
+        match winner {
+            Some(winner) if winner == a_color => results.a_wins += 1,
+            Some(_) => results.b_wins += 1,
+            None => results.ties += 1,
+        }
+        if game.state().out_of_time().is_some() {
+            results.time_losses += 1;
+        }
+        results
+            .a_move_times
+            .extend_from_slice(move_times.times(a_color));
+        results
+            .b_move_times
+            .extend_from_slice(move_times.times(b_color));
 
-        match strategies.into_iter().nth(choice).unwrap() {
-            Option::Done => break,
-            Option::Layer(strat) => stack.push(Strategy::Layer(strat)),
-            Option::Decider(strat) => stack.push(Strategy::Decision(strat)),
+        pb.inc(1);
+        if (i + 1) % MESSAGE_EVERY == 0 {
+            let played = results.games() as f64;
+            pb.set_message(format!(
+                "{} {:.1}% | {} {:.1}% | Ties {:.1}% | {:.2?}/game",
+                labels.a,
+                results.a_wins as f64 / played * 100.0,
+                labels.b,
+                results.b_wins as f64 / played * 100.0,
+                results.ties as f64 / played * 100.0,
+                start.elapsed() / (i as u32 + 1),
+            ));
+        }
+        if let Some(checkpoint) = checkpoint
+            && (i + 1) % CHECKPOINT_EVERY == 0
+        {
+            checkpoint.save(results, target_games)?;
         }
     }
+    pb.finish_and_clear();
 
-    // Clear the lines that we've added
-    term.clear_last_lines(stack.len() + 2)?;
+    if let Some(checkpoint) = checkpoint {
+        checkpoint.save(results, target_games)?;
+    }
 
-    let stack = StrategyStack::new(stack);
-    Ok(stack)
+    Ok(())
 }
 
-fn run_simulation(iterations: usize, use_cache: bool) -> Result<()> {
-    let term = console::Term::stdout();
+/// Plays game `index` of a seeded simulation on its own and prints every move with the
+/// options the mover's stack saw, so a game that stands out in the totals can be read
+/// move by move. Caching doesn't change any decision, but a clock does, so games under
+/// `--clock` may not come out the same.
+fn replay_game(
+    a: &SimulationAI,
+    b: &SimulationAI,
+    index: usize,
+    swap_sides: bool,
+    settings: &GameSettings,
+) -> Result<()> {
+    let labels = SimulationLabels::new(swap_sides);
+    let a_color = if swap_sides && index % 2 == 1 {
+        Piece::Yellow
+    } else {
+        Piece::Red
+    };
+    let (red, yellow) = if a_color == Piece::Red {
+        (a, b)
+    } else {
+        (b, a)
+    };
+    let explores =
+        |inner| Exploring::new(inner, settings.epsilon).for_first_moves(settings.explore);
+    let opening = settings.start_game(index, swap_sides, None);
+    let game = play_timed_game(
+        &explores(red),
+        &explores(yellow),
+        &opening,
+        settings.time_control,
+        &mut (),
+    )
+    .context("The replayed game couldn't be finished")?;
 
-    if use_cache {
-        // Let's use caching for red and yellow strategies so they run faster!
-        let red = Box::new(StrategyCache::new(build_strategy_stack(Piece::Red, &term)?));
-        let yellow = Box::new(StrategyCache::new(build_strategy_stack(
-            Piece::Yellow,
-            &term,
-        )?));
+    println!(
+        "Game {index} (seed {}): {} plays {}, {} plays {}",
+        settings.seed.unwrap_or_default(),
+        labels.a,
+        a_color.name(),
+        labels.b,
+        a_color.opponent().name()
+    );
+    println!(
+        "{:<7} {a}\n{:<7} {b}",
+        format!("{}:", a_color.name()),
+        format!("{}:", a_color.opponent().name())
+    );
+    let opened = opening.history().len();
+    // Moves given with `--opening` are the same in every game, so they aren't listed
+    let mut state = settings.start.clone();
+    for (number, &column) in game.moves().iter().enumerate().skip(state.history().len()) {
+        let player = state.to_move();
+        let played = describe_entry(column);
+        let decision = if number < opened {
+            "opening".to_string()
+        } else if column >= COLUMNS {
+            String::new()
+        } else {
+            let mover = if player == Piece::Red { red } else { yellow };
+            let options = mover.options(state.board(), player);
+            let time = game.move_times()[number - opened];
+            let explored = if options.contains(&column) {
+                ""
+            } else {
+                ", explored"
+            };
+            format!("options {options:?}{explored}, {time:.2?}")
+        };
+        println!(
+            "{:>3}. {:<7} {played:<8} {decision}",
+            number + 1,
+            player.name()
+        );
+        state.apply_entry(column)?;
+    }
+    match game.winner() {
+        Some(winner) => println!("{} wins", winner.name()),
+        None => println!("Tie"),
+    }
+    println!("{}", game.board());
+    Ok(())
+}
 
-        let start = Instant::now();
-        let (red_wins, yellow_wins, ties) =
-            simulate_games(red.as_ref(), yellow.as_ref(), iterations)?;
-        let duration = start.elapsed();
+/// Runs a batch of `iterations` games, or with `--until-significant`, keeps running
+/// batches until the result is significant (or we give up).
+fn simulate_until_done(
+    a: &SimulationAI,
+    b: &SimulationAI,
+    iterations: usize,
+    cli: &Cli,
+    run: SimulationRun,
+) -> Result<SimulationResults> {
+    // Evenly matched strategies will never become significant, so don't run forever.
+    const MAX_BATCHES: usize = 100;
 
-        println!(
-            "Result from {} games (took {}ms):",
+    let SimulationRun {
+        mut results,
+        remaining,
+        swap_sides,
+        checkpoint,
+        ..
+    } = run;
+    let checkpoint = checkpoint.as_ref();
+
+    let mut game_log = cli.save_games.as_deref().map(GameLog::append).transpose()?;
+    let mut no_log = ();
+    let recorder: &mut dyn GameObserver = match &mut game_log {
+        Some(log) => log,
+        None => &mut no_log,
+    };
+
+    simulate_games(
+        a,
+        b,
+        remaining,
+        swap_sides,
+        &mut results,
+        checkpoint,
+        recorder,
+        &GameSettings::from_cli(cli),
+    )?;
+
+    let mut batches = 1;
+    while cli.until_significant && !results.is_significant(cli.confidence) && !interrupted() {
+        if batches == MAX_BATCHES {
+            println!(
+                "Still not significant after {} games, giving up.",
+                results.games()
+            );
+            break;
+        }
+        simulate_games(
+            a,
+            b,
             iterations,
-            duration.as_millis()
-        );
+            swap_sides,
+            &mut results,
+            checkpoint,
+            recorder,
+            &GameSettings::from_cli(cli),
+        )?;
+        batches += 1;
+    }
 
+    if let Some(log) = game_log {
+        log.finish()?;
+    }
+    Ok(results)
+}
+
+fn print_results(
+    results: &SimulationResults,
+    labels: &SimulationLabels,
+    duration: Duration,
+    confidence: f64,
+) {
+    let iterations = results.games();
+    println!(
+        "Result from {} games (took {}ms):",
+        iterations,
+        duration.as_millis()
+    );
+
+    let print_rate = |label: &str, count: usize| {
+        let (lo, hi) = wilson_interval(count, iterations, confidence);
         println!(
-            "Red wins:  {:.2}%",
-            red_wins as f64 / iterations as f64 * 100.0
+            "{:<18} {:>6.2}%  ({:.0}% CI {:.2}% - {:.2}%)",
+            label,
+            count as f64 / iterations.max(1) as f64 * 100.0,
+            confidence * 100.0,
+            lo * 100.0,
+            hi * 100.0
         );
+    };
+    print_rate(&format!("{} wins:", labels.a), results.a_wins);
+    print_rate(&format!("{} wins:", labels.b), results.b_wins);
+    print_rate("Ties:", results.ties);
+    if results.time_losses > 0 {
+        println!("{:<18} {:>6} games", "Lost on time:", results.time_losses);
+    }
+    let diversity = &results.diversity;
+    if diversity.games() > 0 {
         println!(
-            "Yellow wins: {:.2}%",
-            yellow_wins as f64 / iterations as f64 * 100.0
+            "Unique games: {} of {} ({:.1}%), most repeated game played {} times",
+            diversity.unique(),
+            diversity.games(),
+            diversity.unique() as f64 / diversity.games() as f64 * 100.0,
+            diversity.most_repeated()
         );
-        println!("Ties:      {:.2}%", ties as f64 / iterations as f64 * 100.0);
-
-        let red_cache_stats = red.cache_stats();
-        let yellow_cache_stats = yellow.cache_stats();
+    }
 
-        println!("Red cache:{}", &red_cache_stats);
-        println!("Yellow cache:{}", &yellow_cache_stats);
+    let p_value = win_difference_p_value(results.a_wins, results.b_wins);
+    println!(
+        "Win-rate difference p-value: {:.4} ({})",
+        p_value,
+        if results.is_significant(confidence) {
+            "significant"
+        } else {
+            "not significant"
+        }
+    );
 
-        let cache_stats = red_cache_stats + yellow_cache_stats;
-        println!("Overall cache stats:{}", &cache_stats);
-    } else {
-        let red = Box::new(build_strategy_stack(Piece::Red, &term)?);
-        let yellow = Box::new(build_strategy_stack(Piece::Yellow, &term)?);
+    if labels.swap_sides {
+        println!("By color:");
+        print_rate(
+            &format!("{} (first) wins:", Piece::Red.name()),
+            results.red_wins,
+        );
+        print_rate(
+            &format!("{} wins:", Piece::Yellow.name()),
+            results.yellow_wins,
+        );
+    }
 
-        let start = Instant::now();
-        let (red_wins, yellow_wins, ties) =
-            simulate_games(red.as_ref(), yellow.as_ref(), iterations)?;
-        let duration = start.elapsed();
+    print_outcomes(results);
 
+    println!("Time per move:");
+    for (label, times) in [
+        (labels.a, &results.a_move_times),
+        (labels.b, &results.b_move_times),
+    ] {
+        let (Some(avg), Some(p50), Some(p90), Some(p99), Some(max)) = (
+            mean_duration(times),
+            percentile(times, 50.0),
+            percentile(times, 90.0),
+            percentile(times, 99.0),
+            percentile(times, 100.0),
+        ) else {
+            continue;
+        };
         println!(
-            "Result from {} games (took {}ms):",
-            iterations,
-            duration.as_millis()
+            "{:<11} avg {:>10.2?}  p50 {:>10.2?}  p90 {:>10.2?}  p99 {:>10.2?}  max {:>10.2?}",
+            format!("{}:", label),
+            avg,
+            p50,
+            p90,
+            p99,
+            max
         );
+    }
+}
+
+fn print_outcomes(results: &SimulationResults) {
+    let outcomes = &results.outcomes;
+    println!(
+        "Average game length: {:.1} pieces",
+        results.average_game_length()
+    );
+
+    let first = outcomes.lengths.iter().position(|&c| c > 0).unwrap_or(0);
+    let last = outcomes.lengths.iter().rposition(|&c| c > 0).unwrap_or(0);
+    print_histogram(
+        "Game length (pieces played):",
+        (first..=last).map(|length| (length.to_string(), outcomes.lengths[length])),
+    );
+    print_histogram(
+        "Winning line direction:",
+        LineDirection::ALL
+            .iter()
+            .zip(outcomes.directions)
+            .map(|(direction, count)| (direction.name().to_string(), count)),
+    );
+    print_histogram(
+        "Winning column:",
+        (outcomes.winning_columns.iter().enumerate())
+            .map(|(column, &count)| (column.to_string(), count)),
+    );
+}
+
+fn print_histogram(title: &str, rows: impl Iterator<Item = (String, usize)>) {
+    const WIDTH: usize = 40;
+    let rows: Vec<_> = rows.collect();
+    let total: usize = rows.iter().map(|(_, count)| count).sum();
+    let max = rows.iter().map(|(_, count)| *count).max().unwrap_or(0);
 
+    println!("{title}");
+    for (label, count) in rows {
+        let bar = (count * WIDTH).checked_div(max).unwrap_or(0);
         println!(
-            "Red wins:  {:.2}%",
-            red_wins as f64 / iterations as f64 * 100.0
+            "  {:>10} {:<WIDTH$} {:>6.2}% ({})",
+            label,
+            "#".repeat(bar),
+            count as f64 / total.max(1) as f64 * 100.0,
+            count
         );
-        println!(
-            "Yellow wins: {:.2}%",
-            yellow_wins as f64 / iterations as f64 * 100.0
+    }
+}
+
+/// An interactive game saved with `s`, resumed with `--load`.
+#[derive(Serialize, Deserialize)]
+struct SavedGame {
+    /// Stored as a `GameRecord`, so the file shows the board and loading checks it
+    /// against the moves.
+    #[serde(flatten)]
+    game: GameState,
+    /// The color the human plays.
+    human: Piece,
+    ai: Vec<StrategyKind>,
+}
+
+impl SavedGame {
+    fn new(state: &GameState, human: Piece, ai: &[StrategyKind]) -> Self {
+        SavedGame {
+            game: state.clone(),
+            human,
+            ai: ai.to_vec(),
+        }
+    }
+
+    fn load(path: &str) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read saved game {path}"))?;
+        serde_json::from_str(&contents).with_context(|| format!("Invalid saved game {path}"))
+    }
+
+    fn save(&self, path: &str) -> Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write saved game {path}"))
+    }
+
+    fn game_state(&self) -> Result<GameState> {
+        ensure!(
+            self.human != Piece::Empty,
+            "The saved game has no human player"
+        );
+        ensure!(!self.game.is_over(), "The saved game is already over");
+        ensure!(
+            self.game.to_move() == self.human,
+            "The saved game is not waiting for {} to move",
+            self.human.name()
         );
-        println!("Ties:      {:.2}%", ties as f64 / iterations as f64 * 100.0);
+        Ok(self.game.clone())
+    }
+}
+
+/// The running score of a best-of-N series between the human and the AI.
+#[derive(Default)]
+struct MatchScore {
+    human: usize,
+    ai: usize,
+    ties: usize,
+}
+
+impl MatchScore {
+    fn record(&mut self, result: GameResult, human: Piece) {
+        match result {
+            GameResult::Winner(winner) if winner == human => self.human += 1,
+            GameResult::Winner(_) => self.ai += 1,
+            GameResult::Tie => self.ties += 1,
+        }
+    }
+
+    /// Whether either side has won more than half of `best_of` games.
+    fn is_decided(&self, best_of: usize) -> bool {
+        self.human * 2 > best_of || self.ai * 2 > best_of
+    }
+}
+
+impl std::fmt::Display for MatchScore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "You {} - {} AI", self.human, self.ai)?;
+        match self.ties {
+            0 => Ok(()),
+            1 => write!(f, " (1 tie)"),
+            n => write!(f, " ({n} ties)"),
+        }
+    }
+}
+
+/// Plays a single game, or a best-of-`best_of` series where the human and the AI
+/// take turns moving first.
+#[allow(clippy::too_many_arguments)]
+fn play_interactive(
+    observer: &mut dyn GameObserver,
+    mut saved: Option<SavedGame>,
+    best_of: usize,
+    coach: bool,
+    accessible: bool,
+    profile: &str,
+    handicap: Handicap,
+    time_control: Option<TimeControl>,
+    variant: impl Fn(GameState) -> GameState,
+) -> Result<()> {
+    let mut term = console::Term::stdout();
+    if !handicap.is_none() {
+        writeln!(term, "Handicap: {}", handicap)?;
+    }
+    let ai_kinds = match &saved {
+        Some(saved) => saved.ai.clone(),
+        None if accessible => choose_difficulty(&term)?,
+        None => choose_strategy_kinds("the AI", &term)?,
+    };
+
+    let mut score = MatchScore::default();
+    for game in 0..best_of {
+        let (state, human) = match saved.take() {
+            Some(saved) => (saved.game_state()?, saved.human),
+            // The human moves first in the first game, then every other game
+            None if game % 2 == 0 => (handicap.starting_state(Piece::Red)?, Piece::Red),
+            None => (handicap.starting_state(Piece::Yellow)?, Piece::Yellow),
+        };
+        let state = variant(state);
+        let mut opponent = Opponent::ai(&ai_kinds, handicap);
+        let result = play_interactive_game(
+            &mut term,
+            observer,
+            state,
+            human,
+            &mut opponent,
+            coach,
+            accessible,
+            time_control,
+        )?;
+        score.record(result, human);
+        // Handicapped results would skew the rating, so they aren't recorded
+        if handicap.is_none() {
+            update_profile(&term, profile, &ai_kinds, human, result)?;
+        }
+
+        if best_of == 1 {
+            return Ok(());
+        }
+        writeln!(term, "Game {} of {}. Score: {}", game + 1, best_of, score)?;
+        if score.is_decided(best_of) || game + 1 == best_of {
+            break;
+        }
+        write!(term, "Press any key to start the next game")?;
+        term.read_key()?;
+        term.clear_line()?;
+        term.write_line("")?;
+    }
+
+    match score.human.cmp(&score.ai) {
+        std::cmp::Ordering::Greater => writeln!(term, "You win the match! {}", score)?,
+        std::cmp::Ordering::Less => writeln!(term, "The AI wins the match. {}", score)?,
+        std::cmp::Ordering::Equal => writeln!(term, "The match is drawn. {}", score)?,
+    }
+    Ok(())
+}
+
+/// Whoever the human is playing against in the terminal UI.
+fn play_daily(
+    observer: &mut dyn GameObserver,
+    coach: bool,
+    accessible: bool,
+    profile: &str,
+) -> Result<()> {
+    let daily = DailyChallenge::today();
+    let mut term = console::Term::stdout();
+    writeln!(
+        term,
+        "Daily challenge for {}: level {}, opening {:?}",
+        daily.date, daily.difficulty, daily.opening
+    )?;
+
+    let mut opponent = Opponent::ai(&daily.opponent, Handicap::default());
+    let mut moves = MoveTimer::new();
+    let result = play_interactive_game(
+        &mut term,
+        &mut (&mut moves, observer),
+        daily.state(),
+        daily.human,
+        &mut opponent,
+        coach,
+        accessible,
+        None,
+    )?;
+
+    update_profile(&term, profile, &daily.opponent, daily.human, result)?;
+
+    writeln!(term)?;
+    writeln!(
+        term,
+        "{}",
+        daily.share(result, moves.times(daily.human).len())
+    )?;
+    Ok(())
+}
+
+/// `--profile`, or a file in the home directory.
+fn profile_path(cli: &Cli) -> String {
+    cli.profile.clone().unwrap_or_else(|| {
+        let home = std::env::var("HOME")
+            .or_else(|_| std::env::var("USERPROFILE"))
+            .unwrap_or_else(|_| ".".to_string());
+        format!("{}/.connect4-profile.json", home)
+    })
+}
+
+/// Adds a finished game against the AI to the profile, creating it (and asking for a
+/// name) the first time.
+fn update_profile(
+    term: &Term,
+    path: &str,
+    opponent: &[StrategyKind],
+    human: Piece,
+    result: GameResult,
+) -> Result<()> {
+    let mut profile = load_or_create_profile(term, path)?;
+    profile.record_game(opponent, human, result);
+    profile.save(path)
+}
+
+/// Loads the profile at `path`, asking for a name to start a new one if there isn't one.
+fn load_or_create_profile(term: &Term, path: &str) -> Result<Profile> {
+    if let Some(profile) = Profile::load(path)? {
+        return Ok(profile);
+    }
+    let name: String = Input::new()
+        .with_prompt("Name for your new profile")
+        .default(std::env::var("USER").unwrap_or_else(|_| "Player".to_string()))
+        .interact_text_on(term)?;
+    Ok(Profile::new(&name))
+}
+
+/// Plays ladder games until the player stops, promoting them after enough wins at a level.
+fn play_ladder(
+    observer: &mut dyn GameObserver,
+    coach: bool,
+    accessible: bool,
+    path: &str,
+) -> Result<()> {
+    let mut term = console::Term::stdout();
+    let mut profile = load_or_create_profile(&term, path)?;
+
+    loop {
+        let progress = profile.ladder;
+        if progress.completed {
+            writeln!(term, "You have beaten every level of the ladder!")?;
+        }
+        let level = progress.level;
+        let kinds = ladder_stack(level);
+        writeln!(
+            term,
+            "Ladder level {} of {}: {}/{} wins to advance",
+            level,
+            LADDER_TOP,
+            progress.wins,
+            LadderProgress::WINS_TO_ADVANCE
+        )?;
+
+        // Alternate who moves first from game to game
+        let human = if profile.games_played % 2 == 0 {
+            Piece::Red
+        } else {
+            Piece::Yellow
+        };
+        let mut opponent = Opponent::ai(&kinds, Handicap::default());
+        let result = play_interactive_game(
+            &mut term,
+            observer,
+            GameState::new(),
+            human,
+            &mut opponent,
+            coach,
+            accessible,
+            None,
+        )?;
+
+        profile.record_game(&kinds, human, result);
+        if profile.ladder.record(result == GameResult::Winner(human)) {
+            if profile.ladder.completed {
+                writeln!(term, "You beat the top of the ladder!")?;
+            } else {
+                writeln!(term, "Promoted to level {}!", profile.ladder.level)?;
+            }
+        }
+        profile.save(path)?;
+
+        let again = Confirm::new()
+            .with_prompt("Play another ladder game?")
+            .default(true)
+            .interact_on(&term)?;
+        if !again {
+            return Ok(());
+        }
+        term.write_line("")?;
+    }
+}
+
+fn run_stats(path: &str) -> Result<()> {
+    let Some(profile) = Profile::load(path)? else {
+        println!("No profile at {} yet. Play a game to start one.", path);
+        return Ok(());
+    };
+
+    println!("Profile:      {}", profile.name);
+    println!("Games played: {}", profile.games_played);
+    println!(
+        "Rating:       {:.0} (ladder levels rated {:.0} to {:.0})",
+        profile.rating,
+        ladder_rating(0),
+        ladder_rating(LADDER_TOP)
+    );
+    println!(
+        "Win streak:   {} (best {})",
+        profile.streak, profile.best_streak
+    );
+    if profile.ladder.completed {
+        println!("Ladder:       completed");
+    } else {
+        println!(
+            "Ladder:       level {} of {}, {}/{} wins to advance",
+            profile.ladder.level,
+            LADDER_TOP,
+            profile.ladder.wins,
+            LadderProgress::WINS_TO_ADVANCE
+        );
+    }
+    if profile.records.is_empty() {
+        return Ok(());
+    }
+
+    let width = profile
+        .records
+        .keys()
+        .map(|label| label.len())
+        .max()
+        .unwrap_or(0);
+    println!();
+    println!(
+        "{:<width$}  {:>4} {:>6} {:>4}",
+        "Opponent", "Wins", "Losses", "Ties"
+    );
+    for (label, record) in &profile.records {
+        println!(
+            "{:<width$}  {:>4} {:>6} {:>4}",
+            label, record.wins, record.losses, record.ties
+        );
+    }
+    Ok(())
+}
+
+enum Opponent {
+    Ai {
+        stack: Box<dyn Connect4AI>,
+        kinds: Vec<StrategyKind>,
+        handicapped: bool,
+    },
+    Remote(Connection),
+}
+
+impl Opponent {
+    fn ai(kinds: &[StrategyKind], handicap: Handicap) -> Self {
+        Opponent::Ai {
+            stack: handicap.wrap(Box::new(build_stack(kinds))),
+            kinds: kinds.to_vec(),
+            handicapped: !handicap.is_none(),
+        }
+    }
+
+    /// Why the AI would play what it does on `board`, worked out again from its stack.
+    /// Handicaps aren't part of the stack, so they don't show up. None for remote
+    /// players.
+    fn explain(&self, board: &Board, piece: Piece) -> Option<DecisionTrace> {
+        match self {
+            Opponent::Ai { kinds, .. } => Some(build_stack(kinds).explain(board, piece)),
+            Opponent::Remote(_) => None,
+        }
+    }
+
+    /// How to refer to the opponent in a sentence.
+    fn subject(&self) -> &'static str {
+        match self {
+            Opponent::Ai { .. } => "The AI",
+            Opponent::Remote(_) => "Your opponent",
+        }
+    }
+
+    fn waiting_message(&self) -> &'static str {
+        match self {
+            Opponent::Ai { .. } => "AI is thinking...",
+            Opponent::Remote(_) => "Waiting for your opponent...",
+        }
+    }
+
+    /// Tells the opponent about the human's move.
+    fn send_move(&mut self, column: usize) -> Result<()> {
+        match self {
+            Opponent::Ai { .. } => Ok(()),
+            Opponent::Remote(connection) => connection.send(&ClientMessage::Move { column }),
+        }
+    }
+
+    /// Waits for the opponent's reply to `state`, which may be `PASS` for a handicapped AI
+    /// `SWAP` under the swap rule or `ROTATE` with rotations.
+    /// `remaining` is the opponent's time in a timed game.
+    fn next_move(&mut self, state: &GameState, remaining: Option<Duration>) -> Result<usize> {
+        match self {
+            Opponent::Ai { stack, .. } => {
+                if state.can_swap() && stack.swaps(state.board()) {
+                    return Ok(SWAP);
+                }
+                let piece = state.to_move();
+                if state.can_rotate() && stack.rotates(state.board(), piece) {
+                    return Ok(ROTATE);
+                }
+                if stack.passes(state.board(), piece) {
+                    return Ok(PASS);
+                }
+                let choice = match remaining {
+                    Some(remaining) => stack.play_timed(state.board(), piece, remaining),
+                    // Give the human a moment to see whose turn it is, except on a clock
+                    None => {
+                        thread::sleep(Duration::from_millis(500));
+                        stack.play(state.board(), piece)
+                    }
+                };
+                choice.context("Failed to get AI move")
+            }
+            Opponent::Remote(connection) => loop {
+                // The server echoes our own moves back, so skip states we already know
+                match connection.receive::<ServerMessage>()? {
+                    Some(ServerMessage::State { history, .. })
+                        if history.len() > state.history().len() =>
+                    {
+                        ensure!(
+                            history.starts_with(state.history()),
+                            "Out of sync with the server"
+                        );
+                        return Ok(history[state.history().len()]);
+                    }
+                    Some(ServerMessage::Error { message }) => {
+                        anyhow::bail!("The server rejected a move: {message}")
+                    }
+                    Some(_) => {}
+                    None => anyhow::bail!("The server hung up"),
+                }
+            },
+        }
+    }
+
+    /// The strategy stack to write to a save file, if this opponent can be saved.
+    fn saved_kinds(&self) -> Result<&[StrategyKind]> {
+        match self {
+            // Passes leave boards that saved games don't accept
+            Opponent::Ai {
+                handicapped: true, ..
+            } => anyhow::bail!("Handicapped games can't be saved"),
+            Opponent::Ai { kinds, .. } => Ok(kinds),
+            Opponent::Remote(_) => anyhow::bail!("Network games can't be saved"),
+        }
+    }
+}
+
+impl std::fmt::Display for Opponent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Opponent::Ai { stack, .. } => write!(f, "{}", stack),
+            Opponent::Remote(_) => write!(f, "a remote player"),
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn play_interactive_game(
+    term: &mut Term,
+    observer: &mut dyn GameObserver,
+    mut state: GameState,
+    human: Piece,
+    opponent: &mut Opponent,
+    coach: bool,
+    accessible: bool,
+    time_control: Option<TimeControl>,
+) -> Result<GameResult> {
+    if accessible {
+        return play_accessible_game(term, observer, state, human, opponent, coach, time_control);
+    }
+    // Welcome:
+    //
+    // [ ] [ ] [ ] [ ] [ ] [ ] [ ]
+    // [ ] [ ] [ ] [ ] [ ] [ ] [ ]
+    // [ ] [ ] [ ] [ ] [ ] [ ] [ ]
+    // [ ] [ ] [B] [ ] [ ] [ ] [ ]
+    // [ ] [ ] [R] [ ] [ ] [ ] [ ]
+    // [R] [ ] [B] [ ] [ ] [ ] [ ]
+    //      ^
+    // Pick your move
+    //
+    let mut selection = COLUMNS / 2;
+    let opponent_piece = human.opponent();
+    let mut clock = time_control.map(Clock::new);
+    // The opponent's last move and the board it was played on, for explaining it
+    let mut last_reply: Option<(Board, usize)> = None;
+
+    // Get a move
+    // Get the AI response
+    // Redraw the board
+    // Is there a winner?
+    // Repeat
+
+    term.hide_cursor()?;
+    writeln!(
+        term,
+        "You are {}. You are playing against {}",
+        human.name(),
+        opponent
+    )?;
+    term.write_line("")?;
+
+    writeln!(term, "{}", state.board())?;
+    if state.to_move() == opponent_piece {
+        // The opponent's turn expects a blank line under the board, as if we had just moved
+        term.write_line("")?;
+    }
+
+    loop {
+        if state.to_move() == human {
+            if state.can_swap() {
+                let swap = Confirm::new()
+                    .with_prompt(format!(
+                        "Take {}'s first move as your own?",
+                        opponent_piece.name()
+                    ))
+                    .default(false)
+                    .interact_on(term)?;
+                term.hide_cursor()?;
+                term.clear_last_lines(1)?;
+                if swap {
+                    state.swap()?;
+                    observer.on_special_move(&state, human, SWAP);
+                    // Leave the screen as the opponent's turn expects it
+                    term.clear_last_lines(ROWS + 1)?;
+                    write!(term, "You take the first move.\n{}\n\n", state.board())?;
+                    continue;
+                }
+            }
+            let start = Instant::now();
+            let prompt = match &clock {
+                Some(clock) => format!(
+                    "Make your move  [you {} | {} {}]",
+                    format_clock(clock.remaining(human)),
+                    opponent_piece.name(),
+                    format_clock(clock.remaining(opponent_piece))
+                ),
+                None => "Make your move".to_string(),
+            };
+            let prompt = if state.can_rotate() {
+                format!(
+                    "{prompt}  ({} to rotate)",
+                    keymap().key_for(KeyAction::Rotate)
+                )
+            } else {
+                prompt
+            };
+            let mut rotate = false;
+            'selection: loop {
+                // Draw the selection
+                writeln!(term, " {}", "    ".repeat(selection) + "^")?;
+                write!(term, "{}", prompt)?;
+                'key: loop {
+                    let action = match read_input(true)? {
+                        UiInput::Key(Key::Unknown) => anyhow::bail!("Problem"),
+                        UiInput::Key(key) => key_action(&key),
+                        UiInput::Click(column) if state.legal_moves().contains(&column) => {
+                            selection = column;
+                            Some(KeyAction::Drop)
+                        }
+                        UiInput::Click(column) => {
+                            observer.on_invalid_move(&state, human, column);
+                            continue 'key;
+                        }
+                        // Follow the pointer with the selection
+                        UiInput::Hover(column) => {
+                            if column == selection {
+                                continue 'key;
+                            }
+                            selection = column;
+                            break 'key;
+                        }
+                    };
+                    match action {
+                        Some(KeyAction::Quit) => anyhow::bail!("Quit!"),
+                        Some(KeyAction::Position) => {
+                            term.clear_line()?;
+                            term.clear_last_lines(ROWS + 2)?;
+                            writeln!(term, "{}", &state.board().short_string())?;
+                            write!(term, "\n{}\n", state.board())?;
+                            continue 'selection;
+                        }
+                        Some(KeyAction::Explain) => {
+                            term.clear_line()?;
+                            term.clear_last_lines(ROWS + 2)?;
+                            let explanation = last_reply.and_then(|(board, column)| {
+                                let explanation = opponent.explain(&board, opponent_piece)?;
+                                Some(format!("Why {column}?\n{explanation}"))
+                            });
+                            match explanation {
+                                Some(explanation) => writeln!(term, "{explanation}")?,
+                                None => writeln!(term, "There is no AI move to explain.")?,
+                            }
+                            write!(term, "\n{}\n", state.board())?;
+                            continue 'selection;
+                        }
+                        Some(KeyAction::Save) => {
+                            term.clear_line()?;
+                            term.clear_last_lines(ROWS + 2)?;
+                            term.show_cursor()?;
+                            let path: String = Input::new()
+                                .with_prompt("Save to")
+                                .default("connect4-save.json".to_string())
+                                .interact_text_on(term)?;
+                            term.hide_cursor()?;
+                            term.clear_last_lines(1)?;
+                            let saved = opponent
+                                .saved_kinds()
+                                .and_then(|kinds| SavedGame::new(&state, human, kinds).save(&path));
+                            match saved {
+                                Ok(()) => writeln!(term, "Saved to {}", path)?,
+                                Err(e) => writeln!(term, "{:#}", e)?,
+                            }
+                            write!(term, "\n{}\n", state.board())?;
+                            continue 'selection;
+                        }
+                        Some(KeyAction::Left) => {
+                            selection = selection.saturating_sub(1);
+                            break 'key;
+                        }
+                        Some(KeyAction::Right) => {
+                            if selection < COLUMNS - 1 {
+                                selection += 1;
+                            }
+                            break 'key;
+                        }
+                        Some(KeyAction::Rotate) if state.can_rotate() => {
+                            rotate = true;
+                            break 'selection;
+                        }
+                        // Ignore the key press if that column can't be played
+                        Some(KeyAction::Drop) if state.legal_moves().contains(&selection) => {
+                            if coach && gives_opponent_a_win(state.board(), selection, human) {
+                                term.clear_line()?;
+                                write!(
+                                    term,
+                                    "That lets your opponent win on their next move. Play it anyway? [y/N]"
+                                )?;
+                                let confirmed = read_input(false)? == UiInput::Key(Key::Char('y'));
+                                term.clear_line()?;
+                                write!(term, "{}", prompt)?;
+                                if !confirmed {
+                                    continue 'key;
+                                }
+                            }
+                            break 'selection;
+                        }
+                        Some(KeyAction::Drop) => observer.on_invalid_move(&state, human, selection),
+                        _ => {}
+                    }
+                }
+                term.clear_last_lines(1)?;
+            }
+
+            // Make the move, unless the human ran out of time choosing it
+            let elapsed = start.elapsed();
+            if let Some(clock) = &mut clock
+                && !clock.charge(human, elapsed)
+            {
+                state.lose_on_time(human)?;
+            } else if rotate {
+                state.rotate()?;
+                observer.on_special_move(&state, human, ROTATE);
+            } else {
+                state.apply_move(selection)?;
+                observer.on_move(&state, human, selection, elapsed);
+                opponent.send_move(selection)?;
+            }
+
+            // Update the board display
+            term.clear_line()?;
+            term.clear_last_lines(ROWS + 2)?;
+            write!(term, "\n{}\n\n", state.board())?;
+        } else {
+            write!(term, "{}", opponent.waiting_message())?;
+
+            let before = *state.board();
+            let note = match opponent_turn(&mut state, opponent, &mut clock, observer)? {
+                Some(entry) => {
+                    last_reply = (entry < COLUMNS).then_some((before, entry));
+                    special_move_note(entry).unwrap_or("")
+                }
+                None => "",
+            };
+
+            // Update the board display
+            term.clear_line()?;
+            term.clear_last_lines(ROWS + 2)?;
+            writeln!(term, "{}\n{}", note, state.board())?;
+        }
+
+        // Is the game over?
+        if let Some(result) = state.result() {
+            observer.on_game_end(&state, result);
+            finish_interactive(term, &state, result)?;
+            offer_analysis(term, &state, human)?;
+            return Ok(result);
+        }
+    }
+}
+
+/// The game loop for `--accessible`: plain lines a screen reader can follow, with no
+/// cursor movement or redrawing. Columns are typed as numbers from 1, and every move
+/// is announced in words.
+fn play_accessible_game(
+    term: &mut Term,
+    observer: &mut dyn GameObserver,
+    mut state: GameState,
+    human: Piece,
+    opponent: &mut Opponent,
+    coach: bool,
+    time_control: Option<TimeControl>,
+) -> Result<GameResult> {
+    let opponent_piece = human.opponent();
+    let mut clock = time_control.map(Clock::new);
+    writeln!(
+        term,
+        "You are {}. You are playing against {}.",
+        human.name(),
+        opponent
+    )?;
+    writeln!(
+        term,
+        "Type a column number from 1 to {COLUMNS} to drop a piece, b to hear the board, s to save or q to quit."
+    )?;
+    writeln!(term, "{}", describe_board(state.board()))?;
+
+    loop {
+        if state.to_move() == human {
+            if state.can_swap() {
+                let prompt = format!(
+                    "Take {}'s first move as your own? Type y or n",
+                    opponent_piece.name()
+                );
+                if read_answer(term, &prompt)? == "y" {
+                    state.swap()?;
+                    observer.on_special_move(&state, human, SWAP);
+                    writeln!(term, "You take the first move.")?;
+                    continue;
+                }
+            }
+            let start = Instant::now();
+            let mut prompt = "Your move".to_string();
+            if let Some(clock) = &clock {
+                prompt += &format!(
+                    ". You have {} left, {} has {}",
+                    format_clock(clock.remaining(human)),
+                    opponent_piece.name(),
+                    format_clock(clock.remaining(opponent_piece))
+                );
+            }
+            if state.can_rotate() {
+                prompt += ". Type r to rotate";
+            }
+            // A column, or None to rotate
+            let choice = loop {
+                let answer = read_answer(term, &prompt)?;
+                match answer.as_str() {
+                    "q" => anyhow::bail!("Quit!"),
+                    "b" => writeln!(term, "{}", describe_board(state.board()))?,
+                    "p" => writeln!(term, "{}", state.board().short_string())?,
+                    "s" => {
+                        let path: String = Input::new()
+                            .with_prompt("Save to")
+                            .default("connect4-save.json".to_string())
+                            .interact_text_on(term)?;
+                        let saved = opponent
+                            .saved_kinds()
+                            .and_then(|kinds| SavedGame::new(&state, human, kinds).save(&path));
+                        match saved {
+                            Ok(()) => writeln!(term, "Saved to {}", path)?,
+                            Err(e) => writeln!(term, "{:#}", e)?,
+                        }
+                    }
+                    "r" if state.can_rotate() => break None,
+                    _ => match answer.parse::<usize>() {
+                        Ok(number) if (1..=COLUMNS).contains(&number) => {
+                            let column = number - 1;
+                            if !state.legal_moves().contains(&column) {
+                                observer.on_invalid_move(&state, human, column);
+                                writeln!(term, "Column {number} is full.")?;
+                            } else if coach
+                                && gives_opponent_a_win(state.board(), column, human)
+                                && read_answer(
+                                    term,
+                                    "That lets your opponent win on their next move. Play it anyway? Type y or n",
+                                )? != "y"
+                            {
+                                continue;
+                            } else {
+                                break Some(column);
+                            }
+                        }
+                        _ => writeln!(
+                            term,
+                            "Type a column number from 1 to {COLUMNS}, b for the board, s to save or q to quit."
+                        )?,
+                    },
+                }
+            };
+
+            let elapsed = start.elapsed();
+            if let Some(clock) = &mut clock
+                && !clock.charge(human, elapsed)
+            {
+                state.lose_on_time(human)?;
+            } else if let Some(column) = choice {
+                state.apply_move(column)?;
+                observer.on_move(&state, human, column, elapsed);
+                opponent.send_move(column)?;
+                writeln!(term, "You play {}.", describe_move(state.board(), column))?;
+            } else {
+                state.rotate()?;
+                observer.on_special_move(&state, human, ROTATE);
+                writeln!(term, "You rotate the board.")?;
+                writeln!(term, "{}", describe_board(state.board()))?;
+            }
+        } else {
+            writeln!(term, "{}", opponent.waiting_message())?;
+            match opponent_turn(&mut state, opponent, &mut clock, observer)? {
+                Some(ROTATE) => {
+                    writeln!(term, "{}", special_move_note(ROTATE).unwrap_or_default())?;
+                    writeln!(term, "{}", describe_board(state.board()))?;
+                }
+                Some(entry) => match special_move_note(entry) {
+                    Some(note) => writeln!(term, "{note}")?,
+                    None => writeln!(
+                        term,
+                        "{} plays {}.",
+                        opponent.subject(),
+                        describe_move(state.board(), entry)
+                    )?,
+                },
+                None => {}
+            }
+        }
+
+        if let Some(result) = state.result() {
+            observer.on_game_end(&state, result);
+            finish_interactive(term, &state, result)?;
+            offer_analysis(term, &state, human)?;
+            return Ok(result);
+        }
+    }
+}
+
+/// Asks a question on its own line and returns the trimmed, lowercase answer.
+fn read_answer(term: &mut Term, prompt: &str) -> Result<String> {
+    write!(term, "{prompt}: ")?;
+    Ok(term.read_line()?.trim().to_ascii_lowercase())
+}
+
+/// Asks for the AI's difficulty as a typed number, instead of the strategy menus.
+fn choose_difficulty(term: &Term) -> Result<Vec<StrategyKind>> {
+    let difficulty: u8 = Input::new()
+        .with_prompt(format!("AI difficulty, from 0 to {MAX_DIFFICULTY}"))
+        .default(3)
+        .validate_with(|difficulty: &u8| {
+            if *difficulty <= MAX_DIFFICULTY {
+                Ok(())
+            } else {
+                Err(format!("Type a number from 0 to {MAX_DIFFICULTY}"))
+            }
+        })
+        .interact_text_on(term)?;
+    Ok(difficulty_stack(difficulty))
+}
+
+/// Makes the opponent's move, charging its clock. Returns the history entry it played,
+/// or None if it ran out of time first.
+fn opponent_turn(
+    state: &mut GameState,
+    opponent: &mut Opponent,
+    clock: &mut Option<Clock>,
+    observer: &mut dyn GameObserver,
+) -> Result<Option<usize>> {
+    let piece = state.to_move();
+    let start = Instant::now();
+    let remaining = clock.as_ref().map(|clock| clock.remaining(piece));
+    let entry = opponent.next_move(state, remaining)?;
+    let elapsed = start.elapsed();
+    if let Some(clock) = clock
+        && !clock.charge(piece, elapsed)
+    {
+        state.lose_on_time(piece)?;
+        return Ok(None);
+    }
+    state.apply_entry(entry)?;
+    if entry < COLUMNS {
+        observer.on_move(state, piece, entry, elapsed);
+    } else {
+        observer.on_special_move(state, piece, entry);
+    }
+    Ok(Some(entry))
+}
+
+/// What to tell the human when the AI passes, swaps or rotates instead of moving.
+fn special_move_note(entry: usize) -> Option<&'static str> {
+    match entry {
+        PASS => Some("The AI skips its turn."),
+        SWAP => Some("The AI takes your first move as its own."),
+        ROTATE => Some("The AI rotates the board."),
+        _ => None,
+    }
+}
+
+/// The key bindings for this run, set from `--keys` or the default keymap file.
+static KEYMAP: OnceLock<Keymap> = OnceLock::new();
+
+fn keymap() -> &'static Keymap {
+    KEYMAP.get_or_init(Keymap::default)
+}
+
+/// Loads the keymap from `path`, or from the default file if there is one.
+fn load_keymap(path: Option<&str>) -> Result<()> {
+    let keymap = match path {
+        Some(path) => Keymap::load(path)?.with_context(|| format!("No keymap at {path}"))?,
+        None => Keymap::load(&keymap_path())?.unwrap_or_default(),
+    };
+    KEYMAP.set(keymap).expect("the keymap is loaded once");
+    Ok(())
+}
+
+/// What `key` does under the keymap, named as keymap files name it.
+fn key_action(key: &Key) -> Option<KeyAction> {
+    let name = match key {
+        Key::Char(' ') => "Space".to_string(),
+        Key::Char(c) => c.to_string(),
+        Key::ArrowLeft => "Left".to_string(),
+        Key::ArrowRight => "Right".to_string(),
+        Key::ArrowUp => "Up".to_string(),
+        Key::ArrowDown => "Down".to_string(),
+        Key::Enter => "Enter".to_string(),
+        Key::Tab => "Tab".to_string(),
+        Key::Escape => "Escape".to_string(),
+        Key::Backspace => "Backspace".to_string(),
+        _ => return None,
+    };
+    keymap().action(&name)
+}
+
+/// One thing the human did while choosing a column.
+#[derive(Debug, PartialEq, Eq)]
+enum UiInput {
+    Key(Key),
+    /// The pointer moved over this column of the board.
+    Hover(usize),
+    /// This column of the board was clicked.
+    Click(usize),
+}
+
+/// Raw mode, plus mouse capture if asked for, until dropped. Capture is only on while
+/// waiting for input, so mouse reports never land on the screen as text.
+struct RawInput {
+    mouse: bool,
+}
+
+impl RawInput {
+    fn start(mouse: bool) -> Result<Self> {
+        crossterm::terminal::enable_raw_mode()?;
+        let input = RawInput { mouse };
+        if mouse {
+            crossterm::execute!(std::io::stdout(), crossterm::event::EnableMouseCapture)?;
+        }
+        Ok(input)
+    }
+}
+
+impl Drop for RawInput {
+    fn drop(&mut self) {
+        if self.mouse {
+            let _ = crossterm::execute!(std::io::stdout(), crossterm::event::DisableMouseCapture);
+        }
+        let _ = crossterm::terminal::disable_raw_mode();
+    }
+}
+
+/// Waits for a key press or, with `mouse`, a click or pointer move over the board.
+/// Columns are found from where they are drawn: four characters each, from the left edge.
+fn read_input(mouse: bool) -> Result<UiInput> {
+    use crossterm::event::{
+        Event, KeyCode, KeyEventKind, KeyModifiers, MouseButton, MouseEventKind,
+    };
+
+    let _raw = RawInput::start(mouse)?;
+    loop {
+        match crossterm::event::read()? {
+            Event::Key(event) if event.kind != KeyEventKind::Release => {
+                let key = match event.code {
+                    KeyCode::Char('c') if event.modifiers.contains(KeyModifiers::CONTROL) => {
+                        anyhow::bail!("Quit!")
+                    }
+                    KeyCode::Char(c) => Key::Char(c),
+                    KeyCode::Left => Key::ArrowLeft,
+                    KeyCode::Right => Key::ArrowRight,
+                    KeyCode::Up => Key::ArrowUp,
+                    KeyCode::Down => Key::ArrowDown,
+                    KeyCode::Enter => Key::Enter,
+                    KeyCode::Esc => Key::Escape,
+                    KeyCode::Backspace => Key::Backspace,
+                    KeyCode::Tab => Key::Tab,
+                    _ => continue,
+                };
+                return Ok(UiInput::Key(key));
+            }
+            Event::Mouse(event) if mouse => {
+                let column = event.column as usize / 4;
+                if column >= COLUMNS {
+                    continue;
+                }
+                match event.kind {
+                    MouseEventKind::Down(MouseButton::Left) => return Ok(UiInput::Click(column)),
+                    MouseEventKind::Moved | MouseEventKind::Drag(_) => {
+                        return Ok(UiInput::Hover(column));
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Whether playing `column` would leave the opponent an immediate win.
+fn gives_opponent_a_win(board: &Board, column: usize, piece: Piece) -> bool {
+    // A winning move has no reply, so it leaves no winning moves
+    !(board.place(column, piece))
+        .winning_moves(piece.opponent())
+        .is_empty()
+}
+
+/// Asks whether to analyze the finished game, and if so annotates every move and
+/// points out where the human lost.
+fn offer_analysis(term: &mut Term, state: &GameState, human: Piece) -> Result<()> {
+    const DEPTH: usize = if cfg!(debug_assertions) { 4 } else { 7 };
+
+    let analyze = Confirm::new()
+        .with_prompt("Analyze the game?")
+        .default(false)
+        .interact_on(term)?;
+    if !analyze {
+        return Ok(());
+    }
+
+    writeln!(term, "Analyzing {} plies ahead...", DEPTH)?;
+    let annotations = analyze_game(state.history(), &DepthSearch::new(DEPTH));
+    term.clear_last_lines(1)?;
+    let lost_at = losing_move(&annotations, human);
+
+    for (i, annotation) in annotations.iter().enumerate() {
+        let who = if annotation.player == human {
+            "You"
+        } else {
+            "AI"
+        };
+        let mut line = format!(
+            "{:>2}. {:<3} column {}  {:<7}",
+            i + 1,
+            who,
+            annotation.column,
+            annotation.quality.name()
+        );
+        if annotation.quality != MoveQuality::Best {
+            line += &format!("  (best: {:?})", annotation.best_columns);
+        }
+        if lost_at == Some(i) {
+            line += "  <- the game was lost here";
+            writeln!(term, "{}", style(line).red().bold())?;
+        } else if annotation.quality == MoveQuality::Blunder {
+            writeln!(term, "{}", style(line).yellow())?;
+        } else {
+            writeln!(term, "{}", line)?;
+        }
+    }
+    Ok(())
+}
+
+fn finish_interactive(term: &mut Term, state: &GameState, result: GameResult) -> Result<()> {
+    match result {
+        GameResult::Winner(winner) if state.out_of_time().is_some() => writeln!(
+            term,
+            "{} ran out of time. {} wins.",
+            winner.opponent().name(),
+            winner.name()
+        )?,
+        GameResult::Winner(winner) => writeln!(
+            term,
+            "{} wins after {} moves.",
+            winner.name(),
+            state.board().num_pieces_played()
+        )?,
+        GameResult::Tie => writeln!(term, "Tie.")?,
+    }
+    term.show_cursor()?;
+    Ok(())
+}
+
+fn init_logging(cli: &Cli) -> Result<()> {
+    let filter = tracing_subscriber::EnvFilter::try_new(&cli.log_level)
+        .with_context(|| format!("Invalid --log-level {:?}", cli.log_level))?;
+    let writer = match &cli.log_file {
+        Some(path) => BoxMakeWriter::new(std::sync::Mutex::new(
+            std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .with_context(|| format!("Failed to open log file {path}"))?,
+        )),
+        None => BoxMakeWriter::new(std::io::stderr),
+    };
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(writer)
+        .with_ansi(cli.log_file.is_none());
+    if cli.log_json {
+        builder.json().init();
+    } else {
+        builder.init();
+    }
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    init_logging(&cli)?;
+    load_keymap(cli.keys.as_deref())?;
+    if let Some(path) = &cli.warm_cache {
+        let positions = search_for_win::warm_start(path)?;
+        tracing::info!(positions, path, "warmed SearchForWinCache");
+    }
+
+    match cli.command {
+        Some(Command::Tune { games, max_depth }) => return run_tune(games, max_depth),
+        Some(Command::Evolve {
+            population,
+            generations,
+            games,
+            max_genes,
+            max_depth,
+        }) => {
+            return run_evolve(&EvolveConfig {
+                population,
+                generations,
+                games_per_pair: games,
+                max_genes,
+                max_depth,
+            });
+        }
+        Some(Command::Move {
+            ref position,
+            ref strategy,
+            ref search_tree,
+        }) => return run_move(position, strategy, search_tree.as_deref()),
+        Some(Command::Analyze {
+            ref position,
+            iterations,
+            ref priors,
+            ref tree,
+            min_visits,
+            ref explain,
+        }) => {
+            return run_analyze(
+                position,
+                iterations,
+                priors.as_deref(),
+                tree.as_deref(),
+                min_visits,
+                explain.as_deref(),
+            );
+        }
+        Some(Command::Strategies) => {
+            run_strategies();
+            return Ok(());
+        }
+        Some(Command::Engine) => return engine::run(std::io::stdin().lock(), std::io::stdout()),
+        Some(Command::Render {
+            ref position,
+            ref output,
+        }) => return run_render(position, output),
+        Some(Command::Precompute {
+            depth,
+            search,
+            ref out,
+        }) => return run_precompute(depth, search, out),
+        Some(Command::Perft {
+            depth,
+            ref position,
+            divide,
+        }) => return run_perft(depth, position, divide),
+        Some(Command::ExportGif {
+            ref file,
+            ref output,
+            game,
+            delay,
+        }) => return run_export_gif(file, output, game, Duration::from_millis(delay)),
+        Some(Command::Api { port }) => return run_api(port),
+        Some(Command::Serve { port, red, yellow }) => return run_serve(port, &red, &yellow),
+        Some(Command::Connect { ref address }) => {
+            return run_connect(&mut sounds(&cli), address, cli.coach, cli.accessible);
+        }
+        Some(Command::Watch { delay }) => return run_watch(Duration::from_millis(delay)),
+        Some(Command::Stats) => return run_stats(&profile_path(&cli)),
+        Some(Command::Puzzle { ref pack }) => return run_puzzle(pack.as_deref()),
+        Some(Command::ThreePlayer) => return run_three_player(),
+        Some(Command::NoGravity { depth }) => return run_no_gravity(depth),
+        Some(Command::Blockers { count }) => return run_blockers(count),
+        Some(Command::Selfplay {
+            games,
+            ref out,
+            ref red,
+            ref yellow,
+        }) => return run_selfplay(games, out, red, yellow),
+        Some(Command::GeneratePuzzles {
+            ref games,
+            win_in,
+            ref output,
+            ref name,
+        }) => return run_generate_puzzles(games, win_in, output, name),
+        None => {}
+    }
+
+    if cli.sim {
+        // Run AI vs AI simulation
+        const GAMES: usize = if cfg!(debug_assertions) { 100 } else { 100_000 };
+        let games = cli.iterations.unwrap_or(GAMES);
+        return run_simulation(games, &cli);
+    }
+
+    if cli.ladder {
+        return play_ladder(
+            &mut sounds(&cli),
+            cli.coach,
+            cli.accessible,
+            &profile_path(&cli),
+        );
+    }
+    if cli.daily {
+        return play_daily(
+            &mut sounds(&cli),
+            cli.coach,
+            cli.accessible,
+            &profile_path(&cli),
+        );
+    }
+
+    // Default behavior: interactive mode
+    ensure!(cli.best_of >= 1, "--best-of must be at least 1");
+    let saved = cli.load.as_deref().map(SavedGame::load).transpose()?;
+    let handicap = Handicap {
+        skip_every: cli.ai_skips,
+        extra_piece: cli.extra_piece,
+        no_center_moves: cli.no_center,
+    };
+    handicap.validate()?;
+    ensure!(
+        saved.is_none() || handicap.is_none(),
+        "Handicaps can't be added to a saved game"
+    );
+    play_interactive(
+        &mut sounds(&cli),
+        saved,
+        cli.best_of,
+        cli.coach,
+        cli.accessible,
+        &profile_path(&cli),
+        handicap,
+        cli.clock,
+        |state| cli.variant(state),
+    )
+}
+
+/// Asks for a strategy stack: one of the saved presets, or a new one from the editor,
+/// which can then be saved as a preset.
+fn choose_strategy_kinds(label: &str, term: &Term) -> Result<Vec<StrategyKind>> {
+    let dir = presets_dir();
+    let presets = load_presets(&dir).unwrap_or_else(|e| {
+        println!("Couldn't load strategy presets: {e:#}");
+        vec![]
+    });
+    if !presets.is_empty() {
+        let choices: Vec<String> = std::iter::once("Build a new stack".to_string())
+            .chain(
+                (presets.iter())
+                    .map(|preset| format!("{}: {}", preset.name, format_genome(&preset.stack))),
+            )
+            .collect();
+        let choice = Select::new()
+            .default(0)
+            .with_prompt(format!("Strategy stack for {}", label))
+            .items(&choices)
+            .interact_on(term)?;
+        term.clear_last_lines(1)?;
+        if choice > 0 {
+            return Ok(presets[choice - 1].stack.clone());
+        }
+    }
+
+    let stack = edit_strategy_stack(label, term)?;
+    if stack.is_empty() {
+        return Ok(stack);
+    }
+    let save = Confirm::new()
+        .with_prompt("Save this stack as a preset?")
+        .default(false)
+        .interact_on(term)?;
+    term.clear_last_lines(1)?;
+    if save {
+        let name: String = Input::new()
+            .with_prompt("Preset name")
+            .validate_with(|name: &String| {
+                StrategyPreset::new(name, vec![])
+                    .map(|_| ())
+                    .map_err(|e| e.to_string())
+            })
+            .interact_text_on(term)?;
+        term.clear_last_lines(1)?;
+        StrategyPreset::new(&name, stack.clone())?.save(&dir)?;
+        println!("Saved preset {} to {}", name, dir);
+    }
+    Ok(stack)
+}
+
+/// An interactive editor for a strategy stack: layers can be added anywhere, removed,
+/// moved and, for searches, given a depth.
+fn edit_strategy_stack(label: &str, term: &Term) -> Result<Vec<StrategyKind>> {
+    let mut stack = vec![];
+    term.write_line(&format!("Build a strategy stack for {}. Layers run from the top: filter layers narrow the possible moves and deciders pick one outright. The AI will pick randomly from the moves left at the end.", label))?;
+    // Every prompt leaves a line behind, cleared once the stack is done
+    let mut lines = 1;
+
+    #[derive(Clone, Copy)]
+    enum Action {
+        Done,
+        Add,
+        Remove,
+        Move,
+        SetDepth,
+    }
+
+    impl std::fmt::Display for Action {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                Action::Done => write!(f, "Done"),
+                Action::Add => write!(f, "Add a layer"),
+                Action::Remove => write!(f, "Remove a layer"),
+                Action::Move => write!(f, "Move a layer"),
+                Action::SetDepth => write!(f, "Change a search depth"),
+            }
+        }
+    }
+
+    loop {
+        term.write_line(&format!(
+            "Current stack: {}",
+            if stack.is_empty() {
+                "(empty, random moves)".to_string()
+            } else {
+                format_genome(&stack)
+            }
+        ))?;
+        let mut actions = vec![Action::Done, Action::Add];
+        if !stack.is_empty() {
+            actions.push(Action::Remove);
+        }
+        if stack.len() > 1 {
+            actions.push(Action::Move);
+        }
+        if stack.iter().any(|kind| kind.depth().is_some()) {
+            actions.push(Action::SetDepth);
+        }
+        let action = actions[Select::new()
+            .default(0)
+            .with_prompt("Edit the stack")
+            .items(&actions)
+            .interact_on(term)?];
+        lines += 2;
+
+        match action {
+            Action::Done => break,
+            Action::Add => {
+                let strategies = StrategyRegistry::strategies();
+                let kinds: Vec<String> = (strategies.iter())
+                    .map(|info| format!("{}: {}", info.role, info.default.name()))
+                    .collect();
+                let mut kind = strategies[Select::new()
+                    .default(0)
+                    .with_prompt("Select a strategy")
+                    .items(&kinds)
+                    .interact_on(term)?]
+                .default;
+                lines += 1;
+                if let Some(depth) = kind.depth() {
+                    kind = kind
+                        .with_depth(prompt_depth(term, depth)?)
+                        .expect("the kind has a depth");
+                    lines += 1;
+                }
+                let at = pick_stack_position(term, &stack, "Insert it", true)?;
+                lines += usize::from(!stack.is_empty());
+                stack.insert(at, kind);
+            }
+            Action::Remove => {
+                let at = pick_stack_position(term, &stack, "Remove which layer", false)?;
+                lines += usize::from(stack.len() > 1);
+                stack.remove(at);
+            }
+            Action::Move => {
+                let from = pick_stack_position(term, &stack, "Move which layer", false)?;
+                let kind = stack.remove(from);
+                let to = pick_stack_position(term, &stack, "Move it", true)?;
+                lines += 2;
+                stack.insert(to, kind);
+            }
+            Action::SetDepth => {
+                let searches: Vec<usize> = (0..stack.len())
+                    .filter(|&i| stack[i].depth().is_some())
+                    .collect();
+                let names: Vec<String> = searches.iter().map(|&i| stack[i].to_string()).collect();
+                let at = searches[Select::new()
+                    .default(0)
+                    .with_prompt("Change which search")
+                    .items(&names)
+                    .interact_on(term)?];
+                let depth = prompt_depth(term, stack[at].depth().expect("searches have a depth"))?;
+                lines += 2;
+                stack[at] = stack[at].with_depth(depth).expect("searches have a depth");
+            }
+        }
+    }
+
+    term.clear_last_lines(lines)?;
+
+    Ok(stack)
+}
+
+fn prompt_depth(term: &Term, default: usize) -> Result<usize> {
+    Ok(Input::new()
+        .with_prompt("Search depth")
+        .default(default)
+        .validate_with(|depth: &usize| match depth {
+            1..=20 => Ok(()),
+            _ => Err("Pick a depth from 1 to 20"),
+        })
+        .interact_text_on(term)?)
+}
+
+/// Asks for a place in `stack`: one of its layers, or with `between` a gap between
+/// them, top first. Doesn't ask when there is only one choice.
+fn pick_stack_position(
+    term: &Term,
+    stack: &[StrategyKind],
+    prompt: &str,
+    between: bool,
+) -> Result<usize> {
+    let choices: Vec<String> = if between {
+        (0..=stack.len())
+            .map(|i| match (i.checked_sub(1), stack.get(i)) {
+                (None, _) => "At the top".to_string(),
+                (Some(above), None) => format!("At the bottom, after {}", stack[above]),
+                (Some(above), Some(below)) => format!("Between {} and {}", stack[above], below),
+            })
+            .collect()
+    } else {
+        stack.iter().map(|kind| kind.to_string()).collect()
+    };
+    if choices.len() == 1 {
+        return Ok(0);
+    }
+    Ok(Select::new()
+        .default(choices.len() - 1)
+        .with_prompt(prompt)
+        .items(&choices)
+        .interact_on(term)?)
+}
+
+/// One side of a simulation, optionally behind a cache. It plays either color, so
+/// sides can be swapped with `--swap-sides`.
+enum SimulationAI {
+    Stack(StrategyStack),
+    Cached(StrategyCache),
+}
+
+impl SimulationAI {
+    fn new(kinds: &[StrategyKind], use_cache: bool, max_cache_bytes: Option<usize>) -> Self {
+        let stack = build_stack(kinds);
+        if use_cache {
+            // Let's use caching so they run faster!
+            let cache = StrategyCache::new(stack);
+            SimulationAI::Cached(match max_cache_bytes {
+                Some(max_bytes) => cache.with_max_bytes(max_bytes),
+                None => cache,
+            })
+        } else {
+            SimulationAI::Stack(stack)
+        }
+    }
+
+    /// The moves the stack rates best, without picking one.
+    fn options(&self, board: &Board, piece: Piece) -> Vec<usize> {
+        match self {
+            SimulationAI::Stack(stack) => stack.evaluate_options(board, piece),
+            SimulationAI::Cached(cache) => cache.stack().evaluate_options(board, piece),
+        }
+    }
+
+    fn cache_stats(&self) -> Option<StrategyCacheStats> {
+        match self {
+            SimulationAI::Stack(_) => None,
+            SimulationAI::Cached(cache) => Some(cache.cache_stats()),
+        }
+    }
+}
+
+impl Connect4AI for SimulationAI {
+    fn play(&self, board: &Board, piece: Piece) -> Option<usize> {
+        match self {
+            SimulationAI::Stack(stack) => stack.play(board, piece),
+            SimulationAI::Cached(cache) => cache.play(board, piece),
+        }
+    }
+
+    fn swaps(&self, board: &Board) -> bool {
+        match self {
+            SimulationAI::Stack(stack) => stack.swaps(board),
+            SimulationAI::Cached(cache) => cache.swaps(board),
+        }
+    }
+
+    fn rotates(&self, board: &Board, piece: Piece) -> bool {
+        match self {
+            SimulationAI::Stack(stack) => stack.rotates(board, piece),
+            SimulationAI::Cached(cache) => cache.rotates(board, piece),
+        }
+    }
+}
+
+impl std::fmt::Display for SimulationAI {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SimulationAI::Stack(stack) => write!(f, "{stack}"),
+            SimulationAI::Cached(cache) => write!(f, "{cache}"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum OutputFormat {
+    Json,
+    Csv,
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "json" => Ok(OutputFormat::Json),
+            "csv" => Ok(OutputFormat::Csv),
+            _ => anyhow::bail!("Unknown output format '{}', expected json or csv", s),
+        }
+    }
+}
+
+/// Flat summary of a simulation run, written by `--output`.
+#[derive(Serialize)]
+struct SimulationRecord {
+    strategy_a: String,
+    strategy_b: String,
+    swap_sides: bool,
+    games: usize,
+    a_wins: usize,
+    b_wins: usize,
+    ties: usize,
+    red_wins: usize,
+    yellow_wins: usize,
+    average_game_length: f64,
+    duration_ms: u128,
+    a_avg_move_ns: Option<u128>,
+    b_avg_move_ns: Option<u128>,
+    a_cache_hits: Option<u64>,
+    a_cache_misses: Option<u64>,
+    a_cache_entries: Option<usize>,
+    a_cache_bytes: Option<usize>,
+    b_cache_hits: Option<u64>,
+    b_cache_misses: Option<u64>,
+    b_cache_entries: Option<usize>,
+    b_cache_bytes: Option<usize>,
+}
+
+impl SimulationRecord {
+    fn new(
+        a: &SimulationAI,
+        b: &SimulationAI,
+        results: &SimulationResults,
+        swap_sides: bool,
+        duration: Duration,
+    ) -> Self {
+        let a_cache = a.cache_stats();
+        let b_cache = b.cache_stats();
+        SimulationRecord {
+            strategy_a: a.to_string(),
+            strategy_b: b.to_string(),
+            swap_sides,
+            games: results.games(),
+            a_wins: results.a_wins,
+            b_wins: results.b_wins,
+            ties: results.ties,
+            red_wins: results.red_wins,
+            yellow_wins: results.yellow_wins,
+            average_game_length: results.average_game_length(),
+            duration_ms: duration.as_millis(),
+            a_avg_move_ns: mean_duration(&results.a_move_times).map(|d| d.as_nanos()),
+            b_avg_move_ns: mean_duration(&results.b_move_times).map(|d| d.as_nanos()),
+            a_cache_hits: a_cache.map(|c| c.hits),
+            a_cache_misses: a_cache.map(|c| c.misses),
+            a_cache_entries: a_cache.map(|c| c.entries),
+            a_cache_bytes: a_cache.map(|c| c.approx_bytes),
+            b_cache_hits: b_cache.map(|c| c.hits),
+            b_cache_misses: b_cache.map(|c| c.misses),
+            b_cache_entries: b_cache.map(|c| c.entries),
+            b_cache_bytes: b_cache.map(|c| c.approx_bytes),
+        }
+    }
+
+    fn to_csv(&self) -> String {
+        fn opt<T: std::fmt::Display>(value: Option<T>) -> String {
+            value.map(|v| v.to_string()).unwrap_or_default()
+        }
+        fn quote(value: &str) -> String {
+            format!("\"{}\"", value.replace('"', "\"\""))
+        }
+
+        let header = "strategy_a,strategy_b,swap_sides,games,a_wins,b_wins,ties,red_wins,yellow_wins,average_game_length,duration_ms,a_avg_move_ns,b_avg_move_ns,a_cache_hits,a_cache_misses,a_cache_entries,a_cache_bytes,b_cache_hits,b_cache_misses,b_cache_entries,b_cache_bytes";
+        let row = [
+            quote(&self.strategy_a),
+            quote(&self.strategy_b),
+            self.swap_sides.to_string(),
+            self.games.to_string(),
+            self.a_wins.to_string(),
+            self.b_wins.to_string(),
+            self.ties.to_string(),
+            self.red_wins.to_string(),
+            self.yellow_wins.to_string(),
+            format!("{:.3}", self.average_game_length),
+            self.duration_ms.to_string(),
+            opt(self.a_avg_move_ns),
+            opt(self.b_avg_move_ns),
+            opt(self.a_cache_hits),
+            opt(self.a_cache_misses),
+            opt(self.a_cache_entries),
+            opt(self.a_cache_bytes),
+            opt(self.b_cache_hits),
+            opt(self.b_cache_misses),
+            opt(self.b_cache_entries),
+            opt(self.b_cache_bytes),
+        ];
+        format!("{header}\n{}\n", row.join(","))
+    }
+
+    fn write(&self, format: OutputFormat, path: &str) -> Result<()> {
+        let contents = match format {
+            OutputFormat::Json => serde_json::to_string_pretty(self)? + "\n",
+            OutputFormat::Csv => self.to_csv(),
+        };
+        std::fs::write(path, contents).with_context(|| format!("Failed to write results to {path}"))
+    }
+}
+
+/// Everything needed to (re)start a simulation.
+struct SimulationRun {
+    a: Vec<StrategyKind>,
+    b: Vec<StrategyKind>,
+    use_cache: bool,
+    swap_sides: bool,
+    results: SimulationResults,
+    /// Games left to play in the first batch
+    remaining: usize,
+    checkpoint: Option<Checkpointer>,
+}
+
+impl SimulationRun {
+    fn start(a: Vec<StrategyKind>, b: Vec<StrategyKind>, iterations: usize, cli: &Cli) -> Self {
+        let checkpoint = cli.checkpoint.clone().map(|path| Checkpointer {
+            path,
+            a: a.clone(),
+            b: b.clone(),
+            use_cache: cli.use_cache,
+            swap_sides: cli.swap_sides,
+        });
+        SimulationRun {
+            a,
+            b,
+            use_cache: cli.use_cache,
+            swap_sides: cli.swap_sides,
+            results: SimulationResults::default(),
+            remaining: iterations,
+            checkpoint,
+        }
+    }
+
+    fn resume(saved: Checkpoint, path: String) -> Result<Self> {
+        let results = saved.results()?;
+        Ok(SimulationRun {
+            remaining: saved.target_games.saturating_sub(results.games()),
+            checkpoint: Some(Checkpointer {
+                path,
+                a: saved.a.clone(),
+                b: saved.b.clone(),
+                use_cache: saved.use_cache,
+                swap_sides: saved.swap_sides,
+            }),
+            a: saved.a,
+            b: saved.b,
+            use_cache: saved.use_cache,
+            swap_sides: saved.swap_sides,
+            results,
+        })
+    }
+
+    fn competitors(&self, max_cache_bytes: Option<usize>) -> (SimulationAI, SimulationAI) {
+        (
+            SimulationAI::new(&self.a, self.use_cache, max_cache_bytes),
+            SimulationAI::new(&self.b, self.use_cache, max_cache_bytes),
+        )
+    }
+}
+
+/// Saved simulation progress, written by `--checkpoint` and read by `--resume`.
+///
+/// There is no RNG state to save: strategies pick moves with a fresh thread RNG, and
+/// games are independent, so the resumed run is statistically the same. With `--seed`
+/// each game is seeded from its index, so resuming with the same seed plays the same
+/// games the whole run would have.
+/// Move times are not saved either; timing only covers the current session.
+#[derive(Serialize, Deserialize)]
+struct Checkpoint {
+    a: Vec<StrategyKind>,
+    b: Vec<StrategyKind>,
+    use_cache: bool,
+    swap_sides: bool,
+    target_games: usize,
+    a_wins: usize,
+    b_wins: usize,
+    ties: usize,
+    red_wins: usize,
+    yellow_wins: usize,
+    lengths: Vec<usize>,
+    directions: Vec<usize>,
+    winning_columns: Vec<usize>,
+}
+
+impl Checkpoint {
+    fn load(path: &str) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read checkpoint {path}"))?;
+        serde_json::from_str(&contents).with_context(|| format!("Invalid checkpoint {path}"))
+    }
+
+    fn games(&self) -> usize {
+        self.a_wins + self.b_wins + self.ties
+    }
+
+    fn results(&self) -> Result<SimulationResults> {
+        let mut outcomes = OutcomeStats::new();
+        ensure!(
+            self.lengths.len() == outcomes.lengths.len()
+                && self.directions.len() == outcomes.directions.len()
+                && self.winning_columns.len() == outcomes.winning_columns.len(),
+            "Checkpoint outcome statistics have the wrong shape"
+        );
+        outcomes.lengths.copy_from_slice(&self.lengths);
+        outcomes.directions.copy_from_slice(&self.directions);
+        outcomes
+            .winning_columns
+            .copy_from_slice(&self.winning_columns);
+
+        Ok(SimulationResults {
+            a_wins: self.a_wins,
+            b_wins: self.b_wins,
+            ties: self.ties,
+            red_wins: self.red_wins,
+            yellow_wins: self.yellow_wins,
+            outcomes,
+            ..Default::default()
+        })
+    }
+}
+
+/// Writes checkpoints for a running simulation.
+struct Checkpointer {
+    path: String,
+    a: Vec<StrategyKind>,
+    b: Vec<StrategyKind>,
+    use_cache: bool,
+    swap_sides: bool,
+}
+
+impl Checkpointer {
+    fn save(&self, results: &SimulationResults, target_games: usize) -> Result<()> {
+        let checkpoint = Checkpoint {
+            a: self.a.clone(),
+            b: self.b.clone(),
+            use_cache: self.use_cache,
+            swap_sides: self.swap_sides,
+            target_games,
+            a_wins: results.a_wins,
+            b_wins: results.b_wins,
+            ties: results.ties,
+            red_wins: results.red_wins,
+            yellow_wins: results.yellow_wins,
+            lengths: results.outcomes.lengths.to_vec(),
+            directions: results.outcomes.directions.to_vec(),
+            winning_columns: results.outcomes.winning_columns.to_vec(),
+        };
+        // Write to a temporary file first so a crash mid-write can't corrupt the checkpoint
+        let tmp = format!("{}.tmp", self.path);
+        std::fs::write(&tmp, serde_json::to_string(&checkpoint)?)
+            .with_context(|| format!("Failed to write checkpoint {tmp}"))?;
+        std::fs::rename(&tmp, &self.path)
+            .with_context(|| format!("Failed to write checkpoint {}", self.path))
+    }
+}
+
+fn run_tune(games: usize, max_depth: usize) -> Result<()> {
+    const MIN_PIECES: [usize; 6] = [0, 8, 12, 16, 20, 24];
+
+    let depths: Vec<usize> = (1..=max_depth).collect();
+    let candidates = tune::grid(&depths, &MIN_PIECES);
+    println!("Tuning SearchForWin => TriesToWin => AvoidTraps against TriesToWin => AvoidTraps",);
+    println!("{} candidates, {} games each", candidates.len(), games);
+
+    let pb = ProgressBar::new(candidates.len() as u64);
+    let results = tune::grid_search(
+        &candidates,
+        games,
+        |params| {
+            StrategyStack::new(vec![
+                Strategy::Decision(Box::new(PhasedStrategy::only_after(
+                    params.min_pieces_played,
+                    SearchForWin::new(params.depth),
+                ))),
+                Strategy::Decision(Box::new(TriesToWin)),
+                Strategy::Layer(Box::new(AvoidTraps)),
+            ])
+        },
+        &StrategyStack::new(vec![
+            Strategy::Decision(Box::new(TriesToWin)),
+            Strategy::Layer(Box::new(AvoidTraps)),
+        ]),
+        |result| {
+            pb.println(format!(
+                "{:<32} score {:.3}",
+                result.params.to_string(),
+                result.score()
+            ));
+            pb.inc(1);
+        },
+    );
+    pb.finish_and_clear();
+
+    println!("Best configurations:");
+    for result in results.iter().take(5) {
+        println!(
+            "{:<32} score {:.3}  (W {} / L {} / T {})",
+            result.params.to_string(),
+            result.score(),
+            result.wins,
+            result.losses,
+            result.ties
+        );
+    }
+
+    Ok(())
+}
+
+fn format_genome(genome: &[StrategyKind]) -> String {
+    genome
+        .iter()
+        .map(|kind| kind.to_string())
+        .collect::<Vec<_>>()
+        .join(" => ")
+}
+
+fn run_evolve(config: &EvolveConfig) -> Result<()> {
+    ensure!(
+        config.population >= 2,
+        "The population needs at least 2 stacks"
+    );
+    ensure!(config.max_genes >= 1, "Stacks need at least 1 strategy");
+    println!(
+        "Evolving {} stacks for {} generations, {} games per pair",
+        config.population, config.generations, config.games_per_pair
+    );
+
+    let ranked = evolve::evolve(config, |generation, ranked| {
+        let best = &ranked[0];
+        println!(
+            "Generation {:>3}: score {:.3}  {}",
+            generation,
+            best.score,
+            format_genome(&best.genome)
+        );
+    });
+
+    println!("Best stacks:");
+    for scored in ranked.iter().take(5) {
+        println!("{:.3}  {}", scored.score, format_genome(&scored.genome));
+    }
+
+    Ok(())
+}
+
+fn run_move(position: &str, strategy: &str, search_tree: Option<&str>) -> Result<()> {
+    let state = GameState::from_moves(position)?;
+    ensure!(!state.is_over(), "The game is already over");
+    let kinds = parse_stack(strategy)?;
+    let ai = build_stack(&kinds);
+    let column = ai
+        .play(state.board(), state.to_move())
+        .context("The AI failed to choose a move")?;
+    println!("{}", column);
+
+    if let Some(path) = search_tree {
+        let (piece, board) = (state.to_move(), state.board());
+        let options = board.valid_moves();
+        let search = kinds.iter().find(|kind| {
+            matches!(
+                kind,
+                StrategyKind::SearchForWin { .. } | StrategyKind::SearchForWinCache { .. }
+            )
+        });
+        let trace = match search {
+            Some(&StrategyKind::SearchForWin { depth }) => {
+                let search = SearchForWin::new(depth).with_trace();
+                search.choose(board, piece, &options);
+                search.take_trace()
+            }
+            Some(&StrategyKind::SearchForWinCache { depth }) => {
+                let search = SearchForWinCache::new(depth).with_trace();
+                search.choose(board, piece, &options);
+                search.take_trace()
+            }
+            _ => anyhow::bail!("The stack has no SearchForWin or SearchForWinCache to trace"),
+        };
+        let trace = trace.expect("tracing was turned on");
+        std::fs::write(path, trace.to_dot()).with_context(|| format!("Failed to write {path}"))?;
+        eprintln!("Wrote {} searched positions to {path}", trace.nodes().len());
+    }
+    Ok(())
+}
+
+fn run_analyze(
+    position: &str,
+    iterations: usize,
+    priors: Option<&str>,
+    tree_path: Option<&str>,
+    min_visits: u32,
+    explain: Option<&str>,
+) -> Result<()> {
+    let state = GameState::from_moves(position)?;
+    ensure!(!state.is_over(), "The game is already over");
+    // Parse the stack before spending time on the search
+    let explained = explain.map(parse_stack).transpose()?;
+    let mut mcts = Mcts::new(iterations);
+    if let Some(priors) = priors {
+        mcts = mcts.with_priors(&parse_stack(priors)?);
+    }
+    let tree = mcts.search(state.board(), state.to_move());
+
+    println!("{}", state.board());
+    println!(
+        "{} to move, {} playouts",
+        state.to_move().name(),
+        tree.visits()
+    );
+    println!(
+        "{:>6}  {:>7}  {:>6}  {:>5}",
+        "Column", "Visits", "Value", "Prior"
+    );
+    for stats in tree.root_stats() {
+        println!(
+            "{:>6}  {:>7}  {:>6.3}  {:>5.3}",
+            stats.column, stats.visits, stats.mean_value, stats.prior
+        );
+    }
+    println!(
+        "Principal variation: {}",
+        format_line(&tree.principal_variation())
+    );
+    if let Some(kinds) = explained {
+        let stack = build_stack(&kinds);
+        let explanation = stack.explain(state.board(), state.to_move());
+        println!("\n{stack}:\n{explanation}");
+    }
+
+    if let Some(path) = tree_path {
+        let contents = if path.ends_with(".json") {
+            serde_json::to_string_pretty(&tree.to_json(min_visits))?
+        } else {
+            tree.to_dot(min_visits)
+        };
+        std::fs::write(path, contents).with_context(|| format!("Failed to write {path}"))?;
+        println!("Wrote the search tree to {path}");
+    }
+    Ok(())
+}
+
+fn run_strategies() {
+    for info in StrategyRegistry::strategies() {
+        println!(
+            "{} ({}, {})",
+            style(info.default.name()).bold(),
+            info.role,
+            info.cost
+        );
+        println!("    {}", info.description);
+        if !info.parameters.is_empty() {
+            println!("    Parameters: {}", info.parameters);
+        }
+    }
+    println!();
+    println!("Stacks are written top first, e.g. \"SearchForWin(3) => TriesToWin => AvoidTraps\".");
+}
+
+/// A line of play in column-number notation, e.g. "3 4 3 2".
+fn format_line(line: &[usize]) -> String {
+    (line.iter().map(|col| col.to_string()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// What a history entry did, e.g. "plays 3" or "swaps".
+fn describe_entry(entry: usize) -> String {
+    match entry {
+        PASS => "passes".to_string(),
+        SWAP => "swaps".to_string(),
+        ROTATE => "rotates".to_string(),
+        column => format!("plays {column}"),
+    }
+}
+
+fn run_connect(
+    observer: &mut dyn GameObserver,
+    address: &str,
+    coach: bool,
+    accessible: bool,
+) -> Result<()> {
+    let mut term = console::Term::stdout();
+    let (mut connection, human) = join_game(address)?;
+    writeln!(
+        term,
+        "Joined as {}. Waiting for the game to start...",
+        human.name()
+    )?;
+
+    let state = loop {
+        match connection.receive::<ServerMessage>()? {
+            Some(ServerMessage::State { history, .. }) => break GameState::from_history(&history)?,
+            Some(_) => {}
+            None => anyhow::bail!("The server hung up"),
+        }
+    };
+    term.clear_last_lines(1)?;
+
+    let mut opponent = Opponent::Remote(connection);
+    play_interactive_game(
+        &mut term,
+        observer,
+        state,
+        human,
+        &mut opponent,
+        coach,
+        accessible,
+        None,
+    )?;
+    Ok(())
+}
+
+fn parse_seat(spec: &str) -> Result<Seat> {
+    if spec.eq_ignore_ascii_case("remote") {
+        Ok(Seat::Remote)
+    } else {
+        Ok(Seat::Ai(parse_stack(spec)?))
+    }
+}
+
+fn run_selfplay(games: usize, out: &str, red: &str, yellow: &str) -> Result<()> {
+    let red = build_stack(&parse_stack(red)?);
+    let yellow = build_stack(&parse_stack(yellow)?);
+    let progress = ProgressBar::new(games as u64);
+    let mut samples = vec![];
+    for _ in 0..games {
+        // A game only fails if a strategy gives up, which leaves nothing worth learning
+        if let Some(game) = play_game(&red, &yellow, &GameState::new(), &mut ()) {
+            samples.extend(selfplay::samples(&game));
+        }
+        progress.inc(1);
+    }
+    progress.finish_and_clear();
+
+    let file = std::fs::File::create(out).with_context(|| format!("Failed to create {out}"))?;
+    let mut writer = std::io::BufWriter::new(file);
+    selfplay::write_npy(&mut writer, &samples)?;
+    writer.flush()?;
+    println!(
+        "Wrote {} positions from {} games to {}",
+        samples.len(),
+        games,
+        out
+    );
+    Ok(())
+}
+
+fn run_render(position: &str, output: &str) -> Result<()> {
+    let state = GameState::from_moves(position)?;
+    let board = state.board();
+    let extension = std::path::Path::new(output)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase);
+    let image = match extension.as_deref() {
+        Some("svg") => board.to_svg().into_bytes(),
+        #[cfg(feature = "png")]
+        Some("png") => board.to_png()?,
+        #[cfg(not(feature = "png"))]
+        Some("png") => anyhow::bail!("PNG output needs the png feature"),
+        _ => anyhow::bail!("Can't tell the image format of {output}; use .svg or .png"),
+    };
+    std::fs::write(output, image).with_context(|| format!("Failed to write {output}"))?;
+    Ok(())
+}
+
+fn run_precompute(depth: usize, search: usize, out: &str) -> Result<()> {
+    // Every distinct position up to `depth` plies, transpositions counted once. Each
+    // ply's positions have the same side to move, since the search alternates.
+    let mut positions = vec![(Board::new(), Piece::Red)];
+    let mut frontier = vec![Board::new()];
+    let mut seen = std::collections::HashSet::from([Board::new()]);
+    let mut piece = Piece::Red;
+    for _ in 0..depth {
+        let mut next = vec![];
+        for board in frontier.iter().filter(|board| board.has_winner().is_none()) {
+            for column in board.valid_moves() {
+                let child = board.place(column, piece);
+                if seen.insert(child) {
+                    next.push(child);
+                }
+            }
+        }
+        piece = piece.opponent();
+        positions.extend(next.iter().map(|&board| (board, piece)));
+        frontier = next;
+    }
+    positions.retain(|(board, _)| board.has_winner().is_none());
+
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let cache = SearchForWinCache::new(search).with_threads(threads);
+    println!(
+        "Searching {} positions to depth {search} on {threads} threads",
+        positions.len()
+    );
+    let pb = ProgressBar::new(positions.len() as u64);
+    for (board, piece) in &positions {
+        cache.choose(board, *piece, &board.valid_moves());
+        pb.inc(1);
+    }
+    pb.finish_and_clear();
+
+    let file = std::fs::File::create(out).with_context(|| format!("Failed to write {out}"))?;
+    let written = cache.save(&mut std::io::BufWriter::new(file))?;
+    println!("Wrote {written} positions to {out}");
+    Ok(())
+}
+
+fn run_perft(depth: usize, position: &str, divide: bool) -> Result<()> {
+    ensure!(depth > 0 || !divide, "--divide needs a depth of at least 1");
+    let state = GameState::from_moves(position)?;
+    let (board, piece) = (state.board(), state.to_move());
+    let start = Instant::now();
+    let total = if divide {
+        let mut total = 0;
+        for (column, count) in board.perft_divide(depth, piece) {
+            println!("{column}: {count}");
+            total += count;
+        }
+        total
+    } else {
+        board.perft(depth, piece)
+    };
+    let elapsed = start.elapsed();
+    println!("Leaves at depth {depth}: {total}");
+    println!(
+        "Took {:.3}s ({:.0} leaves/s)",
+        elapsed.as_secs_f64(),
+        total as f64 / elapsed.as_secs_f64().max(f64::EPSILON)
+    );
+    Ok(())
+}
+
+fn run_export_gif(file: &str, output: &str, game: usize, delay: Duration) -> Result<()> {
+    // A saved game is one JSON object; a log has one game per line
+    let contents =
+        std::fs::read_to_string(file).with_context(|| format!("Failed to read {file}"))?;
+    let state = match serde_json::from_str::<GameState>(&contents) {
+        Ok(state) => state,
+        Err(_) => {
+            ensure!(game >= 1, "--game counts from 1");
+            let games = read_games(file)?;
+            let record = games
+                .into_iter()
+                .nth(game - 1)
+                .with_context(|| format!("{file} has fewer than {game} games"))?;
+            GameState::try_from(record)?
+        }
+    };
+    #[cfg(feature = "gif")]
+    {
+        let gif = connect4::render::replay_gif(&state, delay)?;
+        std::fs::write(output, gif).with_context(|| format!("Failed to write {output}"))?;
+        println!("Wrote {} frames to {output}", state.history().len() + 1);
+        Ok(())
+    }
+    #[cfg(not(feature = "gif"))]
+    {
+        let _ = (state, output, delay);
+        anyhow::bail!("GIF export needs the gif feature")
+    }
+}
+
+fn run_api(port: u16) -> Result<()> {
+    let listener = std::net::TcpListener::bind(("0.0.0.0", port))
+        .with_context(|| format!("Failed to listen on port {port}"))?;
+    println!("Serving the API on port {}", port);
+    api::serve(&listener)
+}
+
+fn run_serve(port: u16, red: &str, yellow: &str) -> Result<()> {
+    let red = parse_seat(red)?;
+    let yellow = parse_seat(yellow)?;
+    let listener = std::net::TcpListener::bind(("0.0.0.0", port))
+        .with_context(|| format!("Failed to listen on port {port}"))?;
+    println!("Listening on port {}", port);
+
+    loop {
+        println!("Waiting for players...");
+        match host_game(&listener, &red, &yellow) {
+            Ok(GameResult::Winner(winner)) => println!("{} wins.", winner.name()),
+            Ok(GameResult::Tie) => println!("Tie."),
+            // One bad client shouldn't take the server down
+            Err(e) => println!("Game abandoned: {:#}", e),
+        }
+    }
+}
+
+/// Redraws the board after every move so a game can be followed as it happens.
+struct Watcher<'a> {
+    term: Term,
+    delay: Duration,
+    /// Red's and Yellow's stacks, asked for the line behind each move
+    stacks: [&'a StrategyStack; 2],
+}
+
+impl Watcher<'_> {
+    fn draw(&self, state: &GameState, player: Piece, entry: usize) -> std::io::Result<()> {
+        self.term.clear_last_lines(ROWS + 1)?;
+        writeln!(&self.term, "{}", state.board())?;
+        let mut line = match entry {
+            column if column < COLUMNS => format!("{} played column {}", player.name(), column),
+            entry => format!("{} {}", player.name(), describe_entry(entry)),
+        };
+        if let Some(forced) = self.forced_line(state, player, entry) {
+            line += &format!(", forcing {}", format_line(&forced));
+        }
+        writeln!(&self.term, "{}", line)
+    }
+
+    /// The forced win `player`'s stack found when it played `column`, if any.
+    fn forced_line(&self, state: &GameState, player: Piece, column: usize) -> Option<Vec<usize>> {
+        let history = state.history();
+        let before = GameState::from_history(&history[..history.len() - 1]).ok()?;
+        let stack = self.stacks[usize::from(player == Piece::Yellow)];
+        let line = stack.principal_variation(before.board(), player)?;
+        (line.len() > 1 && line[0] == column).then_some(line)
+    }
+}
+
+impl GameObserver for Watcher<'_> {
+    fn on_move(&mut self, state: &GameState, player: Piece, column: usize, _elapsed: Duration) {
+        // A failed redraw shouldn't stop the game
+        let _ = self.draw(state, player, column);
+        thread::sleep(self.delay);
+    }
+
+    fn on_special_move(&mut self, state: &GameState, player: Piece, entry: usize) {
+        let _ = self.draw(state, player, entry);
+        thread::sleep(self.delay);
+    }
+}
+
+fn run_watch(delay: Duration) -> Result<()> {
+    let term = console::Term::stdout();
+    let red = build_stack(&choose_strategy_kinds(Piece::Red.name(), &term)?);
+    let yellow = build_stack(&choose_strategy_kinds(Piece::Yellow.name(), &term)?);
+
+    writeln!(&term, "{}: {}", Piece::Red.name(), red)?;
+    writeln!(&term, "{}: {}", Piece::Yellow.name(), yellow)?;
+    term.write_line("")?;
+    writeln!(&term, "{}", Board::new())?;
+    term.write_line("")?;
+
+    let mut watcher = Watcher {
+        term: term.clone(),
+        delay,
+        stacks: [&red, &yellow],
+    };
+    let game = play_game(&red, &yellow, &GameState::new(), &mut watcher)
+        .context("An AI failed to make a move")?;
+    match game.winner() {
+        Some(winner) => writeln!(
+            &term,
+            "{} wins after {} moves.",
+            winner.name(),
+            game.board().num_pieces_played()
+        )?,
+        None => writeln!(&term, "Tie.")?,
+    }
+    Ok(())
+}
+
+/// Appends every finished game to a file as one JSON `GameRecord` per line.
+struct GameLog {
+    writer: std::io::BufWriter<std::fs::File>,
+    /// The first write error, reported by `finish` since observers can't fail
+    error: Option<anyhow::Error>,
+}
+
+impl GameLog {
+    fn append(path: &str) -> Result<Self> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Opening {}", path))?;
+        Ok(GameLog {
+            writer: std::io::BufWriter::new(file),
+            error: None,
+        })
+    }
+
+    fn finish(mut self) -> Result<()> {
+        if let Some(error) = self.error.take() {
+            return Err(error.context("Saving games"));
+        }
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+impl GameObserver for GameLog {
+    fn on_game_end(&mut self, state: &GameState, _result: GameResult) {
+        if self.error.is_some() {
+            return;
+        }
+        let written = serde_json::to_writer(&mut self.writer, &GameRecord::from(state))
+            .map_err(anyhow::Error::from)
+            .and_then(|()| Ok(writeln!(self.writer)?));
+        self.error = written.err();
+    }
+}
+
+/// Reads games written by `GameLog`.
+fn read_games(path: &str) -> Result<Vec<GameRecord>> {
+    let text = std::fs::read_to_string(path).with_context(|| format!("Reading {}", path))?;
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str(line).with_context(|| format!("{} line {}", path, i + 1))
+        })
+        .collect()
+}
+
+fn run_generate_puzzles(games: &str, win_in: usize, output: &str, name: &str) -> Result<()> {
+    ensure!(win_in >= 1, "--win-in must be at least 1");
+    let games = read_games(games)?;
+    println!("Searching {} games for wins in {}...", games.len(), win_in);
+    let pack = PuzzlePack {
+        name: name.to_string(),
+        puzzles: mine_puzzles(&games, win_in),
+    };
+    std::fs::write(output, pack.to_json()?).with_context(|| format!("Writing {}", output))?;
+    println!("Wrote {} puzzles to {}", pack.puzzles.len(), output);
+    Ok(())
+}
+
+/// Puzzles solved in a session, and the current and best run of solves in a row.
+#[derive(Default)]
+struct PuzzleScore {
+    solved: usize,
+    attempted: usize,
+    streak: usize,
+    best_streak: usize,
+}
+
+impl PuzzleScore {
+    fn record(&mut self, solved: bool) {
+        self.attempted += 1;
+        if solved {
+            self.solved += 1;
+            self.streak += 1;
+            self.best_streak = self.best_streak.max(self.streak);
+        } else {
+            self.streak = 0;
+        }
+    }
+}
+
+impl std::fmt::Display for PuzzleScore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Solved {}/{}, streak {} (best {})",
+            self.solved, self.attempted, self.streak, self.best_streak
+        )
+    }
+}
+
+fn run_puzzle(pack: Option<&str>) -> Result<()> {
+    let pack = match pack {
+        Some(path) => {
+            let json =
+                std::fs::read_to_string(path).with_context(|| format!("Reading {}", path))?;
+            PuzzlePack::from_json(&json).with_context(|| format!("Loading {}", path))?
+        }
+        None => PuzzlePack::bundled(),
+    };
+    ensure!(!pack.puzzles.is_empty(), "The pack has no puzzles");
+
+    let mut term = Term::stdout();
+    let mut score = PuzzleScore::default();
+    writeln!(
+        term,
+        "{} puzzle pack: {} puzzles. Press q to stop.",
+        pack.name,
+        pack.puzzles.len()
+    )?;
+    term.hide_cursor()?;
+    for (i, puzzle) in pack.puzzles.iter().enumerate() {
+        writeln!(term)?;
+        writeln!(term, "Puzzle {}/{}", i + 1, pack.puzzles.len())?;
+        let Some(solved) = solve_puzzle(&mut term, puzzle)? else {
+            break;
+        };
+        score.record(solved);
+        writeln!(term, "{}", score)?;
+    }
+    term.show_cursor()?;
+    writeln!(term)?;
+    writeln!(term, "Final score: {}", score)?;
+    Ok(())
+}
+
+/// Plays one puzzle, with the defender answering every correct move with its most
+/// stubborn reply. Returns whether it was solved, or None if the player quit.
+fn solve_puzzle(term: &mut Term, puzzle: &Puzzle) -> Result<Option<bool>> {
+    let mut state = puzzle.state()?;
+    let player = state.to_move();
+    let defender = player.opponent();
+    let mut moves_left = puzzle.win_in;
+    let mut selection = COLUMNS / 2;
+
+    writeln!(term, "{} to move and win in {}", player.name(), moves_left)?;
+    writeln!(term, "{}", state.board())?;
+    loop {
+        let solutions = winning_moves_within(state.board(), player, moves_left);
+        let Some(column) = pick_column(term, COLUMNS, &state.legal_moves(), &mut selection)? else {
+            return Ok(None);
+        };
+        if !solutions.contains(&column) {
+            writeln!(
+                term,
+                "{} doesn't force a win in {}. The answer was column {:?}.",
+                column, moves_left, solutions
+            )?;
+            return Ok(Some(false));
+        }
+        state.apply_move(column)?;
+        if state.result().is_some() {
+            writeln!(term, "{}", state.board())?;
+            writeln!(term, "Solved!")?;
+            return Ok(Some(true));
+        }
+
+        moves_left -= 1;
+        let reply = best_defense(state.board(), defender, moves_left)
+            .context("The defender has no moves left")?;
+        state.apply_move(reply)?;
+        writeln!(
+            term,
+            "You played column {}, {} replied in column {}. Win in {} more.",
+            column,
+            defender.name(),
+            reply,
+            moves_left
+        )?;
+        writeln!(term, "{}", state.board())?;
+    }
+}
+
+fn run_three_player() -> Result<()> {
+    let mut term = Term::stdout();
+    let mut rng = rand::rng();
+    let mut game = ThreePlayerGame::new();
+    let mut selection = three_player::COLUMNS / 2;
+    writeln!(
+        term,
+        "You are Red. Yellow and Green are AIs and move after you. Press q to stop."
+    )?;
+    term.hide_cursor()?;
+    let result = loop {
+        writeln!(term, "{}", game.board())?;
+        if let Some(result) = game.result() {
+            break result;
+        }
+        let color = game.to_move();
+        let column = if color == ThreeColor::Red {
+            let legal = game.board().valid_moves();
+            match pick_column(&mut term, three_player::COLUMNS, &legal, &mut selection)? {
+                Some(column) => column,
+                None => {
+                    term.show_cursor()?;
+                    return Ok(());
+                }
+            }
+        } else {
+            three_player::choose_move(game.board(), color, &mut rng)
+                .context("The AI has no moves left")?
+        };
+        game.apply_move(column)?;
+        writeln!(term, "{} played column {}.", color.name(), column)?;
+    };
+    term.show_cursor()?;
+    match result {
+        ThreePlayerResult::Winner(ThreeColor::Red) => writeln!(term, "You win!")?,
+        ThreePlayerResult::Winner(winner) => writeln!(term, "{} wins.", winner.name())?,
+        ThreePlayerResult::Tie => writeln!(term, "Tie.")?,
+    }
+    Ok(())
+}
+
+fn run_no_gravity(depth: usize) -> Result<()> {
+    let mut term = Term::stdout();
+    let mut rng = rand::rng();
+    let mut game = FreeGame::new();
+    let mut cursor = Move::new(ROWS / 2, COLUMNS / 2);
+    writeln!(
+        term,
+        "You are {} and may play any empty cell. Press q to stop.",
+        Piece::Red.name()
+    )?;
+    term.hide_cursor()?;
+    let result = loop {
+        if let Some(result) = game.result() {
+            writeln!(term, "{}", game.board())?;
+            break result;
+        }
+        let piece = game.to_move();
+        let at = if piece == Piece::Red {
+            match pick_cell(&mut term, game.board(), &mut cursor)? {
+                Some(at) => at,
+                None => {
+                    term.show_cursor()?;
+                    return Ok(());
+                }
+            }
+        } else {
+            free_placement::choose_move(game.board(), piece, depth, &mut rng)
+                .context("The AI has no moves left")?
+        };
+        game.apply_move(at)?;
+        writeln!(term, "{} played {}.", piece.name(), at)?;
+    };
+    term.show_cursor()?;
+    match result {
+        GameResult::Winner(Piece::Red) => writeln!(term, "You win!")?,
+        GameResult::Winner(winner) => writeln!(term, "{} wins.", winner.name())?,
+        GameResult::Tie => writeln!(term, "Tie.")?,
+    }
+    Ok(())
+}
+
+fn run_blockers(count: usize) -> Result<()> {
+    let mut term = Term::stdout();
+    let mut rng = rand::rng();
+    let mut game = BlockerGame::new(BlockerBoard::random_setup(count, &mut rng)?)?;
+    let mut selection = COLUMNS / 2;
+    writeln!(
+        term,
+        "You are {}. Blockers ({}) count for neither player. Press q to stop.",
+        Piece::Red.name(),
+        Piece::Blocker
+    )?;
+    term.hide_cursor()?;
+    let result = loop {
+        writeln!(term, "{}", game.board())?;
+        if let Some(result) = game.result() {
+            break result;
+        }
+        let piece = game.to_move();
+        let column = if piece == Piece::Red {
+            let legal = game.board().valid_moves();
+            match pick_column(&mut term, COLUMNS, &legal, &mut selection)? {
+                Some(column) => column,
+                None => {
+                    term.show_cursor()?;
+                    return Ok(());
+                }
+            }
+        } else {
+            blockers::choose_move(game.board(), piece, &mut rng)
+                .context("The AI has no moves left")?
+        };
+        game.apply_move(column)?;
+        writeln!(term, "{} played column {}.", piece.name(), column)?;
+    };
+    term.show_cursor()?;
+    match result {
+        GameResult::Winner(Piece::Red) => writeln!(term, "You win!")?,
+        GameResult::Winner(winner) => writeln!(term, "{} wins.", winner.name())?,
+        GameResult::Tie => writeln!(term, "Tie.")?,
+    }
+    Ok(())
+}
+
+/// Lets the player move a cursor around the board and pick an empty cell with Enter.
+/// Returns None if they press q.
+fn pick_cell(term: &mut Term, board: &FreeBoard, cursor: &mut Move) -> Result<Option<Move>> {
+    loop {
+        for row in (0..ROWS).rev() {
+            let cells: Vec<String> = (0..COLUMNS)
+                .map(|column| {
+                    let at = Move::new(row, column);
+                    match board.get(at) {
+                        Piece::Empty if at == *cursor => style("[*]").bold().to_string(),
+                        piece if at == *cursor => style(piece).reverse().to_string(),
+                        piece => piece.to_string(),
+                    }
+                })
+                .collect();
+            writeln!(term, "{}", cells.join(" "))?;
+        }
+        write!(term, "Make your move")?;
+        let key = term.read_key()?;
+        term.clear_line()?;
+        term.clear_last_lines(ROWS)?;
+        match key {
+            Key::Unknown => anyhow::bail!("Problem"),
+            Key::Char('q') => return Ok(None),
+            Key::ArrowLeft | Key::Char('a') => cursor.column = cursor.column.saturating_sub(1),
+            Key::ArrowRight | Key::Char('d') => {
+                cursor.column = (cursor.column + 1).min(COLUMNS - 1)
+            }
+            Key::ArrowUp | Key::Char('w') => cursor.row = (cursor.row + 1).min(ROWS - 1),
+            Key::ArrowDown | Key::Char('s') => cursor.row = cursor.row.saturating_sub(1),
+            Key::Enter if board.get(*cursor) == Piece::Empty => return Ok(Some(*cursor)),
+            _ => {}
+        }
+    }
+}
+
+/// Lets the player move a cursor over the columns and pick a legal one with Enter.
+/// Returns None if they press q.
+fn pick_column(
+    term: &mut Term,
+    columns: usize,
+    legal: &[usize],
+    selection: &mut usize,
+) -> Result<Option<usize>> {
+    loop {
+        writeln!(term, " {}", "    ".repeat(*selection) + "^")?;
+        write!(term, "Make your move")?;
+        let key = term.read_key()?;
+        term.clear_line()?;
+        term.clear_last_lines(1)?;
+        if key == Key::Unknown {
+            anyhow::bail!("Problem");
+        }
+        match key_action(&key) {
+            Some(KeyAction::Quit) => return Ok(None),
+            Some(KeyAction::Left) => *selection = selection.saturating_sub(1),
+            Some(KeyAction::Right) => *selection = (*selection + 1).min(columns - 1),
+            Some(KeyAction::Drop) if legal.contains(selection) => return Ok(Some(*selection)),
+            _ => {}
+        }
+    }
+}
+
+fn run_simulation(iterations: usize, cli: &Cli) -> Result<()> {
+    let term = console::Term::stdout();
+
+    // Check the output format before spending time on the simulation
+    let output = match cli.output.as_slice() {
+        [format, path] => Some((format.parse::<OutputFormat>()?, path)),
+        _ => None,
+    };
+
+    let run = if cli.resume {
+        // `requires` on the flag guarantees we have a checkpoint path
+        let path = cli.checkpoint.clone().unwrap();
+        let saved = Checkpoint::load(&path)?;
+        println!(
+            "Resuming from {path}: {} of {} games already played.",
+            saved.games(),
+            saved.target_games
+        );
+        SimulationRun::resume(saved, path)?
+    } else {
+        let labels = SimulationLabels::new(cli.swap_sides);
+        let a = choose_strategy_kinds(labels.a, &term)?;
+        let b = choose_strategy_kinds(labels.b, &term)?;
+        SimulationRun::start(a, b, iterations, cli)
+    };
+    let swap_sides = run.swap_sides;
+    let labels = SimulationLabels::new(swap_sides);
+    let (a, b) = run.competitors(cli.max_cache_mb.map(|mb| mb * 1024 * 1024));
+    if let Some(index) = cli.replay_game {
+        return replay_game(&a, &b, index, swap_sides, &GameSettings::from_cli(cli));
+    }
+
+    ctrlc::set_handler(|| INTERRUPTED.store(true, Ordering::Relaxed))
+        .context("Failed to install the Ctrl-C handler")?;
+
+    let start = Instant::now();
+    let results = simulate_until_done(&a, &b, iterations, cli, run)?;
+    let duration = start.elapsed();
+    if interrupted() {
+        println!("Interrupted! Reporting the games that finished.");
+    }
+    print_results(&results, &labels, duration, cli.confidence);
+
+    if let (Some(a_cache_stats), Some(b_cache_stats)) = (a.cache_stats(), b.cache_stats()) {
+        println!("{} cache:{}", labels.a, &a_cache_stats);
+        println!("{} cache:{}", labels.b, &b_cache_stats);
+
+        let cache_stats = a_cache_stats + b_cache_stats;
+        println!("Overall cache stats:{}", &cache_stats);
+    }
+
+    if let Some((format, path)) = output {
+        SimulationRecord::new(&a, &b, &results, swap_sides, duration).write(format, path)?;
+        println!("Wrote results to {path}");
     }
 
     Ok(())
@@ -0,0 +1,357 @@
+use std::fmt::Write;
+
+use rand::seq::IndexedRandom;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+use crate::board::{Board, Piece};
+use crate::strategy::{StrategyDecider, StrategyStack};
+use crate::strategy_kind::{StrategyKind, build_stack};
+
+/// Monte Carlo tree search. Moves are picked by PUCT, which weighs how well a move did
+/// in earlier playouts against its prior from a strategy stack, and new leaves are
+/// valued by playing random moves to the end of the game.
+pub struct Mcts {
+    iterations: usize,
+    exploration: f64,
+    /// The stack whose move probabilities are the priors.
+    priors: StrategyStack,
+}
+
+impl Mcts {
+    /// The usual PUCT exploration constant.
+    pub const DEFAULT_EXPLORATION: f64 = 1.4;
+
+    /// Searches `iterations` playouts per move with uniform priors.
+    pub fn new(iterations: usize) -> Self {
+        Mcts {
+            iterations,
+            exploration: Self::DEFAULT_EXPLORATION,
+            priors: StrategyStack::new(vec![]),
+        }
+    }
+
+    /// Takes priors from the move probabilities of `kinds`.
+    pub fn with_priors(mut self, kinds: &[StrategyKind]) -> Self {
+        self.priors = build_stack(kinds);
+        self
+    }
+
+    pub fn with_exploration(mut self, exploration: f64) -> Self {
+        self.exploration = exploration;
+        self
+    }
+
+    /// Runs the search from `board` with `to_move` to play and returns the whole tree.
+    pub fn search(&self, board: &Board, to_move: Piece) -> SearchTree {
+        let mut tree = SearchTree {
+            nodes: vec![Node::new(*board, to_move.opponent(), None, 1.0)],
+        };
+        for _ in 0..self.iterations {
+            let path = self.select(&tree);
+            let leaf = *path.last().expect("the path starts at the root");
+            let value = match tree.nodes[leaf].terminal_value() {
+                Some(value) => value,
+                None => {
+                    self.expand(&mut tree, leaf);
+                    rollout(&tree.nodes[leaf])
+                }
+            };
+            // The value is for whoever moved into the leaf, and flips at every ply
+            for (i, &node) in path.iter().rev().enumerate() {
+                let node = &mut tree.nodes[node];
+                node.visits += 1;
+                node.value_sum += if i % 2 == 0 { value } else { -value };
+            }
+        }
+        tree
+    }
+
+    /// Walks down from the root to a node that hasn't been expanded or ends the game.
+    fn select(&self, tree: &SearchTree) -> Vec<usize> {
+        let mut path = vec![0];
+        let mut current = 0;
+        while !tree.nodes[current].children.is_empty() {
+            let parent = &tree.nodes[current];
+            let scale = self.exploration * (parent.visits as f64).sqrt();
+            current = *(parent.children.iter())
+                .max_by(|&&a, &&b| {
+                    let a = tree.nodes[a].puct(scale);
+                    a.total_cmp(&tree.nodes[b].puct(scale))
+                })
+                .expect("expanded nodes have children");
+            path.push(current);
+        }
+        path
+    }
+
+    fn expand(&self, tree: &mut SearchTree, node: usize) {
+        let board = tree.nodes[node].board;
+        let to_move = tree.nodes[node].mover.opponent();
+        let priors = self.priors.move_probabilities(&board, to_move);
+        for col in board.valid_moves() {
+            let prior = (priors.iter())
+                .find(|&&(option, _)| option == col)
+                .map_or(0.0, |&(_, chance)| chance);
+            let child = tree.nodes.len();
+            tree.nodes.push(Node::new(
+                board.place(col, to_move),
+                to_move,
+                Some(col),
+                prior,
+            ));
+            tree.nodes[node].children.push(child);
+        }
+    }
+}
+
+/// Plays random moves from `node` to the end of the game, and returns the result for
+/// whoever moved into it.
+fn rollout(node: &Node) -> f64 {
+    let mut rng = crate::rng::rng();
+    let mut board = node.board;
+    let mut to_move = node.mover.opponent();
+    loop {
+        if let Some(winner) = board.has_winner() {
+            return if winner == node.mover { 1.0 } else { -1.0 };
+        }
+        let Some(&col) = board.valid_moves().choose(&mut rng) else {
+            return 0.0;
+        };
+        board = board.place(col, to_move);
+        to_move = to_move.opponent();
+    }
+}
+
+impl StrategyDecider for Mcts {
+    /// Picks the most visited option.
+    fn choose(&self, board: &Board, piece: Piece, options: &[usize]) -> Option<usize> {
+        let stats = self.search(board, piece).root_stats();
+        let best = (stats.iter())
+            .filter(|stats| options.contains(&stats.column))
+            .max_by_key(|stats| stats.visits)?;
+        tracing::debug!(
+            column = best.column,
+            visits = best.visits,
+            value = best.mean_value,
+            "most visited move"
+        );
+        Some(best.column)
+    }
+
+    fn name(&self) -> &'static str {
+        "Mcts"
+    }
+
+    fn is_expensive(&self) -> bool {
+        true
+    }
+}
+
+struct Node {
+    board: Board,
+    /// Who played the move into this node. For the root, the player who moved last.
+    mover: Piece,
+    column: Option<usize>,
+    prior: f64,
+    visits: u32,
+    /// Summed playout results for `mover`.
+    value_sum: f64,
+    children: Vec<usize>,
+}
+
+impl Node {
+    fn new(board: Board, mover: Piece, column: Option<usize>, prior: f64) -> Self {
+        Node {
+            board,
+            mover,
+            column,
+            prior,
+            visits: 0,
+            value_sum: 0.0,
+            children: vec![],
+        }
+    }
+
+    fn mean_value(&self) -> f64 {
+        if self.visits == 0 {
+            0.0
+        } else {
+            self.value_sum / self.visits as f64
+        }
+    }
+
+    /// The node's PUCT score as a choice for its parent's player.
+    fn puct(&self, scale: f64) -> f64 {
+        self.mean_value() + scale * self.prior / (1.0 + self.visits as f64)
+    }
+
+    fn terminal_value(&self) -> Option<f64> {
+        match self.board.has_winner() {
+            Some(winner) if winner == self.mover => Some(1.0),
+            Some(_) => Some(-1.0),
+            None if self.board.valid_moves().is_empty() => Some(0.0),
+            None => None,
+        }
+    }
+}
+
+/// How one move from the root fared in the search.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, serde::Deserialize))]
+pub struct MoveStats {
+    pub column: usize,
+    pub visits: u32,
+    /// The average playout result for the side to move, from -1 to 1.
+    pub mean_value: f64,
+    pub prior: f64,
+}
+
+/// Everything an `Mcts::search` explored, for inspection after the fact.
+pub struct SearchTree {
+    nodes: Vec<Node>,
+}
+
+impl SearchTree {
+    /// How many playouts went through the root.
+    pub fn visits(&self) -> u32 {
+        self.nodes[0].visits
+    }
+
+    /// The root's moves, most visited first.
+    pub fn root_stats(&self) -> Vec<MoveStats> {
+        let mut stats: Vec<MoveStats> = (self.nodes[0].children.iter())
+            .map(|&child| {
+                let node = &self.nodes[child];
+                MoveStats {
+                    column: node.column.expect("only the root has no column"),
+                    visits: node.visits,
+                    mean_value: node.mean_value(),
+                    prior: node.prior,
+                }
+            })
+            .collect();
+        stats.sort_by_key(|stats| std::cmp::Reverse(stats.visits));
+        stats
+    }
+
+    /// The line the search expects, in columns: the most visited move, then the most
+    /// visited reply, and so on while they have been visited.
+    pub fn principal_variation(&self) -> Vec<usize> {
+        let mut line = vec![];
+        let mut current = &self.nodes[0];
+        while let Some(next) = (current.children.iter())
+            .map(|&child| &self.nodes[child])
+            .filter(|node| node.visits > 0)
+            .max_by_key(|node| node.visits)
+        {
+            line.push(next.column.expect("only the root has no column"));
+            current = next;
+        }
+        line
+    }
+
+    /// The tree as a Graphviz digraph, leaving out nodes visited fewer than
+    /// `min_visits` times. Each node shows its board's short string, the mover's mean
+    /// value and its visits, and each edge the column and prior.
+    pub fn to_dot(&self, min_visits: u32) -> String {
+        let mut dot = String::from("digraph mcts {\n    node [shape=box, fontname=monospace];\n");
+        let mut stack = vec![0];
+        while let Some(i) = stack.pop() {
+            let node = &self.nodes[i];
+            writeln!(
+                dot,
+                "    n{i} [label=\"{}\\nvalue {:.3}  visits {}\"];",
+                node.board.short_string(),
+                node.mean_value(),
+                node.visits
+            )
+            .unwrap();
+            for &child in &node.children {
+                let next = &self.nodes[child];
+                if next.visits < min_visits.max(1) {
+                    continue;
+                }
+                writeln!(
+                    dot,
+                    "    n{i} -> n{child} [label=\"{} ({:.2})\"];",
+                    next.column.expect("only the root has no column"),
+                    next.prior
+                )
+                .unwrap();
+                stack.push(child);
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// The tree as nested JSON, leaving out nodes visited fewer than `min_visits` times.
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self, min_visits: u32) -> serde_json::Value {
+        serde_json::to_value(self.export(0, min_visits.max(1))).expect("trees serialize")
+    }
+
+    #[cfg(feature = "serde")]
+    fn export(&self, i: usize, min_visits: u32) -> ExportedNode {
+        let node = &self.nodes[i];
+        ExportedNode {
+            board: node.board,
+            mover: node.mover,
+            column: node.column,
+            visits: node.visits,
+            mean_value: node.mean_value(),
+            prior: node.prior,
+            children: (node.children.iter())
+                .filter(|&&child| self.nodes[child].visits >= min_visits)
+                .map(|&child| self.export(child, min_visits))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+#[derive(Serialize)]
+struct ExportedNode {
+    board: Board,
+    mover: Piece,
+    column: Option<usize>,
+    visits: u32,
+    mean_value: f64,
+    prior: f64,
+    children: Vec<ExportedNode>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_winning_move() {
+        let board = (0..3).fold(Board::new(), |board, _| {
+            board.place(0, Piece::Red).place(1, Piece::Yellow)
+        });
+        let mcts = Mcts::new(500);
+        let tree = mcts.search(&board, Piece::Red);
+        assert_eq!(tree.visits(), 500);
+        let stats = tree.root_stats();
+        assert_eq!(stats.len(), 7);
+        assert_eq!(tree.principal_variation()[0], 0);
+        assert_eq!(stats[0].column, 0);
+        assert!(stats[0].mean_value > 0.99, "{stats:?}");
+        assert!((stats.iter().map(|s| s.prior).sum::<f64>() - 1.0).abs() < 1e-9);
+        assert_eq!(
+            mcts.choose(&board, Piece::Red, &board.valid_moves()),
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn dot_export_skips_rarely_visited_nodes() {
+        let tree = Mcts::new(50).search(&Board::new(), Piece::Red);
+        let dot = tree.to_dot(1);
+        assert!(dot.starts_with("digraph mcts {"));
+        assert_eq!(dot.matches(" -> ").count(), 49);
+        assert_eq!(tree.to_dot(51).matches(" -> ").count(), 0);
+    }
+}
@@ -0,0 +1,100 @@
+use crate::board::{Board, COLUMNS, Piece, ROWS};
+
+/// Rows and columns in words are numbered from 1, columns from the left and rows from
+/// the bottom, as people count them.
+fn pieces_in_row(board: &Board, row: usize, piece: Piece) -> Vec<usize> {
+    (0..COLUMNS)
+        .filter(|&column| board.get_checked(column, row) == piece)
+        .map(|column| column + 1)
+        .collect()
+}
+
+/// "3", "3 and 4" or "1, 3 and 4".
+fn list(numbers: &[usize]) -> String {
+    match numbers {
+        [] => String::new(),
+        [only] => only.to_string(),
+        [rest @ .., last] => {
+            let rest: Vec<String> = rest.iter().map(usize::to_string).collect();
+            format!("{} and {last}", rest.join(", "))
+        }
+    }
+}
+
+/// The piece just dropped in `column` of `board`, e.g. "Red in column 4, row 2".
+pub fn describe_move(board: &Board, column: usize) -> String {
+    let row = (0..ROWS)
+        .take_while(|&row| board.get_checked(column, row) != Piece::Empty)
+        .count();
+    match row.checked_sub(1) {
+        Some(top) => format!(
+            "{} in column {}, row {row}",
+            board.get_checked(column, top).name(),
+            column + 1
+        ),
+        None => format!("Column {} is empty", column + 1),
+    }
+}
+
+/// Every piece on `board` in words, a sentence per row from the bottom up, then the
+/// columns that are full.
+pub fn describe_board(board: &Board) -> String {
+    let mut sentences = vec![];
+    for row in 0..ROWS {
+        let pieces: Vec<String> = [Piece::Red, Piece::Yellow, Piece::Blocker]
+            .into_iter()
+            .filter_map(|piece| {
+                let columns = pieces_in_row(board, row, piece);
+                let noun = if columns.len() == 1 {
+                    "column"
+                } else {
+                    "columns"
+                };
+                (!columns.is_empty())
+                    .then(|| format!("{} in {noun} {}", piece.name(), list(&columns)))
+            })
+            .collect();
+        if !pieces.is_empty() {
+            sentences.push(format!("Row {}: {}.", row + 1, pieces.join(", ")));
+        }
+    }
+    if sentences.is_empty() {
+        return "The board is empty.".to_string();
+    }
+    let full: Vec<usize> = (0..COLUMNS)
+        .filter(|&column| board.get_checked(column, ROWS - 1) != Piece::Empty)
+        .map(|column| column + 1)
+        .collect();
+    if !full.is_empty() {
+        let noun = if full.len() == 1 { "Column" } else { "Columns" };
+        let verb = if full.len() == 1 { "is" } else { "are" };
+        sentences.push(format!("{noun} {} {verb} full.", list(&full)));
+    }
+    sentences.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::GameState;
+
+    #[test]
+    fn moves_and_boards_in_words() {
+        let board = *GameState::from_moves("3324").unwrap().board();
+        assert_eq!(describe_move(&board, 3), "Yellow in column 4, row 2");
+        assert_eq!(describe_move(&board, 4), "Yellow in column 5, row 1");
+        assert_eq!(describe_move(&board, 0), "Column 1 is empty");
+        assert_eq!(
+            describe_board(&board),
+            "Row 1: Red in columns 3 and 4, Yellow in column 5. Row 2: Yellow in column 4."
+        );
+        assert_eq!(describe_board(&Board::new()), "The board is empty.");
+
+        let board = [Piece::Red, Piece::Yellow]
+            .into_iter()
+            .cycle()
+            .take(ROWS)
+            .fold(Board::new(), |board, piece| board.place(0, piece));
+        assert!(describe_board(&board).ends_with("Row 6: Yellow in column 1. Column 1 is full."));
+    }
+}
@@ -0,0 +1,245 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::board::Piece;
+use crate::game::{GameResult, GameState};
+use crate::strategy::Connect4AI;
+use crate::strategy_kind::{StrategyKind, build_stack};
+
+/// Messages the server sends. Every message is one line of JSON.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    /// Sent once a client has a seat.
+    Welcome { piece: Piece },
+    /// Sent to everyone after the game starts and after every move.
+    State {
+        /// The board as a short string, e.g. `"!/////   R"`.
+        board: String,
+        history: Vec<usize>,
+        to_move: Piece,
+    },
+    /// The last message sent; `winner` is null for a tie.
+    GameOver { winner: Option<Piece> },
+    /// Sent only to the client whose message was rejected.
+    Error { message: String },
+}
+
+/// Messages a client sends. Every message is one line of JSON.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    Move { column: usize },
+}
+
+pub fn send<T: Serialize>(out: &mut impl Write, message: &T) -> Result<()> {
+    let mut line = serde_json::to_string(message)?;
+    line.push('\n');
+    out.write_all(line.as_bytes())?;
+    out.flush()?;
+    Ok(())
+}
+
+/// Reads the next message, or None once the other side hangs up.
+pub fn receive<T: for<'de> Deserialize<'de>>(input: &mut impl BufRead) -> Result<Option<T>> {
+    let mut line = String::new();
+    if input.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    let message =
+        serde_json::from_str(&line).with_context(|| format!("Invalid message {line:?}"))?;
+    Ok(Some(message))
+}
+
+/// Who plays one color in a hosted game.
+#[derive(Debug, Clone)]
+pub enum Seat {
+    /// A client that connects over the network.
+    Remote,
+    /// The server plays this color itself with the given stack.
+    Ai(Vec<StrategyKind>),
+}
+
+/// One end of a game connection, used by both the server and clients.
+pub struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl Connection {
+    pub fn new(stream: TcpStream) -> Result<Self> {
+        Ok(Connection {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+        })
+    }
+
+    pub fn send<T: Serialize>(&mut self, message: &T) -> Result<()> {
+        send(&mut self.writer, message)
+    }
+
+    pub fn receive<T: for<'de> Deserialize<'de>>(&mut self) -> Result<Option<T>> {
+        receive(&mut self.reader)
+    }
+}
+
+/// Connects to a hosted game and waits for a seat. Returns the connection and the
+/// color we were given.
+pub fn join_game(address: &str) -> Result<(Connection, Piece)> {
+    let stream =
+        TcpStream::connect(address).with_context(|| format!("Failed to connect to {address}"))?;
+    let mut connection = Connection::new(stream)?;
+    match connection.receive::<ServerMessage>()? {
+        Some(ServerMessage::Welcome { piece }) => Ok((connection, piece)),
+        Some(other) => bail!("Expected a welcome from the server, got {other:?}"),
+        None => bail!("The server hung up"),
+    }
+}
+
+fn state_message(state: &GameState) -> ServerMessage {
+    ServerMessage::State {
+        board: state.board().short_string(),
+        history: state.history().to_vec(),
+        to_move: state.to_move(),
+    }
+}
+
+/// Waits for a client to take every remote seat, then plays one game, checking every
+/// move against the rules before applying it. Returns how the game ended.
+pub fn host_game(listener: &TcpListener, red: &Seat, yellow: &Seat) -> Result<GameResult> {
+    let mut clients: Vec<(Piece, Connection)> = vec![];
+    for (piece, seat) in [(Piece::Red, red), (Piece::Yellow, yellow)] {
+        if let Seat::Remote = seat {
+            let (stream, address) = listener.accept()?;
+            tracing::info!(piece = piece.name(), %address, "player joined");
+            let mut client = Connection::new(stream)?;
+            client.send(&ServerMessage::Welcome { piece })?;
+            clients.push((piece, client));
+        }
+    }
+    let red_ai = match red {
+        Seat::Ai(kinds) => Some(build_stack(kinds)),
+        Seat::Remote => None,
+    };
+    let yellow_ai = match yellow {
+        Seat::Ai(kinds) => Some(build_stack(kinds)),
+        Seat::Remote => None,
+    };
+
+    let mut state = GameState::new();
+    let broadcast = |clients: &mut Vec<(Piece, Connection)>, message: &ServerMessage| {
+        // A client that hung up will be noticed when it's their turn
+        for (_, client) in clients.iter_mut() {
+            let _ = client.send(message);
+        }
+    };
+    broadcast(&mut clients, &state_message(&state));
+
+    let result = loop {
+        let piece = state.to_move();
+        let ai = match piece {
+            Piece::Red => red_ai.as_ref(),
+            _ => yellow_ai.as_ref(),
+        };
+        match ai {
+            Some(ai) => {
+                let column = ai
+                    .play(state.board(), piece)
+                    .context("The server's AI failed to move")?;
+                state.apply_move(column)?;
+            }
+            None => {
+                let (_, client) = clients
+                    .iter_mut()
+                    .find(|(p, _)| *p == piece)
+                    .expect("Every remote seat has a client");
+                loop {
+                    let Some(message) = client.receive::<ClientMessage>()? else {
+                        bail!("{} disconnected", piece.name());
+                    };
+                    let ClientMessage::Move { column } = message;
+                    match state.apply_move(column) {
+                        Ok(_) => break,
+                        Err(e) => client.send(&ServerMessage::Error {
+                            message: e.to_string(),
+                        })?,
+                    }
+                }
+            }
+        }
+
+        broadcast(&mut clients, &state_message(&state));
+        if let Some(result) = state.result() {
+            break result;
+        }
+    };
+
+    let winner = match result {
+        GameResult::Winner(piece) => Some(piece),
+        GameResult::Tie => None,
+    };
+    broadcast(&mut clients, &ServerMessage::GameOver { winner });
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_are_tagged_json() {
+        let json = serde_json::to_string(&ClientMessage::Move { column: 3 }).unwrap();
+        assert_eq!(json, r#"{"type":"move","column":3}"#);
+        let message: ServerMessage =
+            serde_json::from_str(r#"{"type":"welcome","piece":"Yellow"}"#).unwrap();
+        assert_eq!(
+            message,
+            ServerMessage::Welcome {
+                piece: Piece::Yellow
+            }
+        );
+    }
+
+    #[test]
+    fn remote_player_against_server_ai() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            host_game(&listener, &Seat::Ai(vec![]), &Seat::Remote).unwrap()
+        });
+
+        let (mut connection, piece) = join_game(&address.to_string()).unwrap();
+        assert_eq!(piece, Piece::Yellow);
+
+        let mut rejected = false;
+        let winner = loop {
+            match connection.receive::<ServerMessage>().unwrap().unwrap() {
+                ServerMessage::State {
+                    history,
+                    to_move: Piece::Yellow,
+                    ..
+                } => {
+                    let state = GameState::from_history(&history).unwrap();
+                    if state.is_over() {
+                        continue;
+                    }
+                    if !rejected {
+                        connection.send(&ClientMessage::Move { column: 7 }).unwrap();
+                    }
+                    let column = state.legal_moves()[0];
+                    connection.send(&ClientMessage::Move { column }).unwrap();
+                }
+                ServerMessage::Error { .. } => rejected = true,
+                ServerMessage::GameOver { winner } => break winner,
+                _ => {}
+            }
+        };
+
+        assert!(rejected);
+        let result = server.join().unwrap();
+        assert_eq!(result, winner.map_or(GameResult::Tie, GameResult::Winner));
+    }
+}
@@ -0,0 +1,127 @@
+#[cfg(feature = "nn")]
+use std::{path::Path, sync::Mutex};
+
+#[cfg(feature = "nn")]
+use anyhow::{Context, Result, ensure};
+
+use crate::board::{Board, COLUMNS, Piece, ROWS};
+#[cfg(feature = "nn")]
+use crate::strategy::StrategyDecider;
+
+/// The shape of one encoded board: two planes of `ROWS` by `COLUMNS`.
+pub const ENCODED_SHAPE: [usize; 3] = [2, ROWS, COLUMNS];
+
+/// How many floats `encode` produces.
+pub const ENCODED_LEN: usize = 2 * ROWS * COLUMNS;
+
+/// Encodes `board` from the view of `to_move` for a neural network. The first plane
+/// marks `to_move`'s pieces with 1.0 and the second the opponent's, so the same model
+/// serves both colors. Each plane is row-major with the bottom row first, so index
+/// `plane * ROWS * COLUMNS + row * COLUMNS + column`.
+pub fn encode(board: &Board, to_move: Piece) -> [f32; ENCODED_LEN] {
+    let mut planes = [0.0; ENCODED_LEN];
+    for column in 0..COLUMNS {
+        for row in 0..ROWS {
+            let plane = match board.get_checked(column, row) {
+                Piece::Empty => continue,
+                piece if piece == to_move => 0,
+                _ => 1,
+            };
+            planes[plane * ROWS * COLUMNS + row * COLUMNS + column] = 1.0;
+        }
+    }
+    planes
+}
+
+/// Picks moves with an ONNX model that values positions for the side to move, from -1
+/// (lost) to 1 (won). The model's first input takes `encode`d boards as a float tensor
+/// of shape `[batch, 2, ROWS, COLUMNS]`, and its first output gives one value per
+/// board, as `[batch]` or `[batch, 1]`. Further outputs, like a policy, are ignored.
+#[cfg(feature = "nn")]
+pub struct NeuralEval {
+    session: Mutex<ort::session::Session>,
+}
+
+#[cfg(feature = "nn")]
+impl NeuralEval {
+    /// Loads the model at `path`. ONNX Runtime itself is loaded on first use, from
+    /// `ORT_DYLIB_PATH` or the system library path.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let session = ort::session::Session::builder()
+            .and_then(|mut builder| builder.commit_from_file(path))
+            .with_context(|| format!("Failed to load the model {}", path.display()))?;
+        Ok(NeuralEval {
+            session: Mutex::new(session),
+        })
+    }
+
+    /// The model's value of each board for `to_move`, in one batch.
+    pub fn evaluate(&self, boards: &[Board], to_move: Piece) -> Result<Vec<f32>> {
+        let input: Vec<f32> = (boards.iter())
+            .flat_map(|board| encode(board, to_move))
+            .collect();
+        let [planes, rows, columns] = ENCODED_SHAPE;
+        let shape = [boards.len(), planes, rows, columns];
+        let tensor = ort::value::Tensor::from_array((shape, input))?;
+        let mut session = self.session.lock().unwrap();
+        let outputs = session.run(ort::inputs![tensor])?;
+        let (_, values) = outputs[0].try_extract_tensor::<f32>()?;
+        ensure!(
+            values.len() == boards.len(),
+            "The model returned {} values for {} boards",
+            values.len(),
+            boards.len()
+        );
+        Ok(values.to_vec())
+    }
+}
+
+#[cfg(feature = "nn")]
+impl StrategyDecider for NeuralEval {
+    /// Takes a win if there is one, and otherwise the move that leaves the opponent the
+    /// worst position by the model.
+    fn choose(&self, board: &Board, piece: Piece, options: &[usize]) -> Option<usize> {
+        let boards: Vec<Board> = (options.iter())
+            .map(|&col| board.place(col, piece))
+            .collect();
+        if let Some(i) = boards.iter().position(|b| b.has_winner() == Some(piece)) {
+            return Some(options[i]);
+        }
+        let values = match self.evaluate(&boards, piece.opponent()) {
+            Ok(values) => values,
+            Err(e) => {
+                tracing::warn!(error = %e, "model evaluation failed");
+                return None;
+            }
+        };
+        (options.iter().zip(values))
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(&col, _)| col)
+    }
+
+    fn name(&self) -> &'static str {
+        "NeuralEval"
+    }
+
+    fn is_expensive(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_from_the_side_to_move() {
+        let board = Board::new().place(3, Piece::Red).place(3, Piece::Yellow);
+        let red = encode(&board, Piece::Red);
+        let yellow = encode(&board, Piece::Yellow);
+        assert_eq!(red[3], 1.0);
+        assert_eq!(red[ROWS * COLUMNS + COLUMNS + 3], 1.0);
+        assert_eq!(red.iter().sum::<f32>(), 2.0);
+        // Swapping sides swaps the planes
+        assert_eq!(red[..ROWS * COLUMNS], yellow[ROWS * COLUMNS..]);
+    }
+}
@@ -0,0 +1,302 @@
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::time::Duration;
+
+use crate::board::{COLUMNS, LineDirection, Piece, ROWS};
+use crate::game::{GameResult, GameState};
+use crate::stats::{mean_duration, percentile};
+
+/// Hooks that get notified as a game is played.
+///
+/// Every method defaults to doing nothing so observers only need to implement what they care about.
+pub trait GameObserver {
+    /// Called after `player` dropped a piece in `column`. `state` already includes the move.
+    /// `elapsed` is how long the player took to pick the move.
+    fn on_move(&mut self, _state: &GameState, _player: Piece, _column: usize, _elapsed: Duration) {}
+
+    /// Called after `player` took a turn without dropping a piece: `entry` is the `PASS`,
+    /// `SWAP` or `ROTATE` marker it added to the history. `state` already includes it.
+    fn on_special_move(&mut self, _state: &GameState, _player: Piece, _entry: usize) {}
+
+    /// Called when `player` tried to drop a piece in `column` but couldn't, e.g. because
+    /// it is full. Only interactive games report these.
+    fn on_invalid_move(&mut self, _state: &GameState, _player: Piece, _column: usize) {}
+
+    /// Called once when the game finishes.
+    fn on_game_end(&mut self, _state: &GameState, _result: GameResult) {}
+}
+
+/// Observer that doesn't observe anything.
+impl GameObserver for () {}
+
+impl<T: GameObserver + ?Sized> GameObserver for &mut T {
+    fn on_move(&mut self, state: &GameState, player: Piece, column: usize, elapsed: Duration) {
+        (**self).on_move(state, player, column, elapsed)
+    }
+
+    fn on_special_move(&mut self, state: &GameState, player: Piece, entry: usize) {
+        (**self).on_special_move(state, player, entry)
+    }
+
+    fn on_invalid_move(&mut self, state: &GameState, player: Piece, column: usize) {
+        (**self).on_invalid_move(state, player, column)
+    }
+
+    fn on_game_end(&mut self, state: &GameState, result: GameResult) {
+        (**self).on_game_end(state, result)
+    }
+}
+
+/// Lets two observers watch the same game.
+impl<A: GameObserver, B: GameObserver> GameObserver for (A, B) {
+    fn on_move(&mut self, state: &GameState, player: Piece, column: usize, elapsed: Duration) {
+        self.0.on_move(state, player, column, elapsed);
+        self.1.on_move(state, player, column, elapsed);
+    }
+
+    fn on_special_move(&mut self, state: &GameState, player: Piece, entry: usize) {
+        self.0.on_special_move(state, player, entry);
+        self.1.on_special_move(state, player, entry);
+    }
+
+    fn on_invalid_move(&mut self, state: &GameState, player: Piece, column: usize) {
+        self.0.on_invalid_move(state, player, column);
+        self.1.on_invalid_move(state, player, column);
+    }
+
+    fn on_game_end(&mut self, state: &GameState, result: GameResult) {
+        self.0.on_game_end(state, result);
+        self.1.on_game_end(state, result);
+    }
+}
+
+/// An observer that may be turned off.
+impl<T: GameObserver> GameObserver for Option<T> {
+    fn on_move(&mut self, state: &GameState, player: Piece, column: usize, elapsed: Duration) {
+        if let Some(observer) = self {
+            observer.on_move(state, player, column, elapsed);
+        }
+    }
+
+    fn on_special_move(&mut self, state: &GameState, player: Piece, entry: usize) {
+        if let Some(observer) = self {
+            observer.on_special_move(state, player, entry);
+        }
+    }
+
+    fn on_invalid_move(&mut self, state: &GameState, player: Piece, column: usize) {
+        if let Some(observer) = self {
+            observer.on_invalid_move(state, player, column);
+        }
+    }
+
+    fn on_game_end(&mut self, state: &GameState, result: GameResult) {
+        if let Some(observer) = self {
+            observer.on_game_end(state, result);
+        }
+    }
+}
+
+/// Records how long each player took for every move.
+#[derive(Debug, Clone, Default)]
+pub struct MoveTimer {
+    red: Vec<Duration>,
+    yellow: Vec<Duration>,
+}
+
+impl MoveTimer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every recorded move time for the given player, in order.
+    pub fn times(&self, player: Piece) -> &[Duration] {
+        match player {
+            Piece::Red => &self.red,
+            Piece::Yellow => &self.yellow,
+            Piece::Empty | Piece::Blocker => panic!("{player:?} never moves"),
+        }
+    }
+
+    /// The average time per move for the given player, if they have moved at all.
+    pub fn average(&self, player: Piece) -> Option<Duration> {
+        mean_duration(self.times(player))
+    }
+
+    /// The move time below which `pct` percent of the player's moves fall (nearest rank).
+    pub fn percentile(&self, player: Piece, pct: f64) -> Option<Duration> {
+        percentile(self.times(player), pct)
+    }
+}
+
+impl GameObserver for MoveTimer {
+    fn on_move(&mut self, _state: &GameState, player: Piece, _column: usize, elapsed: Duration) {
+        match player {
+            Piece::Red => self.red.push(elapsed),
+            Piece::Yellow => self.yellow.push(elapsed),
+            Piece::Empty | Piece::Blocker => unreachable!(),
+        }
+    }
+}
+
+/// Distribution of how games ended: their length, the direction of the winning line,
+/// and the column the winning piece was dropped in. Games won by a rotation have no
+/// winning column.
+#[derive(Debug, Clone)]
+pub struct OutcomeStats {
+    /// Indexed by the number of pieces on the board when the game ended.
+    pub lengths: [usize; ROWS * COLUMNS + 1],
+    /// Indexed like `LineDirection::ALL`.
+    pub directions: [usize; 3],
+    pub winning_columns: [usize; COLUMNS],
+}
+
+impl OutcomeStats {
+    pub fn new() -> Self {
+        OutcomeStats {
+            lengths: [0; ROWS * COLUMNS + 1],
+            directions: [0; 3],
+            winning_columns: [0; COLUMNS],
+        }
+    }
+
+    pub fn merge(&mut self, other: &OutcomeStats) {
+        for (a, b) in self.lengths.iter_mut().zip(other.lengths) {
+            *a += b;
+        }
+        for (a, b) in self.directions.iter_mut().zip(other.directions) {
+            *a += b;
+        }
+        for (a, b) in self.winning_columns.iter_mut().zip(other.winning_columns) {
+            *a += b;
+        }
+    }
+}
+
+impl Default for OutcomeStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GameObserver for OutcomeStats {
+    fn on_game_end(&mut self, state: &GameState, _result: GameResult) {
+        self.lengths[state.board().num_pieces_played()] += 1;
+        if let Some((_, direction)) = state.board().winning_direction() {
+            let index = LineDirection::ALL
+                .iter()
+                .position(|d| *d == direction)
+                .unwrap();
+            self.directions[index] += 1;
+            if let Some(&column) = state.history().last()
+                && column < COLUMNS
+            {
+                self.winning_columns[column] += 1;
+            }
+        }
+    }
+}
+
+/// Counts how many distinct games were played, keyed by a hash of the move sequence.
+/// Deterministic or cached strategies tend to replay the same few games.
+#[derive(Debug, Clone, Default)]
+pub struct GameDiversity {
+    counts: HashMap<u64, usize>,
+}
+
+impl GameDiversity {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn games(&self) -> usize {
+        self.counts.values().sum()
+    }
+
+    pub fn unique(&self) -> usize {
+        self.counts.len()
+    }
+
+    /// How many times the most common game was played.
+    pub fn most_repeated(&self) -> usize {
+        self.counts.values().copied().max().unwrap_or(0)
+    }
+}
+
+impl GameObserver for GameDiversity {
+    fn on_game_end(&mut self, state: &GameState, _result: GameResult) {
+        let mut hasher = DefaultHasher::new();
+        state.history().hash(&mut hasher);
+        *self.counts.entry(hasher.finish()).or_default() += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn move_timer_records_per_player() {
+        let mut state = GameState::new();
+        let mut timer = MoveTimer::new();
+        assert_eq!(timer.average(Piece::Red), None);
+
+        state.apply_move(0).unwrap();
+        timer.on_move(&state, Piece::Red, 0, Duration::from_millis(10));
+        state.apply_move(1).unwrap();
+        timer.on_move(&state, Piece::Yellow, 1, Duration::from_millis(4));
+        state.apply_move(0).unwrap();
+        timer.on_move(&state, Piece::Red, 0, Duration::from_millis(20));
+
+        assert_eq!(timer.times(Piece::Red).len(), 2);
+        assert_eq!(timer.times(Piece::Yellow).len(), 1);
+        assert_eq!(timer.average(Piece::Red), Some(Duration::from_millis(15)));
+        assert_eq!(timer.average(Piece::Yellow), Some(Duration::from_millis(4)));
+        assert_eq!(
+            timer.percentile(Piece::Red, 50.0),
+            Some(Duration::from_millis(10))
+        );
+        assert_eq!(
+            timer.percentile(Piece::Red, 100.0),
+            Some(Duration::from_millis(20))
+        );
+    }
+
+    #[test]
+    fn outcome_stats_records_the_win() {
+        let mut state = GameState::new();
+        for col in [0, 1, 0, 1, 0, 1, 0] {
+            state.apply_move(col).unwrap();
+        }
+        let mut stats = OutcomeStats::new();
+        stats.on_game_end(&state, state.result().unwrap());
+
+        assert_eq!(stats.lengths[7], 1);
+        assert_eq!(stats.directions, [0, 1, 0]);
+        assert_eq!(stats.winning_columns[0], 1);
+    }
+
+    #[test]
+    fn outcome_stats_skips_the_column_of_a_rotation_win() {
+        let game = GameState::from_moves("65656550").unwrap().with_rotations();
+        let stack = crate::strategy::StrategyStack::new(vec![]);
+        let mut stats = OutcomeStats::new();
+        let played = crate::game::play_game(&stack, &stack, &game, &mut stats).unwrap();
+
+        assert_eq!(played.moves().last(), Some(&crate::game::ROTATE));
+        assert_eq!(stats.directions.iter().sum::<usize>(), 1);
+        assert_eq!(stats.winning_columns, [0; COLUMNS]);
+    }
+
+    #[test]
+    fn diversity_counts_repeated_games() {
+        let mut diversity = GameDiversity::new();
+        for moves in ["0101010", "0101010", "1010101"] {
+            let state = GameState::from_moves(moves).unwrap();
+            diversity.on_game_end(&state, state.result().unwrap());
+        }
+        assert_eq!(diversity.games(), 3);
+        assert_eq!(diversity.unique(), 2);
+        assert_eq!(diversity.most_repeated(), 2);
+    }
+}
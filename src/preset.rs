@@ -0,0 +1,133 @@
+use std::path::Path;
+
+use anyhow::{Context, Result, ensure};
+use serde::{Deserialize, Serialize};
+
+use crate::strategy_kind::{StrategyKind, parse_stack};
+
+/// A strategy stack saved under a name, so it doesn't have to be built again every run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StrategyPreset {
+    pub name: String,
+    pub stack: Vec<StrategyKind>,
+}
+
+/// The file format: layers are written the way `StrategyKind` prints them, so the
+/// files are easy to edit by hand.
+#[derive(Serialize, Deserialize)]
+struct PresetFile {
+    name: String,
+    stack: Vec<String>,
+}
+
+impl StrategyPreset {
+    /// Names become file names, so they are limited to letters, digits, `-` and `_`.
+    pub fn new(name: &str, stack: Vec<StrategyKind>) -> Result<Self> {
+        ensure!(!name.is_empty(), "Preset names can't be empty");
+        ensure!(
+            (name.chars()).all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'),
+            "Preset names may only use letters, digits, '-' and '_', not {name:?}"
+        );
+        Ok(StrategyPreset {
+            name: name.to_string(),
+            stack,
+        })
+    }
+
+    pub fn to_toml(&self) -> String {
+        let file = PresetFile {
+            name: self.name.clone(),
+            stack: self.stack.iter().map(|kind| kind.to_string()).collect(),
+        };
+        toml::to_string(&file).expect("presets serialize")
+    }
+
+    pub fn from_toml(text: &str) -> Result<Self> {
+        let file: PresetFile = toml::from_str(text)?;
+        let stack = parse_stack(&file.stack.join(","))?;
+        Self::new(&file.name, stack)
+    }
+
+    /// Writes the preset to `<dir>/<name>.toml`, creating `dir` if needed and replacing
+    /// any preset of the same name.
+    pub fn save(&self, dir: &str) -> Result<()> {
+        std::fs::create_dir_all(dir).with_context(|| format!("Creating {}", dir))?;
+        let path = Path::new(dir).join(format!("{}.toml", self.name));
+        std::fs::write(&path, self.to_toml())
+            .with_context(|| format!("Writing preset {}", path.display()))
+    }
+}
+
+/// The game's configuration directory: `$XDG_CONFIG_HOME/connect-4`, falling back to
+/// `~/.config`.
+pub fn config_dir() -> String {
+    let config = std::env::var("XDG_CONFIG_HOME").unwrap_or_else(|_| {
+        let home = std::env::var("HOME")
+            .or_else(|_| std::env::var("USERPROFILE"))
+            .unwrap_or_else(|_| ".".to_string());
+        format!("{}/.config", home)
+    });
+    format!("{}/connect-4", config)
+}
+
+/// Where presets are kept: `strategies` in `config_dir()`.
+pub fn presets_dir() -> String {
+    format!("{}/strategies", config_dir())
+}
+
+/// Every `.toml` preset in `dir`, by name. A missing directory has none.
+pub fn load_presets(dir: &str) -> Result<Vec<StrategyPreset>> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e).with_context(|| format!("Reading {}", dir)),
+    };
+    let mut presets = vec![];
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().is_none_or(|extension| extension != "toml") {
+            continue;
+        }
+        let text = std::fs::read_to_string(&path)
+            .with_context(|| format!("Reading preset {}", path.display()))?;
+        let preset = StrategyPreset::from_toml(&text)
+            .with_context(|| format!("Reading preset {}", path.display()))?;
+        presets.push(preset);
+    }
+    presets.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(presets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_round_trip_through_a_directory() {
+        let dir = std::env::temp_dir().join(format!("connect4-presets-{}", std::process::id()));
+        let dir = dir.to_str().unwrap();
+        assert!(load_presets(dir).unwrap().is_empty());
+
+        let stack = vec![
+            StrategyKind::SearchForWin { depth: 5 },
+            StrategyKind::TriesToWin,
+        ];
+        let preset = StrategyPreset::new("deep-search", stack).unwrap();
+        assert!(preset.to_toml().contains("\"SearchForWin(5)\""));
+        preset.save(dir).unwrap();
+        StrategyPreset::new("blocker", vec![StrategyKind::AvoidTraps])
+            .unwrap()
+            .save(dir)
+            .unwrap();
+        std::fs::write(format!("{dir}/notes.txt"), "ignored").unwrap();
+
+        let loaded = load_presets(dir).unwrap();
+        std::fs::remove_dir_all(dir).unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded[0].name, "blocker");
+        assert_eq!(loaded[1], preset);
+
+        assert!(StrategyPreset::new("../escape", vec![]).is_err());
+        assert!(StrategyPreset::from_toml("name = \"x\"\nstack = [\"Nonsense\"]").is_err());
+    }
+}
@@ -0,0 +1,261 @@
+use std::collections::BTreeMap;
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::board::Piece;
+use crate::game::GameResult;
+use crate::strategy_kind::{MAX_DIFFICULTY, StrategyKind, difficulty_stack};
+
+/// Bumped whenever the file format changes; `Profile::from_json` upgrades older files.
+pub const PROFILE_VERSION: u32 = 2;
+
+/// The ladder's top level, one above the hardest difficulty.
+pub const LADDER_TOP: u8 = MAX_DIFFICULTY + 1;
+
+/// How many rating points a single game can move.
+const K_FACTOR: f64 = 32.0;
+
+/// Wins, losses and ties against one opponent.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Record {
+    pub wins: usize,
+    pub losses: usize,
+    pub ties: usize,
+}
+
+/// How far up the ladder a player has climbed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LadderProgress {
+    pub level: u8,
+    /// Wins at the current level.
+    pub wins: usize,
+    /// Set once the top level has been beaten.
+    pub completed: bool,
+}
+
+impl LadderProgress {
+    pub const WINS_TO_ADVANCE: usize = 3;
+
+    /// Counts a ladder game. Returns true if the win moved the player up a level or
+    /// completed the top one.
+    pub fn record(&mut self, won: bool) -> bool {
+        if !won || self.completed {
+            return false;
+        }
+        self.wins += 1;
+        if self.wins < Self::WINS_TO_ADVANCE {
+            return false;
+        }
+        self.wins = 0;
+        if self.level < LADDER_TOP {
+            self.level += 1;
+        } else {
+            self.completed = true;
+        }
+        true
+    }
+}
+
+/// A player's history against the AI, kept between sessions.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Profile {
+    pub version: u32,
+    pub name: String,
+    pub games_played: usize,
+    /// Keyed by `opponent_label`.
+    pub records: BTreeMap<String, Record>,
+    /// Wins in a row, reset by a loss or tie.
+    pub streak: usize,
+    pub best_streak: usize,
+    /// Elo rating, moved only by games against the ladder levels.
+    pub rating: f64,
+    pub ladder: LadderProgress,
+}
+
+impl Profile {
+    pub const STARTING_RATING: f64 = 1200.0;
+
+    pub fn new(name: &str) -> Self {
+        Profile {
+            version: PROFILE_VERSION,
+            name: name.to_string(),
+            games_played: 0,
+            records: BTreeMap::new(),
+            streak: 0,
+            best_streak: 0,
+            rating: Self::STARTING_RATING,
+            ladder: LadderProgress::default(),
+        }
+    }
+
+    /// Reads a profile, refusing files written by a newer version.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let mut value: serde_json::Value = serde_json::from_str(json)?;
+        let version = value
+            .get("version")
+            .and_then(|version| version.as_u64())
+            .context("The profile has no version")?;
+        match version {
+            1 => {
+                // Version 1 predates the ladder
+                value["ladder"] = serde_json::to_value(LadderProgress::default())?;
+                value["version"] = PROFILE_VERSION.into();
+                Ok(serde_json::from_value(value)?)
+            }
+            2 => Ok(serde_json::from_value(value)?),
+            _ => bail!(
+                "Profile version {} is newer than this build understands ({})",
+                version,
+                PROFILE_VERSION
+            ),
+        }
+    }
+
+    /// Loads the profile at `path`, or returns None if there isn't one yet.
+    pub fn load(path: &str) -> Result<Option<Self>> {
+        match std::fs::read_to_string(path) {
+            Ok(json) => Self::from_json(&json)
+                .with_context(|| format!("Reading profile {}", path))
+                .map(Some),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e).with_context(|| format!("Reading profile {}", path)),
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json).with_context(|| format!("Writing profile {}", path))
+    }
+
+    /// Records a finished game the human played as `human` against the `opponent` stack.
+    pub fn record_game(&mut self, opponent: &[StrategyKind], human: Piece, result: GameResult) {
+        let record = self.records.entry(opponent_label(opponent)).or_default();
+        let score = match result {
+            GameResult::Winner(winner) if winner == human => {
+                record.wins += 1;
+                self.streak += 1;
+                self.best_streak = self.best_streak.max(self.streak);
+                1.0
+            }
+            GameResult::Winner(_) => {
+                record.losses += 1;
+                self.streak = 0;
+                0.0
+            }
+            GameResult::Tie => {
+                record.ties += 1;
+                self.streak = 0;
+                0.5
+            }
+        };
+        self.games_played += 1;
+
+        if let Some(level) = ladder_level(opponent) {
+            let expected = 1.0 / (1.0 + 10f64.powf((ladder_rating(level) - self.rating) / 400.0));
+            self.rating += K_FACTOR * (score - expected);
+        }
+    }
+}
+
+/// The opponent at each ladder level: the difficulty levels, then a deeper search on top.
+pub fn ladder_stack(level: u8) -> Vec<StrategyKind> {
+    if level < LADDER_TOP {
+        return difficulty_stack(level);
+    }
+    vec![
+        StrategyKind::SearchForWin { depth: 4 },
+        StrategyKind::TriesToWin,
+        StrategyKind::AvoidInescapableTraps,
+        StrategyKind::ThreeInARow,
+    ]
+}
+
+/// The ladder level `stack` is, if it is one of them.
+pub fn ladder_level(stack: &[StrategyKind]) -> Option<u8> {
+    (0..=LADDER_TOP).find(|&level| ladder_stack(level) == stack)
+}
+
+/// The fixed rating of each ladder level.
+pub fn ladder_rating(level: u8) -> f64 {
+    800.0 + 200.0 * level as f64
+}
+
+/// "Level N" for the ladder levels, the stack itself for anything else.
+pub fn opponent_label(stack: &[StrategyKind]) -> String {
+    match ladder_level(stack) {
+        Some(level) => format!("Level {}", level),
+        None => stack
+            .iter()
+            .map(|kind| kind.to_string())
+            .collect::<Vec<_>>()
+            .join(" => "),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_games_streaks_and_rating() {
+        let mut profile = Profile::new("Ada");
+        let level_two = difficulty_stack(2);
+        profile.record_game(&level_two, Piece::Red, GameResult::Winner(Piece::Red));
+        profile.record_game(&level_two, Piece::Yellow, GameResult::Winner(Piece::Yellow));
+        assert_eq!(profile.streak, 2);
+        let rating = profile.rating;
+        assert!(rating > Profile::STARTING_RATING);
+
+        let custom = vec![StrategyKind::Setup];
+        profile.record_game(&custom, Piece::Red, GameResult::Tie);
+        assert_eq!(profile.streak, 0);
+        assert_eq!(profile.best_streak, 2);
+        assert_eq!(profile.rating, rating, "custom stacks aren't rated");
+
+        assert_eq!(profile.games_played, 3);
+        assert_eq!(profile.records["Level 2"].wins, 2);
+        assert_eq!(profile.records["Setup"].ties, 1);
+    }
+
+    #[test]
+    fn round_trips_and_rejects_newer_versions() {
+        let mut profile = Profile::new("Ada");
+        profile.record_game(&[], Piece::Red, GameResult::Winner(Piece::Yellow));
+        let json = serde_json::to_string(&profile).unwrap();
+        assert_eq!(Profile::from_json(&json).unwrap(), profile);
+
+        let newer = json.replace(
+            &format!("\"version\":{}", PROFILE_VERSION),
+            &format!("\"version\":{}", PROFILE_VERSION + 1),
+        );
+        assert!(Profile::from_json(&newer).is_err());
+        assert!(Profile::from_json("{}").is_err());
+    }
+
+    #[test]
+    fn upgrades_version_one_profiles() {
+        let v1 = r#"{"version":1,"name":"Ada","games_played":0,"records":{},"streak":0,"best_streak":0,"rating":1200.0}"#;
+        let profile = Profile::from_json(v1).unwrap();
+        assert_eq!(profile.version, PROFILE_VERSION);
+        assert_eq!(profile.ladder, LadderProgress::default());
+    }
+
+    #[test]
+    fn ladder_promotes_after_enough_wins() {
+        let mut ladder = LadderProgress::default();
+        assert!(!ladder.record(true));
+        assert!(!ladder.record(false));
+        assert!(!ladder.record(true));
+        assert!(ladder.record(true));
+        assert_eq!((ladder.level, ladder.wins), (1, 0));
+
+        ladder.level = LADDER_TOP;
+        for _ in 0..LadderProgress::WINS_TO_ADVANCE {
+            ladder.record(true);
+        }
+        assert!(ladder.completed);
+        assert_eq!(ladder.level, LADDER_TOP);
+        assert_eq!(ladder_level(&ladder_stack(LADDER_TOP)), Some(LADDER_TOP));
+    }
+}
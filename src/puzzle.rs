@@ -0,0 +1,237 @@
+use anyhow::{Context, Result, ensure};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashSet;
+
+use crate::board::{Board, Piece};
+use crate::game::{GameRecord, GameState};
+
+/// Whether `player`, who is to move, can force a win within `moves` of their own moves.
+pub fn wins_within(board: &Board, player: Piece, moves: usize) -> bool {
+    !winning_moves_within(board, player, moves).is_empty()
+}
+
+/// Every column that lets `player`, who is to move, force a win within `moves` of
+/// their own moves.
+pub fn winning_moves_within(board: &Board, player: Piece, moves: usize) -> Vec<usize> {
+    if moves == 0 || board.has_winner().is_some() {
+        return vec![];
+    }
+    board
+        .valid_moves()
+        .into_iter()
+        .filter(|&col| {
+            let next = board.place(col, player);
+            if next.has_winner() == Some(player) {
+                return true;
+            }
+            let replies = next.valid_moves();
+            moves > 1
+                && !replies.is_empty()
+                && replies.into_iter().all(|reply| {
+                    let after = next.place(reply, player.opponent());
+                    after.has_winner().is_none() && wins_within(&after, player, moves - 1)
+                })
+        })
+        .collect()
+}
+
+/// The reply that holds out longest against `attacker`, who has `moves` of their own
+/// left to win. Returns None if the defender has no legal move.
+pub fn best_defense(board: &Board, defender: Piece, moves: usize) -> Option<usize> {
+    let attacker = defender.opponent();
+    board.valid_moves().into_iter().max_by_key(|&col| {
+        let after = board.place(col, defender);
+        // How many moves the attacker needs after this reply; more is a better defense
+        (1..=moves)
+            .find(|&k| wins_within(&after, attacker, k))
+            .unwrap_or(usize::MAX)
+    })
+}
+
+/// A position where the side to move can force a win within `win_in` moves.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Puzzle {
+    /// The columns played to reach the position, numbered from 0.
+    pub moves: String,
+    pub win_in: usize,
+}
+
+impl Puzzle {
+    pub fn new(moves: &str, win_in: usize) -> Self {
+        Puzzle {
+            moves: moves.to_string(),
+            win_in,
+        }
+    }
+
+    pub fn state(&self) -> Result<GameState> {
+        GameState::from_moves(&self.moves)
+    }
+
+    /// The first moves that keep a forced win within `win_in`.
+    pub fn solutions(&self) -> Result<Vec<usize>> {
+        let state = self.state()?;
+        Ok(winning_moves_within(
+            state.board(),
+            state.to_move(),
+            self.win_in,
+        ))
+    }
+
+    /// Checks the position is reachable and actually has a solution.
+    pub fn validate(&self) -> Result<()> {
+        ensure!(self.win_in > 0, "win_in must be at least 1");
+        let state = self.state().context("Invalid puzzle position")?;
+        ensure!(!state.is_over(), "The puzzle's game is already over");
+        ensure!(
+            !self.solutions()?.is_empty(),
+            "No forced win in {} from {:?}",
+            self.win_in,
+            self.moves
+        );
+        Ok(())
+    }
+}
+
+/// A named collection of puzzles.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PuzzlePack {
+    pub name: String,
+    pub puzzles: Vec<Puzzle>,
+}
+
+impl PuzzlePack {
+    /// The puzzles that ship with the game.
+    pub fn bundled() -> Self {
+        PuzzlePack {
+            name: "Bundled".to_string(),
+            puzzles: BUNDLED
+                .iter()
+                .map(|&(moves, win_in)| Puzzle::new(moves, win_in))
+                .collect(),
+        }
+    }
+
+    /// Reads a pack from JSON like
+    /// `{"name": "...", "puzzles": [{"moves": "3324", "win_in": 2}]}`,
+    /// rejecting it if any puzzle has no solution.
+    #[cfg(feature = "serde")]
+    pub fn from_json(json: &str) -> Result<Self> {
+        let pack: PuzzlePack = serde_json::from_str(json)?;
+        for (i, puzzle) in pack.puzzles.iter().enumerate() {
+            puzzle
+                .validate()
+                .with_context(|| format!("Puzzle {} in {:?}", i + 1, pack.name))?;
+        }
+        Ok(pack)
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+/// Mines played games for puzzles: positions where the side to move has exactly one
+/// move that forces a win within `win_in`, and no faster win. Each position is
+/// reported once, however many games pass through it.
+pub fn mine_puzzles<'a>(
+    games: impl IntoIterator<Item = &'a GameRecord>,
+    win_in: usize,
+) -> Vec<Puzzle> {
+    if win_in == 0 {
+        return vec![];
+    }
+    let mut seen = HashSet::new();
+    let mut puzzles = vec![];
+
+    for game in games {
+        let mut state = GameState::new();
+        for (i, &column) in game.history.iter().enumerate() {
+            let board = *state.board();
+            let player = state.to_move();
+            if seen.insert(board)
+                && winning_moves_within(&board, player, win_in).len() == 1
+                && !wins_within(&board, player, win_in - 1)
+            {
+                let moves: String = game.history[..i].iter().map(|c| c.to_string()).collect();
+                puzzles.push(Puzzle::new(&moves, win_in));
+            }
+            if state.apply_move(column).is_err() {
+                break;
+            }
+        }
+    }
+    puzzles
+}
+
+/// (moves, win_in) for the bundled pack, easiest first.
+const BUNDLED: &[(&str, usize)] = &[
+    ("0402200", 2),
+    ("044253125060550", 2),
+    ("040220020132464465631", 2),
+    ("03020015431120621", 3),
+    ("0302001543112062115", 3),
+    ("30612001143640221124066263", 3),
+    ("44055406515522003614426023363", 3),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundled_puzzles_are_solvable() {
+        let pack = PuzzlePack::bundled();
+        assert!(!pack.puzzles.is_empty());
+        for puzzle in &pack.puzzles {
+            puzzle.validate().unwrap();
+            // And not solvable any faster than advertised
+            let shorter = Puzzle::new(&puzzle.moves, puzzle.win_in - 1);
+            assert!(shorter.solutions().unwrap().is_empty(), "{puzzle:?}");
+        }
+    }
+
+    #[test]
+    fn win_in_one_is_the_open_column() {
+        let puzzle = Puzzle::new("010101", 1);
+        assert_eq!(puzzle.solutions().unwrap(), vec![0]);
+        assert!(Puzzle::new("010101", 0).validate().is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn loads_packs_and_rejects_unsolvable_puzzles() {
+        let pack = PuzzlePack::from_json(
+            r#"{"name": "Community", "puzzles": [{"moves": "0402200", "win_in": 2}]}"#,
+        )
+        .unwrap();
+        assert_eq!(pack.puzzles, vec![Puzzle::new("0402200", 2)]);
+
+        let unsolvable = r#"{"name": "Bad", "puzzles": [{"moves": "33", "win_in": 1}]}"#;
+        assert!(PuzzlePack::from_json(unsolvable).is_err());
+    }
+
+    #[test]
+    fn mines_the_unique_forced_win() {
+        let game = GameRecord::from(GameState::from_moves("04022003").unwrap());
+        // Yellow already had the same forced win two moves earlier, and the repeated
+        // game adds nothing new
+        let puzzles = mine_puzzles([&game, &game], 2);
+        assert_eq!(
+            puzzles,
+            vec![Puzzle::new("04022", 2), Puzzle::new("0402200", 2)]
+        );
+    }
+
+    #[test]
+    fn defense_blocks_the_immediate_threat() {
+        let state = GameState::from_moves("01010").unwrap();
+        // Yellow must block column 0, otherwise Red wins at once
+        assert_eq!(best_defense(state.board(), Piece::Yellow, 2), Some(0));
+    }
+}
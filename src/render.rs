@@ -0,0 +1,230 @@
+use std::fmt::Write;
+#[cfg(feature = "gif")]
+use std::time::Duration;
+
+use crate::board::{BitBoard, Piece};
+#[cfg(feature = "gif")]
+use crate::{board::Board, game::GameState};
+
+/// The width and height of one cell in rendered images, in pixels.
+pub const CELL: u32 = 64;
+/// The radius of a disc, leaving a rim of board between neighbours.
+const RADIUS: f32 = 26.0;
+/// How far the board's corners are rounded.
+const CORNER: f32 = 12.0;
+
+const BOARD_COLOR: Rgb = Rgb(0x1f, 0x4e, 0xb4);
+
+#[derive(Clone, Copy)]
+struct Rgb(u8, u8, u8);
+
+impl Rgb {
+    fn hex(self) -> String {
+        format!("#{:02x}{:02x}{:02x}", self.0, self.1, self.2)
+    }
+}
+
+fn color(piece: Piece) -> Rgb {
+    match piece {
+        Piece::Empty => Rgb(0xf2, 0xf2, 0xf2),
+        _ => {
+            let (r, g, b) = piece.color().rgb;
+            Rgb(r, g, b)
+        }
+    }
+}
+
+impl<const R: usize, const C: usize> BitBoard<R, C> {
+    /// The width and height of this board's images, in pixels.
+    pub fn image_size() -> (u32, u32) {
+        (C as u32 * CELL, R as u32 * CELL)
+    }
+
+    /// The centre of every cell, in pixels from the top left, and what is in it.
+    fn discs(&self) -> impl Iterator<Item = (f32, f32, Piece)> + '_ {
+        let cell = CELL as f32;
+        (0..C).flat_map(move |column| {
+            (0..R).map(move |row| {
+                let x = (column as f32 + 0.5) * cell;
+                let y = ((R - row) as f32 - 0.5) * cell;
+                (x, y, self.get_checked(column, row))
+            })
+        })
+    }
+
+    /// The board drawn as a standalone SVG image, `image_size()` pixels across.
+    pub fn to_svg(&self) -> String {
+        let (width, height) = Self::image_size();
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" viewBox=\"0 0 {width} {height}\">\n"
+        );
+        let _ = writeln!(svg, "<title>{}</title>", self.short_string());
+        let _ = writeln!(
+            svg,
+            "<rect width=\"{width}\" height=\"{height}\" rx=\"{CORNER}\" fill=\"{}\"/>",
+            BOARD_COLOR.hex()
+        );
+        for (x, y, piece) in self.discs() {
+            let _ = writeln!(
+                svg,
+                "<circle cx=\"{x}\" cy=\"{y}\" r=\"{RADIUS}\" fill=\"{}\"/>",
+                color(piece).hex()
+            );
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    /// The board drawn as `to_svg` draws it, in pixels.
+    #[cfg(feature = "png")]
+    pub fn to_pixmap(&self) -> tiny_skia::Pixmap {
+        use tiny_skia::{FillRule, Paint, PathBuilder, Pixmap, Rect, Transform};
+
+        let paint = |rgb: Rgb| {
+            let mut paint = Paint::default();
+            paint.set_color_rgba8(rgb.0, rgb.1, rgb.2, 255);
+            paint.anti_alias = true;
+            paint
+        };
+        let (width, height) = Self::image_size();
+        let mut pixmap = Pixmap::new(width, height).expect("boards have a size");
+        let (width, height) = (width as f32, height as f32);
+
+        // The rounded board: two overlapping rectangles and a circle in each corner
+        let mut board = PathBuilder::new();
+        let inner = [
+            Rect::from_xywh(CORNER, 0.0, width - 2.0 * CORNER, height),
+            Rect::from_xywh(0.0, CORNER, width, height - 2.0 * CORNER),
+        ];
+        for rect in inner.into_iter().flatten() {
+            board.push_rect(rect);
+        }
+        for x in [CORNER, width - CORNER] {
+            for y in [CORNER, height - CORNER] {
+                board.push_circle(x, y, CORNER);
+            }
+        }
+        let board = board.finish().expect("the board has a shape");
+        pixmap.fill_path(
+            &board,
+            &paint(BOARD_COLOR),
+            FillRule::Winding,
+            Transform::identity(),
+            None,
+        );
+
+        for (x, y, piece) in self.discs() {
+            let disc = PathBuilder::from_circle(x, y, RADIUS).expect("discs have a radius");
+            pixmap.fill_path(
+                &disc,
+                &paint(color(piece)),
+                FillRule::Winding,
+                Transform::identity(),
+                None,
+            );
+        }
+        pixmap
+    }
+
+    /// The board as a PNG file.
+    #[cfg(feature = "png")]
+    pub fn to_png(&self) -> anyhow::Result<Vec<u8>> {
+        Ok(self.to_pixmap().encode_png()?)
+    }
+}
+
+/// An animated GIF replaying `game` from the empty board, one frame per entry of its
+/// history, `delay` apart. The final position is held three times as long.
+#[cfg(feature = "gif")]
+pub fn replay_gif(game: &GameState, delay: Duration) -> anyhow::Result<Vec<u8>> {
+    use gif::{Encoder, Frame, Repeat};
+    use tiny_skia::{Color, Pixmap, PixmapPaint, Transform};
+
+    let (width, height) = Board::image_size();
+    let mut boards = vec![Board::new()];
+    let mut replay = GameState::new();
+    for &entry in game.history() {
+        replay.apply_entry(entry)?;
+        boards.push(*replay.board());
+    }
+
+    let delay = (delay.as_millis() / 10).clamp(1, u16::MAX.into()) as u16;
+    let mut bytes = vec![];
+    {
+        let mut encoder = Encoder::new(&mut bytes, width as u16, height as u16, &[])?;
+        encoder.set_repeat(Repeat::Infinite)?;
+        for (i, board) in boards.iter().enumerate() {
+            // GIFs have no partial transparency, so flatten the rounded corners onto white
+            let mut frame = Pixmap::new(width, height).expect("boards have a size");
+            frame.fill(Color::WHITE);
+            frame.draw_pixmap(
+                0,
+                0,
+                board.to_pixmap().as_ref(),
+                &PixmapPaint::default(),
+                Transform::identity(),
+                None,
+            );
+            let mut frame =
+                Frame::from_rgba_speed(width as u16, height as u16, frame.data_mut(), 10);
+            frame.delay = if i + 1 == boards.len() {
+                delay.saturating_mul(3)
+            } else {
+                delay
+            };
+            encoder.write_frame(&frame)?;
+        }
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::{Board, Piece};
+
+    #[test]
+    fn svg_has_a_disc_per_cell() {
+        let board = Board::new().place(3, Piece::Red).place(3, Piece::Yellow);
+        let svg = board.to_svg();
+        assert!(
+            svg.starts_with(
+                "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"448\" height=\"384\""
+            )
+        );
+        assert_eq!(svg.matches("<circle").count(), 42);
+        // Red sits in the bottom cell of the middle column, Yellow on top of it
+        assert!(svg.contains("<circle cx=\"224\" cy=\"352\" r=\"26\" fill=\"#d62828\"/>"));
+        assert!(svg.contains("<circle cx=\"224\" cy=\"288\" r=\"26\" fill=\"#f4c430\"/>"));
+        assert!(svg.trim_end().ends_with("</svg>"));
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn png_matches_the_svg_colors() {
+        let board = Board::new().place(0, Piece::Red);
+        let pixmap = board.to_pixmap();
+        let pixel = |x, y| {
+            let p = pixmap.pixel(x, y).unwrap();
+            (p.red(), p.green(), p.blue())
+        };
+        assert_eq!(pixel(32, 352), (0xd6, 0x28, 0x28));
+        assert_eq!(pixel(32, 288), (0xf2, 0xf2, 0xf2));
+        assert_eq!(pixel(64, 320), (0x1f, 0x4e, 0xb4));
+        assert!(board.to_png().unwrap().starts_with(b"\x89PNG"));
+    }
+
+    #[cfg(feature = "gif")]
+    #[test]
+    fn gif_has_a_frame_per_move() {
+        use crate::game::{GameState, SWAP};
+
+        let game = GameState::from_history(&[3, SWAP, 3]).unwrap();
+        let bytes = super::replay_gif(&game, std::time::Duration::from_millis(500)).unwrap();
+        let mut decoder = gif::DecodeOptions::new().read_info(&bytes[..]).unwrap();
+        let mut delays = vec![];
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            delays.push(frame.delay);
+        }
+        assert_eq!(delays, [50, 50, 50, 150]);
+    }
+}
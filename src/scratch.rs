@@ -0,0 +1,54 @@
+use crate::board::Board;
+
+/// Column lists reused across the nodes of a search, so a deep search doesn't allocate
+/// a fresh `Vec` at every node: move lists, and lines of play that may be thrown away.
+/// Each level takes a list and hands it back when done, so the pool only grows as deep
+/// as the search goes. Searches on several threads need one each.
+#[derive(Debug, Default)]
+pub struct MoveBuffers {
+    free: Vec<Vec<usize>>,
+}
+
+impl MoveBuffers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A list holding `board`'s valid moves. Hand it back with `give_back`.
+    pub fn valid_moves(&mut self, board: &Board) -> Vec<usize> {
+        let mut moves = self.free.pop().unwrap_or_default();
+        board.valid_moves_into(&mut moves);
+        moves
+    }
+
+    /// An empty list, e.g. to start a line in.
+    pub fn empty(&mut self) -> Vec<usize> {
+        let mut list = self.free.pop().unwrap_or_default();
+        list.clear();
+        list
+    }
+
+    pub fn give_back(&mut self, moves: Vec<usize>) {
+        self.free.push(moves);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Piece;
+
+    #[test]
+    fn reuses_returned_lists() {
+        let mut buffers = MoveBuffers::new();
+        let moves = buffers.valid_moves(&Board::new());
+        assert_eq!(moves, Board::new().valid_moves());
+        let ptr = moves.as_ptr();
+        buffers.give_back(moves);
+
+        let board = (0..5).fold(Board::new(), |board, _| board.place(3, Piece::Red));
+        let moves = buffers.valid_moves(&board);
+        assert_eq!(moves.as_ptr(), ptr);
+        assert_eq!(moves, vec![0, 1, 2, 4, 5, 6]);
+    }
+}
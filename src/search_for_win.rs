@@ -1,65 +1,291 @@
-use std::{cell::RefCell, collections::HashMap};
+use std::{
+    collections::HashMap,
+    sync::{
+        Mutex, RwLock,
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+    },
+    thread,
+};
+
+#[cfg(feature = "serde")]
+use anyhow::{Context, Result};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 use crate::{
     board::{Board, Piece},
+    scratch::MoveBuffers,
+    search_trace::SearchTrace,
     strategy::StrategyDecider,
-    strategy_cache::StrategyCacheStats,
+    strategy_cache::{StrategyCacheStats, entry_bytes, mirror_columns},
 };
 
+#[derive(Clone)]
 struct SearchForWinCacheEntry {
     /// Store the depth we used when we calculated. If we arrive at this entry and don't know the result,
     /// but are willing to search deeper, we should do so.
     depth_searched_at: usize,
     /// Is this a good board for us?? None if we do not know because we bottomed out.
     forced_win: Option<bool>,
+    /// How we win, when we do. Empty otherwise.
+    line: Vec<usize>,
+}
+
+/// What a search proved about a board.
+enum Outcome {
+    /// We win by force: the opponent's most stubborn replies and our answers, in columns.
+    Win(Vec<usize>),
+    /// The opponent has a way out.
+    NoWin,
+    /// We bottomed out before finding out.
+    Unknown,
+}
+
+/// Positions are cached per piece searching for the win, so one cache serves both
+/// sides.
+type CacheShard = HashMap<(Board, Piece), SearchForWinCacheEntry>;
+
+/// How many locks the cache is split over, so searching threads rarely wait on each
+/// other.
+const SHARDS: usize = 16;
+
+/// One cached position as `SearchForWinCache::save` writes it, a JSON object per line.
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+struct SavedEntry {
+    board: Board,
+    piece: Piece,
+    depth_searched_at: usize,
+    forced_win: Option<bool>,
+    line: Vec<usize>,
+}
+
+/// Positions every new `SearchForWinCache` starts with, set by `warm_start`.
+static WARM_START: RwLock<Vec<(Board, Piece, SearchForWinCacheEntry)>> = RwLock::new(Vec::new());
+
+/// Makes every `SearchForWinCache` created from now on start with the positions saved
+/// in `path`, e.g. by the `precompute` command, instead of searching them again.
+/// Returns how many there were.
+#[cfg(feature = "serde")]
+pub fn warm_start(path: &str) -> Result<usize> {
+    let file = std::fs::File::open(path).with_context(|| format!("Failed to read {path}"))?;
+    let cache = SearchForWinCache::new(0);
+    cache
+        .load(std::io::BufReader::new(file))
+        .with_context(|| format!("Invalid cache file {path}"))?;
+    let mut warm = WARM_START.write().unwrap();
+    *warm = (cache.cache.into_iter())
+        .flat_map(|shard| shard.into_inner().unwrap())
+        .map(|((board, piece), entry)| (board, piece, entry))
+        .collect();
+    Ok(warm.len())
 }
 
 /// Strategy that searches for an unstoppable move with a given depth, but also
 /// uses a cache so it runs in a reasonable time.
 pub struct SearchForWinCache {
-    piece: Piece,
     depth: usize,
-    cache: RefCell<HashMap<Board, SearchForWinCacheEntry>>,
-    stats: RefCell<StrategyCacheStats>,
+    threads: usize,
+    /// Whether mirror images share an entry.
+    symmetry: bool,
+    cache: Vec<RwLock<CacheShard>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    trace: Option<Mutex<SearchTrace>>,
 }
 
 impl SearchForWinCache {
-    pub fn new(piece: Piece, depth: usize) -> Self {
+    pub fn new(depth: usize) -> Self {
         Self {
-            piece,
             depth,
-            cache: RefCell::new(HashMap::new()),
-            stats: RefCell::new(StrategyCacheStats::default()),
+            threads: 1,
+            symmetry: false,
+            cache: (0..SHARDS).map(|_| RwLock::new(HashMap::new())).collect(),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            trace: None,
         }
+        .warmed()
+    }
+
+    fn warmed(self) -> Self {
+        for (board, piece, entry) in WARM_START.read().unwrap().iter() {
+            self.store(board, *piece, entry.clone());
+        }
+        self
+    }
+
+    /// Searches our moves on `threads` threads at once, sharing the cache. Each thread
+    /// takes the next unsearched move, and they stop once an earlier move is known to
+    /// win, so the first winning move is still the one played.
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Caches a board and its mirror image under one entry, with the winning line
+    /// reflected to match, for the cost of a reflection per lookup.
+    pub fn with_symmetry(mut self) -> Self {
+        self.symmetry = true;
+        self
+    }
+
+    /// Records the tree searched for each decision, for `take_trace`. Slow; for
+    /// debugging only. Traced searches run on one thread.
+    pub fn with_trace(mut self) -> Self {
+        self.trace = Some(Mutex::new(SearchTrace::new()));
+        self
+    }
+
+    /// The tree searched for the last decision, if tracing is on.
+    pub fn take_trace(&self) -> Option<SearchTrace> {
+        (self.trace.as_ref()).map(|trace| std::mem::take(&mut *trace.lock().unwrap()))
     }
 
     #[allow(unused)]
     pub fn get_stats(&self) -> StrategyCacheStats {
-        let mut partial = *self.stats.borrow();
-        partial.entries = self.cache.borrow().len();
-        partial
+        StrategyCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: (self.cache.iter())
+                .map(|shard| shard.read().unwrap().len())
+                .sum(),
+            approx_bytes: (self.cache.iter())
+                .map(|shard| {
+                    (shard.read().unwrap().values())
+                        .map(|entry| {
+                            entry_bytes::<(Board, Piece), SearchForWinCacheEntry>(
+                                entry.line.capacity(),
+                            )
+                        })
+                        .sum::<usize>()
+                })
+                .sum(),
+        }
+    }
+
+    /// Writes every cached position to `out`, one JSON object per line, for `load` or
+    /// `warm_start` to read back. Returns how many were written.
+    #[cfg(feature = "serde")]
+    pub fn save(&self, out: &mut impl std::io::Write) -> Result<usize> {
+        let mut written = 0;
+        for shard in &self.cache {
+            for ((board, piece), entry) in shard.read().unwrap().iter() {
+                let saved = SavedEntry {
+                    board: *board,
+                    piece: *piece,
+                    depth_searched_at: entry.depth_searched_at,
+                    forced_win: entry.forced_win,
+                    line: entry.line.clone(),
+                };
+                serde_json::to_writer(&mut *out, &saved)?;
+                writeln!(out)?;
+                written += 1;
+            }
+        }
+        out.flush()?;
+        Ok(written)
+    }
+
+    /// Adds the positions `save` wrote to the cache. Where both know a position, the
+    /// same rules as during a search decide which entry stays. Returns how many were read.
+    #[cfg(feature = "serde")]
+    pub fn load(&self, input: impl std::io::BufRead) -> Result<usize> {
+        let mut read = 0;
+        for (number, line) in input.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let saved: SavedEntry = serde_json::from_str(&line)
+                .with_context(|| format!("Invalid entry on line {}", number + 1))?;
+            let entry = SearchForWinCacheEntry {
+                depth_searched_at: saved.depth_searched_at,
+                forced_win: saved.forced_win,
+                line: saved.line,
+            };
+            self.store(&saved.board, saved.piece, entry);
+            read += 1;
+        }
+        Ok(read)
+    }
+
+    /// The board `board` is cached under, and whether that is its mirror image.
+    fn key(&self, board: &Board) -> (Board, bool) {
+        if self.symmetry {
+            board.canonical()
+        } else {
+            (*board, false)
+        }
+    }
+
+    /// The part of the cache `board` lives in.
+    fn shard(&self, board: &Board) -> &RwLock<CacheShard> {
+        // Mix the bits first, since boards that differ only high up share their low bits
+        let mixed = board.as_u64().wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        &self.cache[(mixed >> 32) as usize % SHARDS]
+    }
+
+    /// Caches what we learned about `board`. Another thread may have learned more in
+    /// the meantime, so not knowing never replaces a result or a deeper search.
+    fn store(&self, board: &Board, piece: Piece, mut entry: SearchForWinCacheEntry) {
+        let (board, mirrored) = self.key(board);
+        mirror_columns(&mut entry.line, mirrored);
+        let mut shard = self.shard(&board).write().unwrap();
+        if let Some(old) = shard.get(&(board, piece))
+            && entry.forced_win.is_none()
+            && (old.forced_win.is_some() || old.depth_searched_at >= entry.depth_searched_at)
+        {
+            return;
+        }
+        shard.insert((board, piece), entry);
     }
 
     /// Same scemantics as the other SearchForWin
-    fn has_guaranteed_win(&self, board: &Board, depth: usize) -> Option<bool> {
+    fn has_guaranteed_win(
+        &self,
+        board: &Board,
+        piece: Piece,
+        depth: usize,
+        buffers: &mut MoveBuffers,
+    ) -> Outcome {
+        if let Some(trace) = &self.trace {
+            trace.lock().unwrap().enter(board, depth);
+        }
+        let outcome = self.search(board, piece, depth, buffers);
+        if let Some(trace) = &self.trace {
+            trace.lock().unwrap().exit(match outcome {
+                Outcome::Win(_) => Some(true),
+                Outcome::NoWin => Some(false),
+                Outcome::Unknown => None,
+            });
+        }
+        outcome
+    }
+
+    fn search(
+        &self,
+        board: &Board,
+        piece: Piece,
+        depth: usize,
+        buffers: &mut MoveBuffers,
+    ) -> Outcome {
         // This searches vertically... it might be faster to search horizontally
         // todo:: consider using a stack here instead and get rid of recursion
 
-        assert!(board.next_player() == self.piece.opponent()); // make sure I don't fuck it up
-
         // ------------------------------------------------------------
         // For these two, I'm guessing that they're
         // faster than a hashmap lookup.
 
         // If we've won, we've won.
-        if board.has_winner() == Some(self.piece) {
-            return Some(true);
+        if board.has_winner() == Some(piece) {
+            return Outcome::Win(buffers.empty());
         }
 
         // Otherwise, if this is our search depth, we can't guarantee a win
         if depth == 0 {
             // We don't know the result.
-            return None;
+            return Outcome::Unknown;
         }
 
         // ------------------------------------------------------------
@@ -67,76 +293,122 @@ impl SearchForWinCache {
         // Here's where the magic is:
 
         // First, the cache lookup
-        if let Some(entry) = self.cache.borrow().get(board) {
-            self.stats.borrow_mut().hits += 1;
-            // Ok, first let's check if we found a solution:
+        let (key, mirrored) = self.key(board);
+        if let Some(entry) = self.shard(&key).read().unwrap().get(&(key, piece)) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            // Ok, first let's check if we found a solution. Either way, or if we can't
+            // search any deeper than last time, the cache has the answer.
+            if (entry.forced_win.is_some() || entry.depth_searched_at >= depth)
+                && let Some(trace) = &self.trace
+            {
+                trace.lock().unwrap().mark_cached();
+            }
             if entry.forced_win == Some(true) {
                 // Yay! we would win!
-                return Some(true);
+                let mut line = entry.line.clone();
+                mirror_columns(&mut line, mirrored);
+                return Outcome::Win(line);
             }
             if entry.forced_win == Some(false) {
                 // Oh no... we would not win.
-                return Some(false);
+                return Outcome::NoWin;
             }
             debug_assert!(entry.forced_win.is_none());
             // Ok, so we don't know the result yet.
             // We'll need to search deeper. Are we allowed to?
             if entry.depth_searched_at >= depth {
                 // Ok... we know we won't make any progress here. Short circuit!
-                return None;
+                return Outcome::Unknown;
             }
             // Otherwise, allow us to fall through!
         } else {
-            self.stats.borrow_mut().misses += 1;
+            self.misses.fetch_add(1, Ordering::Relaxed);
         }
 
-        // Look at all of the possible ways the enemy could respond
-        let enemy_moves = board.all_future_boards(self.piece.opponent());
+        let enemy_moves = buffers.valid_moves(board);
+        let outcome = self.search_replies(board, piece, &enemy_moves, depth, buffers);
+        buffers.give_back(enemy_moves);
+        outcome
+    }
 
-        for enemy_board in enemy_moves {
+    /// The rest of `search`, once we know we have to look at `enemy_moves`.
+    fn search_replies(
+        &self,
+        board: &Board,
+        piece: Piece,
+        enemy_moves: &[usize],
+        depth: usize,
+        buffers: &mut MoveBuffers,
+    ) -> Outcome {
+        // Look at all of the possible ways the enemy could respond. The line we keep
+        // follows the one that holds out longest.
+        let mut longest = buffers.empty();
+        for &enemy_col in enemy_moves {
+            let enemy_board = board.place(enemy_col, piece.opponent());
             // If the enemy has won, we've obviously lost!
-            if enemy_board.has_winner() == Some(self.piece.opponent()) {
-                return Some(false);
+            if enemy_board.has_winner() == Some(piece.opponent()) {
+                buffers.give_back(longest);
+                return Outcome::NoWin;
+            }
+            let our_moves = buffers.valid_moves(&enemy_board);
+            let mut response = Outcome::NoWin;
+            for &col in &our_moves {
+                match self.has_guaranteed_win(
+                    &enemy_board.place(col, piece),
+                    piece,
+                    depth - 1,
+                    buffers,
+                ) {
+                    Outcome::NoWin => {}
+                    Outcome::Win(mut line) => {
+                        line.splice(0..0, [enemy_col, col]);
+                        response = Outcome::Win(line);
+                        break;
+                    }
+                    // Bottoming out ends the search too, see below
+                    Outcome::Unknown => {
+                        response = Outcome::Unknown;
+                        break;
+                    }
+                }
             }
-            let responses = enemy_board.all_future_boards(self.piece);
-            let mut found_winning_response = false;
-            for response_board in responses {
-                let res = self.has_guaranteed_win(&response_board, depth - 1);
+            buffers.give_back(our_moves);
+
+            match response {
                 // If we hit the search depth at any point, we need to abort.
-                if res.is_none() {
+                Outcome::Unknown => {
                     // Let's cache that we couldn't quite find it.
-                    let old = self.cache.borrow_mut().insert(
-                        *board,
+                    self.store(
+                        board,
+                        piece,
                         SearchForWinCacheEntry {
                             depth_searched_at: depth,
                             forced_win: None,
+                            line: vec![],
                         },
                     );
-                    if let Some(old) = old {
-                        // Let's double check that we didn't already know the answer and that the depth was lower.
-                        assert!(old.depth_searched_at < depth);
-                        assert!(old.forced_win.is_none());
-                    }
-                    return None;
+                    buffers.give_back(longest);
+                    return Outcome::Unknown;
                 }
-
-                if res == Some(true) {
-                    found_winning_response = true;
-                    break;
+                // So if we did not find a winning response, the enemy has a way out.
+                Outcome::NoWin => {
+                    // Cache this value as well.
+                    self.store(
+                        board,
+                        piece,
+                        SearchForWinCacheEntry {
+                            depth_searched_at: 0, // The depth doesn't matter here, we know the opponent has a way out.
+                            forced_win: Some(false),
+                            line: vec![],
+                        },
+                    );
+                    buffers.give_back(longest);
+                    return Outcome::NoWin;
                 }
-            }
-
-            // So if we did not find a winning response, the enemy has a way out.
-            if !found_winning_response {
-                // Cache this value as well.
-                self.cache.borrow_mut().insert(
-                    *board,
-                    SearchForWinCacheEntry {
-                        depth_searched_at: 0, // The depth doesn't matter here, we know the opponent has a way out.
-                        forced_win: Some(false),
-                    },
-                );
-                return Some(false);
+                Outcome::Win(line) if line.len() > longest.len() => {
+                    buffers.give_back(std::mem::replace(&mut longest, line))
+                }
+                Outcome::Win(line) => buffers.give_back(line),
             }
         }
 
@@ -145,30 +417,170 @@ impl SearchForWinCache {
         // 2. The opponent cannot win if we play perfectly for the next depth moves.
         // This means we 100% win in the next `depth` moves if we play `move_to_test`.
         // Cache that and return.
-        self.cache.borrow_mut().insert(
-            *board,
+        self.store(
+            board,
+            piece,
             SearchForWinCacheEntry {
                 depth_searched_at: 0, // The depth doesn't matter here, we know we're winning and don't care how long it takes.
                 forced_win: Some(true),
+                line: longest.clone(),
             },
         );
 
-        Some(true)
+        Outcome::Win(longest)
     }
 }
 
 impl StrategyDecider for SearchForWinCache {
-    fn choose(&self, board: &Board, options: &[usize]) -> Option<usize> {
-        for col in options {
-            let board = &board.place(*col, self.piece);
-            if self.has_guaranteed_win(board, self.depth) == Some(true) {
-                return Some(*col);
+    fn choose(&self, board: &Board, piece: Piece, options: &[usize]) -> Option<usize> {
+        self.principal_variation(board, piece, options)
+            .map(|line| line[0])
+    }
+
+    /// The forced win behind the choice, from our move to four in a row.
+    fn principal_variation(
+        &self,
+        board: &Board,
+        piece: Piece,
+        options: &[usize],
+    ) -> Option<Vec<usize>> {
+        let threads = match &self.trace {
+            Some(trace) => {
+                *trace.lock().unwrap() = SearchTrace::new();
+                1
             }
+            None => self.threads.min(options.len()),
+        };
+        let win_with = |col: usize, buffers: &mut MoveBuffers| match self.has_guaranteed_win(
+            &board.place(col, piece),
+            piece,
+            self.depth,
+            buffers,
+        ) {
+            Outcome::Win(rest) => Some(std::iter::once(col).chain(rest).collect::<Vec<_>>()),
+            _ => None,
+        };
+
+        let line = if threads <= 1 {
+            let mut buffers = MoveBuffers::new();
+            options.iter().find_map(|&col| win_with(col, &mut buffers))
+        } else {
+            let next = AtomicUsize::new(0);
+            let first_win = AtomicUsize::new(usize::MAX);
+            let lines = Mutex::new(vec![None; options.len()]);
+            thread::scope(|scope| {
+                for _ in 0..threads {
+                    scope.spawn(|| {
+                        let mut buffers = MoveBuffers::new();
+                        loop {
+                            let i = next.fetch_add(1, Ordering::Relaxed);
+                            if i >= options.len() || i > first_win.load(Ordering::Relaxed) {
+                                break;
+                            }
+                            if let Some(line) = win_with(options[i], &mut buffers) {
+                                first_win.fetch_min(i, Ordering::Relaxed);
+                                lines.lock().unwrap()[i] = Some(line);
+                            }
+                        }
+                    });
+                }
+            });
+            let first_win = first_win.into_inner();
+            lines
+                .into_inner()
+                .unwrap()
+                .get_mut(first_win)
+                .and_then(Option::take)
+        };
+        if let Some(line) = &line {
+            tracing::debug!(
+                column = line[0],
+                depth = self.depth,
+                ?line,
+                "forced win found"
+            );
         }
-        None
+        line
     }
 
     fn name(&self) -> &'static str {
         "SearchForWinCache"
     }
+
+    fn is_expensive(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::GameState;
+
+    #[test]
+    fn symmetry_shares_entries_between_mirror_images() {
+        let board = *GameState::from_moves("3241").unwrap().board();
+        let mirrored = board.mirrored();
+        let options = board.valid_moves();
+
+        let cache = SearchForWinCache::new(3).with_symmetry();
+        cache.choose(&board, Piece::Red, &options);
+        let entries = cache.get_stats().entries;
+        let misses = cache.get_stats().misses;
+        cache.choose(&mirrored, Piece::Red, &options);
+        assert_eq!(cache.get_stats().entries, entries);
+        assert_eq!(cache.get_stats().misses, misses);
+
+        // Lines are stored for the canonical board and come back reflected
+        let entry = SearchForWinCacheEntry {
+            depth_searched_at: 0,
+            forced_win: Some(true),
+            line: vec![0, 1],
+        };
+        cache.store(&board, Piece::Red, entry);
+        let Outcome::Win(line) = cache.search(&mirrored, Piece::Red, 3, &mut MoveBuffers::new())
+        else {
+            panic!("the stored win should be found");
+        };
+        assert_eq!(line, [6, 5]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn saved_positions_load_into_a_fresh_cache() {
+        let board = *GameState::from_moves("3241").unwrap().board();
+        let options = board.valid_moves();
+        let searched = SearchForWinCache::new(3);
+        let expected = searched.choose(&board, Piece::Red, &options);
+
+        let mut saved = vec![];
+        let written = searched.save(&mut saved).unwrap();
+        assert_eq!(written, searched.get_stats().entries);
+
+        let loaded = SearchForWinCache::new(3);
+        assert_eq!(loaded.load(&saved[..]).unwrap(), written);
+        assert_eq!(loaded.choose(&board, Piece::Red, &options), expected);
+        assert_eq!(loaded.get_stats().entries, written);
+        assert!(loaded.load(&b"{\"board\": 1}\n"[..]).is_err());
+    }
+
+    #[test]
+    fn threads_find_the_same_first_win() {
+        // Red wins on either end of three in a row, and 2 comes first
+        let state = GameState::from_moves("443355").unwrap();
+        let options = state.board().valid_moves();
+        let single = SearchForWinCache::new(3);
+        let threaded = SearchForWinCache::new(3).with_threads(4);
+        let expected = single.choose(state.board(), Piece::Red, &options);
+        assert_eq!(expected, Some(2));
+        assert_eq!(
+            threaded.choose(state.board(), Piece::Red, &options),
+            expected
+        );
+        let line = threaded
+            .principal_variation(state.board(), Piece::Red, &options)
+            .unwrap();
+        assert_eq!(line[0], expected.unwrap());
+        assert!(threaded.get_stats().entries > 0);
+    }
 }
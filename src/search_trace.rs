@@ -0,0 +1,116 @@
+use std::fmt::Write;
+
+use crate::board::Board;
+
+/// One position a forced-win search visited.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceNode {
+    /// The position after one of our moves, with the opponent to reply.
+    pub board: Board,
+    /// How many more of our moves the search could still make from here.
+    pub depth: usize,
+    pub parent: Option<usize>,
+    /// `Some(true)` for a forced win, `Some(false)` when the opponent escapes, and
+    /// `None` when the search ran out of depth first.
+    pub result: Option<bool>,
+    /// Whether the result came from a cache instead of searching.
+    pub cached: bool,
+}
+
+/// The tree a forced-win search explored for one move decision, for working out why it
+/// did or didn't find a win. Each edge spans an opponent reply and our answer to it.
+#[derive(Debug, Clone, Default)]
+pub struct SearchTrace {
+    nodes: Vec<TraceNode>,
+    /// The nodes being searched, innermost last.
+    path: Vec<usize>,
+}
+
+impl SearchTrace {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts searching `board` below the current node.
+    pub fn enter(&mut self, board: &Board, depth: usize) {
+        self.nodes.push(TraceNode {
+            board: *board,
+            depth,
+            parent: self.path.last().copied(),
+            result: None,
+            cached: false,
+        });
+        self.path.push(self.nodes.len() - 1);
+    }
+
+    /// Marks the current node as answered by a cache.
+    pub fn mark_cached(&mut self) {
+        if let Some(&current) = self.path.last() {
+            self.nodes[current].cached = true;
+        }
+    }
+
+    /// Finishes the current node with `result`.
+    pub fn exit(&mut self, result: Option<bool>) {
+        let current = self.path.pop().expect("exit matches an enter");
+        self.nodes[current].result = result;
+    }
+
+    pub fn nodes(&self) -> &[TraceNode] {
+        &self.nodes
+    }
+
+    /// The tree as a Graphviz digraph. Each node shows its board's short string, the
+    /// result and the depth left; wins are green, escapes red and unknowns grey.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph search {\n    node [shape=box, fontname=monospace];\n");
+        for (i, node) in self.nodes.iter().enumerate() {
+            let (result, color) = match node.result {
+                Some(true) => ("win", "palegreen"),
+                Some(false) => ("no win", "lightpink"),
+                None => ("unknown", "lightgrey"),
+            };
+            let cached = if node.cached { " (cached)" } else { "" };
+            writeln!(
+                dot,
+                "    n{i} [label=\"{}\\n{result}{cached}  depth {}\", style=filled, fillcolor={color}];",
+                node.board.short_string(),
+                node.depth
+            )
+            .unwrap();
+            if let Some(parent) = node.parent {
+                writeln!(dot, "    n{parent} -> n{i};").unwrap();
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Piece;
+
+    #[test]
+    fn nests_entries_and_writes_dot() {
+        let board = Board::new();
+        let mut trace = SearchTrace::new();
+        trace.enter(&board, 2);
+        trace.enter(&board.place(3, Piece::Red), 1);
+        trace.mark_cached();
+        trace.exit(Some(true));
+        trace.enter(&board.place(4, Piece::Red), 1);
+        trace.exit(None);
+        trace.exit(Some(false));
+
+        let nodes = trace.nodes();
+        assert_eq!(nodes.len(), 3);
+        assert_eq!(nodes[0].parent, None);
+        assert_eq!((nodes[1].parent, nodes[1].cached), (Some(0), true));
+        assert_eq!((nodes[2].parent, nodes[2].result), (Some(0), None));
+        let dot = trace.to_dot();
+        assert_eq!(dot.matches(" -> ").count(), 2);
+        assert!(dot.contains("win (cached)  depth 1"), "{dot}");
+    }
+}
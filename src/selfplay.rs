@@ -0,0 +1,136 @@
+use std::io::Write;
+
+use anyhow::Result;
+
+use crate::board::COLUMNS;
+use crate::game::{GameResult, GameState, PlayedGame};
+use crate::neural::{ENCODED_LEN, ENCODED_SHAPE, encode};
+
+/// One training example: a position, the move played from it, and how the game ended
+/// for the player who moved.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    /// The position before the move, as `neural::encode` writes it for the mover.
+    pub position: [f32; ENCODED_LEN],
+    pub column: u8,
+    /// 1 if the mover went on to win, -1 if they lost, 0 for a tie.
+    pub outcome: i8,
+}
+
+/// A sample for every move in `game`, opening included. Passes, swaps and rotations
+/// aren't moves from a position, so they are skipped.
+pub fn samples(game: &PlayedGame) -> Vec<Sample> {
+    let mut state = GameState::new();
+    let mut samples = vec![];
+    for &entry in game.moves() {
+        if entry < COLUMNS {
+            let mover = state.to_move();
+            samples.push(Sample {
+                position: encode(state.board(), mover),
+                column: entry as u8,
+                outcome: match game.result() {
+                    GameResult::Winner(winner) if winner == mover => 1,
+                    GameResult::Winner(_) => -1,
+                    GameResult::Tie => 0,
+                },
+            });
+        }
+        state
+            .apply_entry(entry)
+            .expect("a played game replays from its moves");
+    }
+    samples
+}
+
+/// Writes `samples` as a NumPy `.npy` file holding one structured array, which
+/// `numpy.load` reads as is. Each record has:
+///
+/// - `position`: float32 `[2, 6, 7]`, as `neural::encode` writes it
+/// - `column`: uint8, the column played
+/// - `outcome`: int8, 1, 0 or -1 for the player who moved
+///
+/// Records are packed little-endian, 338 bytes each, after the standard version 1.0
+/// header.
+pub fn write_npy(out: &mut impl Write, samples: &[Sample]) -> Result<()> {
+    let [planes, rows, columns] = ENCODED_SHAPE;
+    let descr = format!(
+        "[('position', '<f4', ({planes}, {rows}, {columns})), ('column', '|u1'), ('outcome', '|i1')]"
+    );
+    let mut header = format!(
+        "{{'descr': {descr}, 'fortran_order': False, 'shape': ({},), }}",
+        samples.len()
+    );
+    // The magic, version and length take 10 bytes, and the data starts 64-byte aligned
+    let padding = 63 - (10 + header.len()) % 64;
+    header.push_str(&" ".repeat(padding));
+    header.push('\n');
+
+    out.write_all(b"\x93NUMPY\x01\x00")?;
+    out.write_all(&(header.len() as u16).to_le_bytes())?;
+    out.write_all(header.as_bytes())?;
+    for sample in samples {
+        for value in sample.position {
+            out.write_all(&value.to_le_bytes())?;
+        }
+        out.write_all(&[sample.column, sample.outcome as u8])?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Piece;
+    use crate::game::play_game;
+    use crate::strategy::StrategyStack;
+
+    #[test]
+    fn samples_every_move_with_the_final_result() {
+        let stack = StrategyStack::new(vec![]);
+        let game = play_game(&stack, &stack, &GameState::new(), &mut ()).unwrap();
+        let samples = samples(&game);
+        assert_eq!(samples.len(), game.moves().len());
+        assert!(samples[0].position.iter().all(|&x| x == 0.0));
+        assert_eq!(samples[0].column as usize, game.moves()[0]);
+
+        let last = samples.last().unwrap();
+        let expected = match game.winner() {
+            Some(_) => 1,
+            None => 0,
+        };
+        assert_eq!(last.outcome, expected);
+        if game.winner() == Some(Piece::Red) {
+            assert_eq!(samples[0].outcome, 1);
+            assert_eq!(samples[1].outcome, -1);
+        }
+    }
+
+    #[test]
+    fn npy_header_is_aligned_and_records_are_packed() {
+        let game = GameState::from_moves("3").unwrap();
+        let sample = Sample {
+            position: encode(game.board(), Piece::Yellow),
+            column: 3,
+            outcome: -1,
+        };
+        let mut out = vec![];
+        write_npy(&mut out, &[sample.clone(), sample]).unwrap();
+
+        assert_eq!(&out[..8], b"\x93NUMPY\x01\x00");
+        let header_len = u16::from_le_bytes([out[8], out[9]]) as usize;
+        let data = 10 + header_len;
+        assert_eq!(data % 64, 0);
+        let header = std::str::from_utf8(&out[10..data]).unwrap();
+        assert!(header.contains("'shape': (2,)"), "{header}");
+        assert!(header.ends_with('\n'));
+
+        assert_eq!(out.len(), data + 2 * (ENCODED_LEN * 4 + 2));
+        // Red's piece is the opponent's, so it's in the second plane
+        let at = data + (ENCODED_LEN / 2 + 3) * 4;
+        assert_eq!(out[at..at + 4], 1.0f32.to_le_bytes());
+        assert_eq!(
+            out[data + ENCODED_LEN * 4..data + ENCODED_LEN * 4 + 2],
+            [3, 0xff]
+        );
+    }
+}
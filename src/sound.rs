@@ -0,0 +1,171 @@
+use std::io::Write;
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{Result, bail};
+
+use crate::board::Piece;
+use crate::game::{GameResult, GameState};
+use crate::observer::GameObserver;
+
+/// Something in a game worth a sound.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SoundEvent {
+    Drop,
+    Win,
+    Tie,
+    InvalidMove,
+}
+
+/// Plays sounds. `TerminalBell` is built in; anything that can play a sample, like a
+/// rodio sink, can implement this to give each event its own sound.
+pub trait SoundPlayer {
+    fn play(&mut self, event: SoundEvent);
+}
+
+/// Rings the terminal bell, twice for the end of a game so it stands out from drops.
+pub struct TerminalBell<W> {
+    out: W,
+}
+
+impl TerminalBell<std::io::Stdout> {
+    pub fn stdout() -> Self {
+        TerminalBell {
+            out: std::io::stdout(),
+        }
+    }
+}
+
+impl<W: Write> TerminalBell<W> {
+    pub fn new(out: W) -> Self {
+        TerminalBell { out }
+    }
+}
+
+impl<W: Write> SoundPlayer for TerminalBell<W> {
+    fn play(&mut self, event: SoundEvent) {
+        let bell = match event {
+            SoundEvent::Win | SoundEvent::Tie => "\x07\x07",
+            SoundEvent::Drop | SoundEvent::InvalidMove => "\x07",
+        };
+        // A missing bell isn't worth stopping the game for
+        let _ = self
+            .out
+            .write_all(bell.as_bytes())
+            .and_then(|()| self.out.flush());
+    }
+}
+
+/// Which events make a sound. Written as a comma-separated list of `drop`, `end` and
+/// `invalid`, or `all`; everything is off by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SoundConfig {
+    pub drops: bool,
+    /// Wins and ties.
+    pub game_end: bool,
+    pub invalid_moves: bool,
+}
+
+impl SoundConfig {
+    pub const ALL: SoundConfig = SoundConfig {
+        drops: true,
+        game_end: true,
+        invalid_moves: true,
+    };
+
+    fn plays(&self, event: SoundEvent) -> bool {
+        match event {
+            SoundEvent::Drop => self.drops,
+            SoundEvent::Win | SoundEvent::Tie => self.game_end,
+            SoundEvent::InvalidMove => self.invalid_moves,
+        }
+    }
+}
+
+impl FromStr for SoundConfig {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut config = SoundConfig::default();
+        for event in s.split(',').map(str::trim) {
+            match event {
+                "all" => config = SoundConfig::ALL,
+                "drop" => config.drops = true,
+                "end" => config.game_end = true,
+                "invalid" => config.invalid_moves = true,
+                _ => bail!("Unknown sound {event:?}: expected drop, end, invalid or all"),
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// An observer that plays the configured sounds as a game goes on.
+pub struct SoundEffects<P> {
+    player: P,
+    config: SoundConfig,
+}
+
+impl<P: SoundPlayer> SoundEffects<P> {
+    pub fn new(player: P, config: SoundConfig) -> Self {
+        SoundEffects { player, config }
+    }
+
+    fn play(&mut self, event: SoundEvent) {
+        if self.config.plays(event) {
+            tracing::trace!(?event, "sound");
+            self.player.play(event);
+        }
+    }
+}
+
+impl<P: SoundPlayer> GameObserver for SoundEffects<P> {
+    fn on_move(&mut self, _state: &GameState, _player: Piece, _column: usize, _elapsed: Duration) {
+        self.play(SoundEvent::Drop);
+    }
+
+    fn on_invalid_move(&mut self, _state: &GameState, _player: Piece, _column: usize) {
+        self.play(SoundEvent::InvalidMove);
+    }
+
+    fn on_game_end(&mut self, _state: &GameState, result: GameResult) {
+        match result {
+            GameResult::Winner(_) => self.play(SoundEvent::Win),
+            GameResult::Tie => self.play(SoundEvent::Tie),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    impl SoundPlayer for Vec<SoundEvent> {
+        fn play(&mut self, event: SoundEvent) {
+            self.push(event);
+        }
+    }
+
+    #[test]
+    fn plays_only_configured_events() {
+        let config: SoundConfig = "end, invalid".parse().unwrap();
+        let mut sounds = SoundEffects::new(vec![], config);
+        let state = GameState::from_moves("0101010").unwrap();
+        sounds.on_move(&state, Piece::Red, 0, Duration::ZERO);
+        sounds.on_invalid_move(&state, Piece::Yellow, 0);
+        sounds.on_game_end(&state, GameResult::Winner(Piece::Red));
+        assert_eq!(sounds.player, [SoundEvent::InvalidMove, SoundEvent::Win]);
+
+        assert_eq!("all".parse::<SoundConfig>().unwrap(), SoundConfig::ALL);
+        assert!("beep".parse::<SoundConfig>().is_err());
+    }
+
+    #[test]
+    fn the_bell_rings_twice_at_the_end() {
+        let mut out = vec![];
+        let mut bell = TerminalBell::new(&mut out);
+        bell.play(SoundEvent::Drop);
+        bell.play(SoundEvent::Tie);
+        assert_eq!(out, b"\x07\x07\x07");
+    }
+}
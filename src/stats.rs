@@ -0,0 +1,118 @@
+use std::time::Duration;
+
+/// Standard normal cumulative distribution function.
+pub fn normal_cdf(x: f64) -> f64 {
+    0.5 * (1.0 + erf(x / std::f64::consts::SQRT_2))
+}
+
+/// Error function, using the Abramowitz & Stegun 7.1.26 approximation (max error ~1.5e-7).
+fn erf(x: f64) -> f64 {
+    const A1: f64 = 0.254829592;
+    const A2: f64 = -0.284496736;
+    const A3: f64 = 1.421413741;
+    const A4: f64 = -1.453152027;
+    const A5: f64 = 1.061405429;
+    const P: f64 = 0.3275911;
+
+    let sign = if x < 0.0 { -1.0 } else { 1.0 };
+    let x = x.abs();
+    let t = 1.0 / (1.0 + P * x);
+    let y = 1.0 - (((((A5 * t + A4) * t) + A3) * t + A2) * t + A1) * t * (-x * x).exp();
+    sign * y
+}
+
+/// The two-sided z value for a confidence level, e.g. 0.95 -> ~1.96.
+pub fn z_for_confidence(confidence: f64) -> f64 {
+    assert!(
+        confidence > 0.0 && confidence < 1.0,
+        "Confidence must be between 0 and 1"
+    );
+    let target = 1.0 - (1.0 - confidence) / 2.0;
+    // The CDF is monotonic, so a bisection is plenty.
+    let (mut lo, mut hi) = (0.0, 10.0);
+    for _ in 0..100 {
+        let mid = (lo + hi) / 2.0;
+        if normal_cdf(mid) < target {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    (lo + hi) / 2.0
+}
+
+/// Wilson score interval for a binomial proportion at the given confidence level.
+pub fn wilson_interval(successes: usize, trials: usize, confidence: f64) -> (f64, f64) {
+    if trials == 0 {
+        return (0.0, 1.0);
+    }
+    let z = z_for_confidence(confidence);
+    let n = trials as f64;
+    let p = successes as f64 / n;
+    let denominator = 1.0 + z * z / n;
+    let center = (p + z * z / (2.0 * n)) / denominator;
+    let margin = z * (p * (1.0 - p) / n + z * z / (4.0 * n * n)).sqrt() / denominator;
+    ((center - margin).max(0.0), (center + margin).min(1.0))
+}
+
+/// Two-sided p-value that `a_wins` and `b_wins` come from evenly matched players.
+///
+/// Ties carry no information about which player is stronger, so only decisive games count.
+/// Uses the normal approximation to the binomial sign test.
+pub fn win_difference_p_value(a_wins: usize, b_wins: usize) -> f64 {
+    let decisive = (a_wins + b_wins) as f64;
+    if decisive == 0.0 {
+        return 1.0;
+    }
+    let z = (a_wins as f64 - decisive / 2.0) / (decisive / 4.0).sqrt();
+    (2.0 * (1.0 - normal_cdf(z.abs()))).clamp(0.0, 1.0)
+}
+
+/// The average of a set of durations, if there are any.
+pub fn mean_duration(times: &[Duration]) -> Option<Duration> {
+    if times.is_empty() {
+        return None;
+    }
+    Some(times.iter().sum::<Duration>() / times.len() as u32)
+}
+
+/// The value below which `pct` percent of `times` fall (nearest rank).
+pub fn percentile(times: &[Duration], pct: f64) -> Option<Duration> {
+    debug_assert!(
+        (0.0..=100.0).contains(&pct),
+        "Percentile must be in 0..=100"
+    );
+    if times.is_empty() {
+        return None;
+    }
+    let mut times = times.to_vec();
+    times.sort_unstable();
+    let rank = ((pct / 100.0) * times.len() as f64).ceil() as usize;
+    Some(times[rank.saturating_sub(1).min(times.len() - 1)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn z_values() {
+        assert!((z_for_confidence(0.95) - 1.96).abs() < 1e-3);
+        assert!((z_for_confidence(0.99) - 2.576).abs() < 1e-3);
+    }
+
+    #[test]
+    fn wilson_contains_proportion() {
+        let (lo, hi) = wilson_interval(60, 100, 0.95);
+        assert!(lo < 0.6 && 0.6 < hi);
+        assert!((lo - 0.502).abs() < 1e-2);
+        assert!((hi - 0.691).abs() < 1e-2);
+    }
+
+    #[test]
+    fn p_values() {
+        assert!((win_difference_p_value(50, 50) - 1.0).abs() < 1e-6);
+        assert!(win_difference_p_value(600, 400) < 0.001);
+        assert!(win_difference_p_value(52, 48) > 0.5);
+    }
+}
@@ -1,38 +1,337 @@
-use crate::board::{Board, Piece};
+use crate::board::{BitBoard, Board, COLUMNS, Piece, ROWS};
+use crate::bounded::{BoundedSearch, SearchBudget};
+use crate::explain::{DecisionTrace, TraceStep};
+use crate::scratch::MoveBuffers;
+use crate::search_trace::SearchTrace;
+use rand::Rng;
 use rand::seq::IndexedRandom;
-use std::cell::RefCell;
+use std::sync::Mutex;
+use std::time::Duration;
 
-pub trait Connect4AI: std::fmt::Display {
-    fn play(&self, board: &Board) -> Option<usize>;
+/// AIs are told which piece they play each turn. It can't be read off the board: passes,
+/// swaps and rotations all break the count of pieces played. Like the strategy traits,
+/// this is generic over the board's size and defaults to the usual 6x7 `Board`.
+pub trait Connect4AI<const R: usize = ROWS, const C: usize = COLUMNS>: std::fmt::Display {
+    fn play(&self, board: &BitBoard<R, C>, piece: Piece) -> Option<usize>;
+
+    /// Like `play`, but told how much time is left on the AI's clock so it can budget
+    /// its thinking. Ignores the clock by default.
+    fn play_timed(
+        &self,
+        board: &BitBoard<R, C>,
+        piece: Piece,
+        _remaining: Duration,
+    ) -> Option<usize> {
+        self.play(board, piece)
+    }
+
+    /// Whether to pass this turn instead of moving. Asked once per turn, before `play`.
+    fn passes(&self, _board: &BitBoard<R, C>, _piece: Piece) -> bool {
+        false
+    }
+
+    /// Under the swap rule, whether to take Red's first move instead of answering it.
+    /// Asked of Yellow once, before `passes`.
+    fn swaps(&self, _board: &BitBoard<R, C>) -> bool {
+        false
+    }
+
+    /// With rotations on, whether to spend this player's rotation now instead of
+    /// moving. Asked before `passes` while the rotation is unused.
+    fn rotates(&self, _board: &BitBoard<R, C>, _piece: Piece) -> bool {
+        false
+    }
+}
+
+/// How a stack picks among the moves its strategies leave.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Selection {
+    /// Layers prune the options in turn; a random survivor is played.
+    #[default]
+    Prune,
+    /// Layers score every legal move and the scores are summed; a random best-scored
+    /// move is played.
+    Argmax,
+    /// Like `Argmax`, but moves are sampled in proportion to `exp(score / temperature)`.
+    /// Low temperatures almost always play the best-scored move, high ones play freely.
+    Softmax { temperature: f64 },
 }
 
-pub struct StrategyStack {
-    strategies: Vec<Strategy>,
-    rng: RefCell<rand::rngs::ThreadRng>,
+/// Strategies are colorless; the stack passes on the piece it is asked to play, so one
+/// stack can play both sides.
+pub struct StrategyStack<const R: usize = ROWS, const C: usize = COLUMNS> {
+    strategies: Vec<Strategy<R, C>>,
+    selection: Selection,
 }
 
 impl StrategyStack {
-    pub fn new(strategies: Vec<Strategy>) -> Self {
+    /// A stack whose memory and time per move are fixed by `budget`, for
+    /// microcontrollers and WASM: it takes wins and blocks, then runs a
+    /// `BoundedSearch`. Nothing in it grows as the game goes on.
+    pub fn bounded(budget: SearchBudget) -> Self {
+        StrategyStack::new(vec![
+            Strategy::Decision(Box::new(TriesToWin)),
+            Strategy::Decision(Box::new(BoundedSearch::new(budget))),
+        ])
+    }
+}
+
+impl<const R: usize, const C: usize> StrategyStack<R, C> {
+    /// With less than this left on the clock, expensive deciders are skipped.
+    pub const LOW_ON_TIME: Duration = Duration::from_secs(1);
+
+    pub fn new(strategies: Vec<Strategy<R, C>>) -> Self {
         StrategyStack {
             strategies,
-            rng: RefCell::new(rand::rngs::ThreadRng::default()),
+            selection: Selection::Prune,
+        }
+    }
+
+    /// In the scoring modes deciders still pick outright, from the best-scored moves
+    /// so far.
+    pub fn with_selection(mut self, selection: Selection) -> Self {
+        if let Selection::Softmax { temperature } = selection {
+            assert!(temperature > 0.0, "temperature must be positive");
+        }
+        self.selection = selection;
+        self
+    }
+
+    pub fn with_temperature(self, temperature: f64) -> Self {
+        self.with_selection(Selection::Softmax { temperature })
+    }
+
+    /// The chance of playing each legal move.
+    pub fn move_probabilities(&self, board: &BitBoard<R, C>, piece: Piece) -> Vec<(usize, f64)> {
+        self.probabilities(board, piece, false)
+    }
+
+    fn probabilities(
+        &self,
+        board: &BitBoard<R, C>,
+        piece: Piece,
+        skip_expensive: bool,
+    ) -> Vec<(usize, f64)> {
+        let Selection::Softmax { temperature } = self.selection else {
+            let options = self.evaluate(board, piece, skip_expensive, None);
+            let chance = 1.0 / options.len() as f64;
+            return options.into_iter().map(|col| (col, chance)).collect();
+        };
+        let scored = self.score(board, piece, skip_expensive, None);
+        let best = scored
+            .iter()
+            .map(|&(_, score)| score)
+            .fold(f64::MIN, f64::max);
+        // Shifting by the best score keeps exp from overflowing
+        let weights: Vec<f64> = (scored.iter())
+            .map(|&(_, score)| ((score - best) / temperature).exp())
+            .collect();
+        let total: f64 = weights.iter().sum();
+        (scored.iter().zip(weights))
+            .map(|(&(col, _), weight)| (col, weight / total))
+            .collect()
+    }
+
+    /// Summed layer scores for every legal move, or just the decided move.
+    fn score(
+        &self,
+        board: &BitBoard<R, C>,
+        piece: Piece,
+        skip_expensive: bool,
+        mut explain: Option<&mut DecisionTrace>,
+    ) -> Vec<(usize, f64)> {
+        let options = board.valid_moves();
+        assert!(!options.is_empty());
+        let mut scores = vec![0.0; options.len()];
+
+        for strategy in self.strategies.iter() {
+            match strategy {
+                Strategy::Layer(strategy_layer) => {
+                    let layer_scores = strategy_layer.score(board, piece, &options);
+                    tracing::trace!(layer = strategy_layer.name(), scores = ?layer_scores, "layer scored options");
+                    note(&mut explain, || {
+                        TraceStep::scored(strategy_layer.name(), &options, &layer_scores)
+                    });
+                    for (score, layer_score) in scores.iter_mut().zip(layer_scores) {
+                        *score += layer_score;
+                    }
+                }
+                Strategy::Decision(strategy_decider)
+                    if skip_expensive && strategy_decider.is_expensive() =>
+                {
+                    tracing::trace!(decider = strategy_decider.name(), "skipped, low on time");
+                    note(&mut explain, || {
+                        TraceStep::skipped(strategy_decider.name(), &options)
+                    });
+                }
+                Strategy::Decision(strategy_decider) => {
+                    let best = scores.iter().copied().fold(f64::MIN, f64::max);
+                    let best_options: Vec<usize> = (options.iter().zip(&scores))
+                        .filter(|&(_, &score)| score == best)
+                        .map(|(&col, _)| col)
+                        .collect();
+                    let choice = strategy_decider.choose(board, piece, &best_options);
+                    note(&mut explain, || {
+                        TraceStep::decided(strategy_decider.name(), &best_options, choice)
+                    });
+                    if let Some(choice) = choice {
+                        tracing::trace!(decider = strategy_decider.name(), choice, "decider chose");
+                        return vec![(choice, 0.0)];
+                    }
+                }
+            }
+        }
+
+        options.into_iter().zip(scores).collect()
+    }
+
+    fn sample(&self, board: &BitBoard<R, C>, piece: Piece, skip_expensive: bool) -> Option<usize> {
+        let mut rng = crate::rng::rng();
+        if !matches!(self.selection, Selection::Softmax { .. }) {
+            return (self.evaluate(board, piece, skip_expensive, None))
+                .choose(&mut rng)
+                .copied();
+        }
+        (self.probabilities(board, piece, skip_expensive))
+            .choose_weighted(&mut rng, |&(_, chance)| chance)
+            .ok()
+            .map(|&(col, _)| col)
+    }
+
+    /// The moves the stack rates best: the survivors when pruning, otherwise the
+    /// best-scored moves.
+    pub fn evaluate_options(&self, board: &BitBoard<R, C>, piece: Piece) -> Vec<usize> {
+        self.evaluate(board, piece, false, None)
+    }
+
+    /// `evaluate_options`, along with what each of the stack's strategies did to get
+    /// there: which columns each layer pruned and which decider picked the move.
+    pub fn explain(&self, board: &BitBoard<R, C>, piece: Piece) -> DecisionTrace {
+        let mut trace = DecisionTrace::default();
+        trace.options = self.evaluate(board, piece, false, Some(&mut trace));
+        trace
+    }
+
+    fn evaluate(
+        &self,
+        board: &BitBoard<R, C>,
+        piece: Piece,
+        skip_expensive: bool,
+        explain: Option<&mut DecisionTrace>,
+    ) -> Vec<usize> {
+        if self.selection == Selection::Prune {
+            return self.prune(board, piece, skip_expensive, explain);
+        }
+        let scored = self.score(board, piece, skip_expensive, explain);
+        let best = (scored.iter())
+            .map(|&(_, score)| score)
+            .fold(f64::MIN, f64::max);
+        (scored.into_iter())
+            .filter(|&(_, score)| score == best)
+            .map(|(col, _)| col)
+            .collect()
+    }
+
+    /// The names of the stack's strategies, in order.
+    pub fn strategy_names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.strategies.iter().map(Strategy::name)
+    }
+
+    /// Whether any of the stack's deciders is expensive.
+    pub fn is_expensive(&self) -> bool {
+        (self.strategies.iter()).any(
+            |strategy| matches!(strategy, Strategy::Decision(decider) if decider.is_expensive()),
+        )
+    }
+
+    /// The line behind the stack's move: a search's whole forced win, or just the move
+    /// when a simpler decider picks it. None when no decider picks and the move is left
+    /// to chance. Layers prune as in `Selection::Prune`, whatever the stack's selection.
+    pub fn principal_variation(&self, board: &BitBoard<R, C>, piece: Piece) -> Option<Vec<usize>> {
+        let mut options = board.valid_moves();
+        for strategy in self.strategies.iter() {
+            match strategy {
+                Strategy::Layer(strategy_layer) => {
+                    let new_options = strategy_layer.prune_from(board, piece, &options);
+                    if !new_options.is_empty() {
+                        options = new_options
+                    }
+                }
+                Strategy::Decision(strategy_decider) => {
+                    if let Some(line) = strategy_decider.principal_variation(board, piece, &options)
+                    {
+                        return Some(line);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// The move one of the stack's deciders picks from `options`, after its layers
+    /// prune them. None when the move would be left to chance.
+    pub fn decide(&self, board: &BitBoard<R, C>, piece: Piece, options: &[usize]) -> Option<usize> {
+        let mut options = options.to_vec();
+        for strategy in self.strategies.iter() {
+            match strategy {
+                Strategy::Layer(strategy_layer) => {
+                    let new_options = strategy_layer.prune_from(board, piece, &options);
+                    if !new_options.is_empty() {
+                        options = new_options
+                    }
+                }
+                Strategy::Decision(strategy_decider) => {
+                    if let Some(choice) = strategy_decider.choose(board, piece, &options) {
+                        return Some(choice);
+                    }
+                }
+            }
         }
+        None
     }
 
-    pub fn evaluate_options(&self, board: &Board) -> Vec<usize> {
+    fn prune(
+        &self,
+        board: &BitBoard<R, C>,
+        piece: Piece,
+        skip_expensive: bool,
+        mut explain: Option<&mut DecisionTrace>,
+    ) -> Vec<usize> {
         let mut options = board.valid_moves();
         assert!(!options.is_empty());
 
-        for strategy in &self.strategies {
+        for strategy in self.strategies.iter() {
             match strategy {
                 Strategy::Layer(strategy_layer) => {
-                    let new_options = strategy_layer.prune_from(board, &options);
+                    let new_options = strategy_layer.prune_from(board, piece, &options);
+                    tracing::trace!(
+                        layer = strategy_layer.name(),
+                        before = ?options,
+                        after = ?new_options,
+                        "layer pruned options"
+                    );
+                    note(&mut explain, || {
+                        TraceStep::pruned(strategy_layer.name(), &options, &new_options)
+                    });
                     if !new_options.is_empty() {
                         options = new_options
                     }
                 }
+                Strategy::Decision(strategy_decider)
+                    if skip_expensive && strategy_decider.is_expensive() =>
+                {
+                    tracing::trace!(decider = strategy_decider.name(), "skipped, low on time");
+                    note(&mut explain, || {
+                        TraceStep::skipped(strategy_decider.name(), &options)
+                    });
+                }
                 Strategy::Decision(strategy_decider) => {
-                    if let Some(choice) = strategy_decider.choose(board, &options) {
+                    let choice = strategy_decider.choose(board, piece, &options);
+                    note(&mut explain, || {
+                        TraceStep::decided(strategy_decider.name(), &options, choice)
+                    });
+                    if let Some(choice) = choice {
+                        tracing::trace!(decider = strategy_decider.name(), choice, "decider chose");
                         assert!(options.contains(&choice));
                         // Short circuit!
                         return vec![choice];
@@ -50,32 +349,169 @@ impl StrategyStack {
     }
 }
 
-impl Connect4AI for StrategyStack {
-    fn play(&self, board: &Board) -> Option<usize> {
-        let moves = self.evaluate_options(board);
-        moves.choose(&mut self.rng.borrow_mut()).copied()
+/// Records a step when explaining. Steps are built only then, so play doesn't pay for them.
+fn note(explain: &mut Option<&mut DecisionTrace>, step: impl FnOnce() -> TraceStep) {
+    if let Some(trace) = explain {
+        trace.steps.push(step());
     }
 }
 
-impl std::fmt::Display for StrategyStack {
+impl<const R: usize, const C: usize> Connect4AI<R, C> for StrategyStack<R, C> {
+    fn play(&self, board: &BitBoard<R, C>, piece: Piece) -> Option<usize> {
+        self.sample(board, piece, false)
+    }
+
+    fn play_timed(
+        &self,
+        board: &BitBoard<R, C>,
+        piece: Piece,
+        remaining: Duration,
+    ) -> Option<usize> {
+        self.sample(board, piece, remaining < Self::LOW_ON_TIME)
+    }
+
+    /// Takes a first move in the center column, the strongest opening.
+    fn swaps(&self, board: &BitBoard<R, C>) -> bool {
+        *board == BitBoard::new().place(C / 2, Piece::Red)
+    }
+
+    /// Rotates when that wins outright, or when it escapes a double threat it
+    /// couldn't otherwise block.
+    fn rotates(&self, board: &BitBoard<R, C>, piece: Piece) -> bool {
+        let Some(rotated) = board.rotate_and_settle() else {
+            return false;
+        };
+        if rotated.has_four(piece.opponent()) {
+            return false;
+        }
+        let wins = |board: &BitBoard<R, C>| {
+            (board.threat_squares(piece.opponent()).into_iter())
+                .filter(|&(row, column)| board.is_playable(row, column))
+                .count()
+        };
+        rotated.has_four(piece) || (wins(board) > 1 && wins(&rotated) == 0)
+    }
+}
+
+impl<const R: usize, const C: usize> std::fmt::Display for StrategyStack<R, C> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "StrategyStack(")?;
-        for (i, strategy) in self.strategies.iter().enumerate() {
+        for (i, name) in self.strategy_names().enumerate() {
             if i > 0 {
                 write!(f, " => ")?;
             }
-            write!(f, "{}", strategy.name())?;
+            write!(f, "{}", name)?;
         }
         write!(f, ")")
     }
 }
 
-pub enum Strategy {
-    Layer(Box<dyn StrategyLayer>),
-    Decision(Box<dyn StrategyDecider>),
+/// Lets borrowed AIs be wrapped, e.g. in `Exploring`.
+impl<const R: usize, const C: usize, A: Connect4AI<R, C> + ?Sized> Connect4AI<R, C> for &A {
+    fn play(&self, board: &BitBoard<R, C>, piece: Piece) -> Option<usize> {
+        (**self).play(board, piece)
+    }
+
+    fn play_timed(
+        &self,
+        board: &BitBoard<R, C>,
+        piece: Piece,
+        remaining: Duration,
+    ) -> Option<usize> {
+        (**self).play_timed(board, piece, remaining)
+    }
+
+    fn passes(&self, board: &BitBoard<R, C>, piece: Piece) -> bool {
+        (**self).passes(board, piece)
+    }
+
+    fn swaps(&self, board: &BitBoard<R, C>) -> bool {
+        (**self).swaps(board)
+    }
+
+    fn rotates(&self, board: &BitBoard<R, C>, piece: Piece) -> bool {
+        (**self).rotates(board, piece)
+    }
 }
 
-impl Strategy {
+/// Epsilon-greedy exploration: plays a uniformly random legal move with probability
+/// `epsilon` and otherwise whatever `inner` would play. Useful for diverse self-play.
+pub struct Exploring<A> {
+    inner: A,
+    epsilon: f64,
+    /// Only explore while fewer pieces than this are on the board.
+    until_pieces: usize,
+}
+
+impl<A> Exploring<A> {
+    pub fn new(inner: A, epsilon: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&epsilon),
+            "epsilon must be between 0 and 1"
+        );
+        Exploring {
+            inner,
+            epsilon,
+            until_pieces: usize::MAX,
+        }
+    }
+
+    /// Only explore during the first `moves` moves of the game.
+    pub fn for_first_moves(mut self, moves: usize) -> Self {
+        self.until_pieces = moves;
+        self
+    }
+
+    fn explore<const R: usize, const C: usize>(&self, board: &BitBoard<R, C>) -> Option<usize> {
+        let mut rng = crate::rng::rng();
+        if board.num_pieces_played() < self.until_pieces && rng.random_bool(self.epsilon) {
+            board.valid_moves().choose(&mut rng).copied()
+        } else {
+            None
+        }
+    }
+}
+
+impl<const R: usize, const C: usize, A: Connect4AI<R, C>> Connect4AI<R, C> for Exploring<A> {
+    fn play(&self, board: &BitBoard<R, C>, piece: Piece) -> Option<usize> {
+        self.explore(board)
+            .or_else(|| self.inner.play(board, piece))
+    }
+
+    fn play_timed(
+        &self,
+        board: &BitBoard<R, C>,
+        piece: Piece,
+        remaining: Duration,
+    ) -> Option<usize> {
+        (self.explore(board)).or_else(|| self.inner.play_timed(board, piece, remaining))
+    }
+
+    fn passes(&self, board: &BitBoard<R, C>, piece: Piece) -> bool {
+        self.inner.passes(board, piece)
+    }
+
+    fn swaps(&self, board: &BitBoard<R, C>) -> bool {
+        self.inner.swaps(board)
+    }
+
+    fn rotates(&self, board: &BitBoard<R, C>, piece: Piece) -> bool {
+        self.inner.rotates(board, piece)
+    }
+}
+
+impl<A: std::fmt::Display> std::fmt::Display for Exploring<A> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Exploring({}, {})", self.inner, self.epsilon)
+    }
+}
+
+pub enum Strategy<const R: usize = ROWS, const C: usize = COLUMNS> {
+    Layer(Box<dyn StrategyLayer<R, C>>),
+    Decision(Box<dyn StrategyDecider<R, C>>),
+}
+
+impl<const R: usize, const C: usize> Strategy<R, C> {
     pub fn name(&self) -> &'static str {
         match self {
             Strategy::Layer(layer) => layer.name(),
@@ -84,37 +520,67 @@ impl Strategy {
     }
 }
 
-pub trait StrategyDecider {
-    fn choose(&self, board: &Board, options: &[usize]) -> Option<usize>;
+/// Deciders and layers are told which piece they play for, so one can serve both
+/// sides. They are shared between threads, so any state they keep needs a lock.
+pub trait StrategyDecider<const R: usize = ROWS, const C: usize = COLUMNS>: Send + Sync {
+    fn choose(&self, board: &BitBoard<R, C>, piece: Piece, options: &[usize]) -> Option<usize>;
     fn name(&self) -> &'static str;
-}
 
-pub trait StrategyLayer {
-    fn prune_from(&self, board: &Board, options: &[usize]) -> Vec<usize>;
-    fn name(&self) -> &'static str;
-}
+    /// The line of play behind `choose`, in columns and starting with its choice.
+    /// Searches give the whole forced line they found; by default it's just the choice.
+    fn principal_variation(
+        &self,
+        board: &BitBoard<R, C>,
+        piece: Piece,
+        options: &[usize],
+    ) -> Option<Vec<usize>> {
+        self.choose(board, piece, options).map(|col| vec![col])
+    }
 
-pub struct TriesToWin {
-    piece: Piece,
+    /// Whether this decider searches deep enough to be skipped when time is short.
+    fn is_expensive(&self) -> bool {
+        false
+    }
 }
 
-impl TriesToWin {
-    pub fn new(piece: Piece) -> Self {
-        TriesToWin { piece }
+/// The default score for a move a layer would prune. Low enough that graded
+/// preferences from a handful of layers never outweigh it.
+pub const PRUNED_SCORE: f64 = -10.0;
+
+pub trait StrategyLayer<const R: usize = ROWS, const C: usize = COLUMNS>: Send + Sync {
+    fn prune_from(&self, board: &BitBoard<R, C>, piece: Piece, options: &[usize]) -> Vec<usize>;
+    fn name(&self) -> &'static str;
+
+    /// How much the layer likes each option, for stacks that select by score. Graded
+    /// preferences run from 0 to 1. Defaults to 0 for the options `prune_from` keeps
+    /// and `PRUNED_SCORE` for the rest.
+    fn score(&self, board: &BitBoard<R, C>, piece: Piece, options: &[usize]) -> Vec<f64> {
+        let kept = self.prune_from(board, piece, options);
+        (options.iter())
+            .map(|col| {
+                if kept.contains(col) {
+                    0.0
+                } else {
+                    PRUNED_SCORE
+                }
+            })
+            .collect()
     }
 }
 
-impl StrategyDecider for TriesToWin {
-    fn choose(&self, board: &Board, options: &[usize]) -> Option<usize> {
+pub struct TriesToWin;
+
+impl<const R: usize, const C: usize> StrategyDecider<R, C> for TriesToWin {
+    fn choose(&self, board: &BitBoard<R, C>, piece: Piece, options: &[usize]) -> Option<usize> {
         for col in options {
             // If we could win, add it.
-            let test_board = board.place(*col, self.piece);
-            if test_board.has_winner() == Some(self.piece) {
+            let test_board = board.place(*col, piece);
+            if test_board.has_winner() == Some(piece) {
                 return Some(*col);
             }
             // If we would lose, add it to block
-            let test_board = board.place(*col, self.piece.opponent());
-            if test_board.has_winner() == Some(self.piece.opponent()) {
+            let test_board = board.place(*col, piece.opponent());
+            if test_board.has_winner() == Some(piece.opponent()) {
                 return Some(*col);
             }
         }
@@ -126,24 +592,17 @@ impl StrategyDecider for TriesToWin {
     }
 }
 
-pub struct Setup {
-    piece: Piece,
-}
+pub struct Setup;
 
-impl Setup {
-    pub fn new(piece: Piece) -> Self {
-        Setup { piece }
-    }
-}
-
-impl StrategyDecider for Setup {
-    fn choose(&self, board: &Board, options: &[usize]) -> Option<usize> {
+impl<const R: usize, const C: usize> StrategyDecider<R, C> for Setup {
+    fn choose(&self, board: &BitBoard<R, C>, piece: Piece, options: &[usize]) -> Option<usize> {
         for col in options {
-            let test_board = board.place(*col, self.piece);
-            if test_board.has_winner() == Some(self.piece) {
+            let test_board = board.place(*col, piece);
+            if test_board.has_winner() == Some(piece) {
                 return Some(*col);
             }
-            if !test_board.winning_moves(self.piece).is_empty() {
+            let threats = test_board.threat_squares(piece);
+            if (threats.into_iter()).any(|(row, column)| test_board.is_playable(row, column)) {
                 return Some(*col);
             }
         }
@@ -155,27 +614,19 @@ impl StrategyDecider for Setup {
     }
 }
 
-pub struct ThreeInARow {
-    piece: Piece,
-}
-
-impl ThreeInARow {
-    pub fn new(piece: Piece) -> Self {
-        ThreeInARow { piece }
-    }
-}
+pub struct ThreeInARow;
 
-impl StrategyLayer for ThreeInARow {
-    fn prune_from(&self, board: &Board, options: &[usize]) -> Vec<usize> {
+impl<const R: usize, const C: usize> StrategyLayer<R, C> for ThreeInARow {
+    fn prune_from(&self, board: &BitBoard<R, C>, piece: Piece, options: &[usize]) -> Vec<usize> {
         let mut best = 0;
         let mut best_moves = vec![];
 
         for col in options {
-            let test_board = board.place(*col, self.piece);
-            if test_board.has_winner() == Some(self.piece) {
+            let test_board = board.place(*col, piece);
+            if test_board.has_winner() == Some(piece) {
                 return vec![*col];
             }
-            let score = test_board.count_winning_opportunities(self.piece);
+            let score = test_board.count_winning_opportunities(piece);
             if score > best {
                 best = score;
                 best_moves.clear();
@@ -192,33 +643,103 @@ impl StrategyLayer for ThreeInARow {
     fn name(&self) -> &'static str {
         "ThreeInARow"
     }
+
+    /// Graded: each option's winning opportunities relative to the best option's.
+    fn score(&self, board: &BitBoard<R, C>, piece: Piece, options: &[usize]) -> Vec<f64> {
+        let boards: Vec<BitBoard<R, C>> = (options.iter())
+            .map(|&col| board.place(col, piece))
+            .collect();
+        if boards.iter().any(|b| b.has_winner() == Some(piece)) {
+            return (boards.iter())
+                .map(|b| f64::from(u8::from(b.has_winner() == Some(piece))))
+                .collect();
+        }
+        let counts: Vec<usize> = (boards.iter())
+            .map(|b| b.count_winning_opportunities(piece))
+            .collect();
+        let best = counts.iter().copied().max().unwrap_or(0).max(1);
+        counts.iter().map(|&c| c as f64 / best as f64).collect()
+    }
 }
 
-/// Strategy that avoids placing pieces in columns that would allow the opponent to win on their next turn.
-pub struct AvoidTraps {
-    piece: Piece,
+/// Strategy that prefers central columns, which take part in the most four-in-a-rows.
+/// Prunes to the most heavily weighted options, or scores each by its column's weight.
+pub struct PreferCenter {
+    weights: [f64; COLUMNS],
 }
 
-impl AvoidTraps {
-    pub fn new(piece: Piece) -> Self {
-        AvoidTraps { piece }
+impl PreferCenter {
+    /// Proportional to the number of possible four-in-a-rows through each column.
+    pub const DEFAULT_WEIGHTS: [f64; COLUMNS] = [2.0, 3.0, 4.0, 5.0, 4.0, 3.0, 2.0];
+
+    pub fn new() -> Self {
+        Self::with_weights(Self::DEFAULT_WEIGHTS)
+    }
+
+    pub fn with_weights(weights: [f64; COLUMNS]) -> Self {
+        assert!(
+            weights.iter().all(|w| w.is_finite() && *w >= 0.0),
+            "column weights must be finite and non-negative"
+        );
+        PreferCenter { weights }
     }
 }
 
-impl StrategyLayer for AvoidTraps {
-    fn prune_from(&self, board: &Board, options: &[usize]) -> Vec<usize> {
+impl Default for PreferCenter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StrategyLayer for PreferCenter {
+    fn prune_from(&self, _board: &Board, _piece: Piece, options: &[usize]) -> Vec<usize> {
+        let best = (options.iter())
+            .map(|&col| self.weights[col])
+            .fold(f64::MIN, f64::max);
+        (options.iter())
+            .copied()
+            .filter(|&col| self.weights[col] == best)
+            .collect()
+    }
+
+    fn name(&self) -> &'static str {
+        "PreferCenter"
+    }
+
+    /// Each option's weight relative to the heaviest option's.
+    fn score(&self, _board: &Board, _piece: Piece, options: &[usize]) -> Vec<f64> {
+        let best = (options.iter())
+            .map(|&col| self.weights[col])
+            .fold(0.0, f64::max);
+        (options.iter())
+            .map(|&col| {
+                if best > 0.0 {
+                    self.weights[col] / best
+                } else {
+                    0.0
+                }
+            })
+            .collect()
+    }
+}
+
+/// Strategy that avoids placing pieces in columns that would allow the opponent to win on their next turn.
+pub struct AvoidTraps;
+
+impl<const R: usize, const C: usize> StrategyLayer<R, C> for AvoidTraps {
+    fn prune_from(&self, board: &BitBoard<R, C>, piece: Piece, options: &[usize]) -> Vec<usize> {
         // Disqualify columns that would allow the opponent to win on their next turn
         let mut allowed = Vec::with_capacity(options.len());
 
         for col in options {
-            let test_board = board.place(*col, self.piece);
+            let test_board = board.place(*col, piece);
             // If this move wins, short-circuit
-            if test_board.has_winner() == Some(self.piece) {
+            if test_board.has_winner() == Some(piece) {
                 allowed.push(*col);
                 continue;
             }
             // No good if the opponent has a winning opportunity
-            if !test_board.winning_moves(self.piece.opponent()).is_empty() {
+            if !test_board.winning_moves(piece.opponent()).is_empty() {
                 continue;
             }
             allowed.push(*col);
@@ -232,36 +753,51 @@ impl StrategyLayer for AvoidTraps {
     }
 }
 
-/// Strategy that avoids placing anywhere the other player gets more than one three-in-a-row.
-pub struct AvoidInescapableTraps {
-    piece: Piece,
-}
+/// Strategy that avoids moves after which the opponent has a reply that wins by force
+/// on their following turn: a double threat, or a threat we can only block by giving
+/// them a winning spot on top of our piece.
+pub struct AvoidInescapableTraps;
 
 impl AvoidInescapableTraps {
-    pub fn new(piece: Piece) -> Self {
-        AvoidInescapableTraps { piece }
+    /// Whether every move we have on `board` either loses to an immediate win or
+    /// doesn't win itself, i.e. the opponent wins next turn whatever we do.
+    fn is_lost<const R: usize, const C: usize>(
+        &self,
+        board: &BitBoard<R, C>,
+        piece: Piece,
+    ) -> bool {
+        let our_moves = board.valid_moves();
+        !our_moves.is_empty()
+            && our_moves.into_iter().all(|col| {
+                // Winning leaves the opponent no winning moves
+                !(board.place(col, piece))
+                    .winning_moves(piece.opponent())
+                    .is_empty()
+            })
     }
 }
 
-impl StrategyLayer for AvoidInescapableTraps {
-    fn prune_from(&self, board: &Board, options: &[usize]) -> Vec<usize> {
-        // Disqualify columns that would allow the opponent to win on their next turn
+impl<const R: usize, const C: usize> StrategyLayer<R, C> for AvoidInescapableTraps {
+    fn prune_from(&self, board: &BitBoard<R, C>, piece: Piece, options: &[usize]) -> Vec<usize> {
+        // Once someone has won there is no trap left to walk into
+        if board.has_winner().is_some() {
+            return options.to_vec();
+        }
         let mut allowed = Vec::with_capacity(options.len());
 
         'candidate_loop: for col in options {
-            let test_board = board.place(*col, self.piece);
+            let test_board = board.place(*col, piece);
             // If this move wins, short-circuit
-            if test_board.has_winner() == Some(self.piece) {
+            if test_board.has_winner() == Some(piece) {
                 allowed.push(*col);
                 continue;
             }
             for next_col in test_board.valid_moves() {
-                let next_board = test_board.place(next_col, self.piece.opponent());
+                let next_board = test_board.place(next_col, piece.opponent());
                 // If we've lost or have a losing position, don't take it.
-                if next_board.has_winner() == Some(self.piece.opponent()) {
-                    continue 'candidate_loop;
-                }
-                if next_board.winning_moves(self.piece.opponent()).len() > 1 {
+                if next_board.has_winner() == Some(piece.opponent())
+                    || self.is_lost(&next_board, piece)
+                {
                     continue 'candidate_loop;
                 }
             }
@@ -276,56 +812,196 @@ impl StrategyLayer for AvoidInescapableTraps {
     }
 }
 
+/// The defensive mirror of `Setup`: prunes moves after which the opponent can make a
+/// double threat (two immediate wins at once) on their next turn, unless we would win
+/// first. Moves that hand the opponent an immediate win are pruned too.
+pub struct BlockSetups;
+
+impl BlockSetups {
+    fn allows_setup<const R: usize, const C: usize>(
+        &self,
+        board: &BitBoard<R, C>,
+        piece: Piece,
+    ) -> bool {
+        let opponent = piece.opponent();
+        board.valid_moves().into_iter().any(|reply| {
+            let next_board = board.place(reply, opponent);
+            if next_board.has_winner() == Some(opponent) {
+                return true;
+            }
+            // Their threats don't matter if we win on the spot
+            next_board.winning_moves(piece).is_empty()
+                && board.creates_double_threat(reply, opponent)
+        })
+    }
+}
+
+impl<const R: usize, const C: usize> StrategyLayer<R, C> for BlockSetups {
+    fn prune_from(&self, board: &BitBoard<R, C>, piece: Piece, options: &[usize]) -> Vec<usize> {
+        // Once someone has won there is no setup left to block
+        if board.has_winner().is_some() {
+            return options.to_vec();
+        }
+        (options.iter())
+            .copied()
+            .filter(|&col| {
+                let test_board = board.place(col, piece);
+                test_board.has_winner() == Some(piece) || !self.allows_setup(&test_board, piece)
+            })
+            .collect()
+    }
+
+    fn name(&self) -> &'static str {
+        "BlockSetups"
+    }
+}
+
 /// Strategy that searches for an unstoppable move with a given depth
 pub struct SearchForWin {
-    piece: Piece,
     depth: usize,
+    trace: Option<Mutex<SearchTrace>>,
 }
 
 impl SearchForWin {
-    pub fn new(piece: Piece, depth: usize) -> Self {
-        SearchForWin { piece, depth }
+    /// Searching early is expensive and rarely finds anything, so built stacks only
+    /// start looking after this many pieces have been played, with a `PhasedStrategy`.
+    pub const MIN_PIECES_PLAYED: usize = 20;
+
+    pub fn new(depth: usize) -> Self {
+        SearchForWin { depth, trace: None }
     }
 
-    fn has_guaranteed_win(&self, board: &Board, depth: usize) -> bool {
-        assert!(board.next_player() == self.piece.opponent());
+    /// Records the tree searched for each decision, for `take_trace`. Slow; for
+    /// debugging only.
+    pub fn with_trace(mut self) -> Self {
+        self.trace = Some(Mutex::new(SearchTrace::new()));
+        self
+    }
+
+    /// The tree searched for the last decision, if tracing is on.
+    pub fn take_trace(&self) -> Option<SearchTrace> {
+        (self.trace.as_ref()).map(|trace| std::mem::take(&mut *trace.lock().unwrap()))
+    }
 
+    /// How we force a win from `board`, with the opponent to move: their most stubborn
+    /// reply and our answer, over and over, in columns. None if we can't within `depth`
+    /// of our moves.
+    fn forced_line(
+        &self,
+        board: &Board,
+        piece: Piece,
+        depth: usize,
+        buffers: &mut MoveBuffers,
+    ) -> Option<Vec<usize>> {
+        if let Some(trace) = &self.trace {
+            trace.lock().unwrap().enter(board, depth);
+        }
+        let line = self.search(board, piece, depth, buffers);
+        if let Some(trace) = &self.trace {
+            // Running out of depth proves nothing either way
+            trace
+                .lock()
+                .unwrap()
+                .exit(Some(line.is_some()).filter(|&won| won || depth > 0));
+        }
+        line
+    }
+
+    fn search(
+        &self,
+        board: &Board,
+        piece: Piece,
+        depth: usize,
+        buffers: &mut MoveBuffers,
+    ) -> Option<Vec<usize>> {
         // If we've won, we've won.
-        if board.has_winner() == Some(self.piece) {
-            return true;
+        if board.has_winner() == Some(piece) {
+            return Some(buffers.empty());
         }
 
         // Otherwise, if this is our search depth, we can't guarantee a win
         if depth == 0 {
-            return false;
+            return None;
         }
 
         // Otherwise, we need to look at all of the possible ways the enemy could respond
-        // and see if we can win no matter what they pick.
-        let enemy_moves = board.all_future_boards(self.piece.opponent());
-        enemy_moves.into_iter().all(|board| {
-            // Get all the ways we could respond
-            let our_moves = board.all_future_boards(self.piece);
-            // Check if _any_ of our responses guarantee a win
-            our_moves
-                .into_iter()
-                .any(|board| self.has_guaranteed_win(&board, depth - 1))
-        })
+        // and see if we can win no matter what they pick. The line follows the reply
+        // that holds out longest.
+        let enemy_moves = buffers.valid_moves(board);
+        let mut longest = buffers.empty();
+        let mut escaped = false;
+        for &enemy_col in &enemy_moves {
+            let board = board.place(enemy_col, piece.opponent());
+            let line = if board.has_winner() == Some(piece.opponent()) {
+                None
+            } else {
+                self.answer(&board, piece, enemy_col, depth, buffers)
+            };
+            match line {
+                None => {
+                    escaped = true;
+                    break;
+                }
+                Some(line) if line.len() > longest.len() => {
+                    buffers.give_back(std::mem::replace(&mut longest, line))
+                }
+                Some(line) => buffers.give_back(line),
+            }
+        }
+        buffers.give_back(enemy_moves);
+        if escaped {
+            buffers.give_back(longest);
+            return None;
+        }
+        Some(longest)
+    }
+
+    /// Our first answer to the enemy playing `enemy_col` that still forces a win, as the
+    /// line from their move on.
+    fn answer(
+        &self,
+        board: &Board,
+        piece: Piece,
+        enemy_col: usize,
+        depth: usize,
+        buffers: &mut MoveBuffers,
+    ) -> Option<Vec<usize>> {
+        // Check if _any_ of our responses guarantee a win
+        let our_moves = buffers.valid_moves(board);
+        let line = our_moves.iter().find_map(|&col| {
+            let mut line = self.forced_line(&board.place(col, piece), piece, depth - 1, buffers)?;
+            line.splice(0..0, [enemy_col, col]);
+            Some(line)
+        });
+        buffers.give_back(our_moves);
+        line
     }
 }
 
 impl StrategyDecider for SearchForWin {
-    fn choose(&self, board: &Board, options: &[usize]) -> Option<usize> {
-        // Let's only start looking after at least N pieces have been played...
-        const MIN_PIECES_PLAYED: usize = 20;
-        if board.num_pieces_played() < MIN_PIECES_PLAYED {
-            return None;
+    fn choose(&self, board: &Board, piece: Piece, options: &[usize]) -> Option<usize> {
+        self.principal_variation(board, piece, options)
+            .map(|line| line[0])
+    }
+
+    /// The forced win behind the choice, from our move to four in a row.
+    fn principal_variation(
+        &self,
+        board: &Board,
+        piece: Piece,
+        options: &[usize],
+    ) -> Option<Vec<usize>> {
+        if let Some(trace) = &self.trace {
+            *trace.lock().unwrap() = SearchTrace::new();
         }
 
-        for col in options {
-            let board = board.place(*col, self.piece);
-            if self.has_guaranteed_win(&board, self.depth) {
-                return Some(*col);
+        let mut buffers = MoveBuffers::new();
+        for &col in options {
+            let board = board.place(col, piece);
+            if let Some(rest) = self.forced_line(&board, piece, self.depth, &mut buffers) {
+                let line: Vec<usize> = std::iter::once(col).chain(rest).collect();
+                tracing::debug!(column = col, depth = self.depth, ?line, "forced win found");
+                return Some(line);
             }
         }
 
@@ -335,13 +1011,24 @@ impl StrategyDecider for SearchForWin {
     fn name(&self) -> &'static str {
         "SearchForWin"
     }
+
+    fn is_expensive(&self) -> bool {
+        true
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::{
-        board::{Board, Piece},
-        strategy::{SearchForWin, StrategyDecider},
+        board::{BitBoard, Board, Piece},
+        game::GameState,
+        search_for_win::SearchForWinCache,
+        strategy::{
+            AvoidInescapableTraps, AvoidTraps, BlockSetups, Connect4AI, Exploring, PreferCenter,
+            SearchForWin, Selection, Strategy, StrategyDecider, StrategyLayer, StrategyStack,
+            ThreeInARow, TriesToWin,
+        },
+        test_positions::{DEFEND_DOUBLE_THREAT, STACKED_THREAT},
     };
 
     #[test]
@@ -354,9 +1041,280 @@ mod tests {
         // [B] [R] [R] [B] [R] [ ] [R]
         let board = "!   RB/   BR/ BRBB/ RBBB/ RRRB/BRRBR R";
         let board = Board::from(board);
-        let strategy = SearchForWin::new(Piece::Red, 1);
+        let strategy = SearchForWin::new(1);
         let options = board.valid_moves();
-        let choice = strategy.choose(&board, &options);
+        let choice = strategy.choose(&board, Piece::Red, &options);
         assert!(choice.is_some());
     }
+
+    #[test]
+    fn layers_cope_with_finished_boards() {
+        // Yellow has already won along the bottom row, as deep searches can reach.
+        // Nothing is left to win or avoid, so every move is kept and rated alike.
+        let board = *GameState::from_moves("03041516").unwrap().board();
+        assert_eq!(board.has_winner(), Some(Piece::Yellow));
+        let options = board.valid_moves();
+        for layer in [
+            Box::new(AvoidTraps) as Box<dyn StrategyLayer>,
+            Box::new(ThreeInARow),
+            Box::new(BlockSetups),
+            Box::new(AvoidInescapableTraps),
+        ] {
+            let name = layer.name();
+            let kept = layer.prune_from(&board, Piece::Red, &options);
+            assert_eq!(kept, options, "{name} pruned a finished board");
+            let scores = layer.score(&board, Piece::Red, &options);
+            assert_eq!(scores.len(), options.len(), "{name}");
+            assert!(
+                scores.iter().all(|&score| score == scores[0]),
+                "{name}: {scores:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn explains_which_strategy_picked_the_move() {
+        // Red wins by filling column 2 between its pieces on the bottom row
+        let board = *GameState::from_moves("041535").unwrap().board();
+        let stack = StrategyStack::new(vec![
+            Strategy::Layer(Box::new(AvoidTraps)),
+            Strategy::Decision(Box::new(TriesToWin)),
+        ]);
+        let trace = stack.explain(&board, Piece::Red);
+        assert_eq!(trace.decider(), Some("TriesToWin"));
+        assert_eq!(trace.options, [2]);
+        assert_eq!(trace.steps.len(), 2, "{trace}");
+        assert_eq!(trace.steps[1].input, trace.steps[0].output);
+    }
+
+    #[test]
+    fn searches_report_the_forced_line() {
+        let wins = |board: &Board, line: &[usize]| {
+            let end = (line
+                .iter()
+                .zip([Piece::Red, Piece::Yellow].into_iter().cycle()))
+            .fold(*board, |board, (&col, piece)| board.place(col, piece));
+            end.has_winner() == Some(Piece::Red)
+        };
+        // Red makes an open three on the bottom row, and Yellow can only block one end
+        let board = *GameState::from_moves("2233").unwrap().board();
+        let search = SearchForWin::new(2);
+        let line = search
+            .principal_variation(&board, Piece::Red, &board.valid_moves())
+            .unwrap();
+        assert_eq!(line.len(), 3, "{line:?}");
+        assert!(wins(&board, &line));
+
+        let board = Board::from("!   RB/   BR/ BRBB/ RBBB/ RRRB/BRRBR R");
+        let cached = SearchForWinCache::new(2);
+        let line = cached
+            .principal_variation(&board, Piece::Red, &board.valid_moves())
+            .unwrap();
+        assert!(wins(&board, &line));
+
+        let stack = StrategyStack::new(vec![Strategy::Decision(Box::new(search))]);
+        assert_eq!(stack.principal_variation(&board, Piece::Red), Some(line));
+        assert_eq!(stack.principal_variation(&Board::new(), Piece::Red), None);
+    }
+
+    #[test]
+    fn traces_the_search_for_one_decision() {
+        let board = Board::from("!   RB/   BR/ BRBB/ RBBB/ RRRB/BRRBR R");
+        let strategy = SearchForWin::new(1).with_trace();
+        let choice = strategy
+            .choose(&board, Piece::Red, &board.valid_moves())
+            .unwrap();
+        let trace = strategy.take_trace().unwrap();
+        let nodes = trace.nodes();
+        // The winning move is the last root searched, and only proven wins lie below it
+        let root = (nodes.iter())
+            .rposition(|node| node.parent.is_none())
+            .unwrap();
+        assert_eq!(nodes[root].board, board.place(choice, Piece::Red));
+        assert_eq!(nodes[root].result, Some(true));
+        assert!(
+            nodes[root + 1..]
+                .iter()
+                .all(|node| node.result == Some(true))
+        );
+        // Taking the trace leaves an empty one for the next decision
+        assert!(strategy.take_trace().unwrap().nodes().is_empty());
+    }
+
+    #[test]
+    fn skips_searches_when_low_on_time() {
+        // Red's forced win takes a search to find; without it, any move is left
+        let board = Board::from("!   RB/   BR/ BRBB/ RBBB/ RRRB/BRRBR R");
+        let searching =
+            StrategyStack::new(vec![Strategy::Decision(Box::new(SearchForWin::new(1)))]);
+        let options = searching.evaluate_options(&board, Piece::Red);
+        assert_eq!(options.len(), 1);
+        let hurried = searching.evaluate(&board, Piece::Red, true, None);
+        assert_eq!(hurried, board.valid_moves());
+    }
+
+    #[test]
+    fn stacks_play_either_piece() {
+        let board = *GameState::from_moves("3434").unwrap().board();
+        let stack = StrategyStack::new(vec![Strategy::Layer(Box::new(ThreeInARow))]);
+        assert_eq!(stack.evaluate_options(&board, Piece::Red), vec![3]);
+        assert_ne!(stack.evaluate_options(&board, Piece::Yellow), vec![3]);
+    }
+
+    #[test]
+    fn stacks_play_on_other_board_sizes() {
+        type Small = BitBoard<5, 6>;
+        let stack = StrategyStack::<5, 6>::new(vec![
+            Strategy::Layer(Box::new(AvoidTraps)),
+            Strategy::Layer(Box::new(ThreeInARow)),
+            Strategy::Decision(Box::new(TriesToWin)),
+        ]);
+        // Red has three along the bottom, so Yellow must block at column 3
+        let board = (Small::new().place(0, Piece::Red).place(0, Piece::Yellow))
+            .place(1, Piece::Red)
+            .place(1, Piece::Yellow)
+            .place(2, Piece::Red);
+        assert_eq!(stack.play(&board, Piece::Yellow), Some(3));
+        assert!(stack.swaps(&Small::new().place(3, Piece::Red)));
+
+        let (mut board, mut piece) = (Small::new(), Piece::Red);
+        while board.has_winner().is_none() && !board.valid_moves().is_empty() {
+            let column = stack.play(&board, piece).unwrap();
+            board = board.try_place(column, piece).unwrap();
+            piece = piece.opponent();
+        }
+    }
+
+    #[test]
+    fn stacks_play_on_many_threads() {
+        fn shareable<T: Send + Sync>(_: &T) {}
+        let stack = crate::strategy_kind::build_stack(&crate::strategy_kind::difficulty_stack(4));
+        let cache = crate::strategy_cache::StrategyCache::new(StrategyStack::new(vec![]));
+        shareable(&stack);
+        shareable(&cache);
+
+        // Red wins at once in column 0, whichever thread asks
+        let board = *GameState::from_moves("010101").unwrap().board();
+        std::thread::scope(|scope| {
+            let threads: Vec<_> = (0..4)
+                .map(|_| scope.spawn(|| stack.play(&board, Piece::Red)))
+                .collect();
+            for thread in threads {
+                assert_eq!(thread.join().unwrap(), Some(0));
+            }
+        });
+    }
+
+    #[test]
+    fn exploring_delegates_or_plays_randomly() {
+        // Red wins at once in column 0, which TriesToWin always finds
+        let board = *GameState::from_moves("010101").unwrap().board();
+        let stack = StrategyStack::new(vec![Strategy::Decision(Box::new(TriesToWin))]);
+        assert_eq!(stack.play(&board, Piece::Red), Some(0));
+
+        let greedy = Exploring::new(&stack, 0.0);
+        assert_eq!(greedy.play(&board, Piece::Red), Some(0));
+
+        let random = Exploring::new(&stack, 1.0);
+        let picks: Vec<_> = (0..100)
+            .filter_map(|_| random.play(&board, Piece::Red))
+            .collect();
+        assert!(picks.iter().all(|col| board.valid_moves().contains(col)));
+        assert!(picks.iter().any(|&col| col != 0));
+
+        // Past the exploring moves it's back to the strategy
+        let opening_only = Exploring::new(&stack, 1.0).for_first_moves(6);
+        assert_eq!(opening_only.play(&board, Piece::Red), Some(0));
+    }
+
+    #[test]
+    fn temperature_samples_by_score() {
+        // Red can make three in a row in column 3, and there are no wins or traps around
+        let board = *GameState::from_moves("3434").unwrap().board();
+        let layers = || vec![Strategy::Layer(Box::new(ThreeInARow))];
+
+        // Without a temperature the layer just prunes to the best move
+        let pruning = StrategyStack::new(layers());
+        assert_eq!(
+            pruning.move_probabilities(&board, Piece::Red),
+            vec![(3, 1.0)]
+        );
+
+        let chance_of_best = |temperature| {
+            let stack = StrategyStack::new(layers()).with_temperature(temperature);
+            let probabilities = stack.move_probabilities(&board, Piece::Red);
+            assert!((probabilities.iter().map(|&(_, p)| p).sum::<f64>() - 1.0).abs() < 1e-9);
+            assert_eq!(probabilities.len(), board.valid_moves().len());
+            probabilities.iter().find(|&&(col, _)| col == 3).unwrap().1
+        };
+        let cold = chance_of_best(0.01);
+        let hot = chance_of_best(100.0);
+        assert!(cold > 0.99, "{cold}");
+        assert!(hot < 0.5, "{hot}");
+    }
+
+    #[test]
+    fn argmax_keeps_every_best_scored_move() {
+        // Red must block column 0. ThreeInARow prefers columns 1, 3 and 4, but that
+        // doesn't outweigh AvoidTraps' veto
+        let board = *GameState::from_moves("102010").unwrap().board();
+        let stack = StrategyStack::new(vec![
+            Strategy::Layer(Box::new(AvoidTraps)),
+            Strategy::Layer(Box::new(ThreeInARow)),
+        ])
+        .with_selection(Selection::Argmax);
+        assert_eq!(stack.evaluate_options(&board, Piece::Red), vec![0]);
+        assert_eq!(stack.play(&board, Piece::Red), Some(0));
+    }
+
+    #[test]
+    fn prefer_center_weights_columns() {
+        let board = Board::new();
+        let layer = PreferCenter::new();
+        let red = Piece::Red;
+        assert_eq!(layer.prune_from(&board, red, &board.valid_moves()), vec![3]);
+        assert_eq!(layer.prune_from(&board, red, &[0, 2, 4, 6]), vec![2, 4]);
+        assert_eq!(layer.score(&board, red, &[3, 0]), vec![1.0, 0.4]);
+
+        let edges = PreferCenter::with_weights([1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0]);
+        assert_eq!(
+            edges.prune_from(&board, red, &board.valid_moves()),
+            vec![0, 6]
+        );
+    }
+
+    #[test]
+    fn block_setups_stops_open_threes() {
+        let board = *GameState::from_moves(DEFEND_DOUBLE_THREAT).unwrap().board();
+        assert_eq!(
+            BlockSetups.prune_from(&board, Piece::Red, &board.valid_moves()),
+            vec![1, 4, 6]
+        );
+    }
+
+    #[test]
+    fn avoid_inescapable_traps_sees_double_threats() {
+        let board = *GameState::from_moves(DEFEND_DOUBLE_THREAT).unwrap().board();
+        assert_eq!(
+            AvoidInescapableTraps.prune_from(&board, Piece::Red, &board.valid_moves()),
+            vec![1, 4, 6]
+        );
+    }
+
+    #[test]
+    fn avoid_inescapable_traps_sees_stacked_threats() {
+        // BlockSetups only counts immediate threats, so it misses the trap behind 1
+        let board = *GameState::from_moves(STACKED_THREAT).unwrap().board();
+        let options = board.valid_moves();
+        let yellow = Piece::Yellow;
+        assert_eq!(
+            AvoidInescapableTraps.prune_from(&board, yellow, &options),
+            vec![0, 2, 3, 5, 6]
+        );
+        assert!(
+            BlockSetups
+                .prune_from(&board, yellow, &options)
+                .contains(&1)
+        );
+    }
 }
@@ -1,5 +1,4 @@
 use std::{
-    cell::RefCell,
     collections::HashMap,
     ops::Add,
     sync::{Arc, Mutex, RwLock},
@@ -8,17 +7,22 @@ use std::{
 use rand::seq::IndexedRandom;
 
 use crate::{
-    board::Board,
+    board::{Board, COLUMNS, Piece},
     strategy::{Connect4AI, StrategyStack},
 };
 
-type BoardCache = HashMap<Board, Vec<usize>>;
+type BoardCache = HashMap<(Board, Piece), Vec<usize>>;
 
 #[derive(Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct StrategyCacheStats {
     pub hits: u64,
     pub misses: u64,
     pub entries: usize,
+    /// Roughly how much memory the entries take: their keys and values, their
+    /// heap-allocated move lists and the hash table's control bytes.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub approx_bytes: usize,
 }
 
 impl Add for StrategyCacheStats {
@@ -29,6 +33,7 @@ impl Add for StrategyCacheStats {
             hits: self.hits + other.hits,
             misses: self.misses + other.misses,
             entries: self.entries + other.entries,
+            approx_bytes: self.approx_bytes + other.approx_bytes,
         }
     }
 }
@@ -38,35 +43,102 @@ impl std::fmt::Display for StrategyCacheStats {
         writeln!(f)?;
         writeln!(f, "Hits:    {:<10}", self.hits)?;
         writeln!(f, "Misses:  {:<10}", self.misses)?;
-        writeln!(f, "Entries: {:<10}", self.entries)
+        writeln!(f, "Entries: {:<10}", self.entries)?;
+        const MB: usize = 1024 * 1024;
+        if self.approx_bytes < MB {
+            writeln!(f, "Memory:  ~{} KB", self.approx_bytes.div_ceil(1024))
+        } else {
+            writeln!(
+                f,
+                "Memory:  ~{:.1} MB",
+                self.approx_bytes as f64 / MB as f64
+            )
+        }
+    }
+}
+
+/// The memory one cache entry of type `(K, V)` takes, with `moves` more columns on the
+/// heap: the pair itself plus the hash table's control byte.
+pub(crate) fn entry_bytes<K, V>(moves: usize) -> usize {
+    std::mem::size_of::<(K, V)>() + 1 + moves * std::mem::size_of::<usize>()
+}
+
+/// Reflects `columns` left to right if `mirrored`, to move between a board and its
+/// canonical form.
+pub(crate) fn mirror_columns(columns: &mut [usize], mirrored: bool) {
+    if mirrored {
+        for column in columns {
+            *column = COLUMNS - 1 - *column;
+        }
     }
 }
 
 pub struct StrategyCache {
     stack: StrategyStack,
+    /// Whether mirror images share an entry.
+    symmetry: bool,
     cache: Arc<RwLock<BoardCache>>,
-    rng: RefCell<rand::rngs::ThreadRng>,
     hits: Arc<Mutex<u64>>,
     misses: Arc<Mutex<u64>>,
+    /// The `approx_bytes` of the entries, kept up to date as they come and go.
+    bytes: Arc<Mutex<usize>>,
+    /// Entries are evicted once they take more than this.
+    max_bytes: Option<usize>,
 }
 
 impl StrategyCache {
     pub fn new(stack: StrategyStack) -> Self {
         Self {
             stack,
+            symmetry: false,
             cache: Arc::new(RwLock::new(HashMap::new())),
-            rng: RefCell::new(rand::rng()),
             hits: Arc::new(Mutex::new(0)),
             misses: Arc::new(Mutex::new(0)),
+            bytes: Arc::new(Mutex::new(0)),
+            max_bytes: None,
         }
     }
 
+    /// Keeps the cache to about `max_bytes` of memory. Once it grows past that,
+    /// arbitrary entries are dropped until it is down to three quarters, leaving room
+    /// to grow before the next eviction.
+    pub fn with_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+
+    fn evict(&self, cache: &mut BoardCache, bytes: &mut usize, max_bytes: usize) {
+        let target = max_bytes / 4 * 3;
+        cache.retain(|_, options| {
+            if *bytes <= target {
+                return true;
+            }
+            *bytes -= entry_bytes::<(Board, Piece), Vec<usize>>(options.capacity());
+            false
+        });
+        tracing::debug!(entries = cache.len(), bytes, "evicted cache entries");
+    }
+
+    /// Stores a board and its mirror image under one entry, nearly halving the entries
+    /// for the cost of a reflection per lookup. Only right for stacks that judge a
+    /// position and its mirror image alike.
+    pub fn with_symmetry(mut self) -> Self {
+        self.symmetry = true;
+        self
+    }
+
+    /// The stack whose decisions are cached.
+    pub fn stack(&self) -> &StrategyStack {
+        &self.stack
+    }
+
     pub fn cache_stats(&self) -> StrategyCacheStats {
         let cache = self.cache.read().unwrap();
         StrategyCacheStats {
             hits: *self.hits.lock().unwrap(),
             misses: *self.misses.lock().unwrap(),
             entries: cache.len(),
+            approx_bytes: *self.bytes.lock().unwrap(),
         }
     }
 }
@@ -78,17 +150,74 @@ impl std::fmt::Display for StrategyCache {
 }
 
 impl Connect4AI for StrategyCache {
-    fn play(&self, board: &Board) -> Option<usize> {
+    fn play(&self, board: &Board, piece: Piece) -> Option<usize> {
+        let (key, mirrored) = if self.symmetry {
+            board.canonical()
+        } else {
+            (*board, false)
+        };
         // See if we have this cached
-        if let Some(result) = self.cache.read().unwrap().get(board) {
+        if let Some(result) = self.cache.read().unwrap().get(&(key, piece)) {
+            tracing::trace!(options = ?result, "cache hit");
             *self.hits.lock().unwrap() += 1;
-            result.choose(&mut self.rng.borrow_mut()).copied()
+            let mut choice = result.choose(&mut crate::rng::rng()).copied();
+            mirror_columns(choice.as_mut_slice(), mirrored);
+            choice
         } else {
-            let result = self.stack.evaluate_options(board);
-            let choice = result.choose(&mut self.rng.borrow_mut()).copied();
-            self.cache.write().unwrap().insert(*board, result);
+            let mut result = self.stack.evaluate_options(board, piece);
+            tracing::trace!(options = ?result, "cache miss");
+            let choice = result.choose(&mut crate::rng::rng()).copied();
+            mirror_columns(&mut result, mirrored);
+            let added = entry_bytes::<(Board, Piece), Vec<usize>>(result.capacity());
+            let mut cache = self.cache.write().unwrap();
+            let mut bytes = self.bytes.lock().unwrap();
+            // Another thread may have cached this board in the meantime
+            if let Some(old) = cache.insert((key, piece), result) {
+                *bytes -= entry_bytes::<(Board, Piece), Vec<usize>>(old.capacity());
+            }
+            *bytes += added;
+            if let Some(max_bytes) = self.max_bytes
+                && *bytes > max_bytes
+            {
+                self.evict(&mut cache, &mut bytes, max_bytes);
+            }
+            drop((cache, bytes));
             *self.misses.lock().unwrap() += 1;
             choice
         }
     }
+
+    fn swaps(&self, board: &Board) -> bool {
+        self.stack.swaps(board)
+    }
+
+    fn rotates(&self, board: &Board, piece: Piece) -> bool {
+        self.stack.rotates(board, piece)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategy_kind::{build_stack, parse_stack};
+
+    #[test]
+    fn tracks_memory_and_evicts_past_the_limit() {
+        let stack = || build_stack(&parse_stack("TriesToWin").unwrap());
+        let entry = entry_bytes::<(Board, Piece), Vec<usize>>(COLUMNS);
+        let cache = StrategyCache::new(stack()).with_max_bytes(10 * entry);
+        let unbounded = StrategyCache::new(stack());
+        let (mut board, mut piece) = (Board::new(), Piece::Red);
+        for column in [0, 1, 2, 3, 4, 5, 6, 0, 1, 2, 3, 4, 5, 6, 1, 0] {
+            cache.play(&board, piece);
+            unbounded.play(&board, piece);
+            assert!(cache.cache_stats().approx_bytes <= 10 * entry);
+            board = board.place(column, piece);
+            piece = piece.opponent();
+        }
+        let stats = unbounded.cache_stats();
+        assert_eq!(stats.entries, 16);
+        assert!(stats.approx_bytes >= 16 * std::mem::size_of::<((Board, Piece), Vec<usize>)>());
+        assert!(cache.cache_stats().entries < 16);
+    }
 }
@@ -0,0 +1,575 @@
+use std::sync::RwLock;
+
+use anyhow::{Context, Result, ensure};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::combinators::PhasedStrategy;
+use crate::expectiminimax::Expectiminimax;
+use crate::search_for_win::SearchForWinCache;
+use crate::strategy::{
+    AvoidInescapableTraps, AvoidTraps, BlockSetups, PreferCenter, SearchForWin, Setup, Strategy,
+    StrategyStack, ThreeInARow, TriesToWin,
+};
+
+/// A strategy and its parameters, without a color. The same description can be built
+/// for either piece, saved to a file, or mutated by the tuning tools.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum StrategyKind {
+    SearchForWin {
+        depth: usize,
+    },
+    SearchForWinCache {
+        depth: usize,
+    },
+    /// Plays for the best expected result against a uniformly random opponent.
+    Expectiminimax {
+        depth: usize,
+    },
+    AvoidInescapableTraps,
+    AvoidTraps,
+    BlockSetups,
+    ThreeInARow,
+    PreferCenter,
+    Setup,
+    TriesToWin,
+    /// A strategy registered with `StrategyRegistry::register`.
+    Custom {
+        #[cfg_attr(feature = "serde", serde(with = "registered_name"))]
+        name: RegisteredName,
+        depth: Option<usize>,
+    },
+}
+
+/// Spelled through an alias so serde doesn't try to borrow the name from its input.
+type RegisteredName = &'static str;
+
+/// Custom strategies are saved by name, and only load once registered again.
+#[cfg(feature = "serde")]
+mod registered_name {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(name: &&'static str, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(name)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<&'static str, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        super::StrategyRegistry::custom(&name)
+            .map(|custom| custom.name)
+            .ok_or_else(|| D::Error::custom(format!("Unknown strategy {name:?}")))
+    }
+}
+
+/// Whether a strategy narrows the moves or picks one outright.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Role {
+    Layer,
+    Decider,
+}
+
+impl std::fmt::Display for Role {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Role::Layer => write!(f, "Filter Layer"),
+            Role::Decider => write!(f, "Decider"),
+        }
+    }
+}
+
+/// Roughly how long a strategy takes per move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Cost {
+    /// Looks one or two moves ahead.
+    Cheap,
+    /// Looks at every reply to every move.
+    Moderate,
+    /// Searches a tree that grows with its depth.
+    Expensive,
+}
+
+impl std::fmt::Display for Cost {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Cost::Cheap => write!(f, "cheap"),
+            Cost::Moderate => write!(f, "moderate"),
+            Cost::Expensive => write!(f, "expensive"),
+        }
+    }
+}
+
+/// What the CLI, the parser and the docs know about a strategy.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StrategyInfo {
+    /// The strategy with its default parameters.
+    pub default: StrategyKind,
+    pub role: Role,
+    /// The parameters it takes, empty for none.
+    pub parameters: &'static str,
+    pub description: &'static str,
+    pub cost: Cost,
+}
+
+/// The built-in strategies, in menu order. `StrategyRegistry` adds custom ones after
+/// them.
+pub const REGISTRY: [StrategyInfo; 10] = [
+    StrategyInfo {
+        default: StrategyKind::SearchForWin { depth: 3 },
+        role: Role::Decider,
+        parameters: "depth: how many of our moves to look ahead (default 3)",
+        description: "Plays a move that wins by force within `depth` of our moves, once 20 pieces are down",
+        cost: Cost::Expensive,
+    },
+    StrategyInfo {
+        default: StrategyKind::SearchForWinCache { depth: 6 },
+        role: Role::Decider,
+        parameters: "depth: how many of our moves to look ahead (default 6)",
+        description: "Searches for a forced win from any position, caching every position it settles",
+        cost: Cost::Expensive,
+    },
+    StrategyInfo {
+        default: StrategyKind::Expectiminimax { depth: 2 },
+        role: Role::Decider,
+        parameters: "depth: how many of our moves to look ahead (default 2)",
+        description: "Plays for the best expected result against a uniformly random opponent",
+        cost: Cost::Expensive,
+    },
+    StrategyInfo {
+        default: StrategyKind::AvoidInescapableTraps,
+        role: Role::Layer,
+        parameters: "",
+        description: "Drops moves after which the opponent has a reply that wins by force on their next turn",
+        cost: Cost::Moderate,
+    },
+    StrategyInfo {
+        default: StrategyKind::AvoidTraps,
+        role: Role::Layer,
+        parameters: "",
+        description: "Drops moves that let the opponent win on their next move",
+        cost: Cost::Cheap,
+    },
+    StrategyInfo {
+        default: StrategyKind::BlockSetups,
+        role: Role::Layer,
+        parameters: "",
+        description: "Drops moves that let the opponent make a double threat on their next turn",
+        cost: Cost::Moderate,
+    },
+    StrategyInfo {
+        default: StrategyKind::ThreeInARow,
+        role: Role::Layer,
+        parameters: "",
+        description: "Keeps the moves that leave us the most lines of three with an open end",
+        cost: Cost::Cheap,
+    },
+    StrategyInfo {
+        default: StrategyKind::PreferCenter,
+        role: Role::Layer,
+        parameters: "",
+        description: "Keeps the most central columns, which take part in the most four-in-a-rows",
+        cost: Cost::Cheap,
+    },
+    StrategyInfo {
+        default: StrategyKind::Setup,
+        role: Role::Decider,
+        parameters: "",
+        description: "Plays a move that leaves us a winning move for next turn",
+        cost: Cost::Cheap,
+    },
+    StrategyInfo {
+        default: StrategyKind::TriesToWin,
+        role: Role::Decider,
+        parameters: "",
+        description: "Wins if it can, and otherwise blocks the opponent's winning move",
+        cost: Cost::Cheap,
+    },
+];
+
+/// A strategy defined outside this crate. Once registered it is offered in menus,
+/// parsed from stacks and bred by `evolve` like the built-in ones.
+#[derive(Clone, Copy)]
+pub struct CustomStrategy {
+    /// Letters, digits and `_` only, since stacks are parsed from text.
+    pub name: &'static str,
+    pub role: Role,
+    /// The default depth, for strategies that take one.
+    pub depth: Option<usize>,
+    pub parameters: &'static str,
+    pub description: &'static str,
+    pub cost: Cost,
+    /// Builds the strategy, given the depth when it takes one.
+    pub build: fn(Option<usize>) -> Strategy,
+}
+
+static CUSTOM: RwLock<Vec<CustomStrategy>> = RwLock::new(Vec::new());
+
+/// Every strategy a stack can use: the built-ins, then any registered by other crates.
+/// Menus, `StrategyKind`'s parser and the `strategies` command all read from here.
+pub struct StrategyRegistry;
+
+impl StrategyRegistry {
+    /// Adds `strategy` for the rest of the process, returning it with its default
+    /// parameters. Names must be unused, ignoring case.
+    pub fn register(strategy: CustomStrategy) -> Result<StrategyKind> {
+        let name = strategy.name;
+        ensure!(
+            !name.is_empty() && (name.chars()).all(|c| c.is_ascii_alphanumeric() || c == '_'),
+            "Strategy names may only use letters, digits and '_', not {name:?}"
+        );
+        let mut custom = CUSTOM.write().expect("registrations don't panic");
+        ensure!(
+            !(REGISTRY.iter()).any(|info| info.default.name().eq_ignore_ascii_case(name))
+                && !(custom.iter()).any(|other| other.name.eq_ignore_ascii_case(name)),
+            "A strategy named {name} is already registered"
+        );
+        custom.push(strategy);
+        Ok(StrategyKind::Custom {
+            name,
+            depth: strategy.depth,
+        })
+    }
+
+    /// Every strategy, built-ins first, then custom ones in the order they were
+    /// registered.
+    pub fn strategies() -> Vec<StrategyInfo> {
+        let custom = CUSTOM.read().expect("registrations don't panic");
+        (REGISTRY.iter().copied())
+            .chain(custom.iter().map(StrategyInfo::from))
+            .collect()
+    }
+
+    /// The strategy called `name`, ignoring case.
+    pub fn find(name: &str) -> Option<StrategyInfo> {
+        (Self::strategies().into_iter()).find(|info| info.default.name().eq_ignore_ascii_case(name))
+    }
+
+    fn custom(name: &str) -> Option<CustomStrategy> {
+        let custom = CUSTOM.read().expect("registrations don't panic");
+        (custom.iter())
+            .find(|other| other.name.eq_ignore_ascii_case(name))
+            .copied()
+    }
+}
+
+impl From<&CustomStrategy> for StrategyInfo {
+    fn from(custom: &CustomStrategy) -> Self {
+        StrategyInfo {
+            default: StrategyKind::Custom {
+                name: custom.name,
+                depth: custom.depth,
+            },
+            role: custom.role,
+            parameters: custom.parameters,
+            description: custom.description,
+            cost: custom.cost,
+        }
+    }
+}
+
+impl StrategyKind {
+    /// Every built-in strategy with its default parameters, in `REGISTRY` order.
+    pub const ALL: [StrategyKind; 10] = [
+        StrategyKind::SearchForWin { depth: 3 },
+        StrategyKind::SearchForWinCache { depth: 6 },
+        StrategyKind::Expectiminimax { depth: 2 },
+        StrategyKind::AvoidInescapableTraps,
+        StrategyKind::AvoidTraps,
+        StrategyKind::BlockSetups,
+        StrategyKind::ThreeInARow,
+        StrategyKind::PreferCenter,
+        StrategyKind::Setup,
+        StrategyKind::TriesToWin,
+    ];
+
+    /// Strategies play whichever piece their stack does.
+    pub fn build(self) -> Strategy {
+        match self {
+            StrategyKind::SearchForWin { depth } => {
+                Strategy::Decision(Box::new(PhasedStrategy::only_after(
+                    SearchForWin::MIN_PIECES_PLAYED,
+                    SearchForWin::new(depth),
+                )))
+            }
+            StrategyKind::SearchForWinCache { depth } => {
+                Strategy::Decision(Box::new(SearchForWinCache::new(depth)))
+            }
+            StrategyKind::Expectiminimax { depth } => {
+                Strategy::Decision(Box::new(Expectiminimax::new(depth)))
+            }
+            StrategyKind::AvoidInescapableTraps => Strategy::Layer(Box::new(AvoidInescapableTraps)),
+            StrategyKind::AvoidTraps => Strategy::Layer(Box::new(AvoidTraps)),
+            StrategyKind::BlockSetups => Strategy::Layer(Box::new(BlockSetups)),
+            StrategyKind::ThreeInARow => Strategy::Layer(Box::new(ThreeInARow)),
+            StrategyKind::PreferCenter => Strategy::Layer(Box::new(PreferCenter::new())),
+            StrategyKind::Setup => Strategy::Decision(Box::new(Setup)),
+            StrategyKind::TriesToWin => Strategy::Decision(Box::new(TriesToWin)),
+            StrategyKind::Custom { name, depth } => {
+                let custom = StrategyRegistry::custom(name)
+                    .unwrap_or_else(|| panic!("{name} isn't a registered strategy"));
+                (custom.build)(depth)
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for StrategyKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StrategyKind::SearchForWin { depth }
+            | StrategyKind::SearchForWinCache { depth }
+            | StrategyKind::Expectiminimax { depth }
+            | StrategyKind::Custom {
+                depth: Some(depth), ..
+            } => write!(f, "{}({depth})", self.name()),
+            _ => write!(f, "{}", self.name()),
+        }
+    }
+}
+
+/// Parses the names printed by `Display`, e.g. `TriesToWin` or `SearchForWin(3)`.
+/// Search strategies without a depth get their default one.
+impl std::str::FromStr for StrategyKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let (name, depth) = match s.split_once('(') {
+            Some((name, rest)) => {
+                let depth = rest
+                    .strip_suffix(')')
+                    .with_context(|| format!("Missing ')' in {s:?}"))?
+                    .trim()
+                    .parse::<usize>()
+                    .with_context(|| format!("Invalid depth in {s:?}"))?;
+                (name.trim(), Some(depth))
+            }
+            None => (s, None),
+        };
+
+        let kind = StrategyRegistry::find(name)
+            .with_context(|| format!("Unknown strategy {name:?}"))?
+            .default;
+        match depth {
+            None => Ok(kind),
+            Some(depth) => kind
+                .with_depth(depth)
+                .with_context(|| format!("{} does not take a depth", kind.name())),
+        }
+    }
+}
+
+impl StrategyKind {
+    /// The search depth, for the strategies that take one.
+    pub fn depth(&self) -> Option<usize> {
+        match self {
+            StrategyKind::SearchForWin { depth }
+            | StrategyKind::SearchForWinCache { depth }
+            | StrategyKind::Expectiminimax { depth } => Some(*depth),
+            StrategyKind::Custom { depth, .. } => *depth,
+            _ => None,
+        }
+    }
+
+    /// The same strategy searching `depth` deep, or None if it doesn't search.
+    pub fn with_depth(self, depth: usize) -> Option<Self> {
+        match self {
+            StrategyKind::SearchForWin { .. } => Some(StrategyKind::SearchForWin { depth }),
+            StrategyKind::SearchForWinCache { .. } => {
+                Some(StrategyKind::SearchForWinCache { depth })
+            }
+            StrategyKind::Expectiminimax { .. } => Some(StrategyKind::Expectiminimax { depth }),
+            StrategyKind::Custom {
+                name,
+                depth: Some(_),
+            } => Some(StrategyKind::Custom {
+                name,
+                depth: Some(depth),
+            }),
+            _ => None,
+        }
+    }
+
+    /// The strategy's entry in `StrategyRegistry`.
+    pub fn info(&self) -> StrategyInfo {
+        StrategyRegistry::find(self.name()).expect("every strategy is registered")
+    }
+
+    /// The strategy's name without its parameters.
+    pub fn name(&self) -> &'static str {
+        match self {
+            StrategyKind::SearchForWin { .. } => "SearchForWin",
+            StrategyKind::SearchForWinCache { .. } => "SearchForWinCache",
+            StrategyKind::Expectiminimax { .. } => "Expectiminimax",
+            StrategyKind::AvoidInescapableTraps => "AvoidInescapableTraps",
+            StrategyKind::AvoidTraps => "AvoidTraps",
+            StrategyKind::BlockSetups => "BlockSetups",
+            StrategyKind::ThreeInARow => "ThreeInARow",
+            StrategyKind::PreferCenter => "PreferCenter",
+            StrategyKind::Setup => "Setup",
+            StrategyKind::TriesToWin => "TriesToWin",
+            StrategyKind::Custom { name, .. } => name,
+        }
+    }
+}
+
+/// Parses a whole stack, with layers separated by `,` or `=>`,
+/// e.g. `SearchForWin(3) => TriesToWin => AvoidTraps`.
+pub fn parse_stack(spec: &str) -> Result<Vec<StrategyKind>> {
+    spec.replace("=>", ",")
+        .split(',')
+        .filter(|part| !part.trim().is_empty())
+        .map(str::parse)
+        .collect()
+}
+
+/// The strongest difficulty `difficulty_stack` knows about.
+pub const MAX_DIFFICULTY: u8 = 4;
+
+/// A ready-made stack for a difficulty from 0 (random moves) to `MAX_DIFFICULTY`.
+/// Higher values are clamped.
+pub fn difficulty_stack(difficulty: u8) -> Vec<StrategyKind> {
+    match difficulty.min(MAX_DIFFICULTY) {
+        0 => vec![],
+        1 => vec![StrategyKind::TriesToWin],
+        2 => vec![StrategyKind::TriesToWin, StrategyKind::AvoidTraps],
+        3 => vec![
+            StrategyKind::TriesToWin,
+            StrategyKind::AvoidInescapableTraps,
+            StrategyKind::ThreeInARow,
+        ],
+        _ => vec![
+            StrategyKind::SearchForWin { depth: 3 },
+            StrategyKind::TriesToWin,
+            StrategyKind::AvoidInescapableTraps,
+            StrategyKind::ThreeInARow,
+        ],
+    }
+}
+
+/// Builds a stack out of `kinds`, which plays whichever piece it is asked to.
+pub fn build_stack(kinds: &[StrategyKind]) -> StrategyStack {
+    StrategyStack::new(kinds.iter().map(|kind| kind.build()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_what_it_prints() {
+        for kind in StrategyKind::ALL {
+            assert_eq!(kind.to_string().parse::<StrategyKind>().unwrap(), kind);
+        }
+        assert_eq!(
+            "searchforwin( 5 )".parse::<StrategyKind>().unwrap(),
+            StrategyKind::SearchForWin { depth: 5 }
+        );
+        let deeper = StrategyKind::Expectiminimax { depth: 2 }.with_depth(4);
+        assert_eq!(deeper.and_then(|kind| kind.depth()), Some(4));
+        assert_eq!(StrategyKind::AvoidTraps.with_depth(4), None);
+    }
+
+    #[test]
+    fn registry_matches_what_strategies_build() {
+        assert_eq!(REGISTRY.map(|info| info.default), StrategyKind::ALL);
+        for info in REGISTRY {
+            let role = match info.default.build() {
+                Strategy::Layer(_) => Role::Layer,
+                Strategy::Decision(_) => Role::Decider,
+            };
+            assert_eq!(role, info.role, "{}", info.default);
+            assert_eq!(info.parameters.is_empty(), info.default.depth().is_none());
+            assert_eq!(info.default.info(), info);
+        }
+    }
+
+    /// Plays the leftmost option, or the `depth`th from the left.
+    struct Leftmost(usize);
+
+    impl crate::strategy::StrategyDecider for Leftmost {
+        fn choose(
+            &self,
+            _board: &crate::Board,
+            _piece: crate::Piece,
+            options: &[usize],
+        ) -> Option<usize> {
+            options.get(self.0).or(options.last()).copied()
+        }
+
+        fn name(&self) -> &'static str {
+            "Leftmost"
+        }
+    }
+
+    #[test]
+    fn registered_strategies_parse_and_build() {
+        let leftmost = CustomStrategy {
+            name: "Leftmost",
+            role: Role::Decider,
+            depth: Some(0),
+            parameters: "depth: how many options to skip (default 0)",
+            description: "Plays the leftmost option",
+            cost: Cost::Cheap,
+            build: |depth| Strategy::Decision(Box::new(Leftmost(depth.unwrap_or(0)))),
+        };
+        let kind = StrategyRegistry::register(leftmost).unwrap();
+        assert!(StrategyRegistry::register(leftmost).is_err());
+        assert!(
+            StrategyRegistry::register(CustomStrategy {
+                name: "avoidtraps",
+                ..leftmost
+            })
+            .is_err()
+        );
+        assert!(StrategyRegistry::strategies().contains(&kind.info()));
+
+        let stack = parse_stack("leftmost(2), TriesToWin").unwrap();
+        assert_eq!(stack[0], kind.with_depth(2).unwrap());
+        assert_eq!(stack[0].to_string(), "Leftmost(2)");
+        let board = crate::Board::new();
+        assert_eq!(
+            build_stack(&stack).evaluate_options(&board, crate::Piece::Red),
+            vec![2]
+        );
+
+        #[cfg(feature = "serde")]
+        {
+            let json = serde_json::to_string(&stack[0]).unwrap();
+            assert_eq!(
+                serde_json::from_str::<StrategyKind>(&json).unwrap(),
+                stack[0]
+            );
+            let unknown = json.replace("Leftmost", "Unregistered");
+            assert!(serde_json::from_str::<StrategyKind>(&unknown).is_err());
+        }
+    }
+
+    #[test]
+    fn difficulties_grow_and_clamp() {
+        for difficulty in 1..=MAX_DIFFICULTY {
+            assert!(difficulty_stack(difficulty).len() >= difficulty_stack(difficulty - 1).len());
+        }
+        assert_eq!(difficulty_stack(u8::MAX), difficulty_stack(MAX_DIFFICULTY));
+    }
+
+    #[test]
+    fn parses_stacks() {
+        let stack = parse_stack("SearchForWin(2) => TriesToWin, AvoidTraps").unwrap();
+        assert_eq!(
+            stack,
+            vec![
+                StrategyKind::SearchForWin { depth: 2 },
+                StrategyKind::TriesToWin,
+                StrategyKind::AvoidTraps
+            ]
+        );
+        assert!(parse_stack("").unwrap().is_empty());
+        assert!(parse_stack("Nonsense").is_err());
+        assert!(parse_stack("AvoidTraps(2)").is_err());
+        assert!(parse_stack("SearchForWin(x)").is_err());
+    }
+}
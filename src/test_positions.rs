@@ -0,0 +1,211 @@
+/// Red to move wins at once in column 0.
+///
+/// ```text
+/// | | | | | | | |
+/// | | | | | | | |
+/// | | | | | | | |
+/// |R|Y| | | | | |
+/// |R|Y| | | | | |
+/// |R|Y| | | | | |
+/// ```
+pub const SIMPLE_WIN: &str = "010101";
+
+/// Red to move must block Yellow's column 0.
+///
+/// ```text
+/// | | | | | | | |
+/// | | | | | | | |
+/// | | | | | | | |
+/// |Y| | | | | | |
+/// |Y|R| | | | | |
+/// |Y|R|R| | | | |
+/// ```
+pub const BLOCK: &str = "102010";
+
+/// Red to move makes an open three with 1 or 4, leaving two winning moves that Yellow
+/// can't both block.
+///
+/// ```text
+/// | | | | | | | |
+/// | | | | | | | |
+/// | | | | | | | |
+/// | | | | | | | |
+/// | | | | | | |Y|
+/// | | |R|R| | |Y|
+/// ```
+pub const DOUBLE_THREAT: &str = "2636";
+
+/// Red to move must stop Yellow's open three by taking 1 or 4. Column 6 also holds,
+/// since it gives Red three in a column and Yellow has to answer it.
+///
+/// ```text
+/// | | | | | | | |
+/// | | | | | | | |
+/// | | | | | | | |
+/// | | | | | | | |
+/// | | | | | | |R|
+/// | | |Y|Y| | |R|
+/// ```
+pub const DEFEND_DOUBLE_THREAT: &str = "6263";
+
+/// Yellow to move must not play 1: Red answers 2, threatening both the second row and
+/// a diagonal, with the second winning spot right on top of the first. Yellow's block
+/// gives Red the spot above it. 4 loses at once.
+///
+/// ```text
+/// | | | | | | | |
+/// | | | | | | | |
+/// |R| | | | | | |
+/// |Y| | | | | | |
+/// |R| | |R| |R|R|
+/// |Y| |Y|R| |Y|Y|
+/// ```
+pub const STACKED_THREAT: &str = "36600032055";
+
+/// Yellow to move is in zugzwang: only columns 2 and 4 are left, and Red wins on top
+/// of either.
+///
+/// ```text
+/// | | | | | | | |
+/// |R|R| |Y| |R|R|
+/// |Y|R| |Y| |R|Y|
+/// |R|R| |Y| |Y|R|
+/// |Y|Y| |R| |R|Y|
+/// |R|Y|Y|R|Y|Y|R|
+/// ```
+pub const ZUGZWANG: &str = "056056650660310433511253131";
+
+/// Both sides win on the third row of column 3, so whoever plays into that column
+/// first loses. An odd number of squares is left elsewhere, so Red, to move, wins by
+/// parity with any move outside column 3.
+///
+/// ```text
+/// | | | | | | | |
+/// | |Y|R| |Y| | |
+/// |R|R|R| |Y| |Y|
+/// |R|Y|Y| |R| |R|
+/// |Y|R|Y| |R|Y|Y|
+/// |R|Y|Y|R|R|R|Y|
+/// ```
+pub const ODD_EVEN_ENDGAME: &str = "06424046315211660225241401";
+
+/// Every named position, for tests that loop over all of them.
+pub const ALL: [(&str, &str); 7] = [
+    ("SIMPLE_WIN", SIMPLE_WIN),
+    ("BLOCK", BLOCK),
+    ("DOUBLE_THREAT", DOUBLE_THREAT),
+    ("DEFEND_DOUBLE_THREAT", DEFEND_DOUBLE_THREAT),
+    ("STACKED_THREAT", STACKED_THREAT),
+    ("ZUGZWANG", ZUGZWANG),
+    ("ODD_EVEN_ENDGAME", ODD_EVEN_ENDGAME),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{Board, Piece};
+    use crate::game::GameState;
+    use crate::puzzle::{winning_moves_within, wins_within};
+    use crate::strategy::{
+        AvoidInescapableTraps, AvoidTraps, BlockSetups, Connect4AI, SearchForWin, StrategyDecider,
+        StrategyLayer, TriesToWin,
+    };
+    use crate::strategy_kind::{MAX_DIFFICULTY, build_stack, difficulty_stack};
+
+    fn position(moves: &str) -> (Board, Piece) {
+        let state = GameState::from_moves(moves).unwrap();
+        (*state.board(), state.to_move())
+    }
+
+    #[test]
+    fn positions_are_legal_and_unfinished() {
+        for (name, moves) in ALL {
+            let state = GameState::from_moves(moves).unwrap();
+            assert!(!state.is_over(), "{name}");
+        }
+    }
+
+    #[test]
+    fn deciders_take_wins_and_blocks() {
+        for moves in [SIMPLE_WIN, BLOCK] {
+            let (board, piece) = position(moves);
+            let options = board.valid_moves();
+            assert_eq!(TriesToWin.choose(&board, piece, &options), Some(0));
+        }
+        let (board, piece) = position(BLOCK);
+        let options = board.valid_moves();
+        assert_eq!(AvoidTraps.prune_from(&board, piece, &options), vec![0]);
+
+        let (board, piece) = position(SIMPLE_WIN);
+        let search = SearchForWin::new(1);
+        assert_eq!(search.choose(&board, piece, &board.valid_moves()), Some(0));
+    }
+
+    #[test]
+    fn every_difficulty_above_random_takes_wins_and_blocks() {
+        for difficulty in 1..=MAX_DIFFICULTY {
+            for moves in [SIMPLE_WIN, BLOCK] {
+                let (board, piece) = position(moves);
+                let stack = build_stack(&difficulty_stack(difficulty));
+                assert_eq!(stack.play(&board, piece), Some(0), "{difficulty} {moves}");
+            }
+        }
+    }
+
+    #[test]
+    fn search_finds_the_double_threat() {
+        let (board, piece) = position(DOUBLE_THREAT);
+        assert_eq!(winning_moves_within(&board, piece, 2), vec![1, 4]);
+        let doubles: Vec<usize> = (board.valid_moves().into_iter())
+            .filter(|&col| board.creates_double_threat(col, piece))
+            .collect();
+        assert_eq!(doubles, vec![1, 4]);
+        let search = SearchForWin::new(2);
+        let choice = search.choose(&board, piece, &board.valid_moves()).unwrap();
+        assert!([1, 4].contains(&choice), "{choice}");
+    }
+
+    #[test]
+    fn defensive_layers_see_the_traps() {
+        let (board, piece) = position(DEFEND_DOUBLE_THREAT);
+        let options = board.valid_moves();
+        assert_eq!(
+            BlockSetups.prune_from(&board, piece, &options),
+            vec![1, 4, 6]
+        );
+        let inescapable = AvoidInescapableTraps;
+        assert_eq!(
+            inescapable.prune_from(&board, piece, &options),
+            vec![1, 4, 6]
+        );
+
+        let (board, piece) = position(STACKED_THREAT);
+        let options = board.valid_moves();
+        let inescapable = AvoidInescapableTraps;
+        assert_eq!(
+            inescapable.prune_from(&board, piece, &options),
+            vec![0, 2, 3, 5, 6]
+        );
+    }
+
+    #[test]
+    fn zugzwang_leaves_no_safe_move() {
+        let (board, piece) = position(ZUGZWANG);
+        let options = board.valid_moves();
+        assert_eq!(options, vec![2, 4]);
+        assert!(AvoidTraps.prune_from(&board, piece, &options).is_empty());
+        assert!(wins_within(&board.place(2, piece), piece.opponent(), 1));
+    }
+
+    #[test]
+    fn odd_even_endgame_is_won_by_tempo() {
+        let (board, piece) = position(ODD_EVEN_ENDGAME);
+        assert_eq!(winning_moves_within(&board, piece, 5), vec![0, 5, 6]);
+        assert!(!wins_within(&board, piece, 2));
+        let options = board.valid_moves();
+        assert_eq!(
+            AvoidTraps.prune_from(&board, piece, &options),
+            vec![0, 5, 6]
+        );
+    }
+}
@@ -0,0 +1,272 @@
+use std::fmt;
+
+use anyhow::{Result, ensure};
+use rand::Rng;
+use rand::seq::IndexedRandom;
+
+/// The experimental three-player board is wider so three colors have room to connect.
+pub const COLUMNS: usize = 9;
+pub const ROWS: usize = 6;
+
+/// The colors in a three-player game, in turn order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Color {
+    Red,
+    Yellow,
+    Green,
+}
+
+impl Color {
+    pub const ALL: [Color; 3] = [Color::Red, Color::Yellow, Color::Green];
+
+    /// Whoever moves after this color.
+    pub fn next(self) -> Color {
+        match self {
+            Color::Red => Color::Yellow,
+            Color::Yellow => Color::Green,
+            Color::Green => Color::Red,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Color::Red => "Red",
+            Color::Yellow => "Yellow",
+            Color::Green => "Green",
+        }
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use colorize::AnsiColor;
+        match self {
+            Color::Red => write!(f, "{}", "[R]".b_redb()),
+            Color::Yellow => write!(f, "{}", "[Y]".b_blackb().b_yellow()),
+            Color::Green => write!(f, "{}", "[G]".b_greenb()),
+        }
+    }
+}
+
+/// A plain grid for three colors. The packed `Board` only has one bit per piece, so
+/// this variant doesn't share it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ThreePlayerBoard {
+    /// Indexed by column, then row from the bottom.
+    cells: [[Option<Color>; ROWS]; COLUMNS],
+}
+
+impl ThreePlayerBoard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn height(&self, column: usize) -> usize {
+        self.cells[column]
+            .iter()
+            .take_while(|c| c.is_some())
+            .count()
+    }
+
+    /// Columns with room left. Unlike `Board`, columns fill all the way to the top.
+    pub fn valid_moves(&self) -> Vec<usize> {
+        (0..COLUMNS)
+            .filter(|&column| self.height(column) < ROWS)
+            .collect()
+    }
+
+    pub fn place(&self, column: usize, color: Color) -> Self {
+        let row = self.height(column);
+        assert!(row < ROWS, "column {column} is full");
+        let mut next = *self;
+        next.cells[column][row] = Some(color);
+        next
+    }
+
+    pub fn winner(&self) -> Option<Color> {
+        let at = |column: isize, row: isize| {
+            if (0..COLUMNS as isize).contains(&column) && (0..ROWS as isize).contains(&row) {
+                self.cells[column as usize][row as usize]
+            } else {
+                None
+            }
+        };
+        for column in 0..COLUMNS as isize {
+            for row in 0..ROWS as isize {
+                let Some(color) = at(column, row) else {
+                    continue;
+                };
+                for (dc, dr) in [(1, 0), (0, 1), (1, 1), (1, -1)] {
+                    if (1..4).all(|i| at(column + dc * i, row + dr * i) == Some(color)) {
+                        return Some(color);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// Columns where `color` would complete four-in-a-row.
+    pub fn winning_moves(&self, color: Color) -> Vec<usize> {
+        (self.valid_moves().into_iter())
+            .filter(|&column| self.place(column, color).winner() == Some(color))
+            .collect()
+    }
+}
+
+impl fmt::Display for ThreePlayerBoard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use colorize::AnsiColor;
+        for row in (0..ROWS).rev() {
+            for column in 0..COLUMNS {
+                match self.cells[column][row] {
+                    Some(color) => write!(f, "{} ", color)?,
+                    None => write!(f, "{} ", "[ ]".black())?,
+                }
+            }
+            if row != 0 {
+                writeln!(f)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// How a finished three-player game ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThreePlayerResult {
+    Winner(Color),
+    Tie,
+}
+
+/// A three-player game: Red, Yellow and Green take turns in that order.
+#[derive(Debug, Clone, Default)]
+pub struct ThreePlayerGame {
+    board: ThreePlayerBoard,
+    history: Vec<usize>,
+}
+
+impl ThreePlayerGame {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn board(&self) -> &ThreePlayerBoard {
+        &self.board
+    }
+
+    pub fn to_move(&self) -> Color {
+        Color::ALL[self.history.len() % Color::ALL.len()]
+    }
+
+    pub fn history(&self) -> &[usize] {
+        &self.history
+    }
+
+    pub fn result(&self) -> Option<ThreePlayerResult> {
+        if let Some(winner) = self.board.winner() {
+            Some(ThreePlayerResult::Winner(winner))
+        } else if self.board.valid_moves().is_empty() {
+            Some(ThreePlayerResult::Tie)
+        } else {
+            None
+        }
+    }
+
+    /// Plays `column` for the side to move and passes the turn on.
+    pub fn apply_move(&mut self, column: usize) -> Result<Option<ThreePlayerResult>> {
+        ensure!(self.result().is_none(), "The game is already over");
+        ensure!(
+            self.board.valid_moves().contains(&column),
+            "Column {} is not a legal move",
+            column
+        );
+        self.board = self.board.place(column, self.to_move());
+        self.history.push(column);
+        Ok(self.result())
+    }
+}
+
+/// A simple three-player AI: wins if it can, blocks the next player and then the one
+/// after, avoids setting up a win for the next player, and otherwise plays near the
+/// center.
+pub fn choose_move(board: &ThreePlayerBoard, color: Color, rng: &mut impl Rng) -> Option<usize> {
+    if let Some(&column) = board.winning_moves(color).first() {
+        return Some(column);
+    }
+    for opponent in [color.next(), color.next().next()] {
+        if let Some(&column) = board.winning_moves(opponent).first() {
+            return Some(column);
+        }
+    }
+
+    let moves = board.valid_moves();
+    let safe: Vec<usize> = (moves.iter().copied())
+        .filter(|&column| {
+            board
+                .place(column, color)
+                .winning_moves(color.next())
+                .is_empty()
+        })
+        .collect();
+    let options = if safe.is_empty() { moves } else { safe };
+    let distance = |column: usize| column.abs_diff(COLUMNS / 2);
+    let closest = options.iter().map(|&c| distance(c)).min()?;
+    let central: Vec<usize> = (options.into_iter())
+        .filter(|&c| distance(c) <= closest + 1)
+        .collect();
+    central.choose(rng).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play(moves: &[usize]) -> ThreePlayerGame {
+        let mut game = ThreePlayerGame::new();
+        for &column in moves {
+            game.apply_move(column).unwrap();
+        }
+        game
+    }
+
+    #[test]
+    fn turns_rotate_through_three_colors() {
+        let game = play(&[0, 1, 2]);
+        assert_eq!(game.to_move(), Color::Red);
+        assert_eq!(game.board().height(0), 1);
+        assert_eq!(play(&[0, 1]).to_move(), Color::Green);
+    }
+
+    #[test]
+    fn four_in_a_row_wins_for_any_color() {
+        // Green stacks column 8 while Red and Yellow play elsewhere
+        let game = play(&[0, 1, 8, 0, 1, 8, 2, 3, 8, 2, 3, 8]);
+        assert_eq!(game.result(), Some(ThreePlayerResult::Winner(Color::Green)));
+
+        // Red along the bottom row
+        let game = play(&[0, 0, 0, 1, 1, 1, 2, 2, 2, 3]);
+        assert_eq!(game.result(), Some(ThreePlayerResult::Winner(Color::Red)));
+    }
+
+    #[test]
+    fn ai_wins_then_blocks_either_opponent() {
+        let mut rng = rand::rng();
+        let board = ThreePlayerBoard::new();
+        let threatened = |color| (0..3).fold(board, |board, column| board.place(column + 4, color));
+
+        assert_eq!(
+            choose_move(&threatened(Color::Red), Color::Red, &mut rng),
+            Some(3)
+        );
+        // Both opponents are blocked, whichever of them threatens
+        assert_eq!(
+            choose_move(&threatened(Color::Yellow), Color::Red, &mut rng),
+            Some(3)
+        );
+        assert_eq!(
+            choose_move(&threatened(Color::Green), Color::Red, &mut rng),
+            Some(3)
+        );
+    }
+}
@@ -0,0 +1,137 @@
+use crate::board::Piece;
+use crate::game::{GameState, play_game};
+use crate::strategy::StrategyStack;
+
+/// The tunable knobs of a strategy stack.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TuneParams {
+    /// How many of our moves to search ahead for a forced win.
+    pub depth: usize,
+    /// How many pieces must be on the board before searching at all.
+    pub min_pieces_played: usize,
+}
+
+impl std::fmt::Display for TuneParams {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "depth={} min_pieces_played={}",
+            self.depth, self.min_pieces_played
+        )
+    }
+}
+
+/// How a candidate did against the baseline.
+#[derive(Debug, Clone, Copy)]
+pub struct TuneResult {
+    pub params: TuneParams,
+    pub wins: usize,
+    pub losses: usize,
+    pub ties: usize,
+}
+
+impl TuneResult {
+    /// Wins count fully and ties count half, so 0.5 means "as good as the baseline".
+    pub fn score(&self) -> f64 {
+        let games = self.wins + self.losses + self.ties;
+        if games == 0 {
+            return 0.0;
+        }
+        (self.wins as f64 + self.ties as f64 / 2.0) / games as f64
+    }
+}
+
+/// Every combination of the given depths and thresholds.
+pub fn grid(depths: &[usize], min_pieces: &[usize]) -> Vec<TuneParams> {
+    depths
+        .iter()
+        .flat_map(|&depth| {
+            min_pieces.iter().map(move |&min_pieces_played| TuneParams {
+                depth,
+                min_pieces_played,
+            })
+        })
+        .collect()
+}
+
+/// Plays `games` games between every candidate and the baseline, alternating who moves
+/// first, and returns the results sorted best first. `on_result` is called as each
+/// candidate finishes so callers can report progress.
+pub fn grid_search(
+    candidates: &[TuneParams],
+    games: usize,
+    build: impl Fn(&TuneParams) -> StrategyStack,
+    baseline: &StrategyStack,
+    mut on_result: impl FnMut(&TuneResult),
+) -> Vec<TuneResult> {
+    let mut results: Vec<TuneResult> = candidates
+        .iter()
+        .map(|params| {
+            let candidate = build(params);
+            let mut result = TuneResult {
+                params: *params,
+                wins: 0,
+                losses: 0,
+                ties: 0,
+            };
+
+            for i in 0..games {
+                let candidate_color = if i % 2 == 0 {
+                    Piece::Red
+                } else {
+                    Piece::Yellow
+                };
+                let start = GameState::new();
+                let game = if candidate_color == Piece::Red {
+                    play_game(&candidate, baseline, &start, &mut ())
+                } else {
+                    play_game(baseline, &candidate, &start, &mut ())
+                };
+                match game.and_then(|game| game.winner()) {
+                    Some(winner) if winner == candidate_color => result.wins += 1,
+                    Some(_) => result.losses += 1,
+                    None => result.ties += 1,
+                }
+            }
+
+            on_result(&result);
+            result
+        })
+        .collect();
+
+    results.sort_by(|a, b| b.score().total_cmp(&a.score()));
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategy::{Strategy, TriesToWin};
+
+    #[test]
+    fn grid_covers_every_combination() {
+        let candidates = grid(&[1, 2], &[0, 10, 20]);
+        assert_eq!(candidates.len(), 6);
+        assert!(candidates.contains(&TuneParams {
+            depth: 2,
+            min_pieces_played: 10
+        }));
+    }
+
+    #[test]
+    fn search_ranks_candidates() {
+        let candidates = grid(&[1], &[0, 20]);
+        let mut reported = 0;
+        let results = grid_search(
+            &candidates,
+            4,
+            |_| StrategyStack::new(vec![Strategy::Decision(Box::new(TriesToWin))]),
+            &StrategyStack::new(vec![]),
+            |_| reported += 1,
+        );
+        assert_eq!(reported, 2);
+        assert_eq!(results.len(), 2);
+        assert!(results[0].score() >= results[1].score());
+        assert_eq!(results[0].wins + results[0].losses + results[0].ties, 4);
+    }
+}
@@ -0,0 +1,36 @@
+use wasm_bindgen::prelude::*;
+
+use crate::bounded::SearchBudget;
+use crate::game::GameState;
+use crate::strategy::{Connect4AI, StrategyStack};
+use crate::strategy_kind::{build_stack, difficulty_stack};
+
+/// The column the AI would play. `position` is the columns played so far, numbered
+/// from 0 (e.g. `"3324"`), and `difficulty` runs from 0 (random) to 4.
+#[wasm_bindgen]
+pub fn best_move(position: &str, difficulty: u8) -> Result<usize, JsError> {
+    let state = GameState::from_moves(position).map_err(|e| JsError::new(&format!("{e:#}")))?;
+    if state.is_over() {
+        return Err(JsError::new("The game is already over"));
+    }
+    let ai = build_stack(&difficulty_stack(difficulty));
+    ai.play(state.board(), state.to_move())
+        .ok_or_else(|| JsError::new("No legal moves"))
+}
+
+/// Like `best_move`, but from `StrategyStack::bounded`, whose memory is fixed up front
+/// and which searches at most `max_nodes` positions.
+#[wasm_bindgen]
+pub fn bounded_move(position: &str, max_nodes: u32) -> Result<usize, JsError> {
+    let state = GameState::from_moves(position).map_err(|e| JsError::new(&format!("{e:#}")))?;
+    if state.is_over() {
+        return Err(JsError::new("The game is already over"));
+    }
+    let budget = SearchBudget {
+        max_nodes: max_nodes.into(),
+        ..SearchBudget::default()
+    };
+    let ai = StrategyStack::bounded(budget);
+    ai.play(state.board(), state.to_move())
+        .ok_or_else(|| JsError::new("No legal moves"))
+}